@@ -1,18 +1,65 @@
 use gtk::prelude::*;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub type Result<T> = std::result::Result<T, Box<dyn Error + Sync + Send>>;
 
+/// How we read capture files
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum InputBackend {
+    /// everything goes through tshark
+    #[default]
+    #[serde(rename = "tshark")]
+    TShark,
+    /// we decode HTTP/1 and PostgreSQL ourselves, and let tshark handle the rest
+    #[serde(rename = "native")]
+    Native,
+}
+
+/// Which output we ask tshark for
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TSharkOutputFormat {
+    /// every field of every protocol
+    #[default]
+    #[serde(rename = "pdml")]
+    Pdml,
+    /// only the protocols we decode: much smaller on large captures
+    #[serde(rename = "json")]
+    Json,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Config {
     pub prefer_dark_theme: bool,
     pub custom_tcpdump_buffer_size_kib: Option<usize>,
     pub tcpdump_use_pkexec_if_possible: bool,
+    #[serde(default)]
+    pub input_backend: InputBackend,
+    #[serde(default)]
+    pub tshark_output_format: TSharkOutputFormat,
+    /// HTTP requests taking longer than that are highlighted
+    #[serde(default = "default_http_slow_threshold_ms")]
+    pub http_slow_threshold_ms: u32,
+    /// PostgreSQL queries taking longer than that are highlighted
+    #[serde(default = "default_pg_slow_threshold_ms")]
+    pub pg_slow_threshold_ms: u32,
+    /// a folder of .proto files, a .proto file, or a descriptor set,
+    /// to decode gRPC messages with field names
+    #[serde(default)]
+    pub grpc_proto_path: Option<String>,
+}
+
+fn default_http_slow_threshold_ms() -> u32 {
+    1000
+}
+
+fn default_pg_slow_threshold_ms() -> u32 {
+    500
 }
 
 impl Config {
@@ -21,6 +68,11 @@ impl Config {
             prefer_dark_theme: false,
             custom_tcpdump_buffer_size_kib: Some(8192),
             tcpdump_use_pkexec_if_possible: true,
+            input_backend: InputBackend::TShark,
+            tshark_output_format: TSharkOutputFormat::Pdml,
+            http_slow_threshold_ms: default_http_slow_threshold_ms(),
+            pg_slow_threshold_ms: default_pg_slow_threshold_ms(),
+            grpc_proto_path: None,
         }
     }
 
@@ -29,7 +81,7 @@ impl Config {
         Ok(config_folder.join("config.toml"))
     }
 
-    fn read_config_file() -> Result<Config> {
+    pub fn read_config_file() -> Result<Config> {
         let config_file = Self::config_file_path()?;
         if !config_file.is_file() {
             return Ok(Self::default_config());
@@ -123,7 +175,9 @@ pub fn remove_obsolete_tcpdump_files(remove_mode: RemoveMode) -> Result<()> {
             .is_some()
     };
     let is_my_file = |f: &str| {
-        f.replace(".pcap", "")
+        f.split('.')
+            .next()
+            .unwrap_or("")
             .ends_with(&format!("-{}", std::process::id()))
     };
     for direntry in paths {
@@ -131,7 +185,11 @@ pub fn remove_obsolete_tcpdump_files(remove_mode: RemoveMode) -> Result<()> {
         let should_remove = path
             .file_name()
             .and_then(|f| f.to_str())
-            .filter(|f| f.starts_with("hotwire-record-") || f.starts_with("hotwire-save-"))
+            .filter(|f| {
+                f.starts_with("hotwire-record-")
+                    || f.starts_with("hotwire-save-")
+                    || f.starts_with("hotwire-keylog-")
+            })
             .filter(|f| {
                 is_old(&path) || (remove_mode == RemoveMode::OldFilesAndMyFiles && is_my_file(f))
             })
@@ -156,3 +214,56 @@ pub fn get_tshark_pcap_output_path() -> PathBuf {
     pcap_path.push(format!("hotwire-save-{}.pcap", std::process::id()));
     pcap_path
 }
+
+/// when running in flatpak, we merge the TLS key log in a copy of the capture file
+pub fn get_tls_keylog_injected_pcap_path() -> PathBuf {
+    let mut pcap_path = data_folder().unwrap();
+    pcap_path.push(format!("hotwire-keylog-{}.pcapng", std::process::id()));
+    pcap_path
+}
+
+/// the TLS key logs that were used to open capture files, so that
+/// we can use them again when reopening the captures from the recent files
+#[derive(Deserialize, Serialize, Default)]
+struct RecentTlsKeylogs {
+    keylogs: HashMap<String, String>,
+}
+
+fn recent_tls_keylogs_path() -> Result<PathBuf> {
+    Ok(data_folder()?.join("recent_tls_keylogs.toml"))
+}
+
+fn read_recent_tls_keylogs_file() -> Result<RecentTlsKeylogs> {
+    let keylogs_file = recent_tls_keylogs_path()?;
+    if !keylogs_file.is_file() {
+        return Ok(RecentTlsKeylogs::default());
+    }
+    let mut contents = String::new();
+    File::open(keylogs_file)?.read_to_string(&mut contents)?;
+    Ok(toml::from_str(&contents)?)
+}
+
+/// capture file => TLS key log file
+pub fn read_recent_tls_keylogs() -> Result<HashMap<PathBuf, PathBuf>> {
+    Ok(read_recent_tls_keylogs_file()?
+        .keylogs
+        .into_iter()
+        .map(|(capture, keylog)| (PathBuf::from(capture), PathBuf::from(keylog)))
+        .collect())
+}
+
+pub fn save_recent_tls_keylog(capture: &Path, keylog: &Path) -> Result<()> {
+    let mut recent = read_recent_tls_keylogs_file()?;
+    let capture_str = capture.to_string_lossy().to_string();
+    if recent.keylogs.get(&capture_str).map(Path::new) == Some(keylog) {
+        return Ok(());
+    }
+    recent
+        .keylogs
+        .insert(capture_str, keylog.to_string_lossy().to_string());
+    // forget about the captures which were deleted since
+    recent.keylogs.retain(|c, _| Path::new(c).is_file());
+    let mut file = File::create(recent_tls_keylogs_path()?)?;
+    file.write_all(toml::to_string_pretty(&recent)?.as_bytes())?;
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::config::Config;
 use crate::icons::Icon;
 use crate::search_expr;
 use crate::tshark_communication::{NetworkPort, TSharkPacket, TcpStreamId};
@@ -5,9 +6,11 @@ use crate::widgets::win;
 use crate::BgFunc;
 use gtk::prelude::*;
 use std::net::IpAddr;
+use std::str::FromStr;
 use std::sync::mpsc;
+use strum_macros::{EnumString, EnumVariantNames};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 pub struct ClientServerInfo {
     // need to say who is the server. i have 50:50 chance
     // that the first message that was capture is from the
@@ -20,27 +23,53 @@ pub struct ClientServerInfo {
 
 pub const TREE_STORE_STREAM_ID_COL_IDX: u32 = 2;
 pub const TREE_STORE_MESSAGE_INDEX_COL_IDX: u32 = 3;
+/// all the stores keep the message timestamp in nanoseconds in that column
+pub const TREE_STORE_TIMESTAMP_NANOS_COL_IDX: u32 = 5;
 
-/// A custom streams store parses, stores & displays messages related to
-/// a certain protocol, for instance HTTP. The custom streams store deals with
-/// parsing packets, storing them by TCP stream as well as displaying them.
-///
-/// The first step is to take TSharkPackets and build streams (list of
-/// messages and meta-data) from it.
-///
-/// So, one TSharkPacket may cause you to create zero, one, or multiple
-/// messages in your stream. You also must try to populate the
-/// ClientServerInfo, letting hotwire know who is the server and who is
-/// the client, based on your protocol knowledge.
-/// Because we can parse realtime traffic that is being recorded live,
-/// you don't get the list of packets all at once, rather you can store
-/// your state in your 'globals' object, and you get fed new packets
-/// through 'add_to_packet'. After the last packet, 'finish_stream' will
-/// be called for you to clean up your state.
-///
-/// Then there are methods for populating the GUI treeview, the details
-/// area, and others.
-pub trait CustomStreamsStore {
+/// filter keys that every store supports: they're evaluated from
+/// the ClientServerInfo and the message timestamps, see `common_matches_filter`
+#[derive(EnumString, EnumVariantNames)]
+pub enum CommonStringFilterKeys {
+    #[strum(serialize = "ip.client")]
+    ClientIp,
+    #[strum(serialize = "ip.server")]
+    ServerIp,
+}
+
+#[derive(EnumString, EnumVariantNames)]
+pub enum CommonNumericFilterKeys {
+    // the server port: the client port is usually random
+    #[strum(serialize = "tcp.port")]
+    ServerPort,
+    #[strum(serialize = "tcp.stream")]
+    StreamId,
+    // milliseconds since the epoch, the search parser converts timestamps
+    #[strum(serialize = "time")]
+    Time,
+}
+
+/// statistics over the messages of a server, grouped by endpoint or
+/// query for instance. See `CustomStreamsStore::streams_stats`
+pub struct StatsTable {
+    pub column_titles: Vec<&'static str>,
+    pub rows: Vec<StatsRow>,
+}
+
+pub struct StatsRow {
+    pub cells: Vec<StatsCell>,
+    /// a search expression matching the messages of that row
+    pub search_filter: String,
+}
+
+pub enum StatsCell {
+    Text(String),
+    /// sorted by the value, displayed with the string
+    Number(u64, String),
+}
+
+/// The parsing part of a custom streams store: everything that
+/// doesn't involve GTK. See `CustomStreamsStore`.
+pub trait CustomStreamsParser {
     fn is_my_message(&self, msg: &TSharkPacket) -> bool;
 
     /// by restricting tshark to only the packets we can decode,
@@ -48,8 +77,6 @@ pub trait CustomStreamsStore {
     /// for your protocol (for instance 'http2', 'pgsql' and so on)
     fn tshark_filter_string(&self) -> &'static str;
 
-    fn protocol_icon(&self) -> Icon;
-
     fn protocol_name(&self) -> &'static str;
 
     fn tcp_stream_ids(&self) -> Vec<TcpStreamId>;
@@ -73,17 +100,61 @@ pub trait CustomStreamsStore {
     ) -> Result<Option<ClientServerInfo>, String>;
     fn finish_stream(&mut self, stream_id: TcpStreamId) -> Result<(), String>;
 
+    // headless mode
+    /// the messages of that stream, for the --dump command-line mode
+    fn stream_messages_json(&self, stream_id: TcpStreamId) -> Vec<serde_json::Value>;
+
+    /// HAR 1.2 entries for the messages of that stream, only makes sense for HTTP
+    fn stream_har_entries(&self, _stream_id: TcpStreamId) -> Vec<serde_json::Value> {
+        vec![]
+    }
+}
+
+/// A custom streams store parses, stores & displays messages related to
+/// a certain protocol, for instance HTTP. The custom streams store deals with
+/// parsing packets, storing them by TCP stream as well as displaying them.
+///
+/// The first step is to take TSharkPackets and build streams (list of
+/// messages and meta-data) from it.
+///
+/// So, one TSharkPacket may cause you to create zero, one, or multiple
+/// messages in your stream. You also must try to populate the
+/// ClientServerInfo, letting hotwire know who is the server and who is
+/// the client, based on your protocol knowledge.
+/// Because we can parse realtime traffic that is being recorded live,
+/// you don't get the list of packets all at once, rather you can store
+/// your state in your 'globals' object, and you get fed new packets
+/// through 'add_to_packet'. After the last packet, 'finish_stream' will
+/// be called for you to clean up your state.
+///
+/// Then there are methods for populating the GUI treeview, the details
+/// area, and others.
+///
+/// The parsing half lives in `CustomStreamsParser`, which doesn't need
+/// GTK, so that we can also decode captures in headless mode.
+pub trait CustomStreamsStore: CustomStreamsParser {
+    fn protocol_icon(&self) -> Icon;
+
+    /// called before we load a capture, with the current preferences
+    fn apply_config(&mut self, _config: &Config) {}
+
+    /// how many messages of that stream took longer than the slow
+    /// threshold from the preferences, for protocols where that makes sense
+    fn stream_slow_message_count(&self, _stream_id: TcpStreamId) -> usize {
+        0
+    }
+
     // treeview
     fn prepare_treeview(&self, tv: &gtk::TreeView);
-    fn get_empty_liststore(&self) -> gtk::ListStore;
+    fn get_empty_treestore(&self) -> gtk::TreeStore;
     fn populate_treeview(
         &self,
-        ls: &gtk::ListStore,
+        ts: &gtk::TreeStore,
         session_id: TcpStreamId,
         start_idx: usize,
         item_count: usize,
     );
-    fn end_populate_treeview(&self, tv: &gtk::TreeView, ls: &gtk::ListStore);
+    fn end_populate_treeview(&self, tv: &gtk::TreeView, ts: &gtk::TreeStore);
 
     fn display_in_details_widget(
         &self,
@@ -102,6 +173,12 @@ pub trait CustomStreamsStore {
         win_msg_sender: relm::StreamHandle<win::Msg>,
     );
 
+    /// statistics over the messages of these streams, for the
+    /// statistics window. None if that doesn't make sense for the protocol
+    fn streams_stats(&self, _stream_ids: &[TcpStreamId]) -> Option<StatsTable> {
+        None
+    }
+
     // search
     fn supported_string_filter_keys(&self) -> &'static [&'static str];
     fn supported_numeric_filter_keys(&self) -> &'static [&'static str];
@@ -127,8 +204,46 @@ pub fn get_message_helper(model: &gtk::TreeModel, iter: &gtk::TreeIter) -> (TcpS
     (stream_id, idx)
 }
 
+/// evaluate the filter keys that every store supports.
+/// None if that's not one of the common filter keys.
+pub fn common_matches_filter(
+    store: &dyn CustomStreamsStore,
+    filter: &search_expr::SearchOpExpr,
+    model: &gtk::TreeModel,
+    iter: &gtk::TreeIter,
+) -> Option<bool> {
+    let (stream_id, _) = get_message_helper(model, iter);
+    let client_server = || store.stream_client_server(stream_id);
+    let op = &filter.op;
+    if let Ok(filter_key) = CommonStringFilterKeys::from_str(filter.filter_key) {
+        return Some(client_server().map_or(false, |cs| {
+            op.matches_str(
+                &match filter_key {
+                    CommonStringFilterKeys::ClientIp => cs.client_ip,
+                    CommonStringFilterKeys::ServerIp => cs.server_ip,
+                }
+                .to_string(),
+            )
+        }));
+    }
+    let filter_key = CommonNumericFilterKeys::from_str(filter.filter_key).ok()?;
+    Some(match filter_key {
+        CommonNumericFilterKeys::ServerPort => client_server().map_or(false, |cs| {
+            op.matches_number(cs.server_port.as_u16() as usize, 1)
+        }),
+        CommonNumericFilterKeys::StreamId => op.matches_number(stream_id.as_u32() as usize, 1),
+        CommonNumericFilterKeys::Time => {
+            let nanos = model
+                .value(iter, TREE_STORE_TIMESTAMP_NANOS_COL_IDX as i32)
+                .get::<i64>()
+                .unwrap_or(0);
+            op.matches_number((nanos / 1_000_000).max(0) as usize, 1)
+        }
+    })
+}
+
 #[cfg(test)]
-pub fn common_tests_parse_stream<S: CustomStreamsStore>(
+pub fn common_tests_parse_stream<S: CustomStreamsParser>(
     parser: &mut S,
     packets: Result<Vec<TSharkPacket>, String>,
 ) -> Result<TcpStreamId, String> {
@@ -0,0 +1,136 @@
+// command-line mode, where we don't open any window: we parse a capture file
+// and print the decoded messages to stdout. Useful to grep or diff
+// the conversations, for instance in CI.
+use crate::config::{Config, InputBackend};
+use crate::native_capture;
+use crate::packets_read::{self, InputStep, ParseInputStep, TSharkInputType};
+use crate::streams::Streams;
+use crate::tshark_communication::{self, TcpStreamId};
+use std::path::Path;
+use std::process::Child;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+use strum_macros::EnumString;
+
+#[derive(EnumString, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DumpFormat {
+    #[strum(serialize = "json")]
+    Json,
+}
+
+pub const USAGE: &str = "Usage: hotwire --dump <capture file> [--format json]";
+
+/// parse the parameters following --dump, then dump the capture
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut fname = None;
+    let mut format = DumpFormat::Json;
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--format" => {
+                let f = it.next().ok_or_else(|| USAGE.to_string())?;
+                format = DumpFormat::from_str(f)
+                    .map_err(|_| format!("Unsupported dump format: {}\n{}", f, USAGE))?;
+            }
+            _ if fname.is_none() => fname = Some(tshark_communication::string_to_path(arg)),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let fname = fname.ok_or_else(|| USAGE.to_string())?;
+    let streams = parse_capture(&fname)?;
+    match format {
+        DumpFormat::Json => println!("{}", dump_json(&streams)),
+    }
+    Ok(())
+}
+
+/// the same steps as the GUI when loading a file, minus the display
+fn parse_capture(fname: &Path) -> Result<Streams, String> {
+    // no GUI to display errors: fall back to the defaults
+    let config = Config::read_config_file().unwrap_or_else(|e| {
+        eprintln!("Error loading the configuration, using the defaults: {}", e);
+        Config::default_config()
+    });
+    let mut streams = Streams::default();
+    streams.apply_config(&config);
+    let filter = streams.tshark_filter_string();
+    let (sender, receiver) = mpsc::channel();
+    let fname_thread = fname.to_path_buf();
+    let input_backend = config.input_backend;
+    let output_format = config.tshark_output_format;
+    thread::spawn(move || match input_backend {
+        InputBackend::Native => {
+            native_capture::read_capture_file(&fname_thread, &filter, output_format, None, sender)
+        }
+        InputBackend::TShark => packets_read::invoke_tshark(
+            TSharkInputType::File,
+            &fname_thread,
+            &filter,
+            output_format,
+            None,
+            sender,
+        ),
+    });
+    let mut tshark_child = None;
+    let read_result = read_packets(&mut streams, receiver, &mut tshark_child);
+    if let Some(mut child) = tshark_child {
+        if read_result.is_err() {
+            // we stopped reading, tshark may still be running
+            let _ = child.kill();
+        }
+        if let Err(e) = child.wait() {
+            eprintln!("Error waiting for tshark: {}", e);
+        }
+    }
+    read_result?;
+    for stream_id in streams.tcp_stream_ids() {
+        streams
+            .finish_stream(stream_id)
+            .map_err(|msg| format!("Error parsing file, in stream {}: {}", stream_id, msg))?;
+    }
+    Ok(streams)
+}
+
+/// the tshark child is returned even if we fail, so that the caller can reap it
+fn read_packets(
+    streams: &mut Streams,
+    receiver: mpsc::Receiver<ParseInputStep>,
+    tshark_child: &mut Option<Child>,
+) -> Result<(), String> {
+    for step in receiver {
+        match step? {
+            InputStep::StartedTShark(child) => *tshark_child = Some(child),
+            InputStep::Packet(p) => {
+                let tcp_stream_id = p.basic_info.tcp_stream_id;
+                streams.handle_got_packet(*p).map_err(|msg| {
+                    format!("Error parsing file, in stream {}: {}", tcp_stream_id, msg)
+                })?;
+            }
+            InputStep::Eof => break,
+        }
+    }
+    Ok(())
+}
+
+fn dump_json(streams: &Streams) -> String {
+    let mut stream_ids: Vec<TcpStreamId> = streams.tcp_stream_ids();
+    stream_ids.sort_by_key(|s| s.0);
+    let dumped_streams: Vec<_> = stream_ids
+        .into_iter()
+        .filter_map(|stream_id| {
+            let store = streams.get_streams_store(streams.get_store_index(stream_id)?);
+            let client_server = store.stream_client_server(stream_id);
+            Some(serde_json::json!({
+                "protocol": store.protocol_name(),
+                "tcp_stream_id": stream_id.0,
+                "client_ip": client_server.map(|cs| cs.client_ip.to_string()),
+                "server_ip": client_server.map(|cs| cs.server_ip.to_string()),
+                "server_port": client_server.map(|cs| cs.server_port.0),
+                "summary": store.stream_summary_details(stream_id),
+                "messages": store.stream_messages_json(stream_id),
+            }))
+        })
+        .collect();
+    serde_json::to_string_pretty(&dumped_streams).unwrap()
+}
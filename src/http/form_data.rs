@@ -0,0 +1,223 @@
+// the parameters of a request: the query string, and the form bodies,
+// application/x-www-form-urlencoded & multipart/form-data
+// https://www.rfc-editor.org/rfc/rfc7578
+use super::http_streams_store::{self, HttpBody, HttpRequestResponseData};
+use std::str;
+
+/// a form field. For multipart bodies, a part with its headers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormField {
+    pub name: String,
+    // only for multipart bodies
+    pub headers: Vec<(String, String)>,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub data: Vec<u8>,
+}
+
+impl FormField {
+    fn from_name_value(name: String, value: String) -> FormField {
+        FormField {
+            name,
+            headers: vec![],
+            filename: None,
+            content_type: None,
+            data: value.into_bytes(),
+        }
+    }
+
+    pub fn value_as_str(&self) -> Option<&str> {
+        str::from_utf8(&self.data).ok()
+    }
+
+    /// for file uploads we use the file name as the value
+    fn param_value(&self) -> Option<String> {
+        self.filename
+            .clone()
+            .or_else(|| self.value_as_str().map(|s| s.to_string()))
+    }
+}
+
+pub fn percent_decode(input: &str, plus_as_space: bool) -> String {
+    let mut bytes = vec![];
+    let mut rest = input.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        match tail
+            .get(..2)
+            .filter(|_| b == b'%')
+            .and_then(|h| str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok())
+        {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(if plus_as_space && b == b'+' { b' ' } else { b });
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// "a=1&b=2": the query string, or a x-www-form-urlencoded body
+pub fn parse_urlencoded(input: &str) -> Vec<(String, String)> {
+    input
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (name, value) = p.split_once('=').unwrap_or((p, ""));
+            (percent_decode(name, true), percent_decode(value, true))
+        })
+        .collect()
+}
+
+/// the parameters from a request line like "GET /path?a=1&b=2 HTTP/1.1"
+pub fn query_params(request_line: &str) -> Vec<(String, String)> {
+    request_line
+        .split(' ')
+        .nth(1)
+        .and_then(|url| url.split_once('?'))
+        .map(|(_, query)| query.split('#').next().unwrap_or(""))
+        .map(parse_urlencoded)
+        .unwrap_or_default()
+}
+
+/// a parameter from a header like `form-data; name="file"; filename="a.png"`
+pub fn header_param<'a>(header_value: &'a str, param_name: &str) -> Option<&'a str> {
+    header_value
+        .split(';')
+        .skip(1)
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case(param_name))
+        .map(|(_, v)| v.trim().trim_matches('"'))
+}
+
+/// None if the body is incomplete or not multipart
+pub fn parse_multipart(body: &[u8], boundary: &str) -> Option<Vec<FormField>> {
+    let delimiter = format!("--{}", boundary);
+    let part_end = format!("\r\n--{}", boundary);
+    let mut pos = find_subslice(body, delimiter.as_bytes())? + delimiter.len();
+    let mut fields = vec![];
+    loop {
+        let rest = &body[pos..];
+        if rest.starts_with(b"--") {
+            // the closing delimiter
+            return Some(fields);
+        }
+        let rest = rest.strip_prefix(b"\r\n")?;
+        // a part may have no headers
+        let (headers_end, data_start) = if rest.starts_with(b"\r\n") {
+            (0, 2)
+        } else {
+            let end = find_subslice(rest, b"\r\n\r\n")?;
+            (end, end + 4)
+        };
+        let headers: Vec<(String, String)> = str::from_utf8(&rest[..headers_end])
+            .ok()?
+            .lines()
+            .filter_map(|l| l.split_once(':'))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+        let data_len = find_subslice(&rest[data_start..], part_end.as_bytes())?;
+        let disposition =
+            http_streams_store::get_http_header_value(&headers, "Content-Disposition");
+        fields.push(FormField {
+            name: disposition
+                .and_then(|d| header_param(d, "name"))
+                .unwrap_or("")
+                .to_string(),
+            filename: disposition
+                .and_then(|d| header_param(d, "filename"))
+                .map(|f| f.to_string()),
+            content_type: http_streams_store::get_http_header_value(&headers, "Content-Type")
+                .cloned(),
+            data: rest[data_start..data_start + data_len].to_vec(),
+            headers,
+        });
+        pos = body.len() - rest.len() + data_start + data_len + part_end.len();
+    }
+}
+
+fn find_subslice(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|w| w == needle)
+}
+
+/// the fields of a form body, None if the body is not a form
+pub fn form_fields(data: &HttpRequestResponseData) -> Option<Vec<FormField>> {
+    let content_type = data.content_type.as_deref()?;
+    let mime_type = content_type.split(';').next().unwrap_or("").trim();
+    if mime_type.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+        let body = data.body_as_str()?;
+        Some(
+            parse_urlencoded(body.trim_end())
+                .into_iter()
+                .map(|(name, value)| FormField::from_name_value(name, value))
+                .collect(),
+        )
+    } else if mime_type.eq_ignore_ascii_case("multipart/form-data") {
+        let boundary = header_param(content_type, "boundary")?;
+        match &data.body {
+            HttpBody::Text(t) => parse_multipart(t.as_bytes(), boundary),
+            HttpBody::Binary(b) => parse_multipart(b, boundary),
+            HttpBody::Missing => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// the query string parameters & form fields of a request, as name & value
+pub fn request_params(request: &HttpRequestResponseData) -> Vec<(String, String)> {
+    let mut params = query_params(&request.first_line);
+    params.extend(
+        form_fields(request)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|f| Some((f.name.clone(), f.param_value()?))),
+    );
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_query_and_urlencoded_params() {
+        assert_eq!(
+            vec![
+                ("q".to_string(), "hot wire".to_string()),
+                ("page".to_string(), "2".to_string()),
+                ("flag".to_string(), "".to_string()),
+                ("a&b".to_string(), "c=d".to_string()),
+            ],
+            query_params("GET /search?q=hot+wire&page=2&flag&a%26b=c%3Dd#top HTTP/1.1")
+        );
+        assert!(query_params("GET /search HTTP/1.1").is_empty());
+    }
+
+    #[test]
+    fn should_parse_multipart_body() {
+        let body = "preamble\r\n--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nmy photo\r\n\
+             --XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.png\"\r\n\
+             Content-Type: image/png\r\n\r\n\u{1}\u{2}\r\n--XyZ--\r\n";
+        let fields = parse_multipart(body.as_bytes(), "XyZ").unwrap();
+        assert_eq!(2, fields.len());
+        assert_eq!("title", fields[0].name);
+        assert_eq!(Some("my photo"), fields[0].value_as_str());
+        assert_eq!(None, fields[0].filename);
+        assert_eq!("file", fields[1].name);
+        assert_eq!(Some("a.png".to_string()), fields[1].filename);
+        assert_eq!(Some("image/png".to_string()), fields[1].content_type);
+        assert_eq!(vec![1, 2], fields[1].data);
+        assert_eq!(2, fields[1].headers.len());
+        // incomplete body
+        assert_eq!(None, parse_multipart(&body.as_bytes()[..60], "XyZ"));
+        assert_eq!(
+            Some("XyZ"),
+            header_param("multipart/form-data; boundary=XyZ", "boundary")
+        );
+    }
+}
@@ -0,0 +1,269 @@
+// http://www.softwareishard.com/blog/har-12-spec/
+use super::http_streams_store::{self, HttpBody, HttpMessageData, HttpRequestResponseData};
+use crate::custom_streams_store::ClientServerInfo;
+use crate::tshark_communication::TcpStreamId;
+use chrono::{Local, NaiveDateTime, SecondsFormat, TimeZone};
+use serde_json::{json, Value};
+
+pub fn har_document(entries: Vec<Value>) -> Value {
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": {
+                "name": "Hotwire",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "pages": [],
+            "entries": entries,
+        }
+    })
+}
+
+/// None if we don't have the request (we can't build a HAR entry
+/// without a request)
+pub fn har_entry(
+    msg: &HttpMessageData,
+    tcp_stream_id: TcpStreamId,
+    client_server: &ClientServerInfo,
+) -> Option<Value> {
+    if msg.websocket.is_some() {
+        // HAR has no room for websocket messages
+        return None;
+    }
+    let request = msg.request.as_ref()?;
+    let wait_ms = msg
+        .response
+        .as_ref()
+        .map(|r| duration_ms(request.timestamp, r.timestamp))
+        .unwrap_or(0.0);
+    Some(json!({
+        "startedDateTime": iso8601(request.timestamp),
+        "time": wait_ms,
+        "request": har_request(request, client_server),
+        "response": msg
+            .response
+            .as_ref()
+            .map(har_response)
+            .unwrap_or_else(har_missing_response),
+        "cache": {},
+        "timings": {
+            "send": 0,
+            "wait": wait_ms,
+            "receive": 0,
+        },
+        "serverIPAddress": client_server.server_ip.to_string(),
+        "connection": tcp_stream_id.0.to_string(),
+    }))
+}
+
+fn iso8601(timestamp: NaiveDateTime) -> String {
+    // both tshark and the native reader give us local times, without the zone
+    let offset = match Local.from_local_datetime(&timestamp).earliest() {
+        Some(dt) => *dt.offset(),
+        // a time skipped by a DST change, can't happen for a real capture
+        None => Local.offset_from_utc_datetime(&timestamp),
+    };
+    offset
+        .from_local_datetime(&timestamp)
+        .unwrap()
+        .to_rfc3339_opts(SecondsFormat::Millis, false)
+}
+
+fn duration_ms(from: NaiveDateTime, to: NaiveDateTime) -> f64 {
+    (to - from)
+        .num_microseconds()
+        .map(|us| us as f64 / 1000.0)
+        .unwrap_or(0.0)
+}
+
+fn is_http2(data: &HttpRequestResponseData) -> bool {
+    data.headers.iter().any(|(k, _)| k.starts_with(':'))
+}
+
+fn http_version(data: &HttpRequestResponseData, version_from_first_line: Option<&str>) -> String {
+    if is_http2(data) {
+        "HTTP/2.0".to_string()
+    } else {
+        version_from_first_line.unwrap_or("HTTP/1.1").to_string()
+    }
+}
+
+fn har_headers(data: &HttpRequestResponseData) -> Vec<Value> {
+    data.headers
+        .iter()
+        .map(|(k, v)| json!({"name": k, "value": v}))
+        .collect()
+}
+
+fn body_size(body: &HttpBody) -> usize {
+    match body {
+        HttpBody::Text(s) => s.len(),
+        HttpBody::Binary(b) => b.len(),
+        HttpBody::Missing => 0,
+    }
+}
+
+/// the body as text if possible, or base64 for binary contents
+fn har_text(data: &HttpRequestResponseData) -> (Option<String>, Option<&'static str>) {
+    match (data.body_as_str(), &data.body) {
+        (Some(s), _) => (Some(s.to_string()), None),
+        (None, HttpBody::Binary(b)) => (Some(base64::encode(b)), Some("base64")),
+        (None, _) => (None, None),
+    }
+}
+
+fn har_request(request: &HttpRequestResponseData, client_server: &ClientServerInfo) -> Value {
+    let (method, path) = request.request_method_and_path();
+    let query_string: Vec<_> = path
+        .split_once('?')
+        .map(|(_, q)| {
+            q.split('&')
+                .filter(|p| !p.is_empty())
+                .map(|p| {
+                    let (name, value) = p.split_once('=').unwrap_or((p, ""));
+                    json!({"name": name, "value": value})
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut result = json!({
+        "method": method,
+        "url": request.request_url(client_server.server_ip, client_server.server_port),
+        "httpVersion": http_version(request, request.first_line.split(' ').nth(2)),
+        "cookies": [],
+        "headers": har_headers(request),
+        "queryString": query_string,
+        "headersSize": -1,
+        "bodySize": body_size(&request.body),
+    });
+    if let (Some(text), encoding) = har_text(request) {
+        result["postData"] = json!({
+            "mimeType": request.content_type.as_deref().unwrap_or(""),
+            "text": text,
+        });
+        if let Some(e) = encoding {
+            result["postData"]["encoding"] = json!(e);
+        }
+    }
+    result
+}
+
+fn har_response(response: &HttpRequestResponseData) -> Value {
+    let mut first_line_parts = response.first_line.splitn(3, ' ');
+    let version = first_line_parts.next();
+    let status_from_first_line = first_line_parts.next();
+    let status_text = first_line_parts.next().unwrap_or("");
+    let status = http_streams_store::get_http_header_value(&response.headers, ":status")
+        .map(|s| s.as_str())
+        .or(status_from_first_line)
+        .and_then(|s| s.parse::<u16>().ok())
+        .unwrap_or(0);
+    let (text, encoding) = har_text(response);
+    let mut content = json!({
+        "size": body_size(&response.body),
+        "mimeType": response.content_type.as_deref().unwrap_or(""),
+    });
+    if let Some(t) = text {
+        content["text"] = json!(t);
+    }
+    if let Some(e) = encoding {
+        content["encoding"] = json!(e);
+    }
+    json!({
+        "status": status,
+        "statusText": if is_http2(response) { "" } else { status_text },
+        "httpVersion": http_version(response, version),
+        "cookies": [],
+        "headers": har_headers(response),
+        "content": content,
+        "redirectURL": http_streams_store::get_http_header_value(&response.headers, "Location")
+            .map(|s| s.as_str())
+            .unwrap_or(""),
+        "headersSize": -1,
+        "bodySize": body_size(&response.body),
+    })
+}
+
+// browsers also export requests that never got a response,
+// with status 0
+fn har_missing_response() -> Value {
+    json!({
+        "status": 0,
+        "statusText": "",
+        "httpVersion": "",
+        "cookies": [],
+        "headers": [],
+        "content": {
+            "size": 0,
+            "mimeType": "",
+        },
+        "redirectURL": "",
+        "headersSize": -1,
+        "bodySize": -1,
+    })
+}
+
+#[cfg(test)]
+use {
+    super::http_streams_store::ContentEncoding, crate::tshark_communication::NetworkPort,
+    crate::tshark_communication::TcpSeqNumber, chrono::NaiveDate,
+};
+
+#[test]
+fn should_build_har_entry() {
+    let msg = HttpMessageData {
+        http_stream_id: 0,
+        request: Some(HttpRequestResponseData {
+            tcp_stream_no: TcpStreamId(4),
+            tcp_seq_number: TcpSeqNumber(1),
+            timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms_milli(8, 49, 52, 100),
+            first_line: "GET /test?a=1&b HTTP/1.1".to_string(),
+            headers: vec![("Host".to_string(), "example.com".to_string())],
+            body: HttpBody::Missing,
+            content_type: None,
+            content_encoding: ContentEncoding::Plain,
+            trailers: vec![],
+        }),
+        response: Some(HttpRequestResponseData {
+            tcp_stream_no: TcpStreamId(4),
+            tcp_seq_number: TcpSeqNumber(2),
+            timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms_milli(8, 49, 52, 350),
+            first_line: "HTTP/1.1 404 Not Found".to_string(),
+            headers: vec![],
+            body: HttpBody::Binary(vec![1, 2, 3]),
+            content_type: Some("application/octet-stream".to_string()),
+            content_encoding: ContentEncoding::Plain,
+            trailers: vec![],
+        }),
+        http2_stream_end: None,
+        websocket: None,
+    };
+    let client_server = ClientServerInfo {
+        server_ip: "10.0.0.1".parse().unwrap(),
+        server_port: NetworkPort(80),
+        client_ip: "10.0.0.2".parse().unwrap(),
+    };
+    let entry = har_entry(&msg, TcpStreamId(4), &client_server).unwrap();
+    let started = entry["startedDateTime"].as_str().unwrap();
+    assert_eq!(
+        NaiveDate::from_ymd(2021, 3, 5).and_hms_milli(8, 49, 52, 100),
+        chrono::DateTime::parse_from_rfc3339(started)
+            .unwrap()
+            .with_timezone(&Local)
+            .naive_local()
+    );
+    assert!(started.starts_with("2021-03-05T08:49:52.100"));
+    assert_eq!(json!(250.0), entry["time"]);
+    assert_eq!(
+        json!("http://example.com/test?a=1&b"),
+        entry["request"]["url"]
+    );
+    assert_eq!(
+        json!([{"name": "a", "value": "1"}, {"name": "b", "value": ""}]),
+        entry["request"]["queryString"]
+    );
+    assert_eq!(json!(404), entry["response"]["status"]);
+    assert_eq!(json!("Not Found"), entry["response"]["statusText"]);
+    assert_eq!(json!("AQID"), entry["response"]["content"]["text"]);
+    assert_eq!(json!("base64"), entry["response"]["content"]["encoding"]);
+}
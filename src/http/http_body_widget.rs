@@ -1,6 +1,9 @@
 use super::code_formatting;
+use super::form_data::{self, FormField};
 use super::http_streams_store;
 use super::http_streams_store::{HttpBody, HttpRequestResponseData};
+use crate::http2::grpc;
+use crate::http2::protobuf::ProtoSchema;
 use crate::widgets::win;
 use crate::BgFunc;
 use gdk_pixbuf::prelude::*;
@@ -8,11 +11,12 @@ use gtk::prelude::*;
 use relm::Widget;
 use relm_derive::{widget, Msg};
 use std::borrow::Cow;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
 
 const TEXT_CONTENTS_STACK_NAME: &str = "text";
 const IMAGE_CONTENTS_STACK_NAME: &str = "image";
 const BINARY_CONTENTS_STACK_NAME: &str = "binary";
+const FORM_CONTENTS_STACK_NAME: &str = "form";
 
 const KNOWN_CONTENT_TYPE_PREFIXES: &[(&str, &str)] = &[
     ("image/", "image"),
@@ -34,9 +38,12 @@ pub enum Msg {
         request_first_line_if_response: Option<String>,
     },
     SaveContents,
+    SaveFormField(usize),
+    ProtoSchemaChanged(Option<Arc<ProtoSchema>>),
 }
 
 pub struct Model {
+    relm: relm::Relm<HttpBodyWidget>,
     win_msg_sender: relm::StreamHandle<win::Msg>,
 
     format_code: bool,
@@ -44,6 +51,15 @@ pub struct Model {
 
     request_first_line_if_response: Option<String>,
 
+    proto_schema: Option<Arc<ProtoSchema>>,
+    /// the decoded messages, if this is a gRPC body
+    grpc_contents: Option<String>,
+
+    /// the query string parameters, if this is a request
+    query_params: Vec<(String, String)>,
+    /// the fields, if the body is a form
+    form_fields: Option<Vec<FormField>>,
+
     _saved_body_channel: relm::Channel<SavedBodyData>,
     saved_body_sender: relm::Sender<SavedBodyData>,
 }
@@ -59,7 +75,7 @@ impl Widget for HttpBodyWidget {
     }
 
     fn model(
-        _relm: &relm::Relm<Self>,
+        relm: &relm::Relm<Self>,
         params: (relm::StreamHandle<win::Msg>, mpsc::Sender<BgFunc>),
     ) -> Model {
         let (win_msg_sender, _bg_sender) = params;
@@ -73,10 +89,15 @@ impl Widget for HttpBodyWidget {
             })
         };
         Model {
+            relm: relm.clone(),
             win_msg_sender,
             format_code: true,
             data: None,
             request_first_line_if_response: None,
+            proto_schema: None,
+            grpc_contents: None,
+            query_params: vec![],
+            form_fields: None,
             _saved_body_channel,
             saved_body_sender,
         }
@@ -87,6 +108,10 @@ impl Widget for HttpBodyWidget {
         match event {
             Msg::FormatCodeChanged(format_code) => {
                 self.model.format_code = format_code;
+                self.refresh_form_fields();
+            }
+            Msg::ProtoSchemaChanged(schema) => {
+                self.model.proto_schema = schema;
             }
             Msg::RequestResponseChanged {
                 http_data,
@@ -98,6 +123,15 @@ impl Widget for HttpBodyWidget {
                 // child after that, if needed.
                 self.model.data = http_data.clone();
                 self.model.request_first_line_if_response = request_first_line_if_response;
+                self.model.grpc_contents = self.format_grpc_contents();
+                self.model.query_params =
+                    match (&http_data, &self.model.request_first_line_if_response) {
+                        (Some(d), None) => form_data::query_params(&d.first_line),
+                        _ => vec![],
+                    };
+                self.model.form_fields = http_data.as_ref().and_then(form_data::form_fields);
+                self.refresh_query_params();
+                self.refresh_form_fields();
 
                 // need to try to decode as string.. the content-type may not be
                 // populated or be too exotic, and binary contents don't mean much
@@ -114,6 +148,17 @@ impl Widget for HttpBodyWidget {
                     {
                         self.display_image(bytes);
                     }
+                    _ if self.model.grpc_contents.is_some() => {
+                        self.widgets.too_long_header.set_visible(false);
+                        self.widgets
+                            .contents_stack
+                            .set_visible_child_name(TEXT_CONTENTS_STACK_NAME);
+                    }
+                    _ if self.model.form_fields.is_some() => {
+                        self.widgets
+                            .contents_stack
+                            .set_visible_child_name(FORM_CONTENTS_STACK_NAME);
+                    }
                     (_, _, false) => {
                         self.widgets
                             .contents_stack
@@ -136,40 +181,168 @@ impl Widget for HttpBodyWidget {
                 }
             }
             Msg::SaveContents => {
-                let dialog = gtk::builders::FileChooserNativeBuilder::new()
-                    .action(gtk::FileChooserAction::Save)
-                    .title("Export to...")
-                    .do_overwrite_confirmation(true)
-                    .modal(true)
-                    .build();
-                dialog.set_current_name(&self.body_save_filename());
-                if dialog.run() == gtk::ResponseType::Accept {
-                    let target_fname = dialog.filename().unwrap(); // ## unwrap
-                    self.model.win_msg_sender.emit(win::Msg::InfoBarShow(
-                        Some(format!(
-                            "Saving to file {}",
-                            &target_fname.to_string_lossy()
-                        )),
-                        win::InfobarOptions::ShowSpinner,
-                    ));
-                    self.model
-                        .saved_body_sender
-                        .send(SavedBodyData {
-                            error_msg: std::fs::write(
-                                target_fname,
-                                match self.model.data.as_ref().map(|d| &d.body) {
-                                    Some(HttpBody::Binary(ref bytes)) => bytes,
-                                    Some(HttpBody::Text(ref txt)) => txt.as_bytes(),
-                                    _ => &[],
-                                },
-                            )
-                            .err()
-                            .map(|e| e.to_string()),
-                        })
-                        .unwrap()
+                self.save_contents(
+                    &self.body_save_filename(),
+                    match self.model.data.as_ref().map(|d| &d.body) {
+                        Some(HttpBody::Binary(ref bytes)) => bytes,
+                        Some(HttpBody::Text(ref txt)) => txt.as_bytes(),
+                        _ => &[],
+                    },
+                );
+            }
+            Msg::SaveFormField(idx) => {
+                if let Some(field) = self.model.form_fields.as_ref().and_then(|f| f.get(idx)) {
+                    self.save_contents(
+                        &Self::save_filename(&field.headers, field.content_type.as_deref(), None),
+                        &field.data,
+                    );
+                }
+            }
+        }
+    }
+
+    fn save_contents(&self, default_filename: &str, contents: &[u8]) {
+        let dialog = gtk::builders::FileChooserNativeBuilder::new()
+            .action(gtk::FileChooserAction::Save)
+            .title("Export to...")
+            .do_overwrite_confirmation(true)
+            .modal(true)
+            .build();
+        dialog.set_current_name(default_filename);
+        if dialog.run() == gtk::ResponseType::Accept {
+            let target_fname = dialog.filename().unwrap(); // ## unwrap
+            self.model.win_msg_sender.emit(win::Msg::InfoBarShow(
+                Some(format!(
+                    "Saving to file {}",
+                    &target_fname.to_string_lossy()
+                )),
+                win::InfobarOptions::ShowSpinner,
+            ));
+            self.model
+                .saved_body_sender
+                .send(SavedBodyData {
+                    error_msg: std::fs::write(target_fname, contents)
+                        .err()
+                        .map(|e| e.to_string()),
+                })
+                .unwrap()
+        }
+    }
+
+    fn refresh_query_params(&self) {
+        let grid = &self.widgets.query_params_grid;
+        for child in grid.children() {
+            grid.remove(&child);
+        }
+        for (row, (name, value)) in self.model.query_params.iter().enumerate() {
+            let row = row as i32;
+            grid.attach(&Self::field_name_label(name), 0, row, 1, 1);
+            grid.attach(
+                &gtk::builders::LabelBuilder::new()
+                    .label(value)
+                    .xalign(0.0)
+                    .selectable(true)
+                    .wrap(true)
+                    .wrap_mode(pango::WrapMode::Char)
+                    .build(),
+                1,
+                row,
+                1,
+                1,
+            );
+        }
+        grid.show_all();
+    }
+
+    fn refresh_form_fields(&self) {
+        let fields_box = &self.widgets.form_fields_box;
+        for child in fields_box.children() {
+            fields_box.remove(&child);
+        }
+        for (idx, field) in self.model.form_fields.iter().flatten().enumerate() {
+            let field_box = gtk::builders::BoxBuilder::new()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(3)
+                .build();
+            field_box.add(&Self::field_name_label(&field.name));
+            if !field.headers.is_empty() {
+                field_box.add(
+                    &gtk::builders::LabelBuilder::new()
+                        .label(
+                            &field
+                                .headers
+                                .iter()
+                                .map(|(k, v)| format!("{}: {}", k, v))
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        )
+                        .xalign(0.0)
+                        .selectable(true)
+                        .wrap(true)
+                        .build(),
+                );
+            }
+            match field.value_as_str().filter(|_| field.filename.is_none()) {
+                Some(value) => field_box.add(
+                    &gtk::builders::LabelBuilder::new()
+                        .use_markup(true)
+                        .label(&code_formatting::highlight_indent_truncate(
+                            self.model.format_code,
+                            value,
+                            field.content_type.as_deref(),
+                        ))
+                        .xalign(0.0)
+                        .selectable(true)
+                        .wrap(true)
+                        .wrap_mode(pango::WrapMode::Char)
+                        .build(),
+                ),
+                None => {
+                    let save_btn = gtk::builders::ButtonBuilder::new()
+                        .always_show_image(true)
+                        .image(&gtk::Image::from_icon_name(
+                            Some("document-save-symbolic"),
+                            gtk::IconSize::Menu,
+                        ))
+                        .label(&format!(
+                            "Save {} ({} bytes)",
+                            field.filename.as_deref().unwrap_or("contents"),
+                            field.data.len()
+                        ))
+                        .halign(gtk::Align::Start)
+                        .build();
+                    relm::connect!(
+                        self.model.relm,
+                        save_btn,
+                        connect_clicked(_),
+                        Msg::SaveFormField(idx)
+                    );
+                    field_box.add(&save_btn);
                 }
             }
+            fields_box.add(&field_box);
         }
+        fields_box.show_all();
+    }
+
+    fn field_name_label(name: &str) -> gtk::Label {
+        let label = gtk::builders::LabelBuilder::new()
+            .label(name)
+            .xalign(0.0)
+            .valign(gtk::Align::Start)
+            .selectable(true)
+            .build();
+        label.style_context().add_class("label");
+        label
+    }
+
+    fn format_grpc_contents(&self) -> Option<String> {
+        let data = self.model.data.as_ref()?;
+        let (method, is_request) = match &self.model.request_first_line_if_response {
+            Some(request_line) => (grpc::method_from_request_line(request_line), false),
+            None => (grpc::method_from_request_line(&data.first_line), true),
+        };
+        grpc::format_grpc_body(data, method, is_request, self.model.proto_schema.as_deref())
     }
 
     fn display_image(&self, bytes: &[u8]) {
@@ -187,32 +360,30 @@ impl Widget for HttpBodyWidget {
     }
 
     fn body_save_filename(&self) -> String {
-        let attachment_name = self
-            .model
-            .data
-            .as_ref()
-            .and_then(|d| {
-                http_streams_store::get_http_header_value(&d.headers, "Content-Disposition")
-            })
-            .and_then(|d| {
-                d.strip_prefix("attachment: filename=\"")
-                    .and_then(|f| f.strip_suffix('"'))
-                    .map(|f| f.to_string())
-            });
+        let data = self.model.data.as_ref();
+        Self::save_filename(
+            data.map(|d| &d.headers[..]).unwrap_or(&[]),
+            data.and_then(|d| d.content_type.as_deref()),
+            self.model.request_first_line_if_response.as_deref(),
+        )
+    }
+
+    /// for the body, or for a part of a multipart body
+    fn save_filename(
+        headers: &[(String, String)],
+        content_type: Option<&str>,
+        request_first_line_if_response: Option<&str>,
+    ) -> String {
+        // attachment; filename="a.png" or form-data; name="file"; filename="a.png"
+        let attachment_name =
+            http_streams_store::get_http_header_value(headers, "Content-Disposition")
+                .and_then(|d| form_data::header_param(d, "filename"))
+                .map(|f| f.to_string());
         attachment_name
             .or_else(|| {
-                self.model
-                    .request_first_line_if_response
-                    .as_deref()
-                    .and_then(Self::extract_fname_from_get_request)
-            })
-            .or_else(|| {
-                self.model
-                    .data
-                    .as_ref()
-                    .and_then(|d| d.content_type.as_ref())
-                    .and_then(|ct| Self::filename_from_binary_content_type(ct))
+                request_first_line_if_response.and_then(Self::extract_fname_from_get_request)
             })
+            .or_else(|| content_type.and_then(Self::filename_from_binary_content_type))
             .unwrap_or_else(|| "data.bin".to_string())
     }
 
@@ -264,73 +435,100 @@ impl Widget for HttpBodyWidget {
     }
 
     view! {
-       #[name="contents_stack"]
-       gtk::Stack {
-           visible: self.model.data.as_ref().filter(|d| !matches!(d.body, HttpBody::Missing)).is_some(),
+       gtk::Box {
+           orientation: gtk::Orientation::Vertical,
+           spacing: 10,
            gtk::Box {
-               child: {
-                   name: Some(TEXT_CONTENTS_STACK_NAME)
-               },
                orientation: gtk::Orientation::Vertical,
-               #[name="too_long_header"]
+               visible: !self.model.query_params.is_empty(),
+               #[style_class="label"]
+               gtk::Label {
+                   label: "Query parameters",
+                   xalign: 0.0,
+               },
+               #[name="query_params_grid"]
+               gtk::Grid {
+                   column_spacing: 10,
+                   row_spacing: 3,
+               },
+           },
+           #[name="contents_stack"]
+           gtk::Stack {
+               visible: self.model.data.as_ref().filter(|d| !matches!(d.body, HttpBody::Missing)).is_some(),
                gtk::Box {
-                   #[name="too_long_infobar"]
-                   gtk::InfoBar {
+                   child: {
+                       name: Some(TEXT_CONTENTS_STACK_NAME)
+                   },
+                   orientation: gtk::Orientation::Vertical,
+                   #[name="too_long_header"]
+                   gtk::Box {
+                       #[name="too_long_infobar"]
+                       gtk::InfoBar {
+                       },
+                       gtk::Button {
+                           always_show_image: true,
+                           image: Some(&gtk::Image::from_icon_name(
+                               Some("document-save-symbolic"), gtk::IconSize::Menu)),
+                           button_press_event(_, _) => (Msg::SaveContents, Inhibit(false)),
+                           label: "Save contents"
+                       }
+                   },
+                   gtk::ScrolledWindow {
+                       vscrollbar_policy: gtk::PolicyType::Never,
+                       gtk::Label {
+                           markup: &self.model.grpc_contents.as_deref().map(|g| glib::markup_escape_text(g).to_string())
+                               .unwrap_or_else(|| code_formatting::highlight_indent_truncate(
+                                   self.model.format_code,
+                                   &self.model.data.as_ref().and_then(|d| d.body_as_str()).unwrap_or(Cow::Borrowed("")),
+                                   self.model.data.as_ref().and_then(|d| d.content_type.as_deref()))),
+                           xalign: 0.0,
+                           selectable: true,
+                       },
+                   }
+               },
+               gtk::Box {
+                   child: {
+                       name: Some(IMAGE_CONTENTS_STACK_NAME)
+                   },
+                   orientation: gtk::Orientation::Vertical,
+                   #[name="body_image"]
+                   gtk::Image {
+                       halign: gtk::Align::Start,
                    },
                    gtk::Button {
+                       halign: gtk::Align::Start,
                        always_show_image: true,
                        image: Some(&gtk::Image::from_icon_name(
-                           Some("document-save-symbolic"), gtk::IconSize::Menu)),
+                            Some("document-save-symbolic"), gtk::IconSize::Menu)),
                        button_press_event(_, _) => (Msg::SaveContents, Inhibit(false)),
-                       label: "Save contents"
+                       label: "Save image"
                    }
                },
-               gtk::ScrolledWindow {
-                   vscrollbar_policy: gtk::PolicyType::Never,
+               gtk::Box {
+                   child: {
+                       name: Some(BINARY_CONTENTS_STACK_NAME)
+                   },
+                   orientation: gtk::Orientation::Vertical,
                    gtk::Label {
-                       markup: &code_formatting::highlight_indent_truncate(
-                           self.model.format_code,
-                           &self.model.data.as_ref().and_then(|d| d.body_as_str()).unwrap_or(Cow::Borrowed("")),
-                           self.model.data.as_ref().and_then(|d| d.content_type.as_deref())),
-                       xalign: 0.0,
-                       selectable: true,
+                       text: "Body contents are binary data",
+                       halign: gtk::Align::Start,
                    },
-               }
-           },
-           gtk::Box {
-               child: {
-                   name: Some(IMAGE_CONTENTS_STACK_NAME)
-               },
-               orientation: gtk::Orientation::Vertical,
-               #[name="body_image"]
-               gtk::Image {
-                   halign: gtk::Align::Start,
-               },
-               gtk::Button {
-                   halign: gtk::Align::Start,
-                   always_show_image: true,
-                   image: Some(&gtk::Image::from_icon_name(
-                        Some("document-save-symbolic"), gtk::IconSize::Menu)),
-                   button_press_event(_, _) => (Msg::SaveContents, Inhibit(false)),
-                   label: "Save image"
-               }
-           },
-           gtk::Box {
-               child: {
-                   name: Some(BINARY_CONTENTS_STACK_NAME)
-               },
-               orientation: gtk::Orientation::Vertical,
-               gtk::Label {
-                   text: "Body contents are binary data",
-                   halign: gtk::Align::Start,
+                   gtk::Button {
+                       always_show_image: true,
+                       image: Some(&gtk::Image::from_icon_name(
+                            Some("document-save-symbolic"), gtk::IconSize::Menu)),
+                        button_press_event(_, _) => (Msg::SaveContents, Inhibit(false)),
+                       label: "Save body contents",
+                       halign: gtk::Align::Start,
+                   }
                },
-               gtk::Button {
-                   always_show_image: true,
-                   image: Some(&gtk::Image::from_icon_name(
-                        Some("document-save-symbolic"), gtk::IconSize::Menu)),
-                    button_press_event(_, _) => (Msg::SaveContents, Inhibit(false)),
-                   label: "Save body contents",
-                   halign: gtk::Align::Start,
+               #[name="form_fields_box"]
+               gtk::Box {
+                   child: {
+                       name: Some(FORM_CONTENTS_STACK_NAME)
+                   },
+                   orientation: gtk::Orientation::Vertical,
+                   spacing: 10,
                }
            }
        }
@@ -1,8 +1,11 @@
 use super::http_body_widget;
 use super::http_body_widget::HttpBodyWidget;
 use super::http_streams_store::{HttpMessageData, HttpRequestResponseData};
+use super::request_commands;
+use crate::custom_streams_store::ClientServerInfo;
+use crate::http2::protobuf::ProtoSchema;
 use crate::icons::Icon;
-use crate::tshark_communication::TcpStreamId;
+use crate::tshark_communication::{NetworkPort, TcpStreamId};
 use crate::widgets::comm_info_header;
 use crate::widgets::comm_info_header::CommInfoHeader;
 use crate::widgets::win;
@@ -13,14 +16,22 @@ use relm::Widget;
 use relm_derive::{widget, Msg};
 use std::borrow::Cow;
 use std::net::IpAddr;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
 
 #[derive(Msg, Debug)]
 pub enum Msg {
-    DisplayDetails(mpsc::Sender<BgFunc>, IpAddr, TcpStreamId, HttpMessageData),
+    DisplayDetails(
+        mpsc::Sender<BgFunc>,
+        ClientServerInfo,
+        TcpStreamId,
+        HttpMessageData,
+    ),
     RemoveFormatToggled,
     CopyContentsClick,
+    CopyAsCurlClick,
+    CopyAsHttpieClick,
     ToggleDisplayPassword,
+    ProtoSchemaChanged(Option<Arc<ProtoSchema>>),
 }
 
 pub struct Model {
@@ -28,6 +39,8 @@ pub struct Model {
     bg_sender: mpsc::Sender<BgFunc>,
     stream_id: TcpStreamId,
     client_ip: IpAddr,
+    // needed to rebuild the request URL if there is no Host header
+    server_ip_port: Option<(IpAddr, NetworkPort)>,
     data: HttpMessageData,
     basic_auth_username: Option<String>,
     basic_auth_password: Option<String>,
@@ -62,6 +75,14 @@ impl Widget for HttpCommEntry {
             .label("Copy to clipboard")
             .build();
         popover_box.add(&copy_to_clipboard_lbl);
+        let copy_as_curl_lbl = gtk::builders::ButtonBuilder::new()
+            .label("Copy as curl")
+            .build();
+        popover_box.add(&copy_as_curl_lbl);
+        let copy_as_httpie_lbl = gtk::builders::ButtonBuilder::new()
+            .label("Copy as HTTPie")
+            .build();
+        popover_box.add(&copy_as_httpie_lbl);
         popover_box.show_all();
 
         relm::connect!(
@@ -70,6 +91,18 @@ impl Widget for HttpCommEntry {
             connect_clicked(_),
             Msg::CopyContentsClick
         );
+        relm::connect!(
+            relm,
+            copy_as_curl_lbl,
+            connect_clicked(_),
+            Msg::CopyAsCurlClick
+        );
+        relm::connect!(
+            relm,
+            copy_as_httpie_lbl,
+            connect_clicked(_),
+            Msg::CopyAsHttpieClick
+        );
 
         let options_popover = gtk::builders::PopoverBuilder::new()
             .child(&popover_box)
@@ -116,6 +149,7 @@ impl Widget for HttpCommEntry {
             data,
             stream_id,
             client_ip,
+            server_ip_port: None,
             format_contents_btn,
             options_popover,
             format_request_response: true,
@@ -167,7 +201,10 @@ impl Widget for HttpCommEntry {
     fn update(&mut self, event: Msg) {
         // dbg!(&event);
         match event {
-            Msg::DisplayDetails(.., client_ip, stream_id, message_data) => {
+            Msg::DisplayDetails(.., client_server, stream_id, message_data) => {
+                let client_ip = client_server.client_ip;
+                self.model.server_ip_port =
+                    Some((client_server.server_ip, client_server.server_port));
                 self.update_basic_auth_data(&message_data);
                 self.model.data = message_data;
                 self.streams
@@ -246,12 +283,42 @@ impl Widget for HttpCommEntry {
                 }
                 self.model.options_popover.popdown();
             }
+            Msg::CopyAsCurlClick => {
+                self.copy_request_as(request_commands::as_curl);
+            }
+            Msg::CopyAsHttpieClick => {
+                self.copy_request_as(request_commands::as_httpie);
+            }
+            Msg::ProtoSchemaChanged(schema) => {
+                self.streams
+                    .request_body
+                    .emit(http_body_widget::Msg::ProtoSchemaChanged(schema.clone()));
+                self.streams
+                    .response_body
+                    .emit(http_body_widget::Msg::ProtoSchemaChanged(schema));
+            }
             Msg::ToggleDisplayPassword => {
                 self.refresh_display_password();
             }
         }
     }
 
+    fn copy_request_as(&mut self, to_command: fn(&HttpRequestResponseData, &str) -> String) {
+        if let (Some(request), Some((server_ip, server_port)), Some(clip)) = (
+            self.model.data.request.as_ref(),
+            self.model.server_ip_port,
+            gtk::Clipboard::default(&self.widgets.comm_info_header.display()),
+        ) {
+            let url = request.request_url(server_ip, server_port);
+            clip.set_text(&to_command(request, &url));
+            self.model.win_msg_sender.emit(win::Msg::InfoBarShow(
+                Some("Copied to the clipboard".to_string()),
+                win::InfobarOptions::TimeLimitedWithCloseButton,
+            ))
+        }
+        self.model.options_popover.popdown();
+    }
+
     fn format_headers(headers: &[(String, String)]) -> String {
         headers
             .iter()
@@ -353,6 +420,13 @@ impl Widget for HttpCommEntry {
                         line_wrap: true,
                         wrap_mode: pango::WrapMode::Char,
                     },
+                    #[style_class="http_stream_end"]
+                    gtk::Label {
+                        label: self.model.data.http2_stream_end.map(|e| e.to_string()).as_deref().unwrap_or(""),
+                        visible: self.model.data.http2_stream_end.is_some(),
+                        xalign: 0.0,
+                        selectable: true,
+                    },
                     gtk::Label {
                         label: self.model.data.response.as_ref()
                                                     .map(|r| &r.headers[..])
@@ -365,6 +439,23 @@ impl Widget for HttpCommEntry {
                     },
                     #[name="response_body"]
                     HttpBodyWidget((self.model.win_msg_sender.clone(), self.model.bg_sender.clone())),
+                    #[style_class="label"]
+                    gtk::Label {
+                        label: "Trailers",
+                        xalign: 0.0,
+                        visible: self.model.data.response.as_ref().map_or(false, |r| !r.trailers.is_empty()),
+                    },
+                    gtk::Label {
+                        label: self.model.data.response.as_ref()
+                                                    .map(|r| &r.trailers[..])
+                                                    .map(Self::format_headers)
+                                                    .as_deref()
+                                                    .unwrap_or(""),
+                        xalign: 0.0,
+                        selectable: true,
+                        line_wrap: true,
+                        visible: self.model.data.response.as_ref().map_or(false, |r| !r.trailers.is_empty()),
+                    },
                 }
             }
         }
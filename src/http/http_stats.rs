@@ -0,0 +1,277 @@
+// per-endpoint statistics, for the statistics window. The requests are grouped
+// by method and path template: the ids in the path are replaced by {id}.
+use super::http_streams_store::HttpMessageData;
+use crate::custom_streams_store::{StatsCell, StatsRow, StatsTable};
+use std::collections::{BTreeMap, HashMap};
+
+// numeric ids and UUIDs
+const ID_PATTERN: &str =
+    r"\d+|[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}";
+
+lazy_static! {
+    static ref ID_SEGMENT: regex::Regex =
+        regex::Regex::new(&format!("^(?:{})$", ID_PATTERN)).unwrap();
+}
+
+/// "/users/42/orders?page=2" => "/users/{id}/orders"
+pub fn path_template(path: &str) -> String {
+    path.split(['?', '#'])
+        .next()
+        .unwrap_or("")
+        .split('/')
+        .map(|segment| {
+            if ID_SEGMENT.is_match(segment) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// the method & path template of a request line, either HTTP1
+/// ("GET /a HTTP/1.1") or HTTP2 ("GET /a")
+fn endpoint(request_line: &str) -> (String, String) {
+    let mut parts = request_line.split(' ');
+    let method = parts.next().unwrap_or("").to_string();
+    (method, path_template(parts.next().unwrap_or("")))
+}
+
+/// a search expression matching the requests of that endpoint
+pub fn endpoint_search_filter(method: &str, template: &str) -> String {
+    let path_regex = template
+        .split('/')
+        .map(|segment| {
+            if segment == "{id}" {
+                format!("(?:{})", ID_PATTERN)
+            } else {
+                regex::escape(segment)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    // HTTP1 request lines end with the protocol version, HTTP2 ones with the path
+    let regex = format!("^{} {}(?:[ ?#]|$)", regex::escape(method), path_regex);
+    format!("http.req_line matches /{}/", regex.replace('/', "\\/"))
+}
+
+/// "HTTP/1.1 200 OK" or "HTTP/2 status 200"
+fn response_status(first_line: &str) -> Option<u16> {
+    first_line.split(' ').skip(1).find_map(|s| s.parse().ok())
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KiB", b as f64 / 1024.0),
+        b => format!("{:.1} MiB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+#[derive(Default)]
+struct EndpointStats {
+    count: u64,
+    // "-" when we're missing the response
+    statuses: BTreeMap<String, u64>,
+    durations_ms: Vec<u64>,
+    request_body_bytes: u64,
+    response_count: u64,
+    response_body_bytes: u64,
+}
+
+impl EndpointStats {
+    fn add(&mut self, message: &HttpMessageData) {
+        self.count += 1;
+        if let Some(rq) = message.request.as_ref() {
+            self.request_body_bytes += rq.body.len() as u64;
+        }
+        let status = message
+            .response
+            .as_ref()
+            .and_then(|rs| response_status(&rs.first_line))
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        *self.statuses.entry(status).or_default() += 1;
+        if let Some(rs) = message.response.as_ref() {
+            self.response_count += 1;
+            self.response_body_bytes += rs.body.len() as u64;
+        }
+        if let Some(d) = message.duration_ms() {
+            self.durations_ms.push(d.max(0) as u64);
+        }
+    }
+
+    fn cells(mut self, method: &str, template: &str) -> Vec<StatsCell> {
+        self.durations_ms.sort_unstable();
+        let durations = &self.durations_ms;
+        let duration_cell = |d: Option<u64>| match d {
+            Some(d) => StatsCell::Number(d, format!("{} ms", d)),
+            None => StatsCell::Number(0, "-".to_string()),
+        };
+        // nearest-rank percentile
+        let p95_rank = (durations.len() as f64 * 0.95).ceil() as usize;
+        let avg_size = |total: u64, count: u64| {
+            let avg = total.checked_div(count).unwrap_or(0);
+            StatsCell::Number(avg, format_size(avg))
+        };
+        vec![
+            StatsCell::Text(format!("{} {}", method, template)),
+            StatsCell::Number(self.count, self.count.to_string()),
+            StatsCell::Text(
+                self.statuses
+                    .iter()
+                    .map(|(status, count)| format!("{}: {}", status, count))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            duration_cell(durations.first().copied()),
+            duration_cell(
+                durations
+                    .iter()
+                    .sum::<u64>()
+                    .checked_div(durations.len() as u64),
+            ),
+            duration_cell(p95_rank.checked_sub(1).map(|i| durations[i])),
+            duration_cell(durations.last().copied()),
+            avg_size(self.request_body_bytes, self.count),
+            avg_size(self.response_body_bytes, self.response_count),
+        ]
+    }
+}
+
+/// the statistics of these messages, grouped by endpoint, the
+/// most frequent first
+pub fn endpoint_stats<'a>(messages: impl Iterator<Item = &'a HttpMessageData>) -> StatsTable {
+    let mut by_endpoint: HashMap<(String, String), EndpointStats> = HashMap::new();
+    for message in messages {
+        if message.websocket.is_some() {
+            continue;
+        }
+        // without the request, we don't know the endpoint
+        if let Some(rq) = message.request.as_ref() {
+            by_endpoint
+                .entry(endpoint(&rq.first_line))
+                .or_default()
+                .add(message);
+        }
+    }
+    let mut endpoints: Vec<_> = by_endpoint.into_iter().collect();
+    endpoints.sort_by(|(k1, s1), (k2, s2)| s2.count.cmp(&s1.count).then_with(|| k1.cmp(k2)));
+    StatsTable {
+        column_titles: vec![
+            "Endpoint",
+            "Count",
+            "Status codes",
+            "Min",
+            "Avg",
+            "P95",
+            "Max",
+            "Avg request body",
+            "Avg response body",
+        ],
+        rows: endpoints
+            .into_iter()
+            .map(|((method, template), stats)| StatsRow {
+                search_filter: endpoint_search_filter(&method, &template),
+                cells: stats.cells(&method, &template),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::http_streams_store::{ContentEncoding, HttpBody, HttpRequestResponseData};
+    use crate::search_expr::{self, SearchExpr, SearchOpExpr};
+    use crate::tshark_communication::{TcpSeqNumber, TcpStreamId};
+    use chrono::NaiveDate;
+    use std::collections::BTreeSet;
+
+    fn message(request_line: &str, status: Option<&str>, duration_ms: i64) -> HttpMessageData {
+        let timestamp = NaiveDate::from_ymd(2021, 3, 5).and_hms(8, 49, 52);
+        let data = |first_line: &str, timestamp, body: &str| HttpRequestResponseData {
+            tcp_stream_no: TcpStreamId(1),
+            tcp_seq_number: TcpSeqNumber(1),
+            timestamp,
+            first_line: first_line.to_string(),
+            headers: vec![],
+            body: HttpBody::Text(body.to_string()),
+            content_type: None,
+            content_encoding: ContentEncoding::Plain,
+            trailers: vec![],
+        };
+        HttpMessageData {
+            http_stream_id: 0,
+            request: Some(data(request_line, timestamp, "ab")),
+            response: status.map(|s| {
+                data(
+                    &format!("HTTP/1.1 {}", s),
+                    timestamp + chrono::Duration::milliseconds(duration_ms),
+                    "abcdef",
+                )
+            }),
+            http2_stream_end: None,
+            websocket: None,
+        }
+    }
+
+    fn cell_text(cell: &StatsCell) -> &str {
+        match cell {
+            StatsCell::Text(t) => t,
+            StatsCell::Number(_, t) => t,
+        }
+    }
+
+    #[test]
+    fn should_normalize_paths_and_match_them_with_the_filter() {
+        assert_eq!(
+            "/users/{id}/orders/{id}",
+            path_template("/users/42/orders/0b5c9e7e-1d8f-4a6b-9c3e-2f1a7d6b8e90?page=2")
+        );
+        assert_eq!("/users/me", path_template("/users/me#top"));
+        let filter = endpoint_search_filter("GET", "/users/{id}/orders");
+        let string_keys: BTreeSet<&'static str> = ["http.req_line"].into_iter().collect();
+        let numeric_keys = BTreeSet::new();
+        let (rest, expr) = search_expr::parse_search(&string_keys, &numeric_keys)(&filter).unwrap();
+        assert_eq!("", rest);
+        let op = match expr {
+            SearchExpr::SearchOpExpr(SearchOpExpr { op, .. }) => op,
+            _ => panic!("expected a single filter: {}", filter),
+        };
+        assert!(op.matches_str("GET /users/42/orders HTTP/1.1"));
+        assert!(op.matches_str("GET /users/42/orders?page=2"));
+        assert!(!op.matches_str("GET /users/me/orders HTTP/1.1"));
+        assert!(!op.matches_str("GET /users/42/orders/3 HTTP/1.1"));
+        assert!(!op.matches_str("POST /users/42/orders HTTP/1.1"));
+    }
+
+    #[test]
+    fn should_group_requests_by_endpoint() {
+        let mut messages: Vec<_> = (1..=20)
+            .map(|i| message(&format!("GET /items/{} HTTP/1.1", i), Some("200 OK"), i))
+            .collect();
+        messages.push(message("GET /items/7 HTTP/1.1", Some("404 Not Found"), 100));
+        messages.push(message("GET /items/8 HTTP/1.1", None, 0));
+        messages.push(message("POST /items HTTP/1.1", Some("201 Created"), 5));
+        let table = endpoint_stats(messages.iter());
+        assert_eq!(2, table.rows.len());
+        let texts: Vec<_> = table.rows[0].cells.iter().map(cell_text).collect();
+        assert_eq!(
+            vec![
+                "GET /items/{id}",
+                "22",
+                "-: 1, 200: 20, 404: 1",
+                "1 ms",
+                "14 ms",
+                "20 ms",
+                "100 ms",
+                "2 B",
+                "6 B"
+            ],
+            texts
+        );
+        assert_eq!("POST /items", cell_text(&table.rows[1].cells[0]));
+    }
+}
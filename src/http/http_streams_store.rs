@@ -1,22 +1,35 @@
+use super::form_data;
+use super::har;
 use super::http_details_widget;
 use super::http_details_widget::HttpCommEntry;
+use super::http_stats;
+use super::native_http;
+use super::websocket::{WebSocketInfo, WebSocketMessage, WebSocketOpcode, WebSocketStream};
 use crate::colors;
+use crate::config::Config;
 use crate::custom_streams_store;
-use crate::custom_streams_store::{ClientServerInfo, CustomStreamsStore};
+use crate::custom_streams_store::{
+    ClientServerInfo, CustomStreamsParser, CustomStreamsStore, StatsTable,
+};
 use crate::http::tshark_http::HttpType;
+use crate::http2::grpc;
+use crate::http2::tshark_http2;
 use crate::icons::Icon;
 use crate::search_expr;
 use crate::search_expr::SearchCriteria;
-use crate::tshark_communication::{NetworkPort, TSharkPacket, TcpSeqNumber, TcpStreamId};
+use crate::tshark_communication::{
+    NetworkPort, TSharkPacket, TSharkPacketBasicInfo, TcpSeqNumber, TcpStreamId,
+};
 use crate::widgets::win;
 use crate::BgFunc;
 use chrono::NaiveDateTime;
-use flate2::read::GzDecoder;
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
 use gtk::prelude::*;
 use relm::ContainerWidget;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::VecDeque;
+use std::fmt;
 use std::io::prelude::*;
 use std::net::IpAddr;
 use std::str;
@@ -51,6 +64,7 @@ pub struct HttpStreamData {
 pub struct HttpStreamsStore {
     streams: HashMap<TcpStreamId, HttpStreamData>,
     component: Option<relm::Component<HttpCommEntry>>,
+    slow_threshold_ms: Option<u32>,
 }
 
 impl HttpStreamsStore {
@@ -78,6 +92,10 @@ pub struct HttpStreamGlobals {
     // if we didn't get the hostname, we use this in finish_stream() to populate
     // the summary details.
     server_info: Option<String>,
+
+    // set after a "101 Switching Protocols" to websocket: from then on,
+    // the TCP payload is websocket frames
+    websocket: Option<WebSocketStream>,
 }
 
 impl HttpStreamGlobals {
@@ -127,6 +145,15 @@ impl HttpStreamGlobals {
         let content_encoding = ContentEncoding::parse_from_str(
             &get_http_header_value(&headers, "Content-Encoding").map(|s| s.as_str()),
         );
+        let is_chunked = get_http_header_value(&headers, "Transfer-Encoding")
+            .map_or(false, |te| te.to_ascii_lowercase().contains("chunked"));
+        // if some chunks are missing, show what we have
+        let dechunked = if is_chunked {
+            native_http::dechunk(raw_body).map(|(b, _)| b)
+        } else {
+            None
+        };
+        let raw_body = dechunked.as_deref().unwrap_or(raw_body);
         let body = match str::from_utf8(raw_body) {
             Ok(txt) => HttpBody::Text(txt.to_string()),
             _ => HttpBody::Binary(raw_body.to_vec()),
@@ -139,6 +166,7 @@ impl HttpStreamGlobals {
             headers,
             content_type,
             content_encoding,
+            trailers: vec![],
             body,
         })
     }
@@ -158,6 +186,8 @@ pub enum HttpNumericFilterKeys {
     RespBodyKilobytes,
     #[strum(serialize = "http.resp_body_megabytes")]
     RespBodyMegabytes,
+    #[strum(serialize = "http.duration_ms")]
+    DurationMs,
 }
 
 #[derive(EnumString, EnumVariantNames, Debug)]
@@ -178,6 +208,9 @@ pub enum HttpStringFilterKeys {
     ReqBody,
     #[strum(serialize = "http.resp_body")]
     RespBody,
+    // a query string parameter or form field, as "name=value"
+    #[strum(serialize = "http.req_param")]
+    ReqParam,
 }
 
 fn get_http_message<'a, 'b>(
@@ -189,7 +222,7 @@ fn get_http_message<'a, 'b>(
     streams.get(&stream_id).and_then(|s| s.get(idx as usize))
 }
 
-impl CustomStreamsStore for HttpStreamsStore {
+impl CustomStreamsParser for HttpStreamsStore {
     fn is_my_message(&self, msg: &TSharkPacket) -> bool {
         msg.http.is_some()
     }
@@ -201,10 +234,6 @@ impl CustomStreamsStore for HttpStreamsStore {
         "http || tcp"
     }
 
-    fn protocol_icon(&self) -> Icon {
-        Icon::HTTP
-    }
-
     fn protocol_name(&self) -> &'static str {
         "HTTP"
     }
@@ -242,7 +271,7 @@ impl CustomStreamsStore for HttpStreamsStore {
     fn add_to_stream(
         &mut self,
         stream_id: TcpStreamId,
-        new_packet: TSharkPacket,
+        mut new_packet: TSharkPacket,
     ) -> Result<Option<ClientServerInfo>, String> {
         let stream = self
             .streams
@@ -253,6 +282,15 @@ impl CustomStreamsStore for HttpStreamsStore {
             // end of a stream. That's the only case I've seen so far.
             stream.stream_globals.tcp_leftover_payload = None;
         }
+        // the native capture can give us the upgrade response and the first
+        // websocket frames in the same packet
+        let websocket_payload =
+            if new_packet.http.is_some() || stream.stream_globals.websocket.is_some() {
+                new_packet.tcp_payload.take()
+            } else {
+                None
+            };
+        let basic_info = new_packet.basic_info;
         let rrs = parse_request_response(
             new_packet,
             stream
@@ -320,10 +358,15 @@ impl CustomStreamsStore for HttpStreamsStore {
                             server_port: srv_port,
                         });
                     }
+                    if let Some(ws) = websocket_upgrade(&r) {
+                        stream.stream_globals.websocket = Some(ws);
+                    }
                     stream.messages.push(HttpMessageData {
                         http_stream_id: 0,
                         request: stream.stream_globals.cur_requests.pop_front(),
                         response: Some(r),
+                        http2_stream_end: None,
+                        websocket: None,
                     });
                 }
                 ReqRespInfo {
@@ -343,6 +386,23 @@ impl CustomStreamsStore for HttpStreamsStore {
                 }
             };
         }
+        if let (Some(ws), Some(payload), Some(cs)) = (
+            stream.stream_globals.websocket.as_mut(),
+            websocket_payload,
+            stream.client_server,
+        ) {
+            let from_client =
+                (basic_info.ip_src, basic_info.port_src) != (cs.server_ip, cs.server_port);
+            match ws.add_data(from_client, &payload) {
+                Ok(messages) => stream.messages.extend(
+                    messages
+                        .into_iter()
+                        .map(|m| websocket_message_data(m, &basic_info)),
+                ),
+                // like tshark, we keep going with the other streams
+                Err(e) => eprintln!("Error parsing stream {}: {}", stream_id, e),
+            }
+        }
         Ok(stream.client_server)
     }
 
@@ -357,6 +417,8 @@ impl CustomStreamsStore for HttpStreamsStore {
                 http_stream_id: 0,
                 request: Some(req),
                 response: globals.http_resp_from_tcp_if_any(stream_id),
+                http2_stream_end: None,
+                websocket: None,
             });
         }
         if stream.summary_details.is_none() && stream.stream_globals.server_info.is_some() {
@@ -366,8 +428,55 @@ impl CustomStreamsStore for HttpStreamsStore {
         Ok(())
     }
 
-    fn get_empty_liststore(&self) -> gtk::ListStore {
-        http_get_empty_liststore()
+    fn stream_messages_json(&self, stream_id: TcpStreamId) -> Vec<serde_json::Value> {
+        self.streams
+            .get(&stream_id)
+            .map(|s| s.messages.iter().map(|m| m.to_json()).collect())
+            .unwrap_or_default()
+    }
+
+    fn stream_har_entries(&self, stream_id: TcpStreamId) -> Vec<serde_json::Value> {
+        self.streams
+            .get(&stream_id)
+            .and_then(|s| {
+                let client_server = s.client_server?;
+                Some(
+                    s.messages
+                        .iter()
+                        .filter_map(|m| har::har_entry(m, stream_id, &client_server))
+                        .collect(),
+                )
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl CustomStreamsStore for HttpStreamsStore {
+    fn protocol_icon(&self) -> Icon {
+        Icon::HTTP
+    }
+
+    fn apply_config(&mut self, config: &Config) {
+        self.slow_threshold_ms = Some(config.http_slow_threshold_ms);
+    }
+
+    fn stream_slow_message_count(&self, stream_id: TcpStreamId) -> usize {
+        self.streams.get(&stream_id).map_or(0, |s| {
+            http_slow_message_count(&s.messages, self.slow_threshold_ms)
+        })
+    }
+
+    fn streams_stats(&self, stream_ids: &[TcpStreamId]) -> Option<StatsTable> {
+        Some(http_stats::endpoint_stats(
+            stream_ids
+                .iter()
+                .filter_map(|id| self.streams.get(id))
+                .flat_map(|s| s.messages.iter()),
+        ))
+    }
+
+    fn get_empty_treestore(&self) -> gtk::TreeStore {
+        http_get_empty_treestore()
     }
 
     fn prepare_treeview(&self, tv: &gtk::TreeView) {
@@ -376,17 +485,24 @@ impl CustomStreamsStore for HttpStreamsStore {
 
     fn populate_treeview(
         &self,
-        ls: &gtk::ListStore,
+        ts: &gtk::TreeStore,
         session_id: TcpStreamId,
         start_idx: usize,
         item_count: usize,
     ) {
         let messages = &self.streams.get(&session_id).unwrap().messages;
-        http_populate_treeview(messages, ls, session_id, start_idx, item_count);
+        http_populate_treeview(
+            messages,
+            ts,
+            session_id,
+            start_idx,
+            item_count,
+            self.slow_threshold_ms,
+        );
     }
 
-    fn end_populate_treeview(&self, tv: &gtk::TreeView, ls: &gtk::ListStore) {
-        http_end_populate_treeview(tv, ls);
+    fn end_populate_treeview(&self, tv: &gtk::TreeView, ts: &gtk::TreeStore) {
+        http_end_populate_treeview(tv, ts);
     }
 
     fn supported_string_filter_keys(&self) -> &'static [&'static str] {
@@ -434,6 +550,8 @@ impl CustomStreamsStore for HttpStreamsStore {
                 http_stream_id: 0,
                 request: None,
                 response: None,
+                http2_stream_end: None,
+                websocket: None,
             },
             overlay.unwrap().clone(),
             bg_sender,
@@ -451,7 +569,7 @@ impl CustomStreamsStore for HttpStreamsStore {
             self.component.as_ref().unwrap().stream().emit(
                 http_details_widget::Msg::DisplayDetails(
                     bg_sender,
-                    client_server.client_ip,
+                    client_server,
                     stream_id,
                     http_msg.clone(),
                 ),
@@ -471,179 +589,141 @@ pub fn http_matches_filter(
         HttpNumericFilterKeys::from_str(filter.filter_key),
         &filter.op,
     ) {
-        (Ok(filter_key), _, SearchCriteria::Contains(filter_val_orig)) => {
-            let filter_val = &filter_val_orig.to_lowercase();
-            match filter_key {
-                HttpStringFilterKeys::ReqLine => {
-                    model
-                        .value(iter, 0) // req info
-                        .get::<&str>()
-                        .unwrap()
-                        .to_lowercase()
-                        .contains(filter_val)
-                }
-                HttpStringFilterKeys::RespStatus => {
-                    model
-                        .value(iter, 1) // resp info
-                        .get::<&str>()
-                        .unwrap()
-                        .to_lowercase()
-                        .contains(filter_val)
-                }
-                HttpStringFilterKeys::ReqContentType => {
-                    model
-                        .value(iter, 8) // req content type
-                        .get::<&str>()
-                        .unwrap_or("")
-                        .to_lowercase()
-                        .contains(filter_val)
-                }
-                HttpStringFilterKeys::RespContentType => {
-                    model
-                        .value(iter, 9) // resp content type
-                        .get::<&str>()
-                        .unwrap_or("")
-                        .to_lowercase()
-                        .contains(filter_val)
-                }
-                HttpStringFilterKeys::ReqHeader => {
-                    get_http_message(streams, model, iter).map_or(false, |http_msg| {
-                        http_msg
-                            .request
-                            .as_ref()
-                            .filter(|r| {
-                                r.headers.iter().any(|(k, v)| {
-                                    k.to_lowercase().contains(filter_val)
-                                        || v.to_lowercase().contains(filter_val)
-                                })
-                            })
-                            .is_some()
-                    })
-                }
-                HttpStringFilterKeys::RespHeader => {
-                    get_http_message(streams, model, iter).map_or(false, |http_msg| {
-                        http_msg
-                            .response
-                            .as_ref()
-                            .filter(|r| {
-                                r.headers.iter().any(|(k, v)| {
-                                    k.to_lowercase().contains(filter_val)
-                                        || v.to_lowercase().contains(filter_val)
-                                })
-                            })
-                            .is_some()
-                    })
-                }
-                HttpStringFilterKeys::ReqBody => {
-                    get_http_message(streams, model, iter).map_or(false, |http_msg| {
-                        http_msg
-                            .request
-                            .as_ref()
-                            .filter(|r| {
-                                r.body_as_str()
-                                    .filter(|b| b.to_lowercase().contains(filter_val))
-                                    .is_some()
-                            })
-                            .is_some()
-                    })
-                }
-                HttpStringFilterKeys::RespBody => {
-                    get_http_message(streams, model, iter).map_or(false, |http_msg| {
-                        http_msg
-                            .response
-                            .as_ref()
-                            .filter(|r| {
-                                r.body_as_str()
-                                    .filter(|b| b.to_lowercase().contains(filter_val))
-                                    .is_some()
-                            })
-                            .is_some()
+        (Ok(filter_key), _, op) => match filter_key {
+            HttpStringFilterKeys::ReqLine => {
+                op.matches_str(model.value(iter, 0).get::<&str>().unwrap()) // req info
+            }
+            HttpStringFilterKeys::RespStatus => {
+                op.matches_str(model.value(iter, 1).get::<&str>().unwrap()) // resp info
+            }
+            HttpStringFilterKeys::ReqContentType => {
+                op.matches_str(model.value(iter, 8).get::<&str>().unwrap_or(""))
+                // req content type
+            }
+            HttpStringFilterKeys::RespContentType => {
+                op.matches_str(model.value(iter, 9).get::<&str>().unwrap_or(""))
+                // resp content type
+            }
+            HttpStringFilterKeys::ReqHeader => {
+                get_http_message(streams, model, iter).map_or(false, |http_msg| {
+                    http_msg
+                        .request
+                        .as_ref()
+                        .filter(|r| headers_match(op, &r.headers))
+                        .is_some()
+                })
+            }
+            HttpStringFilterKeys::RespHeader => {
+                get_http_message(streams, model, iter).map_or(false, |http_msg| {
+                    http_msg
+                        .response
+                        .as_ref()
+                        .filter(|r| headers_match(op, &r.headers))
+                        .is_some()
+                })
+            }
+            HttpStringFilterKeys::ReqBody => {
+                get_http_message(streams, model, iter).map_or(false, |http_msg| {
+                    http_msg
+                        .request
+                        .as_ref()
+                        .filter(|r| r.body_as_str().filter(|b| op.matches_str(b)).is_some())
+                        .is_some()
+                })
+            }
+            HttpStringFilterKeys::RespBody => {
+                get_http_message(streams, model, iter).map_or(false, |http_msg| {
+                    http_msg
+                        .response
+                        .as_ref()
+                        .filter(|r| r.body_as_str().filter(|b| op.matches_str(b)).is_some())
+                        .is_some()
+                })
+            }
+            HttpStringFilterKeys::ReqParam => {
+                get_http_message(streams, model, iter).map_or(false, |http_msg| {
+                    http_msg.request.as_ref().map_or(false, |r| {
+                        form_data::request_params(r)
+                            .iter()
+                            .any(|(k, v)| op.matches_str(&format!("{}={}", k, v)))
                     })
-                }
+                })
             }
-        }
-        (_, Ok(filter_key), SearchCriteria::GreaterThan(filter_val, decimals)) => {
-            match filter_key {
-                HttpNumericFilterKeys::ReqBodyBytes => body_size_matches(
-                    streams,
-                    model,
-                    iter,
-                    |m| m.request.as_ref(),
-                    *filter_val,
-                    *decimals,
-                    1,
-                ),
-                HttpNumericFilterKeys::ReqBodyKilobytes => body_size_matches(
-                    streams,
-                    model,
-                    iter,
-                    |m| m.request.as_ref(),
-                    *filter_val,
-                    *decimals,
-                    1024,
-                ),
-                HttpNumericFilterKeys::ReqBodyMegabytes => body_size_matches(
-                    streams,
-                    model,
-                    iter,
-                    |m| m.request.as_ref(),
-                    *filter_val,
-                    *decimals,
-                    1024 * 1024,
-                ),
-                HttpNumericFilterKeys::RespBodyBytes => body_size_matches(
-                    streams,
-                    model,
-                    iter,
-                    |m| m.response.as_ref(),
-                    *filter_val,
-                    *decimals,
-                    1,
-                ),
-                HttpNumericFilterKeys::RespBodyKilobytes => body_size_matches(
-                    streams,
-                    model,
-                    iter,
-                    |m| m.response.as_ref(),
-                    *filter_val,
-                    *decimals,
-                    1024,
-                ),
-                HttpNumericFilterKeys::RespBodyMegabytes => body_size_matches(
-                    streams,
-                    model,
-                    iter,
-                    |m| m.response.as_ref(),
-                    *filter_val,
-                    *decimals,
-                    1024 * 1024,
-                ),
+        },
+        (_, Ok(filter_key), op) => match filter_key {
+            HttpNumericFilterKeys::ReqBodyBytes => {
+                body_size_matches(streams, model, iter, |m| m.request.as_ref(), op, 1)
             }
-        }
+            HttpNumericFilterKeys::ReqBodyKilobytes => {
+                body_size_matches(streams, model, iter, |m| m.request.as_ref(), op, 1024)
+            }
+            HttpNumericFilterKeys::ReqBodyMegabytes => body_size_matches(
+                streams,
+                model,
+                iter,
+                |m| m.request.as_ref(),
+                op,
+                1024 * 1024,
+            ),
+            HttpNumericFilterKeys::RespBodyBytes => {
+                body_size_matches(streams, model, iter, |m| m.response.as_ref(), op, 1)
+            }
+            HttpNumericFilterKeys::RespBodyKilobytes => {
+                body_size_matches(streams, model, iter, |m| m.response.as_ref(), op, 1024)
+            }
+            HttpNumericFilterKeys::RespBodyMegabytes => body_size_matches(
+                streams,
+                model,
+                iter,
+                |m| m.response.as_ref(),
+                op,
+                1024 * 1024,
+            ),
+            HttpNumericFilterKeys::DurationMs => get_http_message(streams, model, iter)
+                .and_then(|http_msg| http_msg.duration_ms())
+                .map_or(false, |d| op.matches_number(d.max(0) as usize, 1)),
+        },
         _ => true,
     }
 }
 
+/// a header matches if its name, its value, or "name: value" match
+fn headers_match(op: &SearchCriteria, headers: &[(String, String)]) -> bool {
+    headers.iter().any(|(k, v)| {
+        op.matches_str(k) || op.matches_str(v) || op.matches_str(&format!("{}: {}", k, v))
+    })
+}
+
 fn body_size_matches<F>(
     streams: &HashMap<TcpStreamId, &Vec<HttpMessageData>>,
     model: &gtk::TreeModel,
     iter: &gtk::TreeIter,
     get_req_resp: F,
-    filter_val: usize,
-    decimals: u8,
-    multiplier: i32,
+    op: &SearchCriteria,
+    multiplier: usize,
 ) -> bool
 where
     F: Fn(&HttpMessageData) -> Option<&HttpRequestResponseData>,
 {
-    let filter_bytes_count = filter_val * (multiplier as usize) / 10_usize.pow(decimals.into());
     get_http_message(streams, model, iter).map_or(false, |http_msg| {
         get_req_resp(http_msg)
-            .filter(|r| r.body.len() > filter_bytes_count)
+            .filter(|r| op.matches_number(r.body.len(), multiplier))
             .is_some()
     })
 }
 
+pub fn http_slow_message_count(
+    messages: &[HttpMessageData],
+    slow_threshold_ms: Option<u32>,
+) -> usize {
+    slow_threshold_ms.map_or(0, |threshold| {
+        messages
+            .iter()
+            .filter(|m| m.duration_ms().map_or(false, |d| d > i64::from(threshold)))
+            .count()
+    })
+}
+
 pub fn http_requests_details_overlay() -> bool {
     true
 }
@@ -700,6 +780,7 @@ pub fn http_prepare_treeview(tv: &gtk::TreeView) {
     let cell_d_txt = gtk::builders::CellRendererTextBuilder::new().build();
     duration_col.pack_start(&cell_d_txt, true);
     duration_col.add_attribute(&cell_d_txt, "text", 7);
+    duration_col.add_attribute(&cell_d_txt, "foreground", 13);
     tv.append_column(&duration_col);
 
     let response_ct_col = gtk::builders::TreeViewColumnBuilder::new()
@@ -713,8 +794,8 @@ pub fn http_prepare_treeview(tv: &gtk::TreeView) {
     tv.append_column(&response_ct_col);
 }
 
-pub fn http_get_empty_liststore() -> gtk::ListStore {
-    gtk::ListStore::new(&[
+pub fn http_get_empty_treestore() -> gtk::TreeStore {
+    gtk::TreeStore::new(&[
         // TODO add: body size...
         String::static_type(), // request first line
         String::static_type(), // response first line
@@ -729,74 +810,96 @@ pub fn http_get_empty_liststore() -> gtk::ListStore {
         u32::static_type(),    // tcp sequence number
         String::static_type(), // stream color
         String::static_type(), // http response color
+        String::static_type(), // duration color (slow requests)
     ])
 }
 
-pub fn http_end_populate_treeview(tv: &gtk::TreeView, ls: &gtk::ListStore) {
-    let model_sort = gtk::TreeModelSort::new(ls);
+pub fn http_end_populate_treeview(tv: &gtk::TreeView, ts: &gtk::TreeStore) {
+    let model_sort = gtk::TreeModelSort::new(ts);
     model_sort.set_sort_column_id(gtk::SortColumn::Index(5), gtk::SortType::Ascending);
     tv.set_model(Some(&model_sort));
 }
 
+/// the text of the response column, which the http.resp_status filter looks at
+pub fn http_response_summary(
+    http: &HttpMessageData,
+    grpc_status: Option<&grpc::GrpcStatus>,
+) -> String {
+    match (&http.response, http.http2_stream_end) {
+        (Some(r), _) => match grpc_status {
+            Some(s) => format!("{} - gRPC {}", r.first_line, s.name()),
+            None => r.first_line.clone(),
+        },
+        (None, _) if http.websocket.is_some() => String::new(),
+        (None, Some(end)) => end.to_string(),
+        (None, None) => "Missing response info".to_string(),
+    }
+}
+
 pub fn http_populate_treeview(
     messages: &[HttpMessageData],
-    ls: &gtk::ListStore,
+    ts: &gtk::TreeStore,
     session_id: TcpStreamId,
     start_idx: usize,
     item_count: usize,
+    slow_threshold_ms: Option<u32>,
 ) {
     for (idx, http) in messages.iter().skip(start_idx).take(item_count).enumerate() {
-        let iter = ls.append();
-        ls.set_value(
+        let iter = ts.append(None);
+        let grpc_status = http
+            .response
+            .as_ref()
+            .filter(|r| grpc::is_grpc(r.content_type.as_deref()))
+            .and_then(grpc::grpc_status);
+        ts.set_value(
             &iter,
             0,
             &http
                 .request
                 .as_ref()
                 .map(|r| r.first_line.as_str())
-                .unwrap_or("Missing request info")
+                .unwrap_or(if http.websocket.is_some() {
+                    // websocket message from the server
+                    ""
+                } else {
+                    "Missing request info"
+                })
                 .to_value(),
         );
-        ls.set_value(
+        ts.set_value(
             &iter,
             1,
-            &http
-                .response
-                .as_ref()
-                .map(|r| r.first_line.as_str())
-                .unwrap_or("Missing response info")
-                .to_value(),
+            &http_response_summary(http, grpc_status.as_ref()).to_value(),
         );
-        ls.set_value(
+        ts.set_value(
             &iter,
             custom_streams_store::TREE_STORE_STREAM_ID_COL_IDX,
             &session_id.as_u32().to_value(),
         );
-        ls.set_value(
+        ts.set_value(
             &iter,
             custom_streams_store::TREE_STORE_MESSAGE_INDEX_COL_IDX,
             &((start_idx + idx) as i32).to_value(),
         );
         if let Some(ref rq) = http.request {
-            ls.set_value(&iter, 4, &rq.timestamp.to_string().to_value());
-            ls.set_value(&iter, 5, &rq.timestamp.timestamp_nanos().to_value());
+            ts.set_value(&iter, 4, &rq.timestamp.to_string().to_value());
+            ts.set_value(&iter, 5, &rq.timestamp.timestamp_nanos().to_value());
             if let Some(ref rs) = http.response {
-                ls.set_value(
-                    &iter,
-                    6,
-                    &(rs.timestamp - rq.timestamp).num_milliseconds().to_value(),
-                );
-                ls.set_value(
-                    &iter,
-                    7,
-                    &format!("{} ms", (rs.timestamp - rq.timestamp).num_milliseconds()).to_value(),
-                );
-                ls.set_value(&iter, 8, &rq.content_type.to_value());
-                ls.set_value(&iter, 9, &rs.content_type.to_value());
-                ls.set_value(&iter, 10, &rs.tcp_seq_number.as_u32().to_value());
+                let duration_ms = (rs.timestamp - rq.timestamp).num_milliseconds();
+                ts.set_value(&iter, 6, &duration_ms.to_value());
+                ts.set_value(&iter, 7, &format!("{} ms", duration_ms).to_value());
+                if slow_threshold_ms.map_or(false, |t| duration_ms > i64::from(t)) {
+                    ts.set_value(&iter, 13, &colors::WARNING_COLOR.to_value());
+                }
+                ts.set_value(&iter, 8, &rq.content_type.to_value());
+                ts.set_value(&iter, 9, &rs.content_type.to_value());
+                ts.set_value(&iter, 10, &rs.tcp_seq_number.as_u32().to_value());
             }
+        } else if let Some(rs) = http.response.as_ref().filter(|_| http.websocket.is_some()) {
+            ts.set_value(&iter, 4, &rs.timestamp.to_string().to_value());
+            ts.set_value(&iter, 5, &rs.timestamp.timestamp_nanos().to_value());
         }
-        ls.set_value(
+        ts.set_value(
             &iter,
             11,
             &colors::STREAM_COLORS[session_id.as_u32() as usize % colors::STREAM_COLORS.len()]
@@ -806,16 +909,30 @@ pub fn http_populate_treeview(
         let resp_code: Option<u16> = http
             .response
             .as_ref()
+            .filter(|_| http.websocket.is_none())
             .and_then(|r| {
                 r.first_line
                     .split_ascii_whitespace()
                     .find(str_is_numbers_only)
             })
             .and_then(|s| s.parse().ok());
-        ls.set_value(
+        ts.set_value(
             &iter,
             12,
             &match resp_code {
+                _ if matches!(http.http2_stream_end, Some(Http2StreamEnd::Reset(code)) if code != 0) => {
+                    colors::ERROR_COLOR.to_value()
+                }
+                _ if grpc_status.as_ref().map_or(false, |s| s.code != 0) => {
+                    colors::ERROR_COLOR.to_value()
+                }
+                _ if http
+                    .websocket
+                    .and_then(|ws| ws.close_code)
+                    .map_or(false, |c| !matches!(c, 1000 | 1001 | 1005)) =>
+                {
+                    colors::ERROR_COLOR.to_value()
+                }
                 Some(r) if (400..500).contains(&r) => colors::WARNING_COLOR.to_value(),
                 Some(r) if (500..600).contains(&r) => colors::ERROR_COLOR.to_value(),
                 _ => None::<&str>.to_value(),
@@ -863,7 +980,7 @@ pub enum HttpBody {
 }
 
 impl HttpBody {
-    fn len(&self) -> usize {
+    pub(crate) fn len(&self) -> usize {
         match &self {
             HttpBody::Text(s) => s.len(),
             HttpBody::Binary(v) => v.len(),
@@ -885,26 +1002,71 @@ pub struct HttpRequestResponseData {
     pub body: HttpBody,
     pub content_type: Option<String>,
     pub content_encoding: ContentEncoding,
+    // the headers sent after the body (only HTTP2 for now)
+    pub trailers: Vec<(String, String)>,
 }
 
 impl HttpRequestResponseData {
+    /// for requests: the method and the path. The first line is
+    /// "GET /path HTTP/1.1" for http1 and "GET /path" for http2.
+    pub fn request_method_and_path(&self) -> (&str, &str) {
+        let mut it = self.first_line.split(' ');
+        (it.next().unwrap_or("-"), it.next().unwrap_or("-"))
+    }
+
+    /// for requests: rebuild the full URL, from the Host header for http1,
+    /// or from the :scheme, :authority and :path pseudo-headers for http2
+    pub fn request_url(&self, server_ip: IpAddr, server_port: NetworkPort) -> String {
+        let scheme = get_http_header_value(&self.headers, ":scheme")
+            .map(|s| s.as_str())
+            .unwrap_or("http");
+        let host = get_http_header_value(&self.headers, ":authority")
+            .or_else(|| get_http_header_value(&self.headers, "Host"))
+            .cloned()
+            .unwrap_or_else(|| match server_ip {
+                IpAddr::V6(_) => format!("[{}]:{}", server_ip, server_port.0),
+                IpAddr::V4(_) => format!("{}:{}", server_ip, server_port.0),
+            });
+        let path = get_http_header_value(&self.headers, ":path")
+            .map(|s| s.as_str())
+            .unwrap_or_else(|| self.request_method_and_path().1);
+        format!("{}://{}{}", scheme, host, path)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let body_str = self.body_as_str();
+        serde_json::json!({
+            "timestamp": self.timestamp.to_string(),
+            "first_line": self.first_line,
+            "headers": self.headers
+                .iter()
+                .map(|(k, v)| serde_json::json!({"name": k, "value": v}))
+                .collect::<Vec<_>>(),
+            "content_type": self.content_type,
+            "trailers": self.trailers
+                .iter()
+                .map(|(k, v)| serde_json::json!({"name": k, "value": v}))
+                .collect::<Vec<_>>(),
+            "body": body_str,
+            // binary bodies that we couldn't decode as text
+            "body_base64": match (&body_str, &self.body) {
+                (None, HttpBody::Binary(b)) => Some(base64::encode(b)),
+                _ => None,
+            },
+        })
+    }
+
     pub fn body_as_str(&self) -> Option<Cow<str>> {
         match (&self.body, &self.content_encoding) {
             (HttpBody::Text(s), _) => Some(Cow::Borrowed(s)), // tshark will do some decoding for us... could have text even if the encoding is gzip
-            (HttpBody::Binary(bytes), ContentEncoding::Brotli) => {
-                let mut r = String::new();
-                brotli::Decompressor::new(&bytes[..], 4096)
-                    .read_to_string(&mut r)
-                    .ok()
-                    .map(|_| Cow::Owned(r))
-            }
-            (HttpBody::Binary(bytes), ContentEncoding::Gzip) => {
-                // not sure we really need the gzip. i think tshark always decodes gzip for us
-                // (doesn't do it for brotli!!)
-                let mut d = GzDecoder::new(&bytes[..]);
-                let mut r = String::new();
-                d.read_to_string(&mut r).ok().map(|_| Cow::Owned(r))
-            }
+            (HttpBody::Binary(_), ContentEncoding::Plain) => None,
+            // tshark decodes gzip & deflate, and brotli & zstd in recent versions,
+            // but not stacked encodings. If the decoding fails, tshark probably
+            // decoded already, and the body is really binary.
+            (HttpBody::Binary(bytes), encoding) => encoding
+                .decode(bytes)
+                .and_then(|d| String::from_utf8(d).ok())
+                .map(Cow::Owned),
             _ => None,
         }
     }
@@ -915,16 +1077,179 @@ pub enum ContentEncoding {
     Plain,
     Gzip,
     Brotli,
-    // TODO deflate
+    Deflate,
+    Zstd,
+    // the unix "compress" LZW format. Obsolete, but still in the HTTP spec
+    Compress,
+    // for instance "gzip, br": in the order they were applied
+    Stacked(Vec<ContentEncoding>),
 }
 
 impl ContentEncoding {
     pub fn parse_from_str(input: &Option<&str>) -> ContentEncoding {
-        match input {
-            Some("br") => ContentEncoding::Brotli,
-            Some("gzip") => ContentEncoding::Gzip,
-            _ => ContentEncoding::Plain,
+        let mut encodings: Vec<_> = input
+            .unwrap_or("")
+            .split(',')
+            .map(|e| match e.trim().to_ascii_lowercase().as_str() {
+                "br" => ContentEncoding::Brotli,
+                "gzip" | "x-gzip" => ContentEncoding::Gzip,
+                "deflate" => ContentEncoding::Deflate,
+                "zstd" => ContentEncoding::Zstd,
+                "compress" | "x-compress" => ContentEncoding::Compress,
+                _ => ContentEncoding::Plain,
+            })
+            .filter(|e| *e != ContentEncoding::Plain)
+            .collect();
+        match encodings.len() {
+            0 => ContentEncoding::Plain,
+            1 => encodings.remove(0),
+            _ => ContentEncoding::Stacked(encodings),
+        }
+    }
+
+    /// None if the data is not valid for this encoding
+    pub fn decode(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let mut r = vec![];
+        match self {
+            ContentEncoding::Plain => r.extend_from_slice(data),
+            ContentEncoding::Gzip => {
+                MultiGzDecoder::new(data).read_to_end(&mut r).ok()?;
+            }
+            ContentEncoding::Brotli => {
+                brotli::Decompressor::new(data, 4096)
+                    .read_to_end(&mut r)
+                    .ok()?;
+            }
+            // deflate is supposed to be zlib-wrapped, but some servers send it raw
+            ContentEncoding::Deflate => {
+                if ZlibDecoder::new(data).read_to_end(&mut r).is_err() {
+                    r.clear();
+                    DeflateDecoder::new(data).read_to_end(&mut r).ok()?;
+                }
+            }
+            ContentEncoding::Zstd => {
+                ruzstd::StreamingDecoder::new(data)
+                    .ok()?
+                    .read_to_end(&mut r)
+                    .ok()?;
+            }
+            ContentEncoding::Compress => return decode_unix_compress(data),
+            ContentEncoding::Stacked(encodings) => {
+                return encodings
+                    .iter()
+                    .rev()
+                    .try_fold(data.to_vec(), |d, e| e.decode(&d))
+            }
         }
+        Some(r)
+    }
+}
+
+/// https://en.wikipedia.org/wiki/Compress_(software)
+/// LZW with codes from 9 bits up to the max bits from the header. The codes
+/// are written in groups of 8, and when the code size changes, the rest of
+/// the current group is padding.
+fn decode_unix_compress(data: &[u8]) -> Option<Vec<u8>> {
+    const CLEAR: usize = 256;
+    if data.get(..2)? != [0x1f, 0x9d] {
+        return None;
+    }
+    let max_bits = u32::from(*data.get(2)? & 0x1f);
+    let block_mode = data[2] & 0x80 != 0;
+    if !(9..=16).contains(&max_bits) {
+        return None;
+    }
+    let max_max_code = 1usize << max_bits;
+    let data = &data[3..];
+    let total_bits = data.len() * 8;
+
+    // prefix code & last byte of each table entry
+    let mut table: Vec<(usize, u8)> = (0..=255).map(|b| (0, b)).collect();
+    table.push((0, 0)); // CLEAR, or the first free entry without block mode
+    let mut free_entry = if block_mode { 257 } else { 256 };
+    table.truncate(free_entry);
+    let mut n_bits = 9;
+    let mut max_code = (1usize << n_bits) - 1;
+    let mut pos = 0;
+    let mut group_start = 0;
+    let mut prev_code: Option<usize> = None;
+    let mut last_byte = 0;
+    let mut result = vec![];
+    let mut stack = vec![];
+    loop {
+        if free_entry > max_code {
+            pos = next_code_group(pos, group_start, n_bits);
+            group_start = pos;
+            n_bits += 1;
+            max_code = if n_bits == max_bits {
+                max_max_code
+            } else {
+                (1usize << n_bits) - 1
+            };
+        }
+        if pos + n_bits as usize > total_bits {
+            break;
+        }
+        let code = (0..n_bits as usize).fold(0, |code, i| {
+            let bit = pos + i;
+            code | (usize::from((data[bit / 8] >> (bit % 8)) & 1) << i)
+        });
+        pos += n_bits as usize;
+        let prev = match prev_code {
+            None => {
+                // the first code is a literal
+                last_byte = u8::try_from(code).ok()?;
+                result.push(last_byte);
+                prev_code = Some(code);
+                continue;
+            }
+            Some(p) => p,
+        };
+        if code == CLEAR && block_mode {
+            table.truncate(257);
+            free_entry = 256;
+            pos = next_code_group(pos, group_start, n_bits);
+            group_start = pos;
+            n_bits = 9;
+            max_code = (1 << n_bits) - 1;
+            continue;
+        }
+        let mut cur = code;
+        if code >= free_entry {
+            // the code we're defining right now (KwKwK)
+            if code > free_entry {
+                return None;
+            }
+            stack.push(last_byte);
+            cur = prev;
+        }
+        while cur > 255 {
+            let (prefix, byte) = *table.get(cur)?;
+            stack.push(byte);
+            cur = prefix;
+        }
+        last_byte = cur as u8;
+        stack.push(last_byte);
+        result.extend(stack.drain(..).rev());
+        if free_entry < max_max_code {
+            if free_entry < table.len() {
+                table[free_entry] = (prev, last_byte);
+            } else {
+                table.push((prev, last_byte));
+            }
+            free_entry += 1;
+        }
+        prev_code = Some(code);
+    }
+    Some(result)
+}
+
+/// where the next group of 8 codes of n_bits starts
+fn next_code_group(pos: usize, group_start: usize, n_bits: u32) -> usize {
+    let group_bits = n_bits as usize * 8;
+    match (pos - group_start) % group_bits {
+        0 => pos,
+        rest => pos + group_bits - rest,
     }
 }
 
@@ -933,6 +1258,64 @@ pub struct HttpMessageData {
     pub http_stream_id: u32, // only used for http2. always 0 for http1
     pub request: Option<HttpRequestResponseData>,
     pub response: Option<HttpRequestResponseData>,
+    // only used for http2. always None for http1
+    pub http2_stream_end: Option<Http2StreamEnd>,
+    // only used for websocket messages, after a HTTP/1.1 upgrade. The message
+    // is in the request if it's from the client, in the response otherwise.
+    pub websocket: Option<WebSocketInfo>,
+}
+
+/// an HTTP2 stream that was ended abnormally, before or after
+/// its request & response were complete
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Http2StreamEnd {
+    /// RST_STREAM frame, with its error code
+    Reset(u32),
+    /// the connection was closed by a GOAWAY frame before the stream
+    /// completed, with the GOAWAY error code
+    GoAway(u32),
+}
+
+impl fmt::Display for Http2StreamEnd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Http2StreamEnd::Reset(code) => {
+                write!(
+                    f,
+                    "stream reset: {}",
+                    tshark_http2::http2_error_code_name(*code)
+                )
+            }
+            Http2StreamEnd::GoAway(code) => write!(
+                f,
+                "connection closed (GOAWAY): {}",
+                tshark_http2::http2_error_code_name(*code)
+            ),
+        }
+    }
+}
+
+impl HttpMessageData {
+    /// None if we're missing the request or the response
+    pub fn duration_ms(&self) -> Option<i64> {
+        match (&self.request, &self.response) {
+            (Some(rq), Some(rs)) => Some((rs.timestamp - rq.timestamp).num_milliseconds()),
+            _ => None,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "http_stream_id": self.http_stream_id,
+            "request": self.request.as_ref().map(|r| r.to_json()),
+            "response": self.response.as_ref().map(|r| r.to_json()),
+            "http2_stream_end": self.http2_stream_end.map(|e| e.to_string()),
+            "websocket": self.websocket.map(|ws| serde_json::json!({
+                "opcode": ws.opcode.to_string(),
+                "close_code": ws.close_code,
+            })),
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -971,6 +1354,84 @@ fn parse_body(body: Option<Vec<u8>>, _headers: &[(String, String)]) -> HttpBody
     .unwrap_or(HttpBody::Missing)
 }
 
+/// a "101 Switching Protocols" response to a websocket upgrade
+fn websocket_upgrade(response: &HttpRequestResponseData) -> Option<WebSocketStream> {
+    if response.first_line.split(' ').nth(1) != Some("101")
+        || !get_http_header_value(&response.headers, "Upgrade")
+            .map_or(false, |u| u.eq_ignore_ascii_case("websocket"))
+    {
+        return None;
+    }
+    Some(WebSocketStream::new(
+        get_http_header_value(&response.headers, "Sec-WebSocket-Extensions")
+            .map_or(false, |e| e.contains("permessage-deflate")),
+    ))
+}
+
+fn websocket_message_data(
+    msg: WebSocketMessage,
+    basic_info: &TSharkPacketBasicInfo,
+) -> HttpMessageData {
+    let info = msg.info();
+    let (first_line, body) = match msg.opcode {
+        WebSocketOpcode::Text => {
+            let text = String::from_utf8_lossy(&msg.payload).to_string();
+            let preview: String = text
+                .lines()
+                .next()
+                .unwrap_or("")
+                .chars()
+                .take(100)
+                .collect();
+            (format!("{}: {}", info, preview), HttpBody::Text(text))
+        }
+        WebSocketOpcode::Close => (
+            info.to_string(),
+            msg.close_reason()
+                .map(HttpBody::Text)
+                .unwrap_or(HttpBody::Missing),
+        ),
+        _ if msg.payload.is_empty() => (info.to_string(), HttpBody::Missing),
+        _ => (
+            format!("{}: {} bytes", info, msg.payload.len()),
+            match String::from_utf8(msg.payload) {
+                Ok(text) => HttpBody::Text(text),
+                Err(e) => HttpBody::Binary(e.into_bytes()),
+            },
+        ),
+    };
+    // so that code_formatting pretty-prints JSON messages
+    let content_type = match &body {
+        HttpBody::Text(t) if serde_json::from_str::<serde_json::Value>(t).is_ok() => {
+            Some("application/json".to_string())
+        }
+        _ => None,
+    };
+    let data = HttpRequestResponseData {
+        tcp_stream_no: basic_info.tcp_stream_id,
+        tcp_seq_number: basic_info.tcp_seq_number,
+        timestamp: basic_info.frame_time,
+        first_line,
+        headers: vec![],
+        body,
+        content_type,
+        content_encoding: ContentEncoding::Plain,
+        trailers: vec![],
+    };
+    let (request, response) = if msg.from_client {
+        (Some(data), None)
+    } else {
+        (None, Some(data))
+    };
+    HttpMessageData {
+        http_stream_id: 0,
+        request,
+        response,
+        http2_stream_end: None,
+        websocket: Some(info),
+    }
+}
+
 fn parse_request_response(
     comm: TSharkPacket,
     server_ip_port_if_known: Option<(IpAddr, NetworkPort)>,
@@ -989,6 +1450,11 @@ fn parse_request_response(
         Some(http_list) => {
             for http in http_list.into_iter() {
                 let http_headers = parse_headers(&http.other_lines);
+                // tshark may have decoded the body already, then decoding it again fails,
+                // and we keep the body as is
+                let content_encoding = ContentEncoding::parse_from_str(
+                    &get_http_header_value(&http_headers, "Content-Encoding").map(|s| s.as_str()),
+                );
                 let http_type = http.http_type.or(ip_port_req_resp_guess);
                 match http_type {
                     Some(HttpType::Request) => reqresp.push(ReqRespInfo {
@@ -1000,7 +1466,8 @@ fn parse_request_response(
                             first_line: http.first_line,
                             headers: http_headers,
                             content_type: http.content_type,
-                            content_encoding: ContentEncoding::Plain, // not sure whether maybe tshark decodes before us...
+                            content_encoding,
+                            trailers: vec![],
                         }),
                         port_dst: comm.basic_info.port_dst,
                         ip_dst,
@@ -1016,7 +1483,8 @@ fn parse_request_response(
                             first_line: http.first_line,
                             headers: http_headers,
                             content_type: http.content_type,
-                            content_encoding: ContentEncoding::Plain, // not sure whether maybe tshark decodes before us...
+                            content_encoding,
+                            trailers: vec![],
                         }),
                         port_dst: comm.basic_info.port_src,
                         ip_dst: ip_src,
@@ -1129,6 +1597,7 @@ fn should_parse_plain_request_response() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                trailers: vec![],
             }),
             response: Some(HttpRequestResponseData {
                 tcp_stream_no: TcpStreamId(0),
@@ -1139,7 +1608,10 @@ fn should_parse_plain_request_response() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                trailers: vec![],
             }),
+            http2_stream_end: None,
+            websocket: None,
         },
         HttpMessageData {
             http_stream_id: 0,
@@ -1152,8 +1624,11 @@ fn should_parse_plain_request_response() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                trailers: vec![],
             }),
             response: None,
+            http2_stream_end: None,
+            websocket: None,
         },
     ];
     assert_eq!(expected, parsed);
@@ -1210,6 +1685,7 @@ fn should_add_non_http_tcp_stream_at_end_of_exchange() {
             body: HttpBody::Missing,
             content_type: None,
             content_encoding: ContentEncoding::Plain,
+            trailers: vec![],
         }),
         response: Some(HttpRequestResponseData {
             tcp_stream_no: TcpStreamId(1),
@@ -1223,7 +1699,10 @@ fn should_add_non_http_tcp_stream_at_end_of_exchange() {
             body: HttpBody::Text("{\"attributes\":{\"description\":\"\",\"kibanaSavedObjectMeta\":{\"searchSourceJSON\":\"{\\\"filter\\\":[],\\\"query\\\":{\\\"language\\\":\\\"kuery\\\",\\\"query\\\":\\\"\\\"}}\"},\"title\":\"System Navigat".to_string()),
             content_type: Some("application/ndjson".to_string()),
             content_encoding: ContentEncoding::Plain,
+            trailers: vec![],
         }),
+        http2_stream_end: None,
+        websocket: None,
     }];
     assert_eq!(expected, parsed);
 }
@@ -1298,6 +1777,7 @@ fn should_parse_http11_pipelining() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                trailers: vec![],
             }),
             response: Some(HttpRequestResponseData {
                 tcp_stream_no: TcpStreamId(0),
@@ -1308,7 +1788,10 @@ fn should_parse_http11_pipelining() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                trailers: vec![],
             }),
+            http2_stream_end: None,
+            websocket: None,
         },
         HttpMessageData {
             http_stream_id: 0,
@@ -1321,6 +1804,7 @@ fn should_parse_http11_pipelining() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                trailers: vec![],
             }),
             response: Some(HttpRequestResponseData {
                 tcp_stream_no: TcpStreamId(0),
@@ -1331,8 +1815,121 @@ fn should_parse_http11_pipelining() {
                 body: HttpBody::Missing,
                 content_type: None,
                 content_encoding: ContentEncoding::Plain,
+                trailers: vec![],
             }),
+            http2_stream_end: None,
+            websocket: None,
         },
     ];
     assert_eq!(expected, parsed);
 }
+
+#[test]
+fn should_decode_content_encodings() {
+    let decode = |encoding, hex_data| {
+        ContentEncoding::parse_from_str(&Some(encoding))
+            .decode(&hex::decode(hex_data).unwrap())
+            .map(|d| String::from_utf8(d).unwrap())
+    };
+    assert_eq!(
+        ContentEncoding::Stacked(vec![ContentEncoding::Gzip, ContentEncoding::Zstd]),
+        ContentEncoding::parse_from_str(&Some("gzip, zstd"))
+    );
+    assert_eq!(
+        ContentEncoding::Plain,
+        ContentEncoding::parse_from_str(&Some("identity"))
+    );
+    assert_eq!(
+        Some("zlib deflate".to_string()),
+        decode("deflate", "789cabcac94c5248494dcb492c4905001e2c04a7")
+    );
+    // raw deflate, without the zlib wrapper
+    assert_eq!(
+        Some("raw deflate".to_string()),
+        decode("deflate", "2b4a2c5748494dcb492c490500")
+    );
+    assert_eq!(
+        Some("{\"status\":\"ok\"}".to_string()),
+        decode(
+            "zstd",
+            "28b52ffd04587900007b22737461747573223a226f6b227da9f9807f"
+        )
+    );
+    assert_eq!(
+        Some("{\"status\":\"ok\"}".to_string()),
+        decode(
+            "gzip, zstd",
+            "28b52ffd04581901001f8b0800000000000003ab562a2e492c292d56b252cacf56aa0500dded131d0f0000001d4f2bdb"
+        )
+    );
+    assert_eq!(
+        Some("TOBEORNOTTOBEORTOBEORNOT".to_string()),
+        decode("compress", "1f9d90549e0829f2448a932754020e2ca890a04184")
+    );
+    assert_eq!(None, decode("zstd", "1234"));
+}
+
+#[test]
+fn should_dechunk_tcp_leftover_payload() {
+    let parsed = HttpStreamGlobals::parse_as_http(
+        TcpSeqNumber(1),
+        NaiveDate::from_ymd(2021, 3, 5).and_hms(8, 49, 52),
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nhel\r\n2\r\nlo\r\n0\r\n\r\n"
+            .to_vec(),
+        TcpStreamId(1),
+    )
+    .unwrap();
+    assert_eq!(HttpBody::Text("hello".to_string()), parsed.body);
+}
+
+#[test]
+fn status_searches_should_match_the_response_column() {
+    let known_keys: std::collections::BTreeSet<_> =
+        HttpStringFilterKeys::VARIANTS.iter().copied().collect();
+    let no_keys = std::collections::BTreeSet::new();
+    let response_row = |first_line: &str| {
+        // http2 responses come from the http2 parser, but with the same first line
+        let response = HttpStreamGlobals::parse_as_http(
+            TcpSeqNumber(1),
+            NaiveDate::from_ymd(2021, 3, 5).and_hms(8, 49, 52),
+            format!("{}\r\nContent-Length: 0\r\n\r\n", first_line).into_bytes(),
+            TcpStreamId(1),
+        )
+        .unwrap();
+        http_response_summary(
+            &HttpMessageData {
+                http_stream_id: 0,
+                request: None,
+                response: Some(response),
+                http2_stream_end: None,
+                websocket: None,
+            },
+            None,
+        )
+    };
+    let searches = crate::saved_searches::builtin_saved_searches();
+    let matches = |search_name: &str, row: &str| {
+        let search = searches.iter().find(|s| s.name == search_name).unwrap();
+        match search_expr::parse_search(&known_keys, &no_keys)(&search.search) {
+            Ok(("", search_expr::SearchExpr::SearchOpExpr(expr))) => {
+                assert_eq!("http.resp_status", expr.filter_key);
+                expr.op.matches_str(row)
+            }
+            _ => panic!("{}", search.search),
+        }
+    };
+    for (row, is_5xx, is_4xx) in &[
+        ("HTTP/1.1 500 Internal Server Error", true, false),
+        ("HTTP/1.0 503 Service Unavailable", true, false),
+        ("HTTP/2 status 502", true, false),
+        ("HTTP/1.1 404 Not Found", false, true),
+        ("HTTP/2 status 429", false, true),
+        ("HTTP/1.1 200 OK", false, false),
+        ("HTTP/2 status 200", false, false),
+        ("HTTP/1.1 301 Moved 500 times", false, false),
+    ] {
+        let row = response_row(row);
+        assert_eq!(*is_5xx, matches("HTTP 5xx", &row), "{}", row);
+        assert_eq!(*is_4xx, matches("HTTP 4xx", &row), "{}", row);
+    }
+}
@@ -1,5 +1,11 @@
-mod code_formatting;
+pub mod code_formatting;
+pub mod form_data;
+pub mod har;
 mod http_body_widget;
 pub mod http_details_widget;
+pub mod http_stats;
 pub mod http_streams_store;
+pub mod native_http;
+pub mod request_commands;
 pub mod tshark_http;
+pub mod websocket;
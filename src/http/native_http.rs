@@ -0,0 +1,336 @@
+// HTTP/1 parsing for the native capture reader. We rebuild what the tshark
+// http dissector gives us: the headers, and the body, de-chunked & decompressed.
+use super::tshark_http::{HttpType, TSharkHttp};
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+use std::collections::VecDeque;
+use std::io::Read;
+use std::str;
+
+const MAX_HEADERS_SIZE: usize = 64 * 1024;
+
+const HTTP_METHODS: &[&str] = &[
+    "GET", "POST", "PUT", "DELETE", "HEAD", "OPTIONS", "PATCH", "CONNECT", "TRACE",
+];
+
+/// does this look like the start of an HTTP/1 request or response?
+pub fn is_http1_start(data: &[u8]) -> Option<HttpType> {
+    if data.starts_with(b"HTTP/1.") {
+        return Some(HttpType::Response);
+    }
+    HTTP_METHODS
+        .iter()
+        .any(|m| data.starts_with(m.as_bytes()) && data.get(m.len()) == Some(&b' '))
+        .then_some(HttpType::Request)
+}
+
+enum BodyLength {
+    NoBody,
+    ContentLength(usize),
+    Chunked,
+    UntilClose,
+}
+
+#[derive(Default)]
+pub struct Http1StreamParser {
+    client_data: Vec<u8>,
+    server_data: Vec<u8>,
+    // the methods of the requests waiting for a response:
+    // the responses to HEAD requests have no body
+    request_methods: VecDeque<String>,
+    // after a protocol upgrade (websockets...), it's not HTTP/1 anymore
+    upgraded: bool,
+}
+
+impl Http1StreamParser {
+    pub fn add_data(&mut self, from_client: bool, data: &[u8]) -> Result<Vec<TSharkHttp>, String> {
+        if from_client {
+            self.client_data.extend_from_slice(data);
+        } else {
+            self.server_data.extend_from_slice(data);
+        }
+        self.parse_messages(from_client, false)
+    }
+
+    /// after a protocol upgrade, the data that's not HTTP/1 anymore. The
+    /// HTTP store decodes it if it's websocket frames.
+    pub fn take_upgraded_data(&mut self, from_client: bool) -> Option<Vec<u8>> {
+        if !self.upgraded {
+            return None;
+        }
+        let data = if from_client {
+            &mut self.client_data
+        } else {
+            &mut self.server_data
+        };
+        Some(std::mem::take(data)).filter(|d| !d.is_empty())
+    }
+
+    /// the connection was closed in that direction: complete the messages
+    /// which are delimited by the end of the connection
+    pub fn end_of_data(&mut self, from_client: bool) -> Result<Vec<TSharkHttp>, String> {
+        if self.upgraded {
+            return Ok(vec![]);
+        }
+        self.parse_messages(from_client, true)
+    }
+
+    fn parse_messages(
+        &mut self,
+        from_client: bool,
+        at_close: bool,
+    ) -> Result<Vec<TSharkHttp>, String> {
+        let mut result = vec![];
+        loop {
+            let data = if from_client {
+                &self.client_data
+            } else {
+                &self.server_data
+            };
+            if data.is_empty() || self.upgraded {
+                break;
+            }
+            let request_method = if from_client {
+                None
+            } else {
+                self.request_methods.front().map(|m| m.as_str())
+            };
+            match parse_message(data, from_client, request_method, at_close)? {
+                Some((msg, consumed)) => {
+                    if from_client {
+                        self.client_data.drain(..consumed);
+                        let method = msg.first_line.split(' ').next().unwrap_or("");
+                        self.request_methods.push_back(method.to_string());
+                    } else {
+                        self.server_data.drain(..consumed);
+                        let status = response_status(&msg.first_line);
+                        // 1xx responses are informational, the final response follows
+                        if !matches!(status, Some(100..=199)) || status == Some(101) {
+                            self.request_methods.pop_front();
+                        }
+                        if status == Some(101) {
+                            self.upgraded = true;
+                        }
+                    }
+                    result.push(msg);
+                }
+                None => break,
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn response_status(first_line: &str) -> Option<u16> {
+    first_line.split(' ').nth(1).and_then(|s| s.parse().ok())
+}
+
+fn find_subslice(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|w| w == needle)
+}
+
+/// the message and the number of bytes it took, or None if it's incomplete
+fn parse_message(
+    data: &[u8],
+    is_request: bool,
+    request_method: Option<&str>,
+    at_close: bool,
+) -> Result<Option<(TSharkHttp, usize)>, String> {
+    let headers_end = match find_subslice(data, b"\r\n\r\n") {
+        Some(e) => e,
+        None if data.len() > MAX_HEADERS_SIZE => {
+            return Err("Can't find the end of the HTTP headers".to_string())
+        }
+        None => return Ok(None),
+    };
+    let head = String::from_utf8_lossy(&data[..headers_end]);
+    let mut lines = head.split("\r\n");
+    let first_line = lines.next().unwrap_or("").to_string();
+    let headers: Vec<(&str, &str)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+    let header_value = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| *v)
+    };
+    let status = if is_request {
+        None
+    } else {
+        response_status(&first_line)
+    };
+    let body_length = if matches!(status, Some(100..=199) | Some(204) | Some(304))
+        || (!is_request && request_method == Some("HEAD"))
+    {
+        BodyLength::NoBody
+    } else if header_value("Transfer-Encoding")
+        .filter(|te| te.to_ascii_lowercase().contains("chunked"))
+        .is_some()
+    {
+        BodyLength::Chunked
+    } else if let Some(len) = header_value("Content-Length") {
+        BodyLength::ContentLength(
+            len.parse()
+                .map_err(|_| format!("Invalid HTTP Content-Length: {}", len))?,
+        )
+    } else if is_request {
+        BodyLength::NoBody
+    } else {
+        BodyLength::UntilClose
+    };
+    let body_start = headers_end + 4;
+    let body_data = &data[body_start..];
+    let (body, body_len) = match body_length {
+        BodyLength::NoBody => (vec![], 0),
+        BodyLength::ContentLength(len) if body_data.len() >= len => {
+            (body_data[..len].to_vec(), len)
+        }
+        BodyLength::ContentLength(_) => return Ok(None),
+        BodyLength::Chunked => match dechunk(body_data) {
+            Some(r) => r,
+            None => return Ok(None),
+        },
+        BodyLength::UntilClose if at_close => (body_data.to_vec(), body_data.len()),
+        BodyLength::UntilClose => return Ok(None),
+    };
+    let body = match header_value("Content-Encoding") {
+        Some(encoding) => decompress(encoding, body),
+        None => body,
+    };
+    let other_lines: String = headers
+        .iter()
+        .map(|(k, v)| format!("{}: {}\r\n", k, v))
+        .collect();
+    Ok(Some((
+        TSharkHttp {
+            http_type: Some(if is_request {
+                HttpType::Request
+            } else {
+                HttpType::Response
+            }),
+            http_host: header_value("Host").map(|h| h.to_string()),
+            first_line,
+            other_lines,
+            body: Some(body).filter(|b| !b.is_empty()),
+            content_type: header_value("Content-Type").map(|c| c.to_string()),
+        },
+        body_start + body_len,
+    )))
+}
+
+/// the de-chunked body, and the number of bytes the chunks took,
+/// or None if we don't have all the chunks yet
+pub fn dechunk(data: &[u8]) -> Option<(Vec<u8>, usize)> {
+    // we're called every time we get more data, so we copy the chunk
+    // contents only once we know we have all the chunks
+    let mut chunk_ranges = vec![];
+    let mut pos = 0;
+    loop {
+        let line_end = pos + find_subslice(&data[pos..], b"\r\n")?;
+        let size_line = str::from_utf8(&data[pos..line_end]).ok()?;
+        // ignore the chunk extensions
+        let size_str = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_str, 16).ok()?;
+        pos = line_end + 2;
+        if size == 0 {
+            // the trailers, then an empty line
+            loop {
+                let line_end = pos + find_subslice(&data[pos..], b"\r\n")?;
+                let is_empty_line = line_end == pos;
+                pos = line_end + 2;
+                if is_empty_line {
+                    let body = chunk_ranges
+                        .into_iter()
+                        .flat_map(|r: std::ops::Range<usize>| data[r].iter().copied())
+                        .collect();
+                    return Some((body, pos));
+                }
+            }
+        }
+        // the chunk data is followed by \r\n
+        data.get(pos..pos + size + 2)?;
+        chunk_ranges.push(pos..pos + size);
+        pos += size + 2;
+    }
+}
+
+/// tshark decompresses gzip & deflate, so we do too.
+/// if the decompression fails, we keep the raw contents
+fn decompress(encoding: &str, body: Vec<u8>) -> Vec<u8> {
+    if body.is_empty() {
+        return body;
+    }
+    let mut decoded = vec![];
+    let ok = match encoding.trim().to_ascii_lowercase().as_str() {
+        "gzip" | "x-gzip" => MultiGzDecoder::new(&body[..])
+            .read_to_end(&mut decoded)
+            .is_ok(),
+        // deflate is supposed to be zlib-wrapped, but some servers send it raw
+        "deflate" => {
+            ZlibDecoder::new(&body[..])
+                .read_to_end(&mut decoded)
+                .is_ok()
+                || {
+                    decoded.clear();
+                    DeflateDecoder::new(&body[..])
+                        .read_to_end(&mut decoded)
+                        .is_ok()
+                }
+        }
+        _ => false,
+    };
+    if ok {
+        decoded
+    } else {
+        body
+    }
+}
+
+#[test]
+fn should_parse_pipelined_messages_and_chunked_body() {
+    let mut parser = Http1StreamParser::default();
+    let reqs = parser
+        .add_data(true, b"HEAD / HTTP/1.1\r\nHost: a.com\r\n\r\nPOST /x HTTP/1.1\r\nContent-Length: 4\r\n\r\nab")
+        .unwrap();
+    assert_eq!(1, reqs.len());
+    assert_eq!("HEAD / HTTP/1.1", reqs[0].first_line);
+    assert_eq!(Some("a.com".to_string()), reqs[0].http_host);
+    assert_eq!("Host: a.com\r\n", reqs[0].other_lines);
+    let reqs = parser.add_data(true, b"cd").unwrap();
+    assert_eq!(Some(b"abcd".to_vec()), reqs[0].body);
+
+    let resps = parser
+        .add_data(
+            false,
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nHTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Type: text/plain\r\n\r\n3;x=y\r\nhel\r\n2\r\nlo\r\n0\r\n\r\n",
+        )
+        .unwrap();
+    assert_eq!(2, resps.len());
+    // the response to HEAD has no body, despite the content-length
+    assert_eq!(None, resps[0].body);
+    assert_eq!(Some(b"hello".to_vec()), resps[1].body);
+    assert_eq!(Some("text/plain".to_string()), resps[1].content_type);
+}
+
+#[test]
+fn should_pass_on_the_data_after_an_upgrade() {
+    let mut parser = Http1StreamParser::default();
+    parser
+        .add_data(true, b"GET /ws HTTP/1.1\r\nUpgrade: websocket\r\n\r\n")
+        .unwrap();
+    assert_eq!(None, parser.take_upgraded_data(true));
+    let resps = parser
+        .add_data(
+            false,
+            b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n\x81\x02hi",
+        )
+        .unwrap();
+    assert_eq!(1, resps.len());
+    assert_eq!(
+        Some(b"\x81\x02hi".to_vec()),
+        parser.take_upgraded_data(false)
+    );
+    assert!(parser.add_data(true, b"\x88\x00").unwrap().is_empty());
+    assert_eq!(Some(b"\x88\x00".to_vec()), parser.take_upgraded_data(true));
+}
@@ -0,0 +1,170 @@
+// rebuild a captured request as a command-line, to replay it by hand
+use super::http_streams_store::{HttpBody, HttpRequestResponseData};
+use itertools::Itertools;
+
+// https://datatracker.ietf.org/doc/html/rfc2616#section-13.5.1
+// plus the headers that the tool computes from the URL and the body
+const SKIPPED_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "trailers",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+    // we replay the decoded body
+    "content-encoding",
+];
+
+fn kept_headers(request: &HttpRequestResponseData) -> impl Iterator<Item = &(String, String)> {
+    request.headers.iter().filter(|(k, _)| {
+        // the http2 pseudo-headers are translated into the URL & method
+        !k.starts_with(':') && !SKIPPED_HEADERS.contains(&k.to_ascii_lowercase().as_str())
+    })
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// the decoded body, quoted for the shell. Binary bodies use the bash $'..' syntax
+fn shell_quoted_body(request: &HttpRequestResponseData) -> Option<String> {
+    match (request.body_as_str(), &request.body) {
+        (Some(s), _) if s.is_empty() => None,
+        (Some(s), _) => Some(shell_quote(&s)),
+        (None, HttpBody::Binary(b)) => {
+            // if the decoding fails, tshark probably decoded it already
+            let decoded = request.content_encoding.decode(b);
+            Some(format!(
+                "$'{}'",
+                decoded
+                    .as_deref()
+                    .unwrap_or(b)
+                    .iter()
+                    .map(|c| format!("\\x{:02x}", c))
+                    .join("")
+            ))
+        }
+        (None, _) => None,
+    }
+}
+
+pub fn as_curl(request: &HttpRequestResponseData, url: &str) -> String {
+    let (method, _) = request.request_method_and_path();
+    let mut parts = vec![format!("curl {}", shell_quote(url))];
+    match method {
+        "GET" => {}
+        // with -X HEAD, curl waits for a body that never comes
+        "HEAD" => parts.push("-I".to_string()),
+        _ => parts.push(format!("-X {}", method)),
+    }
+    parts.extend(
+        kept_headers(request).map(|(k, v)| format!("-H {}", shell_quote(&format!("{}: {}", k, v)))),
+    );
+    if let Some(body) = shell_quoted_body(request) {
+        parts.push(format!("--data-binary {}", body));
+    }
+    parts.join(" \\\n  ")
+}
+
+pub fn as_httpie(request: &HttpRequestResponseData, url: &str) -> String {
+    let (method, _) = request.request_method_and_path();
+    let mut parts = vec![format!("http {} {}", method, shell_quote(url))];
+    parts.extend(kept_headers(request).map(|(k, v)| shell_quote(&format!("{}:{}", k, v))));
+    if let Some(body) = shell_quoted_body(request) {
+        parts.push(format!("--raw {}", body));
+    }
+    parts.join(" \\\n  ")
+}
+
+#[cfg(test)]
+use {
+    super::http_streams_store::ContentEncoding,
+    crate::tshark_communication::{NetworkPort, TcpSeqNumber, TcpStreamId},
+    chrono::NaiveDate,
+};
+
+#[cfg(test)]
+fn test_request(
+    first_line: &str,
+    headers: &[(&str, &str)],
+    body: HttpBody,
+) -> HttpRequestResponseData {
+    HttpRequestResponseData {
+        tcp_stream_no: TcpStreamId(4),
+        tcp_seq_number: TcpSeqNumber(1),
+        timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms(8, 49, 52),
+        first_line: first_line.to_string(),
+        headers: headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        body,
+        content_type: None,
+        content_encoding: ContentEncoding::Plain,
+        trailers: vec![],
+    }
+}
+
+#[test]
+fn should_build_curl_command() {
+    let req = test_request(
+        "POST /api/items HTTP/1.1",
+        &[
+            ("Host", "example.com"),
+            ("Connection", "keep-alive"),
+            ("Content-Type", "application/json"),
+            ("Content-Length", "12"),
+        ],
+        HttpBody::Text("{\"a\":\"it's\"}".to_string()),
+    );
+    let url = req.request_url("10.0.0.1".parse().unwrap(), NetworkPort(80));
+    assert_eq!(
+        "curl 'http://example.com/api/items' \\\n  -X POST \\\n  -H 'Content-Type: application/json' \\\n  --data-binary '{\"a\":\"it'\\''s\"}'",
+        as_curl(&req, &url)
+    );
+}
+
+#[test]
+fn should_build_httpie_command_for_http2() {
+    let req = test_request(
+        "GET /libraries/gbuemRf7.js",
+        &[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":authority", "cdn.jwplayer.com"),
+            (":path", "/libraries/gbuemRf7.js"),
+            ("accept", "*/*"),
+        ],
+        HttpBody::Missing,
+    );
+    let url = req.request_url("10.0.0.1".parse().unwrap(), NetworkPort(443));
+    assert_eq!(
+        "http GET 'https://cdn.jwplayer.com/libraries/gbuemRf7.js' \\\n  'accept:*/*'",
+        as_httpie(&req, &url)
+    );
+}
+
+#[test]
+fn should_replay_the_decoded_body() {
+    let mut req = test_request(
+        "POST /upload HTTP/1.1",
+        &[("Content-Encoding", "gzip"), ("X-Id", "1")],
+        // "hi" gzipped
+        HttpBody::Binary(hex::decode("1f8b0800000000000203cbc80400ac2a93d802000000").unwrap()),
+    );
+    req.content_encoding = ContentEncoding::Gzip;
+    assert_eq!(
+        "curl 'http://a.com/upload' \\\n  -X POST \\\n  -H 'X-Id: 1' \\\n  --data-binary 'hi'",
+        as_curl(&req, "http://a.com/upload")
+    );
+    let head = test_request("HEAD /x HTTP/1.1", &[], HttpBody::Missing);
+    assert_eq!(
+        "curl 'http://a.com/x' \\\n  -I",
+        as_curl(&head, "http://a.com/x")
+    );
+}
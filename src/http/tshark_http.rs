@@ -1,5 +1,7 @@
 use crate::tshark_communication;
+use crate::tshark_json;
 use quick_xml::events::Event;
+use serde_json::Value;
 use std::io::BufRead;
 
 #[derive(Debug, Copy, Clone)]
@@ -102,3 +104,40 @@ pub fn parse_http_info<B: BufRead>(
         }
     )
 }
+
+pub fn parse_http_json(layer: &Value) -> TSharkHttp {
+    let request_lines = tshark_json::all_str(layer, "http.request.line");
+    let response_lines = tshark_json::all_str(layer, "http.response.line");
+    let http_type = if !request_lines.is_empty() {
+        Some(HttpType::Request)
+    } else if !response_lines.is_empty() {
+        Some(HttpType::Response)
+    } else {
+        None
+    };
+    // the first line is a text item, keyed by its label, with the
+    // method or status code fields as children
+    let first_line = layer
+        .as_object()
+        .and_then(|fields| {
+            fields.iter().find(|(_, v)| {
+                v.get("http.request.method").is_some() || v.get("http.response.code").is_some()
+            })
+        })
+        .map(|(k, _)| k.trim_end_matches("\\r\\n").to_string())
+        .unwrap_or_default();
+    let other_lines = request_lines
+        .iter()
+        .chain(response_lines.iter())
+        .map(|l| format!("{}\r\n", l.trim_end_matches(&['\r', '\n'][..])))
+        .collect();
+    TSharkHttp {
+        http_type,
+        http_host: tshark_json::first_str(layer, "http.host"),
+        first_line,
+        other_lines,
+        body: tshark_json::first_bytes(layer, "http.file_data")
+            .or_else(|| tshark_json::first_bytes(layer, "data.data")),
+        content_type: tshark_json::first_str(layer, "http.content_type"),
+    }
+}
@@ -0,0 +1,400 @@
+// after a HTTP/1.1 "101 Switching Protocols" to websocket, the connection carries
+// websocket frames. We parse them from the raw TCP payload, in both directions.
+// https://www.rfc-editor.org/rfc/rfc6455#section-5.2
+use flate2::{Decompress, FlushDecompress};
+use std::fmt;
+
+// the length is on 64 bits: beyond that we assume garbage rather than
+// buffering the rest of the capture waiting for the end of the frame
+const MAX_FRAME_LENGTH: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebSocketOpcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+    Reserved(u8),
+}
+
+impl WebSocketOpcode {
+    fn from_u8(opcode: u8) -> WebSocketOpcode {
+        match opcode {
+            0x0 => WebSocketOpcode::Continuation,
+            0x1 => WebSocketOpcode::Text,
+            0x2 => WebSocketOpcode::Binary,
+            0x8 => WebSocketOpcode::Close,
+            0x9 => WebSocketOpcode::Ping,
+            0xa => WebSocketOpcode::Pong,
+            o => WebSocketOpcode::Reserved(o),
+        }
+    }
+
+    /// control frames can't be fragmented, and can come between the
+    /// fragments of a data message
+    fn is_control(&self) -> bool {
+        match self {
+            WebSocketOpcode::Close | WebSocketOpcode::Ping | WebSocketOpcode::Pong => true,
+            WebSocketOpcode::Reserved(o) => *o >= 0x8,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for WebSocketOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WebSocketOpcode::Continuation => write!(f, "continuation"),
+            WebSocketOpcode::Text => write!(f, "text"),
+            WebSocketOpcode::Binary => write!(f, "binary"),
+            WebSocketOpcode::Close => write!(f, "close"),
+            WebSocketOpcode::Ping => write!(f, "ping"),
+            WebSocketOpcode::Pong => write!(f, "pong"),
+            WebSocketOpcode::Reserved(o) => write!(f, "opcode {:#x}", o),
+        }
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc6455#section-7.4.1
+pub fn close_code_name(code: u16) -> &'static str {
+    match code {
+        1000 => "normal closure",
+        1001 => "going away",
+        1002 => "protocol error",
+        1003 => "unsupported data",
+        1005 => "no status received",
+        1006 => "abnormal closure",
+        1007 => "invalid payload data",
+        1008 => "policy violation",
+        1009 => "message too big",
+        1010 => "mandatory extension",
+        1011 => "internal error",
+        1012 => "service restart",
+        1013 => "try again later",
+        1014 => "bad gateway",
+        1015 => "TLS handshake failure",
+        3000..=3999 => "registered code",
+        4000..=4999 => "application code",
+        _ => "unknown code",
+    }
+}
+
+/// a complete message: the fragments are put together, and the
+/// payload is unmasked and decompressed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSocketMessage {
+    pub from_client: bool,
+    pub opcode: WebSocketOpcode,
+    pub payload: Vec<u8>,
+}
+
+impl WebSocketMessage {
+    pub fn close_code(&self) -> Option<u16> {
+        if self.opcode != WebSocketOpcode::Close {
+            return None;
+        }
+        Some(u16::from_be_bytes(self.payload.get(..2)?.try_into().ok()?))
+    }
+
+    pub fn info(&self) -> WebSocketInfo {
+        WebSocketInfo {
+            opcode: self.opcode,
+            close_code: self.close_code(),
+        }
+    }
+
+    pub fn close_reason(&self) -> Option<String> {
+        self.close_code()?;
+        Some(String::from_utf8_lossy(&self.payload[2..]).to_string()).filter(|r| !r.is_empty())
+    }
+}
+
+/// what we keep of a websocket message, next to its payload, in the HTTP message list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WebSocketInfo {
+    pub opcode: WebSocketOpcode,
+    pub close_code: Option<u16>,
+}
+
+impl fmt::Display for WebSocketInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.close_code {
+            Some(code) => write!(f, "WebSocket close {} ({})", code, close_code_name(code)),
+            None => write!(f, "WebSocket {}", self.opcode),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct WebSocketFrame {
+    fin: bool,
+    rsv1: bool,
+    opcode: WebSocketOpcode,
+    payload: Vec<u8>,
+}
+
+/// the payload length, and where the extended length ends
+fn parse_payload_length(data: &[u8]) -> Option<(u64, usize)> {
+    Some(match *data.get(1)? & 0x7f {
+        126 => (
+            u64::from(u16::from_be_bytes(data.get(2..4)?.try_into().ok()?)),
+            4,
+        ),
+        127 => (u64::from_be_bytes(data.get(2..10)?.try_into().ok()?), 10),
+        l => (u64::from(l), 2),
+    })
+}
+
+/// the frame and the number of bytes it took, or None if it's incomplete
+fn parse_frame(data: &[u8]) -> Option<(WebSocketFrame, usize)> {
+    let b0 = *data.first()?;
+    let b1 = *data.get(1)?;
+    let (len, mut pos) = parse_payload_length(data)?;
+    let mask = if b1 & 0x80 != 0 {
+        let m = data.get(pos..pos + 4)?;
+        pos += 4;
+        Some([m[0], m[1], m[2], m[3]])
+    } else {
+        None
+    };
+    let end = pos.checked_add(usize::try_from(len).ok()?)?;
+    let mut payload = data.get(pos..end)?.to_vec();
+    if let Some(mask) = mask {
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b ^= mask[i % 4];
+        }
+    }
+    Some((
+        WebSocketFrame {
+            fin: b0 & 0x80 != 0,
+            rsv1: b0 & 0x40 != 0,
+            opcode: WebSocketOpcode::from_u8(b0 & 0x0f),
+            payload,
+        },
+        end,
+    ))
+}
+
+#[derive(Default)]
+struct WebSocketDirection {
+    buffer: Vec<u8>,
+    // opcode, compressed, payload so far
+    fragments: Option<(WebSocketOpcode, bool, Vec<u8>)>,
+    // with permessage-deflate, the compression context is kept
+    // from message to message, unless "no_context_takeover" was negotiated.
+    // Keeping it is correct in both cases.
+    inflater: Option<Decompress>,
+}
+
+impl fmt::Debug for WebSocketDirection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WebSocketDirection")
+            .field("buffer_len", &self.buffer.len())
+            .field("fragments", &self.fragments)
+            .finish()
+    }
+}
+
+impl WebSocketDirection {
+    fn add_frame(
+        &mut self,
+        frame: WebSocketFrame,
+        from_client: bool,
+        permessage_deflate: bool,
+    ) -> Option<WebSocketMessage> {
+        let (opcode, compressed, payload) = if frame.opcode.is_control() {
+            (frame.opcode, false, frame.payload)
+        } else if frame.opcode == WebSocketOpcode::Continuation {
+            // a continuation without a first fragment: we probably
+            // missed the beginning of the capture, drop it
+            let (opcode, compressed, mut sofar) = self.fragments.take()?;
+            sofar.extend_from_slice(&frame.payload);
+            if !frame.fin {
+                self.fragments = Some((opcode, compressed, sofar));
+                return None;
+            }
+            (opcode, compressed, sofar)
+        } else {
+            // only the first fragment has the "compressed" bit
+            let compressed = permessage_deflate && frame.rsv1;
+            if !frame.fin {
+                self.fragments = Some((frame.opcode, compressed, frame.payload));
+                return None;
+            }
+            (frame.opcode, compressed, frame.payload)
+        };
+        let payload = if compressed {
+            self.inflate(payload)
+        } else {
+            payload
+        };
+        Some(WebSocketMessage {
+            from_client,
+            opcode,
+            payload,
+        })
+    }
+
+    /// https://www.rfc-editor.org/rfc/rfc7692#section-7.2.2
+    /// if we can't decompress, we keep the compressed payload
+    fn inflate(&mut self, payload: Vec<u8>) -> Vec<u8> {
+        let mut input = payload.clone();
+        input.extend_from_slice(&[0x00, 0x00, 0xff, 0xff]);
+        let inflater = self.inflater.get_or_insert_with(|| Decompress::new(false));
+        let mut result = Vec::with_capacity(input.len() * 4);
+        let mut remaining = &input[..];
+        loop {
+            if result.len() == result.capacity() {
+                result.reserve(input.len().max(1024));
+            }
+            let (in_before, out_before) = (inflater.total_in(), inflater.total_out());
+            if inflater
+                .decompress_vec(remaining, &mut result, FlushDecompress::Sync)
+                .is_err()
+            {
+                return payload;
+            }
+            let consumed = (inflater.total_in() - in_before) as usize;
+            let produced = inflater.total_out() - out_before;
+            remaining = &remaining[consumed..];
+            if (remaining.is_empty() && result.len() < result.capacity())
+                || (consumed == 0 && produced == 0)
+            {
+                break;
+            }
+        }
+        result
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct WebSocketStream {
+    permessage_deflate: bool,
+    client: WebSocketDirection,
+    server: WebSocketDirection,
+    // we gave up on the stream after an invalid frame
+    failed: bool,
+}
+
+impl WebSocketStream {
+    pub fn new(permessage_deflate: bool) -> WebSocketStream {
+        WebSocketStream {
+            permessage_deflate,
+            ..WebSocketStream::default()
+        }
+    }
+
+    /// the messages that this data completes
+    pub fn add_data(
+        &mut self,
+        from_client: bool,
+        data: &[u8],
+    ) -> Result<Vec<WebSocketMessage>, String> {
+        if self.failed {
+            return Ok(vec![]);
+        }
+        let permessage_deflate = self.permessage_deflate;
+        let direction = if from_client {
+            &mut self.client
+        } else {
+            &mut self.server
+        };
+        direction.buffer.extend_from_slice(data);
+        let mut messages = vec![];
+        while let Some((frame, consumed)) = parse_frame(&direction.buffer) {
+            direction.buffer.drain(..consumed);
+            messages.extend(direction.add_frame(frame, from_client, permessage_deflate));
+        }
+        if let Some((len, _)) =
+            parse_payload_length(&direction.buffer).filter(|(l, _)| *l > MAX_FRAME_LENGTH)
+        {
+            self.failed = true;
+            self.client = WebSocketDirection::default();
+            self.server = WebSocketDirection::default();
+            return Err(format!("WebSocket frame too long: {} bytes", len));
+        }
+        Ok(messages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_masked_fragmented_and_control_frames() {
+        let mut ws = WebSocketStream::new(false);
+        // masked "Hello" from the client, example from the RFC
+        assert_eq!(
+            vec![WebSocketMessage {
+                from_client: true,
+                opcode: WebSocketOpcode::Text,
+                payload: b"Hello".to_vec()
+            }],
+            ws.add_data(true, &hex::decode("818537fa213d7f9f4d5158").unwrap())
+                .unwrap()
+        );
+        // fragmented "Hel" + "lo" with a ping in the middle, the last
+        // fragment split across two TCP segments
+        assert_eq!(
+            vec![WebSocketMessage {
+                from_client: false,
+                opcode: WebSocketOpcode::Ping,
+                payload: vec![]
+            }],
+            ws.add_data(false, &hex::decode("010348656c89008002").unwrap())
+                .unwrap()
+        );
+        assert_eq!(
+            vec![WebSocketMessage {
+                from_client: false,
+                opcode: WebSocketOpcode::Text,
+                payload: b"Hello".to_vec()
+            }],
+            ws.add_data(false, &hex::decode("6c6f8804").unwrap())
+                .unwrap()
+        );
+        let close = ws
+            .add_data(false, &hex::decode("03e96279").unwrap())
+            .unwrap();
+        assert_eq!(Some(1001), close[0].close_code());
+        assert_eq!(Some("by".to_string()), close[0].close_reason());
+        assert_eq!(
+            "WebSocket close 1001 (going away)",
+            close[0].info().to_string()
+        );
+    }
+
+    #[test]
+    fn should_inflate_permessage_deflate() {
+        let mut ws = WebSocketStream::new(true);
+        // "Hello" compressed, twice: the second message reuses the
+        // compression context of the first one (RFC 7692 section 7.2.3.2)
+        let messages = ws
+            .add_data(
+                false,
+                &hex::decode("c107f248cdc9c90700c105f200110000").unwrap(),
+            )
+            .unwrap();
+        assert_eq!(2, messages.len());
+        assert_eq!(b"Hello".to_vec(), messages[0].payload);
+        assert_eq!(b"Hello".to_vec(), messages[1].payload);
+    }
+
+    #[test]
+    fn should_give_up_on_huge_frames() {
+        let mut ws = WebSocketStream::new(false);
+        // a binary frame announcing 2^63 bytes
+        assert!(ws
+            .add_data(false, &hex::decode("827f8000000000000000").unwrap())
+            .is_err());
+        // we don't buffer anything anymore, and ignore the following frames
+        assert_eq!(
+            Ok(vec![]),
+            ws.add_data(true, &hex::decode("818537fa213d7f9f4d5158").unwrap())
+        );
+        assert!(ws.server.buffer.is_empty() && ws.client.buffer.is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+// gRPC runs on top of HTTP2: the method is in the :path, the protobuf messages
+// are length-prefixed in the body, and the status is in the trailers.
+// https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md
+use super::protobuf::{self, ProtoSchema};
+use crate::http::form_data;
+use crate::http::http_streams_store::{self, HttpBody, HttpRequestResponseData};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::borrow::Cow;
+use std::fmt;
+use std::fmt::Write;
+use std::io::Read;
+
+/// https://grpc.github.io/grpc/core/md_doc_statuscodes.html
+const GRPC_STATUS_NAMES: &[&str] = &[
+    "OK",
+    "CANCELLED",
+    "UNKNOWN",
+    "INVALID_ARGUMENT",
+    "DEADLINE_EXCEEDED",
+    "NOT_FOUND",
+    "ALREADY_EXISTS",
+    "PERMISSION_DENIED",
+    "RESOURCE_EXHAUSTED",
+    "FAILED_PRECONDITION",
+    "ABORTED",
+    "OUT_OF_RANGE",
+    "UNIMPLEMENTED",
+    "INTERNAL",
+    "UNAVAILABLE",
+    "DATA_LOSS",
+    "UNAUTHENTICATED",
+];
+
+pub fn is_grpc(content_type: Option<&str>) -> bool {
+    // application/grpc-web frames its trailers in the body, we don't handle it
+    content_type.map_or(false, |ct| {
+        ct == "application/grpc" || ct.starts_with("application/grpc+")
+    })
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct GrpcMessage<'a> {
+    pub compressed: bool,
+    pub data: &'a [u8],
+}
+
+/// None if the body isn't a sequence of length-prefixed messages
+pub fn split_grpc_messages(body: &[u8]) -> Option<Vec<GrpcMessage<'_>>> {
+    let mut rest = body;
+    let mut messages = vec![];
+    while !rest.is_empty() {
+        let len = u32::from_be_bytes(rest.get(1..5)?.try_into().ok()?) as usize;
+        let data = rest.get(5..len.checked_add(5)?)?;
+        messages.push(GrpcMessage {
+            compressed: rest[0] == 1,
+            data,
+        });
+        rest = &rest[5 + len..];
+    }
+    Some(messages)
+}
+
+/// "package.Service/Method", from a :path like "/package.Service/Method"
+pub fn method_from_path(path: &str) -> Option<&str> {
+    path.strip_prefix('/').filter(|m| m.contains('/'))
+}
+
+/// "package.Service/Method", from a request line like "POST /package.Service/Method"
+pub fn method_from_request_line(line: &str) -> Option<&str> {
+    line.split(' ').nth(1).and_then(method_from_path)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrpcStatus {
+    pub code: u32,
+    pub message: Option<String>,
+}
+
+impl GrpcStatus {
+    pub fn name(&self) -> String {
+        GRPC_STATUS_NAMES
+            .get(self.code as usize)
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| format!("status {}", self.code))
+    }
+}
+
+impl fmt::Display for GrpcStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.message {
+            Some(msg) => write!(f, "{}: {}", self.name(), msg),
+            None => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+/// grpc-status & grpc-message: in the trailers, or in the headers
+/// for "trailers-only" responses (typically errors without a body)
+pub fn grpc_status(response: &HttpRequestResponseData) -> Option<GrpcStatus> {
+    let header = |name| {
+        http_streams_store::get_http_header_value(&response.trailers, name)
+            .or_else(|| http_streams_store::get_http_header_value(&response.headers, name))
+    };
+    Some(GrpcStatus {
+        code: header("grpc-status")?.trim().parse().ok()?,
+        message: header("grpc-message")
+            // grpc-message is percent-encoded
+            .map(|m| form_data::percent_decode(m, false))
+            .filter(|m| !m.is_empty()),
+    })
+}
+
+fn decompress(encoding: Option<&str>, data: &[u8]) -> Option<Vec<u8>> {
+    let mut r = vec![];
+    match encoding {
+        Some("gzip") => GzDecoder::new(data).read_to_end(&mut r).ok()?,
+        Some("deflate") => ZlibDecoder::new(data).read_to_end(&mut r).ok()?,
+        _ => return None,
+    };
+    Some(r)
+}
+
+/// the text we display for a gRPC body: each message decoded as protobuf,
+/// with field names if we have the schema for the method.
+/// None if this is not a gRPC body.
+pub fn format_grpc_body(
+    data: &HttpRequestResponseData,
+    method: Option<&str>,
+    is_request: bool,
+    schema: Option<&ProtoSchema>,
+) -> Option<String> {
+    if !is_grpc(data.content_type.as_deref()) {
+        return None;
+    }
+    let body = match &data.body {
+        HttpBody::Binary(b) => &b[..],
+        HttpBody::Text(t) => t.as_bytes(),
+        HttpBody::Missing => return None,
+    };
+    let messages = split_grpc_messages(body)?;
+    let type_name = schema
+        .zip(method)
+        .and_then(|(s, m)| s.method_types(m))
+        .map(|(input, output)| if is_request { input } else { output });
+    let encoding = http_streams_store::get_http_header_value(&data.headers, "grpc-encoding")
+        .map(|e| e.as_str());
+    let mut out = String::new();
+    for (idx, message) in messages.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "// message {} ({}{} bytes)",
+            idx + 1,
+            type_name.map(|t| format!("{}, ", t)).unwrap_or_default(),
+            message.data.len()
+        );
+        let bytes = if message.compressed {
+            match decompress(encoding, message.data) {
+                Some(b) => Cow::Owned(b),
+                None => {
+                    let _ = writeln!(
+                        out,
+                        "compressed with {}, can't decode\n",
+                        encoding.unwrap_or("an unknown encoding")
+                    );
+                    continue;
+                }
+            }
+        } else {
+            Cow::Borrowed(message.data)
+        };
+        let decoded = type_name
+            .and_then(|t| schema?.format_message(t, &bytes))
+            .or_else(|| protobuf::format_message_schemaless(&bytes))
+            .unwrap_or_else(|| format!("0x{}\n", hex::encode(&bytes)));
+        out.push_str(&decoded);
+        out.push('\n');
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::http_streams_store::ContentEncoding;
+    use crate::tshark_communication::{TcpSeqNumber, TcpStreamId};
+    use chrono::NaiveDate;
+
+    #[test]
+    fn should_split_grpc_messages() {
+        let body = hex::decode("000000000208010000000000010000000112").unwrap();
+        assert_eq!(
+            Some(vec![
+                GrpcMessage {
+                    compressed: false,
+                    data: &[0x08, 0x01]
+                },
+                GrpcMessage {
+                    compressed: false,
+                    data: &[]
+                },
+                GrpcMessage {
+                    compressed: true,
+                    data: &[0x12]
+                },
+            ]),
+            split_grpc_messages(&body)
+        );
+        assert_eq!(None, split_grpc_messages(&[0, 0, 0, 0, 3, 1]));
+    }
+
+    #[test]
+    fn should_parse_grpc_status_and_body() {
+        let response = HttpRequestResponseData {
+            tcp_stream_no: TcpStreamId(1),
+            tcp_seq_number: TcpSeqNumber(1),
+            timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms(8, 49, 52),
+            first_line: "HTTP/2 status 200".to_string(),
+            headers: vec![(":status".to_string(), "200".to_string())],
+            body: HttpBody::Binary(hex::decode("0000000004120261620000000003089601").unwrap()),
+            content_type: Some("application/grpc".to_string()),
+            content_encoding: ContentEncoding::Plain,
+            trailers: vec![
+                ("grpc-status".to_string(), "5".to_string()),
+                ("grpc-message".to_string(), "no%20such%20user".to_string()),
+            ],
+        };
+        let status = grpc_status(&response).unwrap();
+        assert_eq!(5, status.code);
+        assert_eq!("NOT_FOUND: no such user", status.to_string());
+        assert_eq!(
+            Some("svc.Users/Get"),
+            method_from_request_line("POST /svc.Users/Get")
+        );
+        assert_eq!(
+            "// message 1 (4 bytes)\n2: \"ab\"\n\n// message 2 (3 bytes)\n1: 150\n\n",
+            format_grpc_body(&response, Some("svc.Users/Get"), false, None).unwrap()
+        );
+    }
+}
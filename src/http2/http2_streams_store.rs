@@ -1,10 +1,19 @@
-use crate::custom_streams_store::{ClientServerInfo, CustomStreamsStore};
+use crate::config::Config;
+use crate::custom_streams_store;
+use crate::custom_streams_store::{
+    ClientServerInfo, CustomStreamsParser, CustomStreamsStore, StatsTable,
+};
+use crate::http::har;
 use crate::http::http_details_widget::{self, HttpCommEntry};
+use crate::http::http_stats;
 use crate::http::http_streams_store;
 use crate::http::http_streams_store::{
-    ContentEncoding, HttpBody, HttpMessageData, HttpRequestResponseData,
+    ContentEncoding, Http2StreamEnd, HttpBody, HttpMessageData, HttpRequestResponseData,
 };
-use crate::http2::tshark_http2::TSharkHttp2Message;
+use crate::http2::grpc;
+use crate::http2::proto_schema;
+use crate::http2::protobuf::ProtoSchema;
+use crate::http2::tshark_http2::{Http2GoAway, TSharkHttp2Message, HTTP2_FRAME_TYPE_HEADERS};
 use crate::icons;
 use crate::search_expr;
 use crate::tshark_communication::{TSharkPacket, TSharkPacketBasicInfo, TcpSeqNumber, TcpStreamId};
@@ -13,9 +22,30 @@ use crate::BgFunc;
 use chrono::NaiveDateTime;
 use relm::ContainerWidget;
 use std::collections::HashMap;
+use std::path::Path;
 use std::str;
-use std::sync::mpsc;
+use std::str::FromStr;
+use std::sync::{mpsc, Arc};
 use strum::VariantNames;
+use strum_macros::{EnumString, EnumVariantNames};
+
+#[derive(EnumString, EnumVariantNames, Debug)]
+pub enum GrpcStringFilterKeys {
+    #[strum(serialize = "grpc.method")]
+    Method,
+    #[strum(serialize = "grpc.status")]
+    Status,
+}
+
+lazy_static! {
+    // the HTTP keys also apply to HTTP2, the gRPC keys only to HTTP2
+    static ref HTTP2_STRING_FILTER_KEYS: Vec<&'static str> =
+        http_streams_store::HttpStringFilterKeys::VARIANTS
+            .iter()
+            .chain(GrpcStringFilterKeys::VARIANTS)
+            .copied()
+            .collect();
+}
 
 #[cfg(test)]
 use {
@@ -29,12 +59,44 @@ pub struct Http2StreamData {
     pub client_server: Option<ClientServerInfo>,
     pub messages: Vec<HttpMessageData>,
     pub summary_details: Option<String>,
+    pub goaway: Option<Http2GoAway>,
+}
+
+impl Http2StreamData {
+    fn request_seen(&mut self, request: &HttpRequestResponseData, cur_msg: &TSharkPacketBasicInfo) {
+        if self.summary_details.is_none() {
+            self.summary_details =
+                http_streams_store::get_http_header_value(&request.headers, ":authority")
+                    .map(|c| c.to_string());
+        }
+        if self.client_server.is_none() {
+            self.client_server = Some(ClientServerInfo {
+                client_ip: cur_msg.ip_src,
+                server_ip: cur_msg.ip_dst,
+                server_port: cur_msg.port_dst,
+            });
+        }
+    }
+
+    fn response_seen(&mut self, cur_msg: &TSharkPacketBasicInfo) {
+        if self.client_server.is_none() {
+            self.client_server = Some(ClientServerInfo {
+                client_ip: cur_msg.ip_dst,
+                server_ip: cur_msg.ip_src,
+                server_port: cur_msg.port_src,
+            });
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct Http2StreamsStore {
     streams: HashMap<TcpStreamId, Http2StreamData>,
     component: Option<relm::Component<HttpCommEntry>>,
+    slow_threshold_ms: Option<u32>,
+    proto_schema: Option<Arc<ProtoSchema>>,
+    // the path proto_schema was loaded from
+    proto_schema_path: Option<String>,
 }
 
 impl Http2StreamsStore {
@@ -47,6 +109,48 @@ impl Http2StreamsStore {
         let msg = stream.messages.get(msg_idx)?;
         Some((msg, stream.client_server?))
     }
+
+    fn send_proto_schema_to_component(&self) {
+        if let Some(component) = &self.component {
+            component
+                .stream()
+                .emit(http_details_widget::Msg::ProtoSchemaChanged(
+                    self.proto_schema.clone(),
+                ));
+        }
+    }
+
+    fn grpc_matches_filter(
+        &self,
+        filter_key: GrpcStringFilterKeys,
+        op: &search_expr::SearchCriteria,
+        model: &gtk::TreeModel,
+        iter: &gtk::TreeIter,
+    ) -> bool {
+        let (stream_id, idx) = custom_streams_store::get_message_helper(model, iter);
+        let http_msg = match self
+            .streams
+            .get(&stream_id)
+            .and_then(|s| s.messages.get(idx as usize))
+        {
+            Some(m) => m,
+            None => return false,
+        };
+        match filter_key {
+            GrpcStringFilterKeys::Method => http_msg
+                .request
+                .as_ref()
+                .and_then(|r| grpc::method_from_request_line(&r.first_line))
+                .map_or(false, |m| op.matches_str(m)),
+            GrpcStringFilterKeys::Status => http_msg
+                .response
+                .as_ref()
+                .and_then(grpc::grpc_status)
+                .map_or(false, |s| {
+                    op.matches_str(&s.code.to_string()) || op.matches_str(&s.name())
+                }),
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -61,7 +165,7 @@ pub struct Http2StreamGlobals {
     pub messages_per_stream: HashMap<u32, Http2StreamProcessedContents>,
 }
 
-impl CustomStreamsStore for Http2StreamsStore {
+impl CustomStreamsParser for Http2StreamsStore {
     fn is_my_message(&self, msg: &TSharkPacket) -> bool {
         msg.http2.is_some()
     }
@@ -70,10 +174,6 @@ impl CustomStreamsStore for Http2StreamsStore {
         "http2"
     }
 
-    fn protocol_icon(&self) -> icons::Icon {
-        icons::Icon::HTTP
-    }
-
     fn protocol_name(&self) -> &'static str {
         "HTTP2"
     }
@@ -123,7 +223,25 @@ impl CustomStreamsStore for Http2StreamsStore {
         }
         let http2 = new_packet.http2.unwrap();
         for http2_msg in http2 {
-            if http2_msg.is_end_stream {
+            if let Some(goaway) = http2_msg.goaway {
+                // applies to the whole connection, we'll use it when
+                // flushing the streams that didn't complete
+                stream.goaway = Some(goaway);
+            } else if let Some(error_code) = http2_msg.rst_stream_error {
+                // the stream is over, flush what we have
+                if let Some(stream_messages) = stream
+                    .stream_globals
+                    .messages_per_stream
+                    .remove(&http2_msg.stream_id)
+                {
+                    flush_stream_contents(
+                        stream,
+                        http2_msg.stream_id,
+                        stream_messages,
+                        Some(Http2StreamEnd::Reset(error_code)),
+                    );
+                }
+            } else if http2_msg.is_end_stream {
                 let http2_stream_id = http2_msg.stream_id;
                 // got all the elements of the message, add it to the result
                 let mut stream_messages = stream
@@ -144,20 +262,7 @@ impl CustomStreamsStore for Http2StreamsStore {
                 );
                 match msg_type {
                     MsgType::Request => {
-                        if stream.summary_details.is_none() {
-                            stream.summary_details = http_streams_store::get_http_header_value(
-                                &http_msg.headers,
-                                ":authority",
-                            )
-                            .map(|c| c.to_string());
-                        }
-                        if stream.client_server.is_none() {
-                            stream.client_server = Some(ClientServerInfo {
-                                client_ip: cur_msg.ip_src,
-                                server_ip: cur_msg.ip_dst,
-                                server_port: cur_msg.port_dst,
-                            });
-                        }
+                        stream.request_seen(&http_msg, &cur_msg);
                         stream.stream_globals.messages_per_stream.insert(
                             http2_stream_id,
                             Http2StreamProcessedContents {
@@ -168,17 +273,13 @@ impl CustomStreamsStore for Http2StreamsStore {
                         );
                     }
                     MsgType::Response => {
-                        if stream.client_server.is_none() {
-                            stream.client_server = Some(ClientServerInfo {
-                                client_ip: cur_msg.ip_dst,
-                                server_ip: cur_msg.ip_src,
-                                server_port: cur_msg.port_src,
-                            });
-                        }
+                        stream.response_seen(&cur_msg);
                         stream.messages.push(HttpMessageData {
                             http_stream_id: http2_stream_id,
                             request: stream_messages.cur_request,
                             response: Some(http_msg),
+                            http2_stream_end: None,
+                            websocket: None,
                         });
                     }
                 }
@@ -201,95 +302,108 @@ impl CustomStreamsStore for Http2StreamsStore {
 
     fn finish_stream(&mut self, stream_id: TcpStreamId) -> Result<(), String> {
         // flush all the incomplete messages as best we can
-        let mut stream = self
+        let stream = self
             .streams
             .get_mut(&stream_id)
             .ok_or("No data for stream")?;
         let globals = std::mem::take(&mut stream.stream_globals);
         for (http2_stream_id, stream_contents) in globals.messages_per_stream {
-            let cur_msg = stream_contents.unfinished_basic_info.unwrap();
-            match (
-                stream_contents.cur_request,
-                stream_contents.unfinished_stream_messages,
-            ) {
-                (Some(r), leftover) if leftover.is_empty() => {
-                    stream.messages.push(HttpMessageData {
-                        http_stream_id: http2_stream_id,
-                        request: Some(r),
-                        response: None,
-                    })
-                }
-                (req, leftover) => {
-                    let (http_msg, msg_type) = prepare_http_message(
-                        cur_msg.tcp_stream_id,
-                        cur_msg.tcp_seq_number,
-                        cur_msg.frame_time,
-                        leftover,
-                    );
-                    match msg_type {
-                        MsgType::Request => {
-                            if stream.summary_details.is_none() {
-                                stream.summary_details = http_streams_store::get_http_header_value(
-                                    &http_msg.headers,
-                                    ":authority",
-                                )
-                                .map(|c| c.to_string());
-                            }
-                            if stream.client_server.is_none() {
-                                stream.client_server = Some(ClientServerInfo {
-                                    client_ip: cur_msg.ip_src,
-                                    server_ip: cur_msg.ip_dst,
-                                    server_port: cur_msg.port_dst,
-                                });
-                            }
-                            stream.messages.push(HttpMessageData {
-                                http_stream_id: http2_stream_id,
-                                request: Some(http_msg),
-                                response: None,
-                            });
-                        }
-                        MsgType::Response => {
-                            if stream.client_server.is_none() {
-                                stream.client_server = Some(ClientServerInfo {
-                                    client_ip: cur_msg.ip_dst,
-                                    server_ip: cur_msg.ip_src,
-                                    server_port: cur_msg.port_src,
-                                });
-                            }
-                            stream.messages.push(HttpMessageData {
-                                http_stream_id: http2_stream_id,
-                                request: req,
-                                response: Some(http_msg),
-                            });
-                        }
-                    }
-                }
-            }
+            // the streams that the server didn't process before closing the connection
+            let stream_end = stream
+                .goaway
+                .filter(|g| http2_stream_id > g.last_stream_id)
+                .map(|g| Http2StreamEnd::GoAway(g.error_code));
+            flush_stream_contents(stream, http2_stream_id, stream_contents, stream_end);
         }
         Ok(())
     }
 
+    fn stream_messages_json(&self, stream_id: TcpStreamId) -> Vec<serde_json::Value> {
+        self.streams
+            .get(&stream_id)
+            .map(|s| s.messages.iter().map(|m| m.to_json()).collect())
+            .unwrap_or_default()
+    }
+
+    fn stream_har_entries(&self, stream_id: TcpStreamId) -> Vec<serde_json::Value> {
+        self.streams
+            .get(&stream_id)
+            .and_then(|s| {
+                let client_server = s.client_server?;
+                Some(
+                    s.messages
+                        .iter()
+                        .filter_map(|m| har::har_entry(m, stream_id, &client_server))
+                        .collect(),
+                )
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl CustomStreamsStore for Http2StreamsStore {
+    fn protocol_icon(&self) -> icons::Icon {
+        icons::Icon::HTTP
+    }
+
+    fn apply_config(&mut self, config: &Config) {
+        self.slow_threshold_ms = Some(config.http_slow_threshold_ms);
+        // parsing the .proto files is slow, only do it when the path changes
+        if config.grpc_proto_path != self.proto_schema_path {
+            self.proto_schema_path = config.grpc_proto_path.clone();
+            self.proto_schema = config.grpc_proto_path.as_ref().and_then(|p| {
+                proto_schema::load_proto_schema(Path::new(p))
+                    .map_err(|e| eprintln!("Error loading the gRPC schema: {}", e))
+                    .ok()
+                    .map(Arc::new)
+            });
+            self.send_proto_schema_to_component();
+        }
+    }
+
+    fn stream_slow_message_count(&self, stream_id: TcpStreamId) -> usize {
+        self.streams.get(&stream_id).map_or(0, |s| {
+            http_streams_store::http_slow_message_count(&s.messages, self.slow_threshold_ms)
+        })
+    }
+
+    fn streams_stats(&self, stream_ids: &[TcpStreamId]) -> Option<StatsTable> {
+        Some(http_stats::endpoint_stats(
+            stream_ids
+                .iter()
+                .filter_map(|id| self.streams.get(id))
+                .flat_map(|s| s.messages.iter()),
+        ))
+    }
+
     fn prepare_treeview(&self, tv: &gtk::TreeView) {
         http_streams_store::http_prepare_treeview(tv);
     }
 
-    fn get_empty_liststore(&self) -> gtk::ListStore {
-        http_streams_store::http_get_empty_liststore()
+    fn get_empty_treestore(&self) -> gtk::TreeStore {
+        http_streams_store::http_get_empty_treestore()
     }
 
     fn populate_treeview(
         &self,
-        ls: &gtk::ListStore,
+        ts: &gtk::TreeStore,
         session_id: TcpStreamId,
         start_idx: usize,
         item_count: usize,
     ) {
         let messages = &self.streams.get(&session_id).unwrap().messages;
-        http_streams_store::http_populate_treeview(messages, ls, session_id, start_idx, item_count);
+        http_streams_store::http_populate_treeview(
+            messages,
+            ts,
+            session_id,
+            start_idx,
+            item_count,
+            self.slow_threshold_ms,
+        );
     }
 
-    fn end_populate_treeview(&self, tv: &gtk::TreeView, ls: &gtk::ListStore) {
-        http_streams_store::http_end_populate_treeview(tv, ls);
+    fn end_populate_treeview(&self, tv: &gtk::TreeView, ts: &gtk::TreeStore) {
+        http_streams_store::http_end_populate_treeview(tv, ts);
     }
 
     fn requests_details_overlay(&self) -> bool {
@@ -311,15 +425,18 @@ impl CustomStreamsStore for Http2StreamsStore {
                 http_stream_id: 0,
                 request: None,
                 response: None,
+                http2_stream_end: None,
+                websocket: None,
             },
             overlay.unwrap().clone(),
             bg_sender,
         ));
         self.component = Some(component);
+        self.send_proto_schema_to_component();
     }
 
     fn supported_string_filter_keys(&self) -> &'static [&'static str] {
-        http_streams_store::HttpStringFilterKeys::VARIANTS
+        &HTTP2_STRING_FILTER_KEYS
     }
 
     fn supported_numeric_filter_keys(&self) -> &'static [&'static str] {
@@ -332,6 +449,9 @@ impl CustomStreamsStore for Http2StreamsStore {
         model: &gtk::TreeModel,
         iter: &gtk::TreeIter,
     ) -> bool {
+        if let Ok(filter_key) = GrpcStringFilterKeys::from_str(filter.filter_key) {
+            return self.grpc_matches_filter(filter_key, &filter.op, model, iter);
+        }
         http_streams_store::http_matches_filter(
             &self
                 .streams
@@ -354,7 +474,7 @@ impl CustomStreamsStore for Http2StreamsStore {
             self.component.as_ref().unwrap().stream().emit(
                 http_details_widget::Msg::DisplayDetails(
                     bg_sender,
-                    client_server.client_ip,
+                    client_server,
                     stream_id,
                     http_msg.clone(),
                 ),
@@ -368,16 +488,79 @@ enum MsgType {
     Response,
 }
 
+/// a stream that ended without us seeing its end_stream flag: it was
+/// reset, or the capture/connection ended. Add what we have for it.
+fn flush_stream_contents(
+    stream: &mut Http2StreamData,
+    http2_stream_id: u32,
+    stream_contents: Http2StreamProcessedContents,
+    http2_stream_end: Option<Http2StreamEnd>,
+) {
+    let cur_msg = stream_contents.unfinished_basic_info.unwrap();
+    match (
+        stream_contents.cur_request,
+        stream_contents.unfinished_stream_messages,
+    ) {
+        (Some(r), leftover) if leftover.is_empty() => stream.messages.push(HttpMessageData {
+            http_stream_id: http2_stream_id,
+            request: Some(r),
+            response: None,
+            http2_stream_end,
+            websocket: None,
+        }),
+        (req, leftover) => {
+            let (http_msg, msg_type) = prepare_http_message(
+                cur_msg.tcp_stream_id,
+                cur_msg.tcp_seq_number,
+                cur_msg.frame_time,
+                leftover,
+            );
+            match msg_type {
+                MsgType::Request => {
+                    stream.request_seen(&http_msg, &cur_msg);
+                    stream.messages.push(HttpMessageData {
+                        http_stream_id: http2_stream_id,
+                        request: Some(http_msg),
+                        response: None,
+                        http2_stream_end,
+                        websocket: None,
+                    });
+                }
+                MsgType::Response => {
+                    stream.response_seen(&cur_msg);
+                    stream.messages.push(HttpMessageData {
+                        http_stream_id: http2_stream_id,
+                        request: req,
+                        response: Some(http_msg),
+                        http2_stream_end,
+                        websocket: None,
+                    });
+                }
+            }
+        }
+    }
+}
+
 fn prepare_http_message(
     tcp_stream_no: TcpStreamId,
     tcp_seq_number: TcpSeqNumber,
     timestamp: NaiveDateTime,
     http2_msgs: Vec<TSharkHttp2Message>,
 ) -> (HttpRequestResponseData, MsgType) {
+    let mut trailers = vec![];
     let (headers, data) = http2_msgs.into_iter().fold(
         (vec![], None::<Vec<u8>>),
         |(mut sofar_h, sofar_d), mut cur| {
-            sofar_h.append(&mut cur.headers);
+            // a HEADERS frame after the header block are trailers, unless it has
+            // pseudo-headers: then it's the final response after a 1xx response
+            let is_trailers = cur.frame_type == Some(HTTP2_FRAME_TYPE_HEADERS)
+                && !sofar_h.is_empty()
+                && !cur.headers.iter().any(|(k, _)| k.starts_with(':'));
+            if is_trailers {
+                trailers.append(&mut cur.headers);
+            } else {
+                sofar_h.append(&mut cur.headers);
+            }
             let new_data = match (sofar_d, cur.data) {
                 (None, Some(d)) => Some(d),
                 (Some(_s), Some(n)) => {
@@ -455,6 +638,7 @@ fn prepare_http_message(
             body,
             content_type,
             content_encoding,
+            trailers,
         },
         msg_type,
     )
@@ -545,9 +729,75 @@ fn should_parse_simple_comm() {
             body: HttpBody::Missing,
             content_type: None,
             content_encoding: ContentEncoding::Plain,
+            trailers: vec![],
         }),
         response: None,
+        http2_stream_end: None,
+        websocket: None,
     }];
     assert_eq!(expected, parsed);
     // assert!(false);
 }
+
+#[test]
+fn should_parse_trailers_and_stream_reset() {
+    let header = |name: &str, value: &str| {
+        format!(
+            r#"<field name="http2.header" show="" value="">
+        <field name="http2.header.name" show="{}"/>
+        <field name="http2.header.value" show="{}"/>
+      </field>"#,
+            name, value
+        )
+    };
+    let frame = |stream_id: u32, frame_type: u8, end_stream: bool, contents: &str| {
+        format!(
+            r#"<field name="http2.stream" show="" value="">
+      <field name="http2.type" show="{}"/>
+      <field name="http2.flags.end_stream" show="{}"/>
+      <field name="http2.streamid" show="{}"/>
+      {}
+    </field>"#,
+            frame_type, end_stream as u8, stream_id, contents
+        )
+    };
+    let xml = format!(
+        r#"<proto name="http2" showname="HyperText Transfer Protocol 2" size="341" pos="0">
+    {}{}{}{}{}
+  </proto>"#,
+        frame(
+            1,
+            1,
+            true,
+            &(header(":method", "POST") + &header(":path", "/svc/Get"))
+        ),
+        frame(1, 1, false, &header(":status", "200")),
+        frame(1, 1, true, &header("grpc-status", "0")),
+        frame(3, 1, true, &header(":method", "GET")),
+        frame(
+            3,
+            3,
+            false,
+            r#"<field name="http2.rst_stream.error" show="8"/>"#
+        ),
+    );
+    let parsed = tests_parse_stream(parse_test_xml(&xml)).unwrap();
+    assert_eq!(2, parsed.len());
+    let response = parsed[0].response.as_ref().unwrap();
+    assert_eq!(
+        vec![(":status".to_string(), "200".to_string())],
+        response.headers
+    );
+    assert_eq!(
+        vec![("grpc-status".to_string(), "0".to_string())],
+        response.trailers
+    );
+    assert_eq!(None, parsed[0].http2_stream_end);
+    assert_eq!(3, parsed[1].http_stream_id);
+    assert_eq!(None, parsed[1].response);
+    assert_eq!(Some(Http2StreamEnd::Reset(8)), parsed[1].http2_stream_end);
+    assert_eq!(
+        "stream reset: CANCEL",
+        parsed[1].http2_stream_end.unwrap().to_string()
+    );
+}
@@ -0,0 +1,660 @@
+// loads the protobuf schema that we use to decode gRPC messages: either from
+// .proto files (a single file or a folder, searched recursively), or from a
+// descriptor set, as generated by `protoc --include_imports --descriptor_set_out`.
+// We don't need a full .proto compiler: we only care about messages, enums
+// and services, and we skip anything else (options, extensions...)
+use super::protobuf::{
+    self, FieldDef, FieldType, MessageDef, MethodDef, ProtoSchema, WireField, WireValue,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn load_proto_schema(path: &Path) -> Result<ProtoSchema, String> {
+    let mut schema = ProtoSchema::default();
+    if path.is_dir() {
+        let mut files = vec![];
+        find_proto_files(path, &mut files)?;
+        for file in files {
+            parse_proto_file_path(&mut schema, &file)?;
+        }
+    } else if path.extension().map_or(false, |e| e == "proto") {
+        parse_proto_file_path(&mut schema, path)?;
+    } else {
+        let contents = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        parse_descriptor_set(&mut schema, &contents)
+            .ok_or_else(|| format!("{}: not a valid descriptor set", path.display()))?;
+    }
+    schema.resolve_types();
+    Ok(schema)
+}
+
+fn find_proto_files(folder: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(folder).map_err(|e| format!("{}: {}", folder.display(), e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_dir() {
+            find_proto_files(&path, files)?;
+        } else if path.extension().map_or(false, |e| e == "proto") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn parse_proto_file_path(schema: &mut ProtoSchema, path: &Path) -> Result<(), String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse_proto_file(schema, &contents).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn tokenize(contents: &str) -> Vec<String> {
+    let is_word_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | '+');
+    let mut tokens = vec![];
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                for n in chars.by_ref() {
+                    if n == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for n in chars.by_ref() {
+                    if prev == '*' && n == '/' {
+                        break;
+                    }
+                    prev = n;
+                }
+            }
+            '"' | '\'' => {
+                let mut s = c.to_string();
+                loop {
+                    match chars.next() {
+                        Some('\\') => {
+                            s.push('\\');
+                            if let Some(n) = chars.next() {
+                                s.push(n);
+                            }
+                        }
+                        Some(n) => {
+                            s.push(n);
+                            if n == c {
+                                break;
+                            }
+                        }
+                        None => break,
+                    }
+                }
+                tokens.push(s);
+            }
+            c if is_word_char(c) => {
+                let mut s = c.to_string();
+                while let Some(&n) = chars.peek().filter(|n| is_word_char(**n)) {
+                    s.push(n);
+                    chars.next();
+                }
+                tokens.push(s);
+            }
+            c => tokens.push(c.to_string()),
+        }
+    }
+    tokens
+}
+
+struct ProtoParser<'a> {
+    tokens: Vec<String>,
+    pos: usize,
+    schema: &'a mut ProtoSchema,
+}
+
+fn qualified_name(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+fn field_type_from_name(scope: &str, name: &str) -> FieldType {
+    FieldType::from_scalar_name(name).unwrap_or_else(|| FieldType::Unresolved {
+        scope: scope.to_string(),
+        name: name.to_string(),
+    })
+}
+
+pub fn parse_proto_file(schema: &mut ProtoSchema, contents: &str) -> Result<(), String> {
+    let mut parser = ProtoParser {
+        tokens: tokenize(contents),
+        pos: 0,
+        schema,
+    };
+    parser.parse_file()
+}
+
+impl<'a> ProtoParser<'a> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|t| t.as_str())
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| "unexpected end of file".to_string())?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        let token = self.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(format!("expected '{}', got '{}'", expected, token))
+        }
+    }
+
+    fn skip_if(&mut self, token: &str) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// skip a statement that we're not interested in: either up to
+    /// the next ';', or a whole {} block
+    fn skip_statement(&mut self) -> Result<(), String> {
+        let mut depth = 0;
+        loop {
+            match self.next()?.as_str() {
+                ";" if depth == 0 => return Ok(()),
+                "{" => depth += 1,
+                "}" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// field options, like [deprecated = true]
+    fn skip_field_options(&mut self) -> Result<(), String> {
+        if self.skip_if("[") {
+            while self.next()? != "]" {}
+        }
+        Ok(())
+    }
+
+    fn parse_file(&mut self) -> Result<(), String> {
+        let mut package = String::new();
+        while let Some(token) = self.peek() {
+            match token {
+                "package" => {
+                    self.pos += 1;
+                    package = self.next()?;
+                    self.expect(";")?;
+                }
+                "message" => self.parse_message(&package)?,
+                "enum" => self.parse_enum(&package)?,
+                "service" => self.parse_service(&package)?,
+                ";" => self.pos += 1,
+                // syntax, import, option, extend...
+                _ => self.skip_statement()?,
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_message(&mut self, scope: &str) -> Result<(), String> {
+        self.expect("message")?;
+        let name = qualified_name(scope, &self.next()?);
+        self.expect("{")?;
+        let mut message = MessageDef::default();
+        loop {
+            match self.peek() {
+                Some("}") => {
+                    self.pos += 1;
+                    break;
+                }
+                Some("message") => self.parse_message(&name)?,
+                Some("enum") => self.parse_enum(&name)?,
+                Some("oneof") => {
+                    // the oneof fields are regular fields of the message
+                    self.pos += 2;
+                    self.expect("{")?;
+                    while !self.skip_if("}") {
+                        if self.peek() == Some("option") {
+                            self.skip_statement()?;
+                        } else {
+                            self.parse_field(&name, &mut message)?;
+                        }
+                    }
+                }
+                Some("map") => self.parse_map_field(&name, &mut message)?,
+                Some("option" | "reserved" | "extensions" | "extend") => self.skip_statement()?,
+                Some(";") => self.pos += 1,
+                _ => self.parse_field(&name, &mut message)?,
+            }
+        }
+        self.schema.messages.insert(name, message);
+        Ok(())
+    }
+
+    fn parse_field_number(&mut self) -> Result<u64, String> {
+        self.expect("=")?;
+        let number = self.next()?;
+        let number = number
+            .parse()
+            .map_err(|_| format!("invalid field number: {}", number))?;
+        self.skip_field_options()?;
+        self.expect(";")?;
+        Ok(number)
+    }
+
+    fn parse_field(&mut self, scope: &str, message: &mut MessageDef) -> Result<(), String> {
+        if matches!(self.peek(), Some("repeated" | "optional" | "required")) {
+            self.pos += 1;
+        }
+        let type_name = self.next()?;
+        let name = self.next()?;
+        let number = self.parse_field_number()?;
+        message.fields.insert(
+            number,
+            FieldDef {
+                name,
+                field_type: field_type_from_name(scope, &type_name),
+            },
+        );
+        Ok(())
+    }
+
+    /// map<K, V> name = N; is sugar for a repeated message with key & value fields
+    fn parse_map_field(&mut self, scope: &str, message: &mut MessageDef) -> Result<(), String> {
+        self.expect("map")?;
+        self.expect("<")?;
+        let key_type = self.next()?;
+        self.expect(",")?;
+        let value_type = self.next()?;
+        self.expect(">")?;
+        let name = self.next()?;
+        let number = self.parse_field_number()?;
+        let mut chars = name.chars();
+        let entry_name = qualified_name(
+            scope,
+            &format!(
+                "{}{}Entry",
+                chars.next().map(|c| c.to_ascii_uppercase()).unwrap_or('_'),
+                chars.as_str()
+            ),
+        );
+        self.schema.messages.insert(
+            entry_name.clone(),
+            MessageDef {
+                fields: vec![
+                    (
+                        1,
+                        FieldDef {
+                            name: "key".to_string(),
+                            field_type: field_type_from_name(scope, &key_type),
+                        },
+                    ),
+                    (
+                        2,
+                        FieldDef {
+                            name: "value".to_string(),
+                            field_type: field_type_from_name(scope, &value_type),
+                        },
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            },
+        );
+        message.fields.insert(
+            number,
+            FieldDef {
+                name,
+                field_type: FieldType::Message(entry_name),
+            },
+        );
+        Ok(())
+    }
+
+    fn parse_enum(&mut self, scope: &str) -> Result<(), String> {
+        self.expect("enum")?;
+        let name = qualified_name(scope, &self.next()?);
+        self.expect("{")?;
+        let mut values = std::collections::HashMap::new();
+        loop {
+            match self.peek() {
+                Some("}") => {
+                    self.pos += 1;
+                    break;
+                }
+                Some("option" | "reserved") => self.skip_statement()?,
+                Some(";") => self.pos += 1,
+                _ => {
+                    let value_name = self.next()?;
+                    self.expect("=")?;
+                    let number = self.next()?;
+                    let number = number
+                        .parse()
+                        .or_else(|_| i64::from_str_radix(number.trim_start_matches("0x"), 16))
+                        .map_err(|_| format!("invalid enum value: {}", number))?;
+                    self.skip_field_options()?;
+                    self.expect(";")?;
+                    values.insert(number, value_name);
+                }
+            }
+        }
+        self.schema.enums.insert(name, values);
+        Ok(())
+    }
+
+    fn parse_service(&mut self, package: &str) -> Result<(), String> {
+        self.expect("service")?;
+        let service_name = qualified_name(package, &self.next()?);
+        self.expect("{")?;
+        loop {
+            match self.peek() {
+                Some("}") => {
+                    self.pos += 1;
+                    break;
+                }
+                Some("rpc") => {
+                    self.pos += 1;
+                    let method_name = self.next()?;
+                    self.expect("(")?;
+                    self.skip_if("stream");
+                    let input_type = self.next()?;
+                    self.expect(")")?;
+                    self.expect("returns")?;
+                    self.expect("(")?;
+                    self.skip_if("stream");
+                    let output_type = self.next()?;
+                    self.expect(")")?;
+                    if self.peek() == Some("{") {
+                        // method options
+                        self.skip_statement()?;
+                    } else {
+                        self.expect(";")?;
+                    }
+                    self.schema.methods.insert(
+                        format!("{}/{}", service_name, method_name),
+                        MethodDef {
+                            input_type: field_type_from_name(package, &input_type),
+                            output_type: field_type_from_name(package, &output_type),
+                        },
+                    );
+                }
+                Some(";") => self.pos += 1,
+                _ => self.skip_statement()?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// a descriptor set is itself a protobuf message, described in
+// https://github.com/protocolbuffers/protobuf/blob/main/src/google/protobuf/descriptor.proto
+// we decode the few fields we need by hand.
+
+fn string_field(fields: &[WireField], number: u64) -> Option<String> {
+    fields.iter().rev().find_map(|f| match f.value {
+        WireValue::LengthDelimited(bytes) if f.number == number => {
+            Some(String::from_utf8_lossy(bytes).to_string())
+        }
+        _ => None,
+    })
+}
+
+fn varint_field(fields: &[WireField], number: u64) -> Option<u64> {
+    fields.iter().rev().find_map(|f| match f.value {
+        WireValue::Varint(v) if f.number == number => Some(v),
+        _ => None,
+    })
+}
+
+fn message_fields<'a>(fields: &[WireField<'a>], number: u64) -> Option<Vec<Vec<WireField<'a>>>> {
+    fields
+        .iter()
+        .filter(|f| f.number == number)
+        .map(|f| match f.value {
+            WireValue::LengthDelimited(bytes) => protobuf::decode_wire_fields(bytes),
+            _ => None,
+        })
+        .collect()
+}
+
+/// the descriptors store type names as ".package.Message"
+fn descriptor_type_name(fields: &[WireField]) -> Option<String> {
+    string_field(fields, 6).map(|n| n.trim_start_matches('.').to_string())
+}
+
+fn parse_descriptor_set(schema: &mut ProtoSchema, contents: &[u8]) -> Option<()> {
+    let set = protobuf::decode_wire_fields(contents)?;
+    // FileDescriptorSet.file
+    for file in message_fields(&set, 1)? {
+        // FileDescriptorProto
+        let package = string_field(&file, 2).unwrap_or_default();
+        for message in message_fields(&file, 4)? {
+            parse_descriptor_message(schema, &package, &message)?;
+        }
+        for enum_desc in message_fields(&file, 5)? {
+            parse_descriptor_enum(schema, &package, &enum_desc)?;
+        }
+        for service in message_fields(&file, 6)? {
+            let service_name = qualified_name(&package, &string_field(&service, 1)?);
+            for method in message_fields(&service, 2)? {
+                let type_field = |number| {
+                    string_field(&method, number)
+                        .map(|n| FieldType::Message(n.trim_start_matches('.').to_string()))
+                };
+                schema.methods.insert(
+                    format!("{}/{}", service_name, string_field(&method, 1)?),
+                    MethodDef {
+                        input_type: type_field(2)?,
+                        output_type: type_field(3)?,
+                    },
+                );
+            }
+        }
+    }
+    Some(())
+}
+
+fn parse_descriptor_message(
+    schema: &mut ProtoSchema,
+    scope: &str,
+    message: &[WireField],
+) -> Option<()> {
+    // DescriptorProto
+    let name = qualified_name(scope, &string_field(message, 1)?);
+    let mut message_def = MessageDef::default();
+    for field in message_fields(message, 2)? {
+        // FieldDescriptorProto
+        let field_type = match varint_field(&field, 5) {
+            Some(1) => FieldType::Double,
+            Some(2) => FieldType::Float,
+            Some(3) => FieldType::Int64,
+            Some(4) => FieldType::UInt64,
+            Some(5) => FieldType::Int32,
+            Some(6) => FieldType::Fixed64,
+            Some(7) => FieldType::Fixed32,
+            Some(8) => FieldType::Bool,
+            Some(9) => FieldType::String,
+            Some(11) => FieldType::Message(descriptor_type_name(&field)?),
+            Some(12) => FieldType::Bytes,
+            Some(13) => FieldType::UInt32,
+            Some(14) => FieldType::Enum(descriptor_type_name(&field)?),
+            Some(15) => FieldType::SFixed32,
+            Some(16) => FieldType::SFixed64,
+            Some(17) => FieldType::SInt32,
+            Some(18) => FieldType::SInt64,
+            // groups, or the type is only given by name
+            _ => FieldType::Unresolved {
+                scope: name.clone(),
+                name: string_field(&field, 6)?,
+            },
+        };
+        message_def.fields.insert(
+            varint_field(&field, 3)?,
+            FieldDef {
+                name: string_field(&field, 1)?,
+                field_type,
+            },
+        );
+    }
+    for nested in message_fields(message, 3)? {
+        parse_descriptor_message(schema, &name, &nested)?;
+    }
+    for enum_desc in message_fields(message, 4)? {
+        parse_descriptor_enum(schema, &name, &enum_desc)?;
+    }
+    schema.messages.insert(name, message_def);
+    Some(())
+}
+
+fn parse_descriptor_enum(
+    schema: &mut ProtoSchema,
+    scope: &str,
+    enum_desc: &[WireField],
+) -> Option<()> {
+    // EnumDescriptorProto
+    let name = qualified_name(scope, &string_field(enum_desc, 1)?);
+    let values = message_fields(enum_desc, 2)?
+        .iter()
+        .map(|v| {
+            // EnumValueDescriptorProto
+            Some((
+                i64::from(varint_field(v, 2).unwrap_or(0) as i32),
+                string_field(v, 1)?,
+            ))
+        })
+        .collect::<Option<_>>()?;
+    schema.enums.insert(name, values);
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_proto_file() {
+        let mut schema = ProtoSchema::default();
+        parse_proto_file(
+            &mut schema,
+            r#"
+syntax = "proto3";
+package shop.v1;
+import "google/protobuf/timestamp.proto";
+option java_package = "com.example.shop";
+
+/* a service */
+service Orders {
+  rpc GetOrder (GetOrderRequest) returns (Order);
+  rpc Watch (stream GetOrderRequest) returns (stream Order) {
+    option (google.api.http) = { get: "/v1/orders" };
+  }
+}
+
+message GetOrderRequest {
+  string id = 1; // the order id
+}
+
+message Order {
+  enum Status {
+    UNKNOWN = 0;
+    SHIPPED = 1 [deprecated = true];
+  }
+  message Line {
+    string sku = 1;
+    uint32 quantity = 2;
+  }
+  string id = 1;
+  Status status = 2;
+  repeated Line lines = 3;
+  map<string, int64> totals = 4;
+  oneof payment {
+    string card = 5;
+    string iban = 6;
+  }
+  reserved 7, 8;
+}
+"#,
+        )
+        .unwrap();
+        schema.resolve_types();
+        assert_eq!(
+            Some(("shop.v1.GetOrderRequest", "shop.v1.Order")),
+            schema.method_types("shop.v1.Orders/GetOrder")
+        );
+        assert!(schema.methods.contains_key("shop.v1.Orders/Watch"));
+        let order = &schema.messages["shop.v1.Order"];
+        assert_eq!(
+            FieldType::Enum("shop.v1.Order.Status".to_string()),
+            order.fields[&2].field_type
+        );
+        assert_eq!(
+            FieldType::Message("shop.v1.Order.Line".to_string()),
+            order.fields[&3].field_type
+        );
+        assert_eq!(
+            FieldType::Message("shop.v1.Order.TotalsEntry".to_string()),
+            order.fields[&4].field_type
+        );
+        assert_eq!("iban", order.fields[&6].name);
+        assert_eq!(
+            Some(&"SHIPPED".to_string()),
+            schema.enums["shop.v1.Order.Status"].get(&1)
+        );
+
+        // id: "a1", status: SHIPPED, lines: [{sku: "x", quantity: 2}], totals: {"eur": 10}
+        let data = hex::decode("0a02613110011a050a0178100222070a03657572100a").unwrap();
+        assert_eq!(
+            "id: \"a1\"\nstatus: SHIPPED\nlines {\n  sku: \"x\"\n  quantity: 2\n}\ntotals {\n  key: \"eur\"\n  value: 10\n}\n",
+            schema.format_message("shop.v1.Order", &data).unwrap()
+        );
+    }
+
+    #[test]
+    fn should_parse_descriptor_set() {
+        // built by hand: a file "pkg" with message M { string s = 1; } and
+        // service S { rpc Get (M) returns (M); }
+        let field = hex::decode("0a0173180120012809").unwrap();
+        let message = [&hex::decode("0a014d1209").unwrap()[..], &field].concat();
+        let method = hex::decode("0a0347657412062e706b672e4d1a062e706b672e4d").unwrap();
+        let service = [
+            &hex::decode("0a015312").unwrap()[..],
+            &[method.len() as u8],
+            &method,
+        ]
+        .concat();
+        let file = [
+            &hex::decode("1203706b67").unwrap()[..],
+            &[0x22, message.len() as u8],
+            &message,
+            &[0x32, service.len() as u8],
+            &service,
+        ]
+        .concat();
+        let set = [&[0x0a, file.len() as u8][..], &file].concat();
+        let mut schema = ProtoSchema::default();
+        parse_descriptor_set(&mut schema, &set).unwrap();
+        assert_eq!(Some(("pkg.M", "pkg.M")), schema.method_types("pkg.S/Get"));
+        assert_eq!(
+            "s: \"hi\"\n",
+            schema.format_message("pkg.M", b"\x0a\x02hi").unwrap()
+        );
+    }
+}
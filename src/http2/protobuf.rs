@@ -0,0 +1,520 @@
+// decoding of protobuf messages, as found in gRPC bodies.
+// without a schema we can only display field numbers and wire-level values;
+// with a schema (see proto_schema.rs) we display field names and typed values.
+// https://developers.google.com/protocol-buffers/docs/encoding
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    LengthDelimited(&'a [u8]),
+    Fixed32(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WireField<'a> {
+    pub number: u64,
+    pub value: WireValue<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldType {
+    Double,
+    Float,
+    Int32,
+    Int64,
+    UInt32,
+    UInt64,
+    SInt32,
+    SInt64,
+    Fixed32,
+    Fixed64,
+    SFixed32,
+    SFixed64,
+    Bool,
+    String,
+    Bytes,
+    /// fully qualified name, without the leading dot
+    Message(String),
+    /// fully qualified name, without the leading dot
+    Enum(String),
+    /// a type name as written in a .proto file, which we resolve
+    /// once we have loaded all the files
+    Unresolved {
+        scope: String,
+        name: String,
+    },
+}
+
+impl FieldType {
+    pub fn from_scalar_name(name: &str) -> Option<FieldType> {
+        Some(match name {
+            "double" => FieldType::Double,
+            "float" => FieldType::Float,
+            "int32" => FieldType::Int32,
+            "int64" => FieldType::Int64,
+            "uint32" => FieldType::UInt32,
+            "uint64" => FieldType::UInt64,
+            "sint32" => FieldType::SInt32,
+            "sint64" => FieldType::SInt64,
+            "fixed32" => FieldType::Fixed32,
+            "fixed64" => FieldType::Fixed64,
+            "sfixed32" => FieldType::SFixed32,
+            "sfixed64" => FieldType::SFixed64,
+            "bool" => FieldType::Bool,
+            "string" => FieldType::String,
+            "bytes" => FieldType::Bytes,
+            _ => return None,
+        })
+    }
+
+    /// repeated fields of these types can be "packed" in a single
+    /// length-delimited value: return the wire type of the packed values
+    fn packed_wire_type(&self) -> Option<u8> {
+        match self {
+            FieldType::Int32
+            | FieldType::Int64
+            | FieldType::UInt32
+            | FieldType::UInt64
+            | FieldType::SInt32
+            | FieldType::SInt64
+            | FieldType::Bool
+            | FieldType::Enum(_) => Some(0),
+            FieldType::Double | FieldType::Fixed64 | FieldType::SFixed64 => Some(1),
+            FieldType::Float | FieldType::Fixed32 | FieldType::SFixed32 => Some(5),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDef {
+    pub name: String,
+    pub field_type: FieldType,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MessageDef {
+    pub fields: HashMap<u64, FieldDef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodDef {
+    pub input_type: FieldType,
+    pub output_type: FieldType,
+}
+
+#[derive(Debug, Default)]
+pub struct ProtoSchema {
+    /// by fully qualified name, for instance "helloworld.HelloRequest"
+    pub messages: HashMap<String, MessageDef>,
+    /// by fully qualified name, then value => name
+    pub enums: HashMap<String, HashMap<i64, String>>,
+    /// by gRPC method, for instance "helloworld.Greeter/SayHello"
+    pub methods: HashMap<String, MethodDef>,
+}
+
+impl ProtoSchema {
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty() && self.methods.is_empty()
+    }
+
+    /// the input and output message types for a gRPC method
+    pub fn method_types(&self, method: &str) -> Option<(&str, &str)> {
+        match self.methods.get(method) {
+            Some(MethodDef {
+                input_type: FieldType::Message(input),
+                output_type: FieldType::Message(output),
+            }) => Some((input, output)),
+            _ => None,
+        }
+    }
+
+    /// turn the type names that we got from .proto files into fully qualified
+    /// message or enum types, following the protobuf scoping rules: look in the
+    /// innermost scope first, then in the enclosing scopes.
+    pub fn resolve_types(&mut self) {
+        let message_names: HashSet<String> = self.messages.keys().cloned().collect();
+        let enum_names: HashSet<String> = self.enums.keys().cloned().collect();
+        let resolve = |field_type: &mut FieldType| {
+            if let FieldType::Unresolved { scope, name } = field_type {
+                if let Some(resolved) = resolve_type_name(&message_names, &enum_names, scope, name)
+                {
+                    *field_type = resolved;
+                }
+            }
+        };
+        for message in self.messages.values_mut() {
+            for field in message.fields.values_mut() {
+                resolve(&mut field.field_type);
+            }
+        }
+        for method in self.methods.values_mut() {
+            resolve(&mut method.input_type);
+            resolve(&mut method.output_type);
+        }
+    }
+
+    /// None if the bytes are not a valid protobuf message
+    pub fn format_message(&self, type_name: &str, data: &[u8]) -> Option<String> {
+        let fields = decode_wire_fields(data)?;
+        let mut out = String::new();
+        self.write_typed_fields(&mut out, type_name, &fields, 0);
+        Some(out)
+    }
+
+    fn write_typed_fields(
+        &self,
+        out: &mut String,
+        type_name: &str,
+        fields: &[WireField],
+        indent: usize,
+    ) {
+        let message = self.messages.get(type_name);
+        for field in fields {
+            match message.and_then(|m| m.fields.get(&field.number)) {
+                Some(field_def) => self.write_typed_field(out, field_def, &field.value, indent),
+                None => {
+                    write_schemaless_field(out, &field.number.to_string(), &field.value, indent)
+                }
+            }
+        }
+    }
+
+    fn write_typed_field(
+        &self,
+        out: &mut String,
+        field_def: &FieldDef,
+        value: &WireValue,
+        indent: usize,
+    ) {
+        match (&field_def.field_type, value) {
+            (FieldType::Message(type_name), WireValue::LengthDelimited(bytes)) => {
+                match decode_wire_fields(bytes) {
+                    Some(nested) => {
+                        write_indent(out, indent);
+                        let _ = writeln!(out, "{} {{", field_def.name);
+                        self.write_typed_fields(out, type_name, &nested, indent + 1);
+                        write_indent(out, indent);
+                        out.push_str("}\n");
+                    }
+                    None => write_schemaless_field(out, &field_def.name, value, indent),
+                }
+            }
+            (FieldType::String, WireValue::LengthDelimited(bytes)) => {
+                write_indent(out, indent);
+                let _ = writeln!(
+                    out,
+                    "{}: {:?}",
+                    field_def.name,
+                    String::from_utf8_lossy(bytes)
+                );
+            }
+            (FieldType::Bytes, WireValue::LengthDelimited(bytes)) => {
+                write_indent(out, indent);
+                let _ = writeln!(out, "{}: 0x{}", field_def.name, hex::encode(bytes));
+            }
+            (field_type, WireValue::LengthDelimited(bytes)) => {
+                match field_type
+                    .packed_wire_type()
+                    .and_then(|wire_type| read_packed(bytes, wire_type))
+                {
+                    Some(values) => {
+                        for v in &values {
+                            self.write_typed_field(out, field_def, v, indent);
+                        }
+                    }
+                    None => write_schemaless_field(out, &field_def.name, value, indent),
+                }
+            }
+            (field_type, value) => match self.format_scalar(field_type, value) {
+                Some(s) => {
+                    write_indent(out, indent);
+                    let _ = writeln!(out, "{}: {}", field_def.name, s);
+                }
+                None => write_schemaless_field(out, &field_def.name, value, indent),
+            },
+        }
+    }
+
+    fn format_scalar(&self, field_type: &FieldType, value: &WireValue) -> Option<String> {
+        Some(match (field_type, *value) {
+            (FieldType::Int32, WireValue::Varint(v)) => (v as i32).to_string(),
+            (FieldType::Int64, WireValue::Varint(v)) => (v as i64).to_string(),
+            (FieldType::UInt32, WireValue::Varint(v)) => (v as u32).to_string(),
+            (FieldType::UInt64, WireValue::Varint(v)) => v.to_string(),
+            (FieldType::SInt32 | FieldType::SInt64, WireValue::Varint(v)) => {
+                ((v >> 1) as i64 ^ -((v & 1) as i64)).to_string()
+            }
+            (FieldType::Bool, WireValue::Varint(v)) => (v != 0).to_string(),
+            (FieldType::Enum(enum_name), WireValue::Varint(v)) => self
+                .enums
+                .get(enum_name)
+                .and_then(|e| e.get(&i64::from(v as i32)))
+                .cloned()
+                .unwrap_or_else(|| (v as i32).to_string()),
+            (FieldType::Fixed64, WireValue::Fixed64(v)) => v.to_string(),
+            (FieldType::SFixed64, WireValue::Fixed64(v)) => (v as i64).to_string(),
+            (FieldType::Double, WireValue::Fixed64(v)) => f64::from_bits(v).to_string(),
+            (FieldType::Fixed32, WireValue::Fixed32(v)) => v.to_string(),
+            (FieldType::SFixed32, WireValue::Fixed32(v)) => (v as i32).to_string(),
+            (FieldType::Float, WireValue::Fixed32(v)) => f32::from_bits(v).to_string(),
+            _ => return None,
+        })
+    }
+}
+
+fn resolve_type_name(
+    message_names: &HashSet<String>,
+    enum_names: &HashSet<String>,
+    scope: &str,
+    name: &str,
+) -> Option<FieldType> {
+    let mut candidates = vec![];
+    if let Some(absolute) = name.strip_prefix('.') {
+        candidates.push(absolute.to_string());
+    } else {
+        let mut cur_scope = Some(scope);
+        while let Some(s) = cur_scope {
+            candidates.push(if s.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", s, name)
+            });
+            cur_scope = match s.rsplit_once('.') {
+                Some((parent, _)) => Some(parent),
+                None if !s.is_empty() => Some(""),
+                None => None,
+            };
+        }
+    }
+    candidates.into_iter().find_map(|c| {
+        if message_names.contains(&c) {
+            Some(FieldType::Message(c))
+        } else if enum_names.contains(&c) {
+            Some(FieldType::Enum(c))
+        } else {
+            None
+        }
+    })
+}
+
+pub fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut result = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        result |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(result);
+        }
+    }
+    None
+}
+
+fn read_fixed64(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let bytes = data.get(*pos..*pos + 8)?;
+    *pos += 8;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn read_fixed32(data: &[u8], pos: &mut usize) -> Option<u32> {
+    let bytes = data.get(*pos..*pos + 4)?;
+    *pos += 4;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+fn read_packed(data: &[u8], wire_type: u8) -> Option<Vec<WireValue<'static>>> {
+    let mut pos = 0;
+    let mut values = vec![];
+    while pos < data.len() {
+        values.push(match wire_type {
+            0 => WireValue::Varint(read_varint(data, &mut pos)?),
+            1 => WireValue::Fixed64(read_fixed64(data, &mut pos)?),
+            _ => WireValue::Fixed32(read_fixed32(data, &mut pos)?),
+        });
+    }
+    Some(values)
+}
+
+/// None if the bytes are not a valid protobuf message
+pub fn decode_wire_fields(data: &[u8]) -> Option<Vec<WireField<'_>>> {
+    let mut pos = 0;
+    let mut fields = vec![];
+    while pos < data.len() {
+        let key = read_varint(data, &mut pos)?;
+        let number = key >> 3;
+        if number == 0 {
+            return None;
+        }
+        let value = match key & 0x7 {
+            0 => WireValue::Varint(read_varint(data, &mut pos)?),
+            1 => WireValue::Fixed64(read_fixed64(data, &mut pos)?),
+            2 => {
+                let len = read_varint(data, &mut pos)? as usize;
+                let bytes = data.get(pos..pos.checked_add(len)?)?;
+                pos += len;
+                WireValue::LengthDelimited(bytes)
+            }
+            5 => WireValue::Fixed32(read_fixed32(data, &mut pos)?),
+            // 3 & 4 are the deprecated groups
+            _ => return None,
+        };
+        fields.push(WireField { number, value });
+    }
+    Some(fields)
+}
+
+/// display a message for which we don't have the schema: field numbers and
+/// wire-level values. None if the bytes are not a valid protobuf message
+pub fn format_message_schemaless(data: &[u8]) -> Option<String> {
+    let fields = decode_wire_fields(data)?;
+    let mut out = String::new();
+    for field in &fields {
+        write_schemaless_field(&mut out, &field.number.to_string(), &field.value, 0);
+    }
+    Some(out)
+}
+
+fn write_schemaless_field(out: &mut String, label: &str, value: &WireValue, indent: usize) {
+    match value {
+        WireValue::Varint(v) => {
+            write_indent(out, indent);
+            let _ = writeln!(out, "{}: {}", label, v);
+        }
+        WireValue::Fixed64(v) => {
+            write_indent(out, indent);
+            let _ = writeln!(out, "{}: {} (fixed64)", label, v);
+        }
+        WireValue::Fixed32(v) => {
+            write_indent(out, indent);
+            let _ = writeln!(out, "{}: {} (fixed32)", label, v);
+        }
+        WireValue::LengthDelimited(bytes) => {
+            // could be a string, bytes, a nested message or a packed repeated
+            // field. Strings often also parse as messages, so try them first.
+            match (
+                printable_str(bytes),
+                decode_wire_fields(bytes).filter(|f| !f.is_empty()),
+            ) {
+                (Some(s), _) => {
+                    write_indent(out, indent);
+                    let _ = writeln!(out, "{}: {:?}", label, s);
+                }
+                (None, Some(nested)) => {
+                    write_indent(out, indent);
+                    let _ = writeln!(out, "{} {{", label);
+                    for field in &nested {
+                        write_schemaless_field(
+                            out,
+                            &field.number.to_string(),
+                            &field.value,
+                            indent + 1,
+                        );
+                    }
+                    write_indent(out, indent);
+                    out.push_str("}\n");
+                }
+                (None, None) => {
+                    write_indent(out, indent);
+                    let _ = writeln!(out, "{}: 0x{}", label, hex::encode(bytes));
+                }
+            }
+        }
+    }
+}
+
+fn printable_str(bytes: &[u8]) -> Option<&str> {
+    std::str::from_utf8(bytes).ok().filter(|s| {
+        s.chars()
+            .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+    })
+}
+
+fn write_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_decode_schemaless_message() {
+        // 1: 150, 2: "testing", 3: { 1: 150 }, 4: 1.0f32
+        let data = hex::decode("089601120774657374696e671a03089601250000803f").unwrap();
+        assert_eq!(
+            "1: 150\n2: \"testing\"\n3 {\n  1: 150\n}\n4: 1065353216 (fixed32)\n",
+            format_message_schemaless(&data).unwrap()
+        );
+        assert_eq!(None, format_message_schemaless(&[0x08]));
+    }
+
+    #[test]
+    fn should_decode_typed_message() {
+        let mut schema = ProtoSchema::default();
+        let field = |name: &str, field_type: FieldType| FieldDef {
+            name: name.to_string(),
+            field_type,
+        };
+        schema.messages.insert(
+            "pkg.Outer".to_string(),
+            MessageDef {
+                fields: vec![
+                    (1, field("id", FieldType::SInt32)),
+                    (2, field("name", FieldType::String)),
+                    (
+                        3,
+                        field(
+                            "inner",
+                            FieldType::Unresolved {
+                                scope: "pkg.Outer".to_string(),
+                                name: "Inner".to_string(),
+                            },
+                        ),
+                    ),
+                    (
+                        4,
+                        field(
+                            "status",
+                            FieldType::Unresolved {
+                                scope: "pkg.Outer".to_string(),
+                                name: "Status".to_string(),
+                            },
+                        ),
+                    ),
+                    (5, field("scores", FieldType::Int32)),
+                ]
+                .into_iter()
+                .collect(),
+            },
+        );
+        schema.messages.insert(
+            "pkg.Outer.Inner".to_string(),
+            MessageDef {
+                fields: vec![(1, field("flag", FieldType::Bool))]
+                    .into_iter()
+                    .collect(),
+            },
+        );
+        schema.enums.insert(
+            "pkg.Status".to_string(),
+            vec![(0, "UNKNOWN".to_string()), (1, "ACTIVE".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        schema.resolve_types();
+
+        // id: -2, name: "ab", inner: { flag: true }, status: ACTIVE,
+        // scores: [1, 2] (packed), 9: 3 (not in the schema)
+        let data = hex::decode("0803120261621a02080120012a0201024803").unwrap();
+        assert_eq!(
+            "id: -2\nname: \"ab\"\ninner {\n  flag: true\n}\nstatus: ACTIVE\nscores: 1\nscores: 2\n9: 3\n",
+            schema.format_message("pkg.Outer", &data).unwrap()
+        );
+    }
+}
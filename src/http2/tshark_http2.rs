@@ -1,14 +1,61 @@
 use crate::tshark_communication;
+use crate::tshark_json;
 use quick_xml::events::Event;
+use serde_json::Value;
 use std::fmt::Debug;
 use std::io::BufRead;
 
+pub const HTTP2_FRAME_TYPE_HEADERS: u8 = 1;
+
+/// a GOAWAY frame: the connection is closing, the streams
+/// above last_stream_id won't be processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Http2GoAway {
+    pub last_stream_id: u32,
+    pub error_code: u32,
+}
+
 #[derive(Debug)]
 pub struct TSharkHttp2Message {
     pub headers: Vec<(String, String)>,
     pub data: Option<Vec<u8>>,
     pub stream_id: u32,
     pub is_end_stream: bool,
+    pub frame_type: Option<u8>,
+    /// the error code of a RST_STREAM frame
+    pub rst_stream_error: Option<u32>,
+    pub goaway: Option<Http2GoAway>,
+}
+
+impl TSharkHttp2Message {
+    fn is_relevant(&self) -> bool {
+        !self.headers.is_empty()
+            || matches!(&self.data, Some(v) if !v.is_empty())
+            || self.rst_stream_error.is_some()
+            || self.goaway.is_some()
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc7540#section-7
+pub fn http2_error_code_name(code: u32) -> String {
+    match code {
+        0x0 => "NO_ERROR",
+        0x1 => "PROTOCOL_ERROR",
+        0x2 => "INTERNAL_ERROR",
+        0x3 => "FLOW_CONTROL_ERROR",
+        0x4 => "SETTINGS_TIMEOUT",
+        0x5 => "STREAM_CLOSED",
+        0x6 => "FRAME_SIZE_ERROR",
+        0x7 => "REFUSED_STREAM",
+        0x8 => "CANCEL",
+        0x9 => "COMPRESSION_ERROR",
+        0xa => "CONNECT_ERROR",
+        0xb => "ENHANCE_YOUR_CALM",
+        0xc => "INADEQUATE_SECURITY",
+        0xd => "HTTP_1_1_REQUIRED",
+        _ => return format!("unknown error {:#x}", code),
+    }
+    .to_string()
 }
 
 pub fn parse_http2_info<B: BufRead>(
@@ -22,7 +69,7 @@ pub fn parse_http2_info<B: BufRead>(
                 let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
                 if name.as_deref() == Some(b"http2.stream")  {
                     let msg = parse_http2_stream(xml_reader)?;
-                    if !msg.headers.is_empty() || matches!(&msg.data, Some(v) if !v.is_empty()) {
+                    if msg.is_relevant() {
                         streams.push(msg);
                     }
                 }
@@ -44,6 +91,10 @@ fn parse_http2_stream<B: BufRead>(
     let mut data = None;
     let mut stream_id = 0;
     let mut is_end_stream = false;
+    let mut frame_type = None;
+    let mut rst_stream_error = None;
+    let mut goaway_last_stream_id = None;
+    let mut goaway_error = None;
     let buf = &mut vec![];
     xml_event_loop!(xml_reader, buf,
         Ok(Event::Empty(ref e)) => {
@@ -60,6 +111,18 @@ fn parse_http2_stream<B: BufRead>(
                             tshark_communication::element_attr_val_number(e, b"show")?
                                 == Some(1);
                     }
+                    Some(b"http2.type") => {
+                        frame_type = tshark_communication::element_attr_val_number(e, b"show")?;
+                    }
+                    Some(b"http2.rst_stream.error") => {
+                        rst_stream_error = tshark_communication::element_attr_val_number(e, b"show")?;
+                    }
+                    Some(b"http2.goaway.last_stream_id") => {
+                        goaway_last_stream_id = tshark_communication::element_attr_val_number(e, b"show")?;
+                    }
+                    Some(b"http2.goaway.error") => {
+                        goaway_error = tshark_communication::element_attr_val_number(e, b"show")?;
+                    }
                     Some(b"http2.data.data") => {
                         if let Some(data_hex) = tshark_communication::element_attr_val_string(e, b"show")? {
                             data = hex::decode(data_hex.replace(':', "")).ok();
@@ -88,6 +151,12 @@ fn parse_http2_stream<B: BufRead>(
                         data,
                         stream_id,
                         is_end_stream,
+                        frame_type,
+                        rst_stream_error,
+                        goaway: goaway_last_stream_id.map(|last_stream_id| Http2GoAway {
+                            last_stream_id,
+                            error_code: goaway_error.unwrap_or(0),
+                        }),
                     });
                 }
             }
@@ -126,3 +195,33 @@ fn parse_http2_headers<B: BufRead>(
         }
     )
 }
+
+pub fn parse_http2_json(layer: &Value) -> Vec<TSharkHttp2Message> {
+    tshark_json::find_subtrees(layer, "http2.stream")
+        .into_iter()
+        .map(|stream| TSharkHttp2Message {
+            headers: tshark_json::find_subtrees(stream, "http2.header")
+                .into_iter()
+                .filter_map(|h| {
+                    Some((
+                        tshark_json::first_str(h, "http2.header.name")?,
+                        tshark_json::first_str(h, "http2.header.value")?,
+                    ))
+                })
+                .collect(),
+            data: tshark_json::first_bytes(stream, "http2.data.data"),
+            stream_id: tshark_json::first_number(stream, "http2.streamid").unwrap_or(0),
+            is_end_stream: tshark_json::is_flag_set(stream, "http2.flags.end_stream"),
+            frame_type: tshark_json::first_number(stream, "http2.type"),
+            rst_stream_error: tshark_json::first_number(stream, "http2.rst_stream.error"),
+            goaway: tshark_json::first_number(stream, "http2.goaway.last_stream_id").map(
+                |last_stream_id| Http2GoAway {
+                    last_stream_id,
+                    error_code: tshark_json::first_number(stream, "http2.goaway.error")
+                        .unwrap_or(0),
+                },
+            ),
+        })
+        .filter(|msg| msg.is_relevant())
+        .collect()
+}
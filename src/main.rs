@@ -9,17 +9,23 @@ use widgets::win;
 pub mod colors;
 pub mod config;
 pub mod custom_streams_store;
+pub mod headless;
 pub mod icons;
+pub mod native_capture;
 pub mod packets_read;
+pub mod saved_searches;
 pub mod search_expr;
 pub mod streams;
 #[macro_use]
 mod tshark_communication;
+mod tshark_json;
 mod widgets;
 
 pub mod http;
 pub mod http2;
+pub mod mysql;
 pub mod pgsql;
+pub mod redis;
 
 #[macro_use]
 extern crate lazy_static;
@@ -41,6 +47,15 @@ impl BgFunc {
 }
 
 fn main() {
+    if std::env::args().nth(1).as_deref() == Some("--dump") {
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        if let Err(e) = headless::run(&args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let res_bytes = include_bytes!("icons.bin");
     let data = glib::Bytes::from(&res_bytes[..]);
     let resource = gio::Resource::from_data(&data).unwrap();
@@ -0,0 +1,3 @@
+pub mod mysql_details_widget;
+pub mod mysql_streams_store;
+pub mod tshark_mysql;
@@ -0,0 +1,285 @@
+use super::mysql_streams_store::MysqlMessageData;
+use crate::colors;
+use crate::pgsql::postgres_details_widget::PostgresCommEntry;
+use crate::tshark_communication::TcpStreamId;
+use crate::widgets::comm_info_header;
+use crate::widgets::comm_info_header::CommInfoHeader;
+use crate::widgets::win;
+use crate::BgFunc;
+use gtk::prelude::*;
+use itertools::Itertools;
+use regex::Regex;
+use relm::Widget;
+use relm_derive::{widget, Msg};
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::mpsc;
+
+pub struct Model {
+    bg_sender: mpsc::Sender<BgFunc>,
+    win_msg_sender: relm::StreamHandle<win::Msg>,
+    stream_id: TcpStreamId,
+    client_ip: IpAddr,
+    data: MysqlMessageData,
+    list_store: Option<gtk::ListStore>,
+    syntax_highlight: Vec<(Regex, String)>,
+
+    _saved_resultset_channel: relm::Channel<Option<String>>, // None on success, or error message
+    saved_resultset_sender: relm::Sender<Option<String>>,
+}
+
+#[derive(Msg, Debug)]
+pub enum Msg {
+    DisplayDetails(mpsc::Sender<BgFunc>, IpAddr, TcpStreamId, MysqlMessageData),
+    ExportResultSet,
+}
+
+#[widget]
+impl Widget for MysqlCommEntry {
+    fn init_view(&mut self) {}
+
+    fn model(
+        _relm: &relm::Relm<Self>,
+        params: (
+            TcpStreamId,
+            IpAddr,
+            MysqlMessageData,
+            relm::StreamHandle<win::Msg>,
+            mpsc::Sender<BgFunc>,
+        ),
+    ) -> Model {
+        let (stream_id, client_ip, data, win_msg_sender, bg_sender) = params;
+        let (_saved_resultset_channel, saved_resultset_sender) = {
+            let win_stream = win_msg_sender.clone();
+            relm::Channel::new(move |d: Option<String>| {
+                if d.is_some() {
+                    // error
+                    win_stream.emit(win::Msg::InfoBarShow(
+                        d,
+                        win::InfobarOptions::ShowCloseButton,
+                    ))
+                } else {
+                    // success
+                    win_stream.emit(win::Msg::InfoBarShow(
+                        Some("The export has completed successfully".to_string()),
+                        win::InfobarOptions::TimeLimitedWithCloseButton,
+                    ))
+                }
+            })
+        };
+        Model {
+            bg_sender,
+            win_msg_sender,
+            data,
+            stream_id,
+            client_ip,
+            list_store: None,
+            syntax_highlight: PostgresCommEntry::prepare_syntax_highlight(),
+
+            saved_resultset_sender,
+            _saved_resultset_channel,
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::DisplayDetails(.., client_ip, stream_id, message_data) => {
+                self.model.data = message_data;
+                self.streams
+                    .comm_info_header
+                    .emit(comm_info_header::Msg::Update(client_ip, stream_id));
+                self.model.stream_id = stream_id;
+                self.model.client_ip = client_ip;
+
+                self.fill_resultset();
+            }
+            Msg::ExportResultSet => {
+                let dialog = gtk::builders::FileChooserNativeBuilder::new()
+                    .action(gtk::FileChooserAction::Save)
+                    .title("Export to...")
+                    .do_overwrite_confirmation(true)
+                    .modal(true)
+                    .build();
+                dialog.set_current_name("resultset.csv");
+                if dialog.run() == gtk::ResponseType::Accept {
+                    let target_fname = dialog.filename().unwrap(); // ## unwrap
+                    self.model.win_msg_sender.emit(win::Msg::InfoBarShow(
+                        Some(format!(
+                            "Saving to file {}",
+                            &target_fname.to_string_lossy()
+                        )),
+                        win::InfobarOptions::ShowSpinner,
+                    ));
+                    {
+                        let s = self.model.saved_resultset_sender.clone();
+                        let mysql_data = self.model.data.clone();
+                        self.model
+                            .bg_sender
+                            .send(BgFunc::new(move || {
+                                s.send(
+                                    Self::save_resultset(&target_fname, &mysql_data)
+                                        .map_err(|e| e.to_string())
+                                        .err(),
+                                )
+                                .unwrap()
+                            }))
+                            .unwrap();
+                    }
+                }
+            }
+        }
+    }
+
+    fn fill_resultset(&mut self) {
+        let descs = if self.model.data.resultset_col_names.is_empty() {
+            // gtk really doesn't like if there are no columns (crashes or something like that)
+            vec![String::static_type()]
+        } else {
+            vec![String::static_type(); self.model.data.resultset_col_names.len()]
+        };
+
+        let list_store = gtk::ListStore::new(&descs);
+        for col in &self.widgets.resultset.columns() {
+            self.widgets.resultset.remove_column(col);
+        }
+
+        for (idx, col_name) in self.model.data.resultset_col_names.iter().enumerate() {
+            let col1 = gtk::builders::TreeViewColumnBuilder::new()
+                .title(col_name)
+                .build();
+            let cell_r_txt = gtk::CellRendererText::new();
+            col1.pack_start(&cell_r_txt, true);
+            col1.add_attribute(&cell_r_txt, "text", idx as i32);
+            self.widgets.resultset.append_column(&col1);
+        }
+
+        for row in &self.model.data.resultset_rows {
+            let iter = list_store.append();
+            // the row may have less values than we have columns,
+            // if we missed some column definitions
+            for (col_idx, val) in row.iter().take(descs.len()).enumerate() {
+                list_store.set_value(
+                    &iter,
+                    col_idx as u32,
+                    &val.as_deref().unwrap_or("null").to_value(),
+                );
+            }
+        }
+        self.widgets.resultset.set_model(Some(&list_store));
+        self.model.list_store = Some(list_store);
+    }
+
+    fn save_resultset(
+        target_fname: &Path,
+        mysql_message: &MysqlMessageData,
+    ) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(target_fname)?);
+        file.write_all(mysql_message.resultset_col_names.join("\t").as_bytes())?;
+        for row in &mysql_message.resultset_rows {
+            file.write_all(b"\n")?;
+            file.write_all(
+                row.iter()
+                    .map(|v| v.as_deref().unwrap_or("null"))
+                    .join("\t")
+                    .as_bytes(),
+            )?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    fn error_markup(data: &MysqlMessageData) -> String {
+        data.error
+            .as_ref()
+            .map(|e| {
+                format!(
+                    "<span foreground=\"{}\"><b>Error</b>: {}</span>",
+                    colors::ERROR_COLOR,
+                    glib::markup_escape_text(&e.to_string())
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    view! {
+        gtk::Box {
+            orientation: gtk::Orientation::Vertical,
+            margin_top: 10,
+            margin_bottom: 10,
+            margin_start: 10,
+            margin_end: 10,
+            spacing: 10,
+            #[name="comm_info_header"]
+            CommInfoHeader(self.model.client_ip.clone(), self.model.stream_id) {
+            },
+            gtk::Paned {
+                orientation: gtk::Orientation::Vertical,
+                gtk::ScrolledWindow {
+                    gtk::Box {
+                        orientation: gtk::Orientation::Vertical,
+                        gtk::Label {
+                            markup: &PostgresCommEntry::highlight_sql(
+                                &self.model.syntax_highlight,
+                                self.model.data.query.as_deref().unwrap_or("Failed retrieving the query string")),
+                            line_wrap: true,
+                            xalign: 0.0,
+                            selectable: true,
+                        },
+                        gtk::Label {
+                            markup: &Itertools::intersperse(self.model.data.parameter_values
+                                                    .iter()
+                                                    .enumerate()
+                                                    .map(|(i, p)| format!("<b>?{}</b>: {}", i+1, glib::markup_escape_text(p))),
+                                                    "\n".to_string()).collect::<String>(),
+                            visible: !self.model.data.parameter_values.is_empty(),
+                            xalign: 0.0,
+                        },
+                        gtk::Label {
+                            markup: &Self::error_markup(&self.model.data),
+                            visible: self.model.data.error.is_some(),
+                            line_wrap: true,
+                            xalign: 0.0,
+                            selectable: true,
+                        },
+                    }
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Vertical,
+                    gtk::Box {
+                        orientation: gtk::Orientation::Horizontal,
+                        visible: !self.model.data.resultset_rows.is_empty(),
+                        gtk::Label {
+                            label: &self.model.data.resultset_rows.len().to_string(),
+                            xalign: 0.0,
+                        },
+                        gtk::Label {
+                            label: " row(s)",
+                            xalign: 0.0,
+                        },
+                        gtk::Button {
+                            child: {
+                                pack_type: gtk::PackType::End,
+                            },
+                            always_show_image: true,
+                            image: Some(&gtk::Image::from_icon_name(
+                                Some("document-save-symbolic"), gtk::IconSize::Menu)),
+                            label: "Export resultset...",
+                            button_press_event(_, _) => (Msg::ExportResultSet, Inhibit(false)),
+                        }
+                    },
+                    gtk::ScrolledWindow {
+                        #[name="resultset"]
+                        gtk::TreeView {
+                            hexpand: true,
+                            vexpand: true,
+                            visible: !self.model.data.resultset_rows.is_empty()
+                        },
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,815 @@
+use super::mysql_details_widget;
+use super::mysql_details_widget::MysqlCommEntry;
+use crate::colors;
+use crate::custom_streams_store;
+use crate::custom_streams_store::{ClientServerInfo, CustomStreamsParser, CustomStreamsStore};
+use crate::icons::Icon;
+use crate::mysql::tshark_mysql::MysqlWireMessage;
+use crate::pgsql::postgres_streams_store::get_query_type_desc;
+use crate::search_expr;
+use crate::tshark_communication::{TSharkPacket, TSharkPacketBasicInfo, TcpStreamId};
+use crate::widgets::win;
+use crate::BgFunc;
+use chrono::{NaiveDateTime, Utc};
+use gtk::prelude::*;
+use relm::ContainerWidget;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::mpsc;
+use strum::VariantNames;
+use strum_macros::{EnumString, EnumVariantNames};
+
+#[cfg(test)]
+use {
+    crate::custom_streams_store::common_tests_parse_stream,
+    crate::tshark_communication::parse_test_xml,
+};
+
+#[derive(Default)]
+pub struct MysqlStreamData {
+    pub stream_globals: MysqlStreamGlobals,
+    pub client_server: Option<ClientServerInfo>,
+    pub messages: Vec<MysqlMessageData>,
+    pub summary_details: Option<String>,
+}
+
+impl MysqlStreamData {
+    fn add_schema_to_summary(&mut self, schema: &str) {
+        match self.summary_details.as_ref() {
+            None => {
+                self.summary_details = Some(schema.to_string());
+            }
+            Some(other_schema) if !other_schema.contains(schema) => {
+                self.summary_details = Some(format!("{}, {}", other_schema, schema));
+            }
+            _ => {}
+        }
+    }
+
+    fn set_client_server_from_client(&mut self, basic_info: &TSharkPacketBasicInfo) {
+        if self.client_server.is_none() {
+            self.client_server = Some(ClientServerInfo {
+                server_ip: basic_info.ip_dst,
+                client_ip: basic_info.ip_src,
+                server_port: basic_info.port_dst,
+            });
+        }
+    }
+
+    fn set_client_server_from_server(&mut self, basic_info: &TSharkPacketBasicInfo) {
+        if self.client_server.is_none() {
+            self.client_server = Some(ClientServerInfo {
+                server_ip: basic_info.ip_src,
+                client_ip: basic_info.ip_dst,
+                server_port: basic_info.port_src,
+            });
+        }
+    }
+
+    /// push the current query to the messages and reset all
+    /// the globals, but keep known_statements
+    fn flush_query(&mut self) {
+        let globals = std::mem::take(&mut self.stream_globals);
+        self.stream_globals.known_statements = globals.known_statements;
+        if let Some(query_timestamp) = globals.query_timestamp {
+            self.messages.push(MysqlMessageData {
+                query: globals.cur_query.map(Cow::Owned),
+                query_timestamp,
+                result_timestamp: globals.result_timestamp.unwrap_or(query_timestamp),
+                parameter_values: globals.cur_parameter_values,
+                resultset_col_names: globals.cur_col_names,
+                resultset_rows: globals.cur_rows,
+                affected_rows: globals.affected_rows,
+                error: globals.error,
+            });
+        }
+    }
+
+    fn start_query(
+        &mut self,
+        query: Option<String>,
+        parameter_values: Vec<String>,
+        timestamp: NaiveDateTime,
+    ) {
+        self.flush_query();
+        self.stream_globals.cur_query = query;
+        self.stream_globals.cur_parameter_values = parameter_values;
+        self.stream_globals.query_timestamp = Some(timestamp);
+    }
+}
+
+#[derive(Default)]
+pub struct MysqlStreamsStore {
+    streams: HashMap<TcpStreamId, MysqlStreamData>,
+    component: Option<relm::Component<MysqlCommEntry>>,
+}
+
+impl MysqlStreamsStore {
+    fn get_msg_info(
+        &self,
+        stream_id: TcpStreamId,
+        msg_idx: usize,
+    ) -> Option<(&MysqlMessageData, ClientServerInfo)> {
+        let stream = self.streams.get(&stream_id)?;
+        let msg = stream.messages.get(msg_idx)?;
+        Some((msg, stream.client_server?))
+    }
+}
+
+#[derive(EnumString, EnumVariantNames)]
+enum MysqlFilterKeys {
+    #[strum(serialize = "mysql.query")]
+    QueryString,
+    #[strum(serialize = "mysql.resultset")]
+    ResultSet,
+    #[strum(serialize = "mysql.query_param")]
+    QueryParamValue,
+    #[strum(serialize = "mysql.error")]
+    Error,
+}
+
+fn get_mysql_message<'a, 'b>(
+    streams: &'a HashMap<TcpStreamId, MysqlStreamData>,
+    model: &'b gtk::TreeModel,
+    iter: &'b gtk::TreeIter,
+) -> Option<&'a MysqlMessageData> {
+    let (stream_id, idx) = custom_streams_store::get_message_helper(model, iter);
+    streams
+        .get(&stream_id)
+        .and_then(|s| s.messages.get(idx as usize))
+}
+
+impl CustomStreamsParser for MysqlStreamsStore {
+    fn is_my_message(&self, msg: &TSharkPacket) -> bool {
+        msg.mysql.is_some()
+    }
+
+    fn tshark_filter_string(&self) -> &'static str {
+        "mysql"
+    }
+
+    fn protocol_name(&self) -> &'static str {
+        "MYSQL"
+    }
+
+    fn tcp_stream_ids(&self) -> Vec<TcpStreamId> {
+        self.streams.keys().copied().collect()
+    }
+
+    fn has_stream_id(&self, stream_id: TcpStreamId) -> bool {
+        self.streams.contains_key(&stream_id)
+    }
+
+    fn reset(&mut self) {
+        self.streams = HashMap::new();
+    }
+
+    fn stream_message_count(&self, stream_id: TcpStreamId) -> Option<usize> {
+        self.streams.get(&stream_id).map(|s| s.messages.len())
+    }
+
+    fn stream_summary_details(&self, stream_id: TcpStreamId) -> Option<&str> {
+        self.streams
+            .get(&stream_id)
+            .and_then(|s| s.summary_details.as_deref())
+    }
+
+    fn stream_client_server(&self, stream_id: TcpStreamId) -> Option<ClientServerInfo> {
+        self.streams.get(&stream_id).and_then(|s| s.client_server)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    fn add_to_stream(
+        &mut self,
+        stream_id: TcpStreamId,
+        new_packet: TSharkPacket,
+    ) -> Result<Option<ClientServerInfo>, String> {
+        let stream = self
+            .streams
+            .entry(stream_id)
+            .or_insert_with(MysqlStreamData::default);
+        let timestamp = new_packet.basic_info.frame_time;
+        if let Some(mds) = new_packet.mysql {
+            for md in mds {
+                match md {
+                    MysqlWireMessage::Login { username, schema } => {
+                        stream.set_client_server_from_client(&new_packet.basic_info);
+                        if let Some(s) = &schema {
+                            stream.add_schema_to_summary(s);
+                        }
+                        stream.flush_query();
+                        stream.messages.push(MysqlMessageData {
+                            query: Some(Cow::Owned(format!(
+                                "LOGIN: user: {}, schema: {}",
+                                username.as_deref().unwrap_or("-"),
+                                schema.as_deref().unwrap_or("-"),
+                            ))),
+                            query_timestamp: timestamp,
+                            result_timestamp: timestamp,
+                            parameter_values: vec![],
+                            resultset_col_names: vec![],
+                            resultset_rows: vec![],
+                            affected_rows: None,
+                            error: None,
+                        });
+                    }
+                    MysqlWireMessage::InitDb { schema } => {
+                        stream.set_client_server_from_client(&new_packet.basic_info);
+                        if let Some(s) = &schema {
+                            stream.add_schema_to_summary(s);
+                        }
+                        stream.start_query(
+                            Some(format!("USE {}", schema.as_deref().unwrap_or("-"))),
+                            vec![],
+                            timestamp,
+                        );
+                    }
+                    MysqlWireMessage::Query { query } => {
+                        stream.set_client_server_from_client(&new_packet.basic_info);
+                        stream.start_query(query, vec![], timestamp);
+                    }
+                    MysqlWireMessage::StmtPrepare { query } => {
+                        stream.set_client_server_from_client(&new_packet.basic_info);
+                        stream.flush_query();
+                        stream.stream_globals.pending_prepare = query;
+                    }
+                    MysqlWireMessage::StmtPrepareOk { statement_id } => {
+                        stream.set_client_server_from_server(&new_packet.basic_info);
+                        if let Some(q) = stream.stream_globals.pending_prepare.take() {
+                            stream
+                                .stream_globals
+                                .known_statements
+                                .insert(statement_id, q);
+                        }
+                    }
+                    MysqlWireMessage::StmtExecute {
+                        statement_id,
+                        parameter_values,
+                    } => {
+                        stream.set_client_server_from_client(&new_packet.basic_info);
+                        let query = statement_id.map(|s| {
+                            stream
+                                .stream_globals
+                                .known_statements
+                                .get(&s)
+                                .cloned()
+                                .unwrap_or(format!("Unknown statement: {}", s))
+                        });
+                        stream.start_query(query, parameter_values, timestamp);
+                    }
+                    MysqlWireMessage::ColumnDefinition { col_name } => {
+                        stream.set_client_server_from_server(&new_packet.basic_info);
+                        // column definitions are also sent after a prepare OK,
+                        // ignore them when no query is running
+                        if stream.stream_globals.query_timestamp.is_some() {
+                            stream.stream_globals.cur_col_names.push(col_name);
+                            stream.stream_globals.result_timestamp = Some(timestamp);
+                        }
+                    }
+                    MysqlWireMessage::ResultSetRow { col_vals } => {
+                        stream.set_client_server_from_server(&new_packet.basic_info);
+                        if stream.stream_globals.query_timestamp.is_some() {
+                            stream.stream_globals.cur_rows.push(col_vals);
+                            stream.stream_globals.result_timestamp = Some(timestamp);
+                        }
+                    }
+                    MysqlWireMessage::Eof => {
+                        stream.set_client_server_from_server(&new_packet.basic_info);
+                        if stream.stream_globals.query_timestamp.is_some() {
+                            stream.stream_globals.result_timestamp = Some(timestamp);
+                            stream.stream_globals.eof_count += 1;
+                            // a resultset is: column definitions, EOF, rows, EOF
+                            if stream.stream_globals.eof_count >= 2
+                                || !stream.stream_globals.cur_rows.is_empty()
+                            {
+                                stream.flush_query();
+                            }
+                        }
+                    }
+                    MysqlWireMessage::Ok { affected_rows } => {
+                        stream.set_client_server_from_server(&new_packet.basic_info);
+                        if stream.stream_globals.query_timestamp.is_some() {
+                            stream.stream_globals.result_timestamp = Some(timestamp);
+                            stream.stream_globals.affected_rows = affected_rows;
+                            stream.flush_query();
+                        }
+                    }
+                    MysqlWireMessage::Err {
+                        error_code,
+                        sqlstate,
+                        message,
+                    } => {
+                        stream.set_client_server_from_server(&new_packet.basic_info);
+                        // a failed prepare doesn't give us a statement id
+                        stream.stream_globals.pending_prepare = None;
+                        if stream.stream_globals.query_timestamp.is_some() {
+                            stream.stream_globals.result_timestamp = Some(timestamp);
+                            stream.stream_globals.error = Some(MysqlError {
+                                code: error_code,
+                                sqlstate,
+                                message: message.unwrap_or_default(),
+                            });
+                            stream.flush_query();
+                        }
+                    }
+                }
+            }
+        }
+        Ok(stream.client_server)
+    }
+
+    fn finish_stream(&mut self, stream_id: TcpStreamId) -> Result<(), String> {
+        if let Some(stream) = self.streams.get_mut(&stream_id) {
+            stream.flush_query();
+        }
+        Ok(())
+    }
+
+    fn stream_messages_json(&self, stream_id: TcpStreamId) -> Vec<serde_json::Value> {
+        self.streams
+            .get(&stream_id)
+            .map(|s| s.messages.iter().map(|m| m.to_json()).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl CustomStreamsStore for MysqlStreamsStore {
+    fn protocol_icon(&self) -> Icon {
+        Icon::DATABASE
+    }
+
+    fn prepare_treeview(&self, tv: &gtk::TreeView) {
+        let streamcolor_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("S")
+            .fixed_width(10)
+            .sort_column_id(2)
+            .build();
+        let cell_s_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        streamcolor_col.pack_start(&cell_s_txt, true);
+        streamcolor_col.add_attribute(&cell_s_txt, "background", 10);
+        tv.append_column(&streamcolor_col);
+
+        let queryt_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Type")
+            .fixed_width(24)
+            .sort_column_id(9)
+            .build();
+        let cell_qt_txt = gtk::builders::CellRendererPixbufBuilder::new().build();
+        queryt_col.pack_start(&cell_qt_txt, true);
+        queryt_col.add_attribute(&cell_qt_txt, "icon-name", 9);
+        tv.append_column(&queryt_col);
+
+        let timestamp_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Timestamp")
+            .resizable(true)
+            .sort_column_id(5)
+            .build();
+        let cell_t_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        timestamp_col.pack_start(&cell_t_txt, true);
+        timestamp_col.add_attribute(&cell_t_txt, "text", 4);
+        tv.append_column(&timestamp_col);
+
+        let query_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Query")
+            .expand(true)
+            .resizable(true)
+            .sort_column_id(0)
+            .build();
+        let cell_q_txt = gtk::builders::CellRendererTextBuilder::new()
+            .ellipsize(pango::EllipsizeMode::End)
+            .build();
+        query_col.pack_start(&cell_q_txt, true);
+        query_col.add_attribute(&cell_q_txt, "text", 0);
+        tv.append_column(&query_col);
+
+        let result_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Result")
+            .resizable(true)
+            .sort_column_id(8)
+            .build();
+        let cell_r_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        result_col.pack_start(&cell_r_txt, true);
+        result_col.add_attribute(&cell_r_txt, "text", 1);
+        result_col.add_attribute(&cell_r_txt, "foreground", 11);
+        tv.append_column(&result_col);
+
+        let duration_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Duration")
+            .resizable(true)
+            .sort_column_id(6)
+            .build();
+        let cell_d_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        duration_col.pack_start(&cell_d_txt, true);
+        duration_col.add_attribute(&cell_d_txt, "text", 7);
+        tv.append_column(&duration_col);
+    }
+
+    fn get_empty_treestore(&self) -> gtk::TreeStore {
+        gtk::TreeStore::new(&[
+            String::static_type(), // query first line
+            String::static_type(), // response info (number of rows, error..)
+            u32::static_type(),    // stream_id
+            u32::static_type(),    // index of the comm in the model vector
+            String::static_type(), // query start timestamp (string)
+            i64::static_type(),    // query start timestamp (integer, for sorting)
+            i32::static_type(),    // query duration (nanos, for sorting)
+            String::static_type(), // query duration display
+            i64::static_type(),    // number of rows, for sorting
+            String::static_type(), // query type: update, insert..
+            String::static_type(), // stream color
+            String::static_type(), // result color
+        ])
+    }
+
+    fn populate_treeview(
+        &self,
+        ts: &gtk::TreeStore,
+        session_id: TcpStreamId,
+        start_idx: usize,
+        item_count: usize,
+    ) {
+        let messages = &self.streams.get(&session_id).unwrap().messages;
+        for (idx, mysql) in messages.iter().skip(start_idx).take(item_count).enumerate() {
+            ts.insert_with_values(
+                None,
+                None,
+                &[
+                    (
+                        0,
+                        &mysql
+                            .query
+                            .as_deref()
+                            .map(|q| match q.char_indices().nth(250) {
+                                Some((idx, _)) => &q[..idx],
+                                None => q,
+                            })
+                            .unwrap_or("couldn't get query")
+                            .replace("\n", "")
+                            .to_value(),
+                    ),
+                    (1, &mysql.result_summary().to_value()),
+                    (
+                        custom_streams_store::TREE_STORE_STREAM_ID_COL_IDX,
+                        &session_id.as_u32().to_value(),
+                    ),
+                    (
+                        custom_streams_store::TREE_STORE_MESSAGE_INDEX_COL_IDX,
+                        &((start_idx + idx) as i32).to_value(),
+                    ),
+                    (4, &mysql.query_timestamp.to_string().to_value()),
+                    (5, &mysql.query_timestamp.timestamp_nanos().to_value()),
+                    (
+                        6,
+                        &(mysql.result_timestamp - mysql.query_timestamp)
+                            .num_milliseconds()
+                            .to_value(),
+                    ),
+                    (
+                        7,
+                        &format!(
+                            "{} ms",
+                            (mysql.result_timestamp - mysql.query_timestamp).num_milliseconds()
+                        )
+                        .to_value(),
+                    ),
+                    (8, &(mysql.resultset_rows.len() as i64).to_value()),
+                    (9, &get_query_type_desc(&mysql.query).to_value()),
+                    (
+                        10,
+                        &colors::STREAM_COLORS
+                            [session_id.as_u32() as usize % colors::STREAM_COLORS.len()]
+                        .to_value(),
+                    ),
+                    (
+                        11,
+                        &if mysql.error.is_some() {
+                            colors::ERROR_COLOR.to_value()
+                        } else {
+                            None::<&str>.to_value()
+                        },
+                    ),
+                ],
+            );
+        }
+    }
+
+    fn end_populate_treeview(&self, tv: &gtk::TreeView, ts: &gtk::TreeStore) {
+        let model_sort = gtk::TreeModelSort::new(ts);
+        model_sort.set_sort_column_id(gtk::SortColumn::Index(5), gtk::SortType::Ascending);
+        tv.set_model(Some(&model_sort));
+    }
+
+    fn supported_string_filter_keys(&self) -> &'static [&'static str] {
+        MysqlFilterKeys::VARIANTS
+    }
+
+    fn supported_numeric_filter_keys(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn matches_filter(
+        &self,
+        filter: &search_expr::SearchOpExpr,
+        model: &gtk::TreeModel,
+        iter: &gtk::TreeIter,
+    ) -> bool {
+        let streams = &self.streams;
+        match (MysqlFilterKeys::from_str(filter.filter_key), &filter.op) {
+            (Ok(filter_key), op) => match filter_key {
+                MysqlFilterKeys::QueryString => {
+                    op.matches_str(model.value(iter, 0).get::<&str>().unwrap())
+                }
+                MysqlFilterKeys::ResultSet => {
+                    get_mysql_message(streams, model, iter).map_or(false, |mysql_msg| {
+                        mysql_msg.resultset_rows.iter().any(|row| {
+                            row.iter()
+                                .any(|c| c.as_ref().map_or(false, |v| op.matches_str(v)))
+                        })
+                    })
+                }
+                MysqlFilterKeys::QueryParamValue => get_mysql_message(streams, model, iter)
+                    .map_or(false, |mysql_msg| {
+                        mysql_msg.parameter_values.iter().any(|v| op.matches_str(v))
+                    }),
+                MysqlFilterKeys::Error => get_mysql_message(streams, model, iter)
+                    .and_then(|mysql_msg| mysql_msg.error.as_ref())
+                    .map_or(false, |err| op.matches_str(&err.to_string())),
+            },
+            _ => true,
+        }
+    }
+
+    fn requests_details_overlay(&self) -> bool {
+        false
+    }
+
+    fn add_details_to_scroll(
+        &mut self,
+        parent: &gtk::ScrolledWindow,
+        _overlay: Option<&gtk::Overlay>,
+        bg_sender: mpsc::Sender<BgFunc>,
+        win_msg_sender: relm::StreamHandle<win::Msg>,
+    ) {
+        let component = parent.add_widget::<MysqlCommEntry>((
+            TcpStreamId(0),
+            "0.0.0.0".parse().unwrap(),
+            MysqlMessageData {
+                query: None,
+                query_timestamp: Utc::now().naive_local(),
+                result_timestamp: Utc::now().naive_local(),
+                parameter_values: vec![],
+                resultset_col_names: vec![],
+                resultset_rows: vec![],
+                affected_rows: None,
+                error: None,
+            },
+            win_msg_sender,
+            bg_sender,
+        ));
+        self.component = Some(component);
+    }
+
+    fn display_in_details_widget(
+        &self,
+        bg_sender: mpsc::Sender<BgFunc>,
+        stream_id: TcpStreamId,
+        msg_idx: usize,
+    ) {
+        if let Some((mysql_msg, client_server)) = self.get_msg_info(stream_id, msg_idx) {
+            self.component.as_ref().unwrap().stream().emit(
+                mysql_details_widget::Msg::DisplayDetails(
+                    bg_sender,
+                    client_server.client_ip,
+                    stream_id,
+                    mysql_msg.clone(),
+                ),
+            )
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MysqlError {
+    pub code: Option<u16>,
+    pub sqlstate: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for MysqlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(code) = self.code {
+            write!(f, "{} ", code)?;
+        }
+        if let Some(sqlstate) = &self.sqlstate {
+            write!(f, "({}) ", sqlstate)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MysqlMessageData {
+    // for prepared statements, it's possible the prepare
+    // occured before we started recording the stream.
+    // in that case we won't be able to recover the query string.
+    pub query_timestamp: NaiveDateTime,
+    pub result_timestamp: NaiveDateTime,
+    pub query: Option<Cow<'static, str>>,
+    pub parameter_values: Vec<String>,
+    pub resultset_col_names: Vec<String>,
+    pub resultset_rows: Vec<Vec<Option<String>>>,
+    pub affected_rows: Option<u64>,
+    pub error: Option<MysqlError>,
+}
+
+impl MysqlMessageData {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "query_timestamp": self.query_timestamp.to_string(),
+            "result_timestamp": self.result_timestamp.to_string(),
+            "query": self.query,
+            "parameter_values": self.parameter_values,
+            "resultset_col_names": self.resultset_col_names,
+            "resultset_rows": self.resultset_rows,
+            "affected_rows": self.affected_rows,
+            "error": self.error.as_ref().map(|e| e.to_string()),
+        })
+    }
+
+    pub fn result_summary(&self) -> String {
+        match (&self.error, self.affected_rows) {
+            (Some(err), _) => format!("Error {}", err.code.unwrap_or(0)),
+            (None, Some(affected)) if self.resultset_col_names.is_empty() => {
+                format!("{} affected", affected)
+            }
+            _ => format!("{} rows", self.resultset_rows.len()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct MysqlStreamGlobals {
+    known_statements: HashMap<u32, String>,
+    pending_prepare: Option<String>,
+    cur_query: Option<String>,
+    query_timestamp: Option<NaiveDateTime>,
+    result_timestamp: Option<NaiveDateTime>,
+    cur_parameter_values: Vec<String>,
+    cur_col_names: Vec<String>,
+    cur_rows: Vec<Vec<Option<String>>>,
+    eof_count: usize,
+    affected_rows: Option<u64>,
+    error: Option<MysqlError>,
+}
+
+#[cfg(test)]
+fn tests_parse_stream(
+    packets: Result<Vec<TSharkPacket>, String>,
+) -> Result<Vec<MysqlMessageData>, String> {
+    let mut parser = MysqlStreamsStore::default();
+    let sid = common_tests_parse_stream(&mut parser, packets)?;
+    Ok(parser.streams.get(&sid).unwrap().messages.clone())
+}
+
+#[test]
+fn should_parse_simple_query() {
+    let parsed = tests_parse_stream(parse_test_xml(
+        r#"
+  <proto name="mysql" showname="MySQL Protocol" size="13" pos="66">
+    <field name="mysql.packet_length" showname="Packet Length: 9" size="3" pos="66" show="9" value="090000"/>
+    <field name="mysql.packet_number" showname="Packet Number: 0" size="1" pos="69" show="0" value="00"/>
+    <field name="mysql.command" showname="Command: Query (3)" size="1" pos="70" show="3" value="03"/>
+    <field name="mysql.query" showname="Statement: select 1" size="8" pos="71" show="select 1" value="73656c6563742031"/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="1" pos="66">
+    <field name="mysql.num_fields" showname="Number of fields: 1" size="1" pos="70" show="1" value="01"/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="24" pos="66">
+    <field name="mysql.field.catalog" showname="Catalog: def" size="3" pos="71" show="def" value="646566"/>
+    <field name="mysql.field.name" showname="Name: 1" size="1" pos="76" show="1" value="31"/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="9" pos="66">
+    <field name="mysql.response_code" showname="Response Code: EOF Packet (0xfe)" size="1" pos="70" show="0xfe" value="fe"/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="6" pos="66">
+    <field name="mysql.row.text" showname="text: 1" size="1" pos="71" show="1" value="31"/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="5" pos="66">
+    <field name="mysql.row.text" showname="text: NULL" size="0" pos="70" show="NULL" value=""/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="9" pos="66">
+    <field name="mysql.row.text" showname="text: NULL" size="4" pos="71" show="NULL" value="4e554c4c"/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="9" pos="66">
+    <field name="mysql.response_code" showname="Response Code: EOF Packet (0xfe)" size="1" pos="70" show="0xfe" value="fe"/>
+  </proto>
+        "#,
+    ))
+    .unwrap();
+    let expected: Vec<MysqlMessageData> = vec![MysqlMessageData {
+        query: Some(Cow::Borrowed("select 1")),
+        query_timestamp: parsed[0].query_timestamp,
+        result_timestamp: parsed[0].result_timestamp,
+        parameter_values: vec![],
+        resultset_col_names: vec!["1".to_string()],
+        resultset_rows: vec![
+            vec![Some("1".to_string())],
+            vec![None],
+            vec![Some("NULL".to_string())],
+        ],
+        affected_rows: None,
+        error: None,
+    }];
+    assert_eq!(expected, parsed);
+}
+
+#[test]
+fn should_parse_prepared_statement() {
+    let parsed = tests_parse_stream(parse_test_xml(
+        r#"
+  <proto name="mysql" showname="MySQL Protocol" size="13" pos="66">
+    <field name="mysql.command" showname="Command: Prepare Statement (22)" size="1" pos="70" show="22" value="16"/>
+    <field name="mysql.query" showname="Statement: update t set a=? where b=?" size="8" pos="71" show="update t set a=? where b=?" value=""/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="16" pos="66">
+    <field name="mysql.response_code" showname="Response Code: OK Packet (0x00)" size="1" pos="70" show="0x00" value="00"/>
+    <field name="mysql.stmt_id" showname="Statement ID: 1" size="4" pos="71" show="1" value="01000000"/>
+    <field name="mysql.num_params" showname="Number of parameter: 2" size="2" pos="77" show="2" value="0200"/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="30" pos="66">
+    <field name="mysql.command" showname="Command: Execute Statement (23)" size="1" pos="70" show="23" value="17"/>
+    <field name="mysql.stmt_id" showname="Statement ID: 1" size="4" pos="71" show="1" value="01000000"/>
+    <field name="mysql.exec.field.string" showname="Value: hello" size="5" pos="90" show="hello" value=""/>
+    <field name="mysql.exec.field.longlong" showname="Value: 42" size="8" pos="96" show="42" value=""/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="11" pos="66">
+    <field name="mysql.response_code" showname="Response Code: OK Packet (0x00)" size="1" pos="70" show="0x00" value="00"/>
+    <field name="mysql.affected_rows" showname="Affected Rows: 3" size="1" pos="71" show="3" value="03"/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="30" pos="66">
+    <field name="mysql.command" showname="Command: Execute Statement (23)" size="1" pos="70" show="23" value="17"/>
+    <field name="mysql.stmt_id" showname="Statement ID: 2" size="4" pos="71" show="2" value="02000000"/>
+  </proto>
+  <proto name="mysql" showname="MySQL Protocol" size="11" pos="66">
+    <field name="mysql.response_code" showname="Response Code: ERR Packet (0xff)" size="1" pos="70" show="0xff" value="ff"/>
+    <field name="mysql.error_code" showname="Error Code: 1243" size="2" pos="71" show="1243" value="db04"/>
+    <field name="mysql.sqlstate" showname="SQL state: HY000" size="5" pos="74" show="HY000" value=""/>
+    <field name="mysql.error.message" showname="Error message: Unknown prepared statement handler" size="10" pos="79" show="Unknown prepared statement handler" value=""/>
+  </proto>
+        "#,
+    ))
+    .unwrap();
+    let expected: Vec<MysqlMessageData> = vec![
+        MysqlMessageData {
+            query: Some(Cow::Borrowed("update t set a=? where b=?")),
+            query_timestamp: parsed[0].query_timestamp,
+            result_timestamp: parsed[0].result_timestamp,
+            parameter_values: vec!["hello".to_string(), "42".to_string()],
+            resultset_col_names: vec![],
+            resultset_rows: vec![],
+            affected_rows: Some(3),
+            error: None,
+        },
+        MysqlMessageData {
+            query: Some(Cow::Borrowed("Unknown statement: 2")),
+            query_timestamp: parsed[1].query_timestamp,
+            result_timestamp: parsed[1].result_timestamp,
+            parameter_values: vec![],
+            resultset_col_names: vec![],
+            resultset_rows: vec![],
+            affected_rows: None,
+            error: Some(MysqlError {
+                code: Some(1243),
+                sqlstate: Some("HY000".to_string()),
+                message: "Unknown prepared statement handler".to_string(),
+            }),
+        },
+    ];
+    assert_eq!(expected, parsed);
+}
+
+#[test]
+fn should_parse_login_schema() {
+    let mut parser = MysqlStreamsStore::default();
+    let sid = common_tests_parse_stream(
+        &mut parser,
+        parse_test_xml(
+            r#"
+  <proto name="mysql" showname="MySQL Protocol" size="80" pos="66">
+    <field name="mysql.user" showname="Username: root" size="5" pos="102" show="root" value="726f6f7400"/>
+    <field name="mysql.schema" showname="Schema: shop" size="5" pos="130" show="shop" value="73686f7000"/>
+  </proto>
+        "#,
+        ),
+    )
+    .unwrap();
+    assert_eq!(Some("shop"), parser.stream_summary_details(sid));
+    assert_eq!(
+        Some(Cow::Borrowed("LOGIN: user: root, schema: shop")),
+        parser.streams.get(&sid).unwrap().messages[0].query
+    );
+}
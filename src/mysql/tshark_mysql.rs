@@ -0,0 +1,252 @@
+// https://dev.mysql.com/doc/internals/en/client-server-protocol.html
+use crate::tshark_communication;
+use crate::tshark_json;
+use quick_xml::events::Event;
+use serde_json::Value;
+use std::io::BufRead;
+use std::str;
+
+const COM_INIT_DB: u8 = 0x02;
+const COM_QUERY: u8 = 0x03;
+const COM_STMT_PREPARE: u8 = 0x16;
+const COM_STMT_EXECUTE: u8 = 0x17;
+
+const RESPONSE_OK: u8 = 0x00;
+const RESPONSE_EOF: u8 = 0xfe;
+const RESPONSE_ERR: u8 = 0xff;
+
+#[derive(Debug)]
+pub enum MysqlWireMessage {
+    Login {
+        username: Option<String>,
+        schema: Option<String>,
+    },
+    InitDb {
+        schema: Option<String>,
+    },
+    Query {
+        query: Option<String>,
+    },
+    StmtPrepare {
+        query: Option<String>,
+    },
+    // for prepared statements, the first time we get prepare & query,
+    // the server answers with a statement ID, then the following times we
+    // get execute and only the statement ID.
+    // we can then recover the query from the statement id in post-processing.
+    StmtExecute {
+        statement_id: Option<u32>,
+        parameter_values: Vec<String>,
+    },
+    StmtPrepareOk {
+        statement_id: u32,
+    },
+    ColumnDefinition {
+        col_name: String,
+    },
+    ResultSetRow {
+        col_vals: Vec<Option<String>>,
+    },
+    Ok {
+        affected_rows: Option<u64>,
+    },
+    Err {
+        error_code: Option<u16>,
+        sqlstate: Option<String>,
+        message: Option<String>,
+    },
+    Eof,
+}
+
+fn parse_u8_maybe_hex(val: &str) -> Option<u8> {
+    if let Some(hex) = val.strip_prefix("0x") {
+        u8::from_str_radix(hex, 16).ok()
+    } else {
+        val.parse().ok()
+    }
+}
+
+const EXEC_FIELDS: &[&str] = &[
+    "mysql.exec.field.string",
+    "mysql.exec.field.longlong",
+    "mysql.exec.field.integer",
+    "mysql.exec.field.short",
+    "mysql.exec.field.tiny",
+    "mysql.exec.field.double",
+    "mysql.exec.field.float",
+];
+
+/// the fields we collect from the mysql protocol,
+/// from which we then decide which message it is
+#[derive(Default)]
+struct MysqlFields {
+    command: Option<u8>,
+    response_code: Option<u8>,
+    username: Option<String>,
+    schema: Option<String>,
+    query: Option<String>,
+    statement_id: Option<u32>,
+    affected_rows: Option<u64>,
+    error_code: Option<u16>,
+    sqlstate: Option<String>,
+    error_message: Option<String>,
+    col_name: Option<String>,
+    col_vals: Vec<Option<String>>,
+    parameter_values: Vec<String>,
+}
+
+impl MysqlFields {
+    fn into_message(self) -> Option<MysqlWireMessage> {
+        match (self.command, self.response_code) {
+            (Some(COM_INIT_DB), _) => Some(MysqlWireMessage::InitDb {
+                schema: self.schema,
+            }),
+            (Some(COM_QUERY), _) => Some(MysqlWireMessage::Query { query: self.query }),
+            (Some(COM_STMT_PREPARE), _) => {
+                Some(MysqlWireMessage::StmtPrepare { query: self.query })
+            }
+            (Some(COM_STMT_EXECUTE), _) => Some(MysqlWireMessage::StmtExecute {
+                statement_id: self.statement_id,
+                parameter_values: self.parameter_values,
+            }),
+            (Some(_), _) => None,
+            (None, Some(RESPONSE_OK)) => Some(match self.statement_id {
+                Some(statement_id) => MysqlWireMessage::StmtPrepareOk { statement_id },
+                None => MysqlWireMessage::Ok {
+                    affected_rows: self.affected_rows,
+                },
+            }),
+            (None, Some(RESPONSE_ERR)) => Some(MysqlWireMessage::Err {
+                error_code: self.error_code,
+                sqlstate: self.sqlstate,
+                message: self.error_message,
+            }),
+            (None, Some(RESPONSE_EOF)) => Some(MysqlWireMessage::Eof),
+            _ if self.username.is_some() => Some(MysqlWireMessage::Login {
+                username: self.username,
+                schema: self.schema,
+            }),
+            _ => match self.col_name {
+                Some(col_name) => Some(MysqlWireMessage::ColumnDefinition { col_name }),
+                None if !self.col_vals.is_empty() => Some(MysqlWireMessage::ResultSetRow {
+                    col_vals: self.col_vals,
+                }),
+                None => None,
+            },
+        }
+    }
+}
+
+pub fn parse_mysql_info<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<Option<MysqlWireMessage>, String> {
+    let mut fields = MysqlFields::default();
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                match name.as_deref() {
+                    Some(b"mysql.command") => {
+                        fields.command = tshark_communication::element_attr_val_string(e, b"show")?
+                            .and_then(|c| parse_u8_maybe_hex(&c));
+                    }
+                    Some(b"mysql.response_code") => {
+                        fields.response_code = tshark_communication::element_attr_val_string(e, b"show")?
+                            .and_then(|c| parse_u8_maybe_hex(&c));
+                    }
+                    Some(b"mysql.user") => {
+                        fields.username = tshark_communication::element_attr_val_string(e, b"show")?;
+                    }
+                    Some(b"mysql.schema") => {
+                        fields.schema = tshark_communication::element_attr_val_string(e, b"show")?
+                            .filter(|s| !s.is_empty());
+                    }
+                    Some(b"mysql.query") => {
+                        fields.query = tshark_communication::element_attr_val_string(e, b"show")?;
+                    }
+                    Some(b"mysql.stmt_id") => {
+                        fields.statement_id = tshark_communication::element_attr_val_number(e, b"show")?;
+                    }
+                    Some(b"mysql.affected_rows") => {
+                        fields.affected_rows = tshark_communication::element_attr_val_number(e, b"show")?;
+                    }
+                    Some(b"mysql.error_code") => {
+                        fields.error_code = tshark_communication::element_attr_val_number(e, b"show")?;
+                    }
+                    Some(b"mysql.sqlstate") => {
+                        fields.sqlstate = tshark_communication::element_attr_val_string(e, b"show")?;
+                    }
+                    Some(b"mysql.error.message") => {
+                        fields.error_message = tshark_communication::element_attr_val_string(e, b"show")?;
+                    }
+                    Some(b"mysql.field.name") => {
+                        fields.col_name = tshark_communication::element_attr_val_string(e, b"show")?;
+                    }
+                    Some(b"mysql.row.text") => {
+                        if let Some(val) = tshark_communication::element_attr_val_string(e, b"show")? {
+                            let raw = tshark_communication::element_attr_val_string(e, b"value")?;
+                            fields.col_vals.push(text_row_value(val, raw.as_deref()));
+                        }
+                    }
+                    Some(n) if EXEC_FIELDS.iter().any(|f| f.as_bytes() == n) => {
+                        if let Some(val) = tshark_communication::element_attr_val_string(e, b"show")? {
+                            fields.parameter_values.push(val);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(fields.into_message());
+            }
+        }
+    )
+}
+
+/// the text protocol sends NULL as a 0xfb length, which wireshark
+/// displays as the NULL string. A 'NULL' text value has these bytes
+/// in the field value, the NULL marker doesn't
+fn text_row_value(show: String, raw_hex: Option<&str>) -> Option<String> {
+    if show == "NULL" && !matches!(raw_hex, Some(h) if h.eq_ignore_ascii_case("4e554c4c")) {
+        None
+    } else {
+        Some(show)
+    }
+}
+
+fn json_text_row_values(layer: &Value) -> Vec<Option<String>> {
+    // the JSON output doesn't have the bytes of the values, so there
+    // we can't tell a 'NULL' text from the NULL marker
+    tshark_json::all_str(layer, "mysql.row.text")
+        .into_iter()
+        .map(|val| text_row_value(val, None))
+        .collect()
+}
+
+pub fn parse_mysql_json(layer: &Value) -> Option<MysqlWireMessage> {
+    let field = |name: &str| tshark_json::first_str(layer, name);
+    MysqlFields {
+        command: field("mysql.command").and_then(|c| parse_u8_maybe_hex(&c)),
+        response_code: field("mysql.response_code").and_then(|c| parse_u8_maybe_hex(&c)),
+        username: field("mysql.user"),
+        schema: field("mysql.schema").filter(|s| !s.is_empty()),
+        query: field("mysql.query"),
+        statement_id: tshark_json::first_number(layer, "mysql.stmt_id"),
+        affected_rows: tshark_json::first_number(layer, "mysql.affected_rows"),
+        error_code: tshark_json::first_number(layer, "mysql.error_code"),
+        sqlstate: field("mysql.sqlstate"),
+        error_message: field("mysql.error.message"),
+        col_name: field("mysql.field.name"),
+        col_vals: json_text_row_values(layer),
+        // the json object keys are sorted, so we lose the order between
+        // parameters of different types
+        parameter_values: EXEC_FIELDS
+            .iter()
+            .flat_map(|f| tshark_json::all_str(layer, f))
+            .collect(),
+    }
+    .into_message()
+}
@@ -0,0 +1,533 @@
+// reading capture files without tshark. Parsing the tshark PDML output is slow
+// and memory hungry for large captures, so we decode HTTP/1 and PostgreSQL
+// ourselves. If the capture contains other streams (TLS, HTTP2, mysql...),
+// we then run tshark on the file, and only keep the packets for these streams.
+pub mod pcap_file;
+pub mod tcp_reassembly;
+
+use crate::config::TSharkOutputFormat;
+use crate::http::native_http::{self, Http1StreamParser};
+use crate::http::tshark_http::HttpType;
+use crate::packets_read::{self, InputStep, ParseInputStep, ParseInputStepSender, TSharkInputType};
+use crate::pgsql::native_pgsql::PostgresStreamParser;
+use crate::tshark_communication::{
+    NetworkPort, TSharkPacket, TSharkPacketBasicInfo, TcpSeqNumber, TcpStreamId,
+};
+use chrono::NaiveDateTime;
+use pcap_file::{CaptureReader, Frame, TcpSegment};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::IpAddr;
+use std::path::Path;
+use tcp_reassembly::TcpDirection;
+
+const POSTGRES_PORT: u16 = 5432;
+
+type Endpoint = (IpAddr, u16);
+
+/// a TCP connection, independently of the direction
+#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
+struct ConnectionKey(Endpoint, Endpoint);
+
+impl ConnectionKey {
+    fn new(a: Endpoint, b: Endpoint) -> ConnectionKey {
+        if a <= b {
+            ConnectionKey(a, b)
+        } else {
+            ConnectionKey(b, a)
+        }
+    }
+}
+
+enum StreamParser {
+    // we didn't see any payload yet
+    Unknown,
+    Http(Http1StreamParser),
+    Postgres(PostgresStreamParser),
+    // TLS, HTTP2, other protocols: we leave them to tshark
+    Other,
+}
+
+struct TcpConnection {
+    stream_id: TcpStreamId,
+    client: Option<Endpoint>,
+    // the first one is the direction from the first endpoint of the connection key
+    directions: [TcpDirection; 2],
+    parser: StreamParser,
+    closed: bool,
+}
+
+/// where the bytes that we're parsing come from
+#[derive(Clone, Copy)]
+struct DataOrigin {
+    stream_id: TcpStreamId,
+    src: Endpoint,
+    dst: Endpoint,
+    timestamp: NaiveDateTime,
+    seq: u32,
+}
+
+pub fn read_capture_file(
+    fname: &Path,
+    filters: &str,
+    output_format: TSharkOutputFormat,
+    tls_keylog: Option<&Path>,
+    sender: impl ParseInputStepSender,
+) {
+    let file = match File::open(fname) {
+        Ok(f) => f,
+        Err(e) => {
+            sender.send_step(Err(format!("Error opening {}: {}", fname.display(), e)));
+            return;
+        }
+    };
+    let mut capture_reader = match CaptureReader::new(BufReader::with_capacity(1024 * 1024, file)) {
+        Ok(r) => r,
+        // tshark supports many more capture file formats
+        Err(_) => {
+            return packets_read::invoke_tshark(
+                TSharkInputType::File,
+                fname,
+                filters,
+                output_format,
+                tls_keylog,
+                sender,
+            );
+        }
+    };
+    let mut reader = NativeReader {
+        sender,
+        connections: HashMap::new(),
+        finished_streams: HashMap::new(),
+        next_stream_id: 0,
+        needs_tshark: false,
+    };
+    loop {
+        match capture_reader.next_frame() {
+            Ok(Some(frame)) => {
+                if let Err(e) = reader.handle_frame(frame) {
+                    reader.sender.send_step(Err(e));
+                    return;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                reader.sender.send_step(Err(e));
+                return;
+            }
+        }
+    }
+    if let Err(e) = reader.finish_all_connections() {
+        reader.sender.send_step(Err(e));
+        return;
+    }
+    if reader.needs_tshark {
+        let fallback_sender = TSharkFallbackSender {
+            sender: reader.sender,
+            native_streams: reader.finished_streams,
+            seen_streams_count: RefCell::new(HashMap::new()),
+            tshark_to_native: RefCell::new(HashMap::new()),
+            next_stream_id: Cell::new(reader.next_stream_id),
+        };
+        packets_read::invoke_tshark(
+            TSharkInputType::File,
+            fname,
+            filters,
+            output_format,
+            tls_keylog,
+            fallback_sender,
+        );
+    } else {
+        reader.sender.send_step(Ok(InputStep::Eof));
+    }
+}
+
+struct NativeReader<S: ParseInputStepSender> {
+    sender: S,
+    connections: HashMap<ConnectionKey, TcpConnection>,
+    // for each connection, the successive streams (in case of port reuse),
+    // and whether we decoded them natively
+    finished_streams: HashMap<ConnectionKey, Vec<(TcpStreamId, bool)>>,
+    next_stream_id: u32,
+    needs_tshark: bool,
+}
+
+impl<S: ParseInputStepSender> NativeReader<S> {
+    fn handle_frame(&mut self, frame: Frame) -> Result<(), String> {
+        if !pcap_file::is_supported_link_type(frame.link_type) {
+            self.needs_tshark = true;
+            return Ok(());
+        }
+        if let Some(segment) = pcap_file::decode_tcp_segment(frame.link_type, &frame.data) {
+            self.handle_segment(frame.timestamp, segment)?;
+        }
+        Ok(())
+    }
+
+    fn handle_segment(
+        &mut self,
+        timestamp: NaiveDateTime,
+        segment: TcpSegment,
+    ) -> Result<(), String> {
+        let src = (segment.ip_src, segment.port_src);
+        let dst = (segment.ip_dst, segment.port_dst);
+        let key = ConnectionKey::new(src, dst);
+        let is_connection_start = segment.flags.syn && !segment.flags.ack;
+        if is_connection_start && self.connections.get(&key).filter(|c| c.closed).is_some() {
+            // the ports are reused for a new connection
+            if let Some(connection) = self.connections.remove(&key) {
+                self.finish_connection(key, connection)?;
+            }
+        }
+        let next_stream_id = &mut self.next_stream_id;
+        let connection = self.connections.entry(key).or_insert_with(|| {
+            *next_stream_id += 1;
+            TcpConnection {
+                stream_id: TcpStreamId(*next_stream_id - 1),
+                client: None,
+                directions: [TcpDirection::default(), TcpDirection::default()],
+                parser: StreamParser::Unknown,
+                closed: false,
+            }
+        });
+        if segment.flags.syn && connection.client.is_none() {
+            connection.client = Some(if segment.flags.ack { dst } else { src });
+        }
+        let direction_idx = if src == key.0 { 0 } else { 1 };
+        let mut origin = DataOrigin {
+            stream_id: connection.stream_id,
+            src,
+            dst,
+            timestamp,
+            seq: segment.seq,
+        };
+        let reassembled = connection.directions[direction_idx].add_segment(
+            timestamp,
+            segment.seq,
+            segment.flags.syn,
+            segment.payload,
+        );
+        for data in reassembled {
+            origin.timestamp = data.timestamp;
+            origin.seq = data.seq;
+            parse_data(&self.sender, connection, origin, &data.data)?;
+        }
+        if segment.flags.fin || segment.flags.rst {
+            connection.closed = true;
+            for data in connection.directions[direction_idx].flush() {
+                origin.timestamp = data.timestamp;
+                origin.seq = data.seq;
+                parse_data(&self.sender, connection, origin, &data.data)?;
+            }
+            origin.timestamp = timestamp;
+            origin.seq = segment.seq;
+            parse_end_of_data(&self.sender, connection, origin)?;
+        }
+        Ok(())
+    }
+
+    fn finish_all_connections(&mut self) -> Result<(), String> {
+        let mut connections: Vec<_> = self.connections.drain().collect();
+        connections.sort_by_key(|(_, c)| c.stream_id.as_u32());
+        for (key, connection) in connections {
+            self.finish_connection(key, connection)?;
+        }
+        Ok(())
+    }
+
+    /// the end of the connection, or of the capture:
+    /// parse what we can from the data we have
+    fn finish_connection(
+        &mut self,
+        key: ConnectionKey,
+        mut connection: TcpConnection,
+    ) -> Result<(), String> {
+        for (direction_idx, (src, dst)) in [(key.0, key.1), (key.1, key.0)].into_iter().enumerate()
+        {
+            let flushed = connection.directions[direction_idx].flush();
+            let mut origin = DataOrigin {
+                stream_id: connection.stream_id,
+                src,
+                dst,
+                timestamp: NaiveDateTime::from_timestamp(0, 0),
+                seq: 0,
+            };
+            for data in &flushed {
+                origin.timestamp = data.timestamp;
+                origin.seq = data.seq;
+                parse_data(&self.sender, &mut connection, origin, &data.data)?;
+            }
+            if !connection.closed {
+                if let Some(last) = flushed.last() {
+                    origin.timestamp = last.timestamp;
+                    origin.seq = last.seq;
+                    parse_end_of_data(&self.sender, &mut connection, origin)?;
+                }
+            }
+        }
+        let is_native = !matches!(connection.parser, StreamParser::Other);
+        self.needs_tshark |= !is_native;
+        self.finished_streams
+            .entry(key)
+            .or_default()
+            .push((connection.stream_id, is_native));
+        Ok(())
+    }
+}
+
+/// pick the parser based on the first bytes that we see in the stream
+fn detect_protocol(connection: &mut TcpConnection, src: Endpoint, dst: Endpoint, data: &[u8]) {
+    if src.1 == POSTGRES_PORT || dst.1 == POSTGRES_PORT {
+        if connection.client.is_none() {
+            connection.client = Some(if dst.1 == POSTGRES_PORT { src } else { dst });
+        }
+        connection.parser = StreamParser::Postgres(PostgresStreamParser::default());
+        return;
+    }
+    connection.parser = match native_http::is_http1_start(data) {
+        Some(http_type) => {
+            if connection.client.is_none() {
+                connection.client = Some(match http_type {
+                    HttpType::Request => src,
+                    HttpType::Response => dst,
+                });
+            }
+            StreamParser::Http(Http1StreamParser::default())
+        }
+        None => StreamParser::Other,
+    }
+}
+
+fn parse_data(
+    sender: &impl ParseInputStepSender,
+    connection: &mut TcpConnection,
+    origin: DataOrigin,
+    data: &[u8],
+) -> Result<(), String> {
+    if matches!(connection.parser, StreamParser::Unknown) {
+        detect_protocol(connection, origin.src, origin.dst, data);
+    }
+    let from_client = connection.client.map(|c| c == origin.src).unwrap_or(true);
+    let parsed = match &mut connection.parser {
+        StreamParser::Http(parser) => parser.add_data(from_client, data).map(|msgs| {
+            let upgraded_data = parser.take_upgraded_data(from_client);
+            packet_for_messages(origin, Some(msgs), None, upgraded_data)
+        }),
+        StreamParser::Postgres(parser) => {
+            let parsed = parser
+                .add_data(from_client, data)
+                .map(|msgs| packet_for_messages(origin, None, Some(msgs), None));
+            if parser.is_encrypted() {
+                connection.parser = StreamParser::Other;
+            }
+            parsed
+        }
+        _ => Ok(None),
+    };
+    send_parsed_packet(sender, connection, parsed)
+}
+
+fn parse_end_of_data(
+    sender: &impl ParseInputStepSender,
+    connection: &mut TcpConnection,
+    origin: DataOrigin,
+) -> Result<(), String> {
+    let from_client = connection.client.map(|c| c == origin.src).unwrap_or(true);
+    if let StreamParser::Http(parser) = &mut connection.parser {
+        let parsed = parser
+            .end_of_data(from_client)
+            .map(|msgs| packet_for_messages(origin, Some(msgs), None, None));
+        send_parsed_packet(sender, connection, parsed)?;
+    }
+    Ok(())
+}
+
+/// like for tshark, a parse error stops the loading
+fn send_parsed_packet(
+    sender: &impl ParseInputStepSender,
+    connection: &TcpConnection,
+    parsed: Result<Option<TSharkPacket>, String>,
+) -> Result<(), String> {
+    match parsed {
+        Ok(Some(packet)) => sender.send_step(Ok(InputStep::Packet(Box::new(packet)))),
+        Ok(None) => {}
+        Err(e) => {
+            return Err(format!(
+                "Error parsing file, in stream {}: {}",
+                connection.stream_id, e
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// we emit a packet for each TCP segment which completes messages,
+/// like tshark does when it reassembles
+fn packet_for_messages(
+    origin: DataOrigin,
+    http: Option<Vec<crate::http::tshark_http::TSharkHttp>>,
+    pgsql: Option<Vec<crate::pgsql::tshark_pgsql::PostgresWireMessage>>,
+    tcp_payload: Option<Vec<u8>>,
+) -> Option<TSharkPacket> {
+    let http = http.filter(|h| !h.is_empty());
+    let pgsql = pgsql.filter(|p| !p.is_empty());
+    if http.is_none() && pgsql.is_none() && tcp_payload.is_none() {
+        return None;
+    }
+    Some(TSharkPacket {
+        basic_info: TSharkPacketBasicInfo {
+            frame_time: origin.timestamp,
+            ip_src: origin.src.0,
+            ip_dst: origin.dst.0,
+            tcp_seq_number: TcpSeqNumber(origin.seq),
+            tcp_stream_id: origin.stream_id,
+            port_src: NetworkPort(origin.src.1),
+            port_dst: NetworkPort(origin.dst.1),
+        },
+        http,
+        http2: None,
+        pgsql,
+        mysql: None,
+        redis: None,
+        tcp_payload,
+        is_malformed: false,
+    })
+}
+
+/// forwards the packets from tshark for the streams that we didn't decode
+/// natively, translating the tshark stream ids to ours
+struct TSharkFallbackSender<S: ParseInputStepSender> {
+    sender: S,
+    native_streams: HashMap<ConnectionKey, Vec<(TcpStreamId, bool)>>,
+    seen_streams_count: RefCell<HashMap<ConnectionKey, usize>>,
+    // None for the streams that we decoded natively
+    tshark_to_native: RefCell<HashMap<TcpStreamId, Option<TcpStreamId>>>,
+    next_stream_id: Cell<u32>,
+}
+
+impl<S: ParseInputStepSender> TSharkFallbackSender<S> {
+    fn native_stream_id(&self, basic_info: &TSharkPacketBasicInfo) -> Option<TcpStreamId> {
+        let key = ConnectionKey::new(
+            (basic_info.ip_src, basic_info.port_src.as_u16()),
+            (basic_info.ip_dst, basic_info.port_dst.as_u16()),
+        );
+        let mut seen_streams_count = self.seen_streams_count.borrow_mut();
+        let seen_count = seen_streams_count.entry(key).or_insert(0);
+        let native_stream = self
+            .native_streams
+            .get(&key)
+            .and_then(|streams| streams.get(*seen_count));
+        *seen_count += 1;
+        match native_stream {
+            Some((_, true)) => None,
+            Some((stream_id, false)) => Some(*stream_id),
+            // we didn't see that stream at all, for instance a link type we don't support
+            None => {
+                let stream_id = self.next_stream_id.get();
+                self.next_stream_id.set(stream_id + 1);
+                Some(TcpStreamId(stream_id))
+            }
+        }
+    }
+}
+
+impl<S: ParseInputStepSender> ParseInputStepSender for TSharkFallbackSender<S> {
+    fn send_step(&self, step: ParseInputStep) {
+        match step {
+            Ok(InputStep::Packet(mut packet)) => {
+                let tshark_stream_id = packet.basic_info.tcp_stream_id;
+                let native_stream_id = *self
+                    .tshark_to_native
+                    .borrow_mut()
+                    .entry(tshark_stream_id)
+                    .or_insert_with(|| self.native_stream_id(&packet.basic_info));
+                if let Some(stream_id) = native_stream_id {
+                    packet.basic_info.tcp_stream_id = stream_id;
+                    self.sender.send_step(Ok(InputStep::Packet(packet)));
+                }
+            }
+            _ => self.sender.send_step(step),
+        }
+    }
+}
+
+#[cfg(test)]
+struct TestSender(RefCell<Vec<ParseInputStep>>);
+
+#[cfg(test)]
+impl ParseInputStepSender for &TestSender {
+    fn send_step(&self, step: ParseInputStep) {
+        self.0.borrow_mut().push(step);
+    }
+}
+
+#[cfg(test)]
+fn test_segment<'a>(
+    src: Endpoint,
+    dst: Endpoint,
+    seq: u32,
+    syn: bool,
+    payload: &'a [u8],
+) -> TcpSegment<'a> {
+    TcpSegment {
+        ip_src: src.0,
+        ip_dst: dst.0,
+        port_src: src.1,
+        port_dst: dst.1,
+        seq,
+        flags: pcap_file::TcpFlags {
+            syn,
+            ack: !syn,
+            fin: false,
+            rst: false,
+        },
+        payload,
+    }
+}
+
+#[test]
+fn should_reassemble_http_request_split_in_segments() {
+    let test_sender = TestSender(RefCell::new(vec![]));
+    let mut reader = NativeReader {
+        sender: &test_sender,
+        connections: HashMap::new(),
+        finished_streams: HashMap::new(),
+        next_stream_id: 0,
+        needs_tshark: false,
+    };
+    let ts = NaiveDateTime::from_timestamp(1_600_000_000, 0);
+    let client = ("10.0.0.1".parse().unwrap(), 50000);
+    let server = ("10.0.0.2".parse().unwrap(), 8080);
+    reader
+        .handle_segment(ts, test_segment(client, server, 100, true, b""))
+        .unwrap();
+    // the second segment arrives first
+    reader
+        .handle_segment(
+            ts,
+            test_segment(client, server, 117, false, b"Host: a\r\n\r\n"),
+        )
+        .unwrap();
+    reader
+        .handle_segment(
+            ts,
+            test_segment(client, server, 101, false, b"GET / HTTP/1.1\r\n"),
+        )
+        .unwrap();
+    reader.finish_all_connections().unwrap();
+    assert!(!reader.needs_tshark);
+    let steps = test_sender.0.into_inner();
+    assert_eq!(1, steps.len());
+    match &steps[0] {
+        Ok(InputStep::Packet(p)) => {
+            assert_eq!(TcpStreamId(0), p.basic_info.tcp_stream_id);
+            assert_eq!(NetworkPort(8080), p.basic_info.port_dst);
+            let http = &p.http.as_ref().unwrap()[0];
+            assert_eq!("GET / HTTP/1.1", http.first_line);
+            assert_eq!(Some("a".to_string()), http.http_host);
+        }
+        _ => panic!("expected a packet"),
+    }
+}
@@ -0,0 +1,525 @@
+// reading pcap & pcapng files, and decoding the link, IP & TCP layers.
+// https://wiki.wireshark.org/Development/LibpcapFileFormat
+// https://datatracker.ietf.org/doc/html/draft-tuexen-opsawg-pcapng
+use chrono::{Local, NaiveDateTime, TimeZone};
+use std::io::{ErrorKind, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+// beyond that, we assume the file is corrupted
+const MAX_BLOCK_SIZE: usize = 256 * 1024 * 1024;
+
+const PCAPNG_SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+pub struct Frame {
+    pub timestamp: NaiveDateTime,
+    pub link_type: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpFlags {
+    pub syn: bool,
+    pub ack: bool,
+    pub fin: bool,
+    pub rst: bool,
+}
+
+pub struct TcpSegment<'a> {
+    pub ip_src: IpAddr,
+    pub ip_dst: IpAddr,
+    pub port_src: u16,
+    pub port_dst: u16,
+    pub seq: u32,
+    pub flags: TcpFlags,
+    pub payload: &'a [u8],
+}
+
+struct PcapNgInterface {
+    link_type: u32,
+    ticks_per_second: u64,
+}
+
+enum FileFormat {
+    Pcap {
+        big_endian: bool,
+        ticks_per_second: u64,
+        link_type: u32,
+    },
+    PcapNg {
+        big_endian: bool,
+        interfaces: Vec<PcapNgInterface>,
+    },
+}
+
+pub struct CaptureReader<R: Read> {
+    reader: R,
+    format: FileFormat,
+    // the simple packet blocks of pcapng have no timestamp
+    last_timestamp: NaiveDateTime,
+}
+
+fn read_u16(bytes: &[u8], big_endian: bool) -> u16 {
+    let b = [bytes[0], bytes[1]];
+    if big_endian {
+        u16::from_be_bytes(b)
+    } else {
+        u16::from_le_bytes(b)
+    }
+}
+
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let b = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        u32::from_be_bytes(b)
+    } else {
+        u32::from_le_bytes(b)
+    }
+}
+
+/// tshark displays the frame time in the local timezone,
+/// we do the same for consistency
+fn to_local_time(ticks: u64, ticks_per_second: u64) -> NaiveDateTime {
+    let secs = (ticks / ticks_per_second) as i64;
+    let nanos =
+        ((ticks % ticks_per_second) as u128 * 1_000_000_000 / ticks_per_second as u128) as u32;
+    Local
+        .timestamp_opt(secs, nanos)
+        .single()
+        .map(|d| d.naive_local())
+        .unwrap_or_else(|| NaiveDateTime::from_timestamp(secs, nanos))
+}
+
+/// None if we're at the end of the file. We consider a truncated
+/// last record as the end of the file, like tshark does
+fn read_or_eof<R: Read>(reader: &mut R, len: usize) -> Result<Option<Vec<u8>>, String> {
+    if len > MAX_BLOCK_SIZE {
+        return Err(format!(
+            "Invalid capture file: block size {} is too large",
+            len
+        ));
+    }
+    let mut buf = vec![0; len];
+    match reader.read_exact(&mut buf) {
+        Ok(()) => Ok(Some(buf)),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(format!("Error reading the capture file: {}", e)),
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub fn new(mut reader: R) -> Result<CaptureReader<R>, String> {
+        let magic =
+            read_or_eof(&mut reader, 4)?.ok_or_else(|| "The capture file is empty".to_string())?;
+        let format = match read_u32(&magic, false) {
+            PCAPNG_SECTION_HEADER_BLOCK => {
+                let big_endian = Self::read_section_header(&mut reader)?
+                    .ok_or_else(|| "Truncated pcapng section header".to_string())?;
+                FileFormat::PcapNg {
+                    big_endian,
+                    interfaces: vec![],
+                }
+            }
+            m @ (0xA1B2_C3D4 | 0xD4C3_B2A1 | 0xA1B2_3C4D | 0x4D3C_B2A1) => {
+                let big_endian = m == 0xD4C3_B2A1 || m == 0x4D3C_B2A1;
+                let ticks_per_second = if m == 0xA1B2_3C4D || m == 0x4D3C_B2A1 {
+                    1_000_000_000
+                } else {
+                    1_000_000
+                };
+                let header = read_or_eof(&mut reader, 20)?
+                    .ok_or_else(|| "Truncated pcap file header".to_string())?;
+                FileFormat::Pcap {
+                    big_endian,
+                    ticks_per_second,
+                    link_type: read_u32(&header[16..], big_endian) & 0xFFFF,
+                }
+            }
+            m => return Err(format!("Unsupported capture file format (magic {:08x})", m)),
+        };
+        Ok(CaptureReader {
+            reader,
+            format,
+            last_timestamp: NaiveDateTime::from_timestamp(0, 0),
+        })
+    }
+
+    /// we've read the block type, the rest of the section header
+    /// gives us the byte order. Returns whether it's big endian.
+    fn read_section_header(reader: &mut R) -> Result<Option<bool>, String> {
+        let header = match read_or_eof(reader, 8)? {
+            Some(h) => h,
+            None => return Ok(None),
+        };
+        let big_endian = match read_u32(&header[4..], false) {
+            PCAPNG_BYTE_ORDER_MAGIC => false,
+            m if m.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+            m => return Err(format!("Invalid pcapng byte order magic: {:08x}", m)),
+        };
+        let block_len = read_u32(&header, big_endian) as usize;
+        if block_len < 12 + 4 {
+            return Err(format!(
+                "Invalid pcapng section header length: {}",
+                block_len
+            ));
+        }
+        // skip the rest of the section header: version, section length & options
+        Ok(read_or_eof(reader, block_len - 12)?.map(|_| big_endian))
+    }
+
+    pub fn next_frame(&mut self) -> Result<Option<Frame>, String> {
+        match self.format {
+            FileFormat::Pcap {
+                big_endian,
+                ticks_per_second,
+                link_type,
+            } => {
+                let header = match read_or_eof(&mut self.reader, 16)? {
+                    Some(h) => h,
+                    None => return Ok(None),
+                };
+                let ticks = read_u32(&header, big_endian) as u64 * ticks_per_second
+                    + read_u32(&header[4..], big_endian) as u64;
+                let captured_len = read_u32(&header[8..], big_endian) as usize;
+                Ok(
+                    read_or_eof(&mut self.reader, captured_len)?.map(|data| Frame {
+                        timestamp: to_local_time(ticks, ticks_per_second),
+                        link_type,
+                        data,
+                    }),
+                )
+            }
+            FileFormat::PcapNg { .. } => self.next_pcapng_frame(),
+        }
+    }
+
+    fn next_pcapng_frame(&mut self) -> Result<Option<Frame>, String> {
+        loop {
+            let big_endian = match &self.format {
+                FileFormat::PcapNg { big_endian, .. } => *big_endian,
+                _ => unreachable!(),
+            };
+            let header = match read_or_eof(&mut self.reader, 4)? {
+                Some(h) => h,
+                None => return Ok(None),
+            };
+            if read_u32(&header, false) == PCAPNG_SECTION_HEADER_BLOCK {
+                // a new section: possibly different byte order, the interfaces are reset
+                match Self::read_section_header(&mut self.reader)? {
+                    Some(big_endian) => {
+                        self.format = FileFormat::PcapNg {
+                            big_endian,
+                            interfaces: vec![],
+                        }
+                    }
+                    None => return Ok(None),
+                }
+                continue;
+            }
+            let block_type = read_u32(&header, big_endian);
+            let block_len = match read_or_eof(&mut self.reader, 4)? {
+                Some(l) => read_u32(&l, big_endian) as usize,
+                None => return Ok(None),
+            };
+            if block_len < 12 {
+                return Err(format!("Invalid pcapng block length: {}", block_len));
+            }
+            // the body, then the block length again
+            let body = match read_or_eof(&mut self.reader, block_len - 8)? {
+                Some(b) => b,
+                None => return Ok(None),
+            };
+            let body = &body[..body.len() - 4];
+            if let Some(frame) = self.parse_pcapng_block(block_type, body, big_endian) {
+                return Ok(Some(frame));
+            }
+        }
+    }
+
+    fn parse_pcapng_block(
+        &mut self,
+        block_type: u32,
+        body: &[u8],
+        big_endian: bool,
+    ) -> Option<Frame> {
+        let interfaces = match &mut self.format {
+            FileFormat::PcapNg { interfaces, .. } => interfaces,
+            _ => unreachable!(),
+        };
+        match block_type {
+            // interface description block
+            1 if body.len() >= 8 => {
+                interfaces.push(PcapNgInterface {
+                    link_type: read_u16(body, big_endian) as u32,
+                    ticks_per_second: parse_if_tsresol(&body[8..], big_endian),
+                });
+                None
+            }
+            // enhanced packet block & the obsolete packet block
+            2 | 6 if body.len() >= 20 => {
+                let interface_id = if block_type == 6 {
+                    read_u32(body, big_endian)
+                } else {
+                    read_u16(body, big_endian) as u32
+                } as usize;
+                let interface = interfaces.get(interface_id)?;
+                let ticks = ((read_u32(&body[4..], big_endian) as u64) << 32)
+                    | read_u32(&body[8..], big_endian) as u64;
+                let captured_len =
+                    (read_u32(&body[12..], big_endian) as usize).min(body.len() - 20);
+                self.last_timestamp = to_local_time(ticks, interface.ticks_per_second);
+                Some(Frame {
+                    timestamp: self.last_timestamp,
+                    link_type: interface.link_type,
+                    data: body[20..20 + captured_len].to_vec(),
+                })
+            }
+            // simple packet block
+            3 if body.len() >= 4 => {
+                let interface = interfaces.first()?;
+                let captured_len = (read_u32(body, big_endian) as usize).min(body.len() - 4);
+                Some(Frame {
+                    timestamp: self.last_timestamp,
+                    link_type: interface.link_type,
+                    data: body[4..4 + captured_len].to_vec(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// the if_tsresol option of the interface description block.
+/// microseconds by default.
+fn parse_if_tsresol(mut options: &[u8], big_endian: bool) -> u64 {
+    while options.len() >= 4 {
+        let code = read_u16(options, big_endian);
+        let len = read_u16(&options[2..], big_endian) as usize;
+        let value = options.get(4..4 + len).unwrap_or(&[]);
+        match code {
+            0 => break, // opt_endofopt
+            // the value may be cut short in a truncated block
+            9 if len == 1 => {
+                let resol = match value.first() {
+                    Some(r) => *r,
+                    None => break,
+                };
+                let exponent = (resol & 0x7F) as u32;
+                let ticks = if resol & 0x80 == 0 {
+                    10u64.checked_pow(exponent)
+                } else {
+                    2u64.checked_pow(exponent)
+                };
+                return ticks.filter(|t| *t > 0).unwrap_or(1_000_000);
+            }
+            _ => {}
+        }
+        // the option values are padded to 32 bits
+        let padded_len = 4 + ((len + 3) & !3);
+        options = options.get(padded_len..).unwrap_or(&[]);
+    }
+    1_000_000
+}
+
+pub fn is_supported_link_type(link_type: u32) -> bool {
+    matches!(
+        link_type,
+        0 | 1 | 12 | 14 | 101 | 108 | 113 | 228 | 229 | 276
+    )
+}
+
+/// decode the link layer, IP and TCP. None if it's not a TCP segment
+/// (or an IP fragment, which we don't reassemble)
+pub fn decode_tcp_segment(link_type: u32, data: &[u8]) -> Option<TcpSegment<'_>> {
+    let (ethertype, ip_packet) = match link_type {
+        // ethernet
+        1 => {
+            let mut ethertype = u16::from_be_bytes([*data.get(12)?, *data.get(13)?]);
+            let mut offset = 14;
+            // 802.1Q & 802.1ad VLAN tags
+            while ethertype == 0x8100 || ethertype == 0x88A8 {
+                ethertype = u16::from_be_bytes([*data.get(offset + 2)?, *data.get(offset + 3)?]);
+                offset += 4;
+            }
+            (Some(ethertype), data.get(offset..)?)
+        }
+        // BSD loopback, the address family in host or network byte order
+        0 | 108 => {
+            let family = read_u32(data.get(0..4)?, link_type == 108);
+            let family = if family > 0xFFFF {
+                family.swap_bytes()
+            } else {
+                family
+            };
+            let ethertype = match family {
+                2 => 0x0800,
+                24 | 28 | 30 => 0x86DD,
+                _ => return None,
+            };
+            (Some(ethertype), data.get(4..)?)
+        }
+        // linux cooked capture (tcpdump -i any)
+        113 => (
+            Some(u16::from_be_bytes([*data.get(14)?, *data.get(15)?])),
+            data.get(16..)?,
+        ),
+        // linux cooked capture v2
+        276 => (
+            Some(u16::from_be_bytes([*data.first()?, *data.get(1)?])),
+            data.get(20..)?,
+        ),
+        // raw IP, we'll look at the IP version
+        12 | 14 | 101 | 228 | 229 => (None, data),
+        _ => return None,
+    };
+    let ip_version = ip_packet.first()? >> 4;
+    match (ethertype, ip_version) {
+        (Some(0x0800), 4) | (None, 4) => decode_ipv4(ip_packet),
+        (Some(0x86DD), 6) | (None, 6) => decode_ipv6(ip_packet),
+        _ => None,
+    }
+}
+
+fn decode_ipv4(packet: &[u8]) -> Option<TcpSegment<'_>> {
+    if packet.len() < 20 {
+        return None;
+    }
+    let header_len = ((packet[0] & 0x0F) as usize) * 4;
+    let total_len = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+    let flags_fragment_offset = u16::from_be_bytes([packet[6], packet[7]]);
+    let more_fragments = flags_fragment_offset & 0x2000 != 0;
+    if more_fragments || flags_fragment_offset & 0x1FFF != 0 || packet[9] != 6 {
+        return None;
+    }
+    let ip_src = IpAddr::V4(Ipv4Addr::new(
+        packet[12], packet[13], packet[14], packet[15],
+    ));
+    let ip_dst = IpAddr::V4(Ipv4Addr::new(
+        packet[16], packet[17], packet[18], packet[19],
+    ));
+    // ethernet frames may be padded past the end of the IP packet.
+    // with TSO, the total length may be 0
+    let end = if total_len >= header_len {
+        total_len.min(packet.len())
+    } else {
+        packet.len()
+    };
+    decode_tcp(ip_src, ip_dst, packet.get(header_len..end)?)
+}
+
+fn decode_ipv6(packet: &[u8]) -> Option<TcpSegment<'_>> {
+    let payload_len = u16::from_be_bytes([*packet.get(4)?, *packet.get(5)?]) as usize;
+    let mut next_header = *packet.get(6)?;
+    let src: [u8; 16] = packet.get(8..24)?.try_into().ok()?;
+    let dst: [u8; 16] = packet.get(24..40)?.try_into().ok()?;
+    let end = if payload_len > 0 {
+        (40 + payload_len).min(packet.len())
+    } else {
+        packet.len()
+    };
+    let mut offset = 40;
+    loop {
+        match next_header {
+            6 => break,
+            // hop-by-hop, routing, destination options
+            0 | 43 | 60 => {
+                next_header = *packet.get(offset)?;
+                offset += (*packet.get(offset + 1)? as usize + 1) * 8;
+            }
+            // fragments, or not TCP
+            _ => return None,
+        }
+    }
+    decode_tcp(
+        IpAddr::V6(Ipv6Addr::from(src)),
+        IpAddr::V6(Ipv6Addr::from(dst)),
+        packet.get(offset..end)?,
+    )
+}
+
+fn decode_tcp(ip_src: IpAddr, ip_dst: IpAddr, segment: &[u8]) -> Option<TcpSegment<'_>> {
+    if segment.len() < 20 {
+        return None;
+    }
+    let header_len = ((segment[12] >> 4) as usize) * 4;
+    if header_len < 20 || header_len > segment.len() {
+        return None;
+    }
+    let flags = segment[13];
+    Some(TcpSegment {
+        ip_src,
+        ip_dst,
+        port_src: u16::from_be_bytes([segment[0], segment[1]]),
+        port_dst: u16::from_be_bytes([segment[2], segment[3]]),
+        seq: u32::from_be_bytes([segment[4], segment[5], segment[6], segment[7]]),
+        flags: TcpFlags {
+            fin: flags & 0x01 != 0,
+            syn: flags & 0x02 != 0,
+            rst: flags & 0x04 != 0,
+            ack: flags & 0x10 != 0,
+        },
+        payload: &segment[header_len..],
+    })
+}
+
+#[test]
+fn should_read_pcap_ethernet_tcp_segment() {
+    let mut file = vec![];
+    // pcap header, little endian, microseconds, ethernet
+    file.extend_from_slice(&0xA1B2_C3D4u32.to_le_bytes());
+    file.extend_from_slice(&[
+        2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0, 1, 0, 0, 0,
+    ]);
+    let mut frame = vec![0; 12];
+    frame.extend_from_slice(&[0x08, 0x00]);
+    // IPv4, 20 bytes header + 20 bytes TCP + 2 bytes payload
+    frame.extend_from_slice(&[0x45, 0, 0, 42, 0, 0, 0x40, 0, 64, 6, 0, 0]);
+    frame.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+    frame.extend_from_slice(&[0xC3, 0x50, 0, 80, 0, 0, 0, 10, 0, 0, 0, 0, 0x50, 0x18]);
+    frame.extend_from_slice(&[0; 6]);
+    frame.extend_from_slice(b"hi");
+    // ethernet padding
+    frame.extend_from_slice(&[0; 4]);
+    file.extend_from_slice(&1_000u32.to_le_bytes());
+    file.extend_from_slice(&500u32.to_le_bytes());
+    file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+    file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+    file.extend_from_slice(&frame);
+
+    let mut reader = CaptureReader::new(&file[..]).unwrap();
+    let frame = reader.next_frame().unwrap().unwrap();
+    assert!(reader.next_frame().unwrap().is_none());
+    assert_eq!(1, frame.link_type);
+    let segment = decode_tcp_segment(frame.link_type, &frame.data).unwrap();
+    assert_eq!("10.0.0.1".parse::<IpAddr>().unwrap(), segment.ip_src);
+    assert_eq!("10.0.0.2".parse::<IpAddr>().unwrap(), segment.ip_dst);
+    assert_eq!(50000, segment.port_src);
+    assert_eq!(80, segment.port_dst);
+    assert_eq!(10, segment.seq);
+    assert!(segment.flags.ack && !segment.flags.syn);
+    assert_eq!(b"hi", segment.payload);
+}
+
+#[test]
+fn should_reject_truncated_tcp_headers() {
+    let ip = "10.0.0.1".parse::<IpAddr>().unwrap();
+    assert!(decode_tcp(ip, ip, &[0; 13]).is_none());
+    // data offset of 15 words, but only 20 bytes
+    let mut segment = [0; 20];
+    segment[12] = 0xF0;
+    assert!(decode_tcp(ip, ip, &segment).is_none());
+    // data offset below the minimum header
+    segment[12] = 0x10;
+    assert!(decode_tcp(ip, ip, &segment).is_none());
+}
+
+#[test]
+fn should_ignore_truncated_if_tsresol() {
+    // nanosecond resolution, padded to 32 bits
+    assert_eq!(
+        1_000_000_000,
+        parse_if_tsresol(&[9, 0, 1, 0, 9, 0, 0, 0], false)
+    );
+    // the option claims one byte of value, but the block ends
+    assert_eq!(1_000_000, parse_if_tsresol(&[9, 0, 1, 0], false));
+}
@@ -0,0 +1,174 @@
+// putting back in order the TCP segments of one direction of a connection
+use chrono::NaiveDateTime;
+
+// if a segment was lost (not captured), we can't wait for it forever
+const MAX_OUT_OF_ORDER_SEGMENTS: usize = 256;
+
+struct OutOfOrderSegment {
+    seq: u32,
+    timestamp: NaiveDateTime,
+    payload: Vec<u8>,
+}
+
+/// contiguous bytes, with the time & sequence number of the segment
+/// that gave them to us
+pub struct ReassembledData {
+    pub timestamp: NaiveDateTime,
+    pub seq: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct TcpDirection {
+    next_seq: Option<u32>,
+    out_of_order: Vec<OutOfOrderSegment>,
+}
+
+/// the distance between the two sequence numbers, accounting for wrapping
+fn seq_offset(seq: u32, reference: u32) -> i32 {
+    seq.wrapping_sub(reference) as i32
+}
+
+impl TcpDirection {
+    pub fn add_segment(
+        &mut self,
+        timestamp: NaiveDateTime,
+        seq: u32,
+        syn: bool,
+        payload: &[u8],
+    ) -> Vec<ReassembledData> {
+        let seq = if syn {
+            // the SYN takes one sequence number
+            self.next_seq = Some(seq.wrapping_add(1));
+            seq.wrapping_add(1)
+        } else {
+            seq
+        };
+        if payload.is_empty() {
+            return vec![];
+        }
+        // the capture may have started in the middle of the connection
+        let next_seq = *self.next_seq.get_or_insert(seq);
+        if seq_offset(seq, next_seq) > 0 {
+            self.out_of_order.push(OutOfOrderSegment {
+                seq,
+                timestamp,
+                payload: payload.to_vec(),
+            });
+            if self.out_of_order.len() > MAX_OUT_OF_ORDER_SEGMENTS {
+                return self.skip_gap();
+            }
+            return vec![];
+        }
+        let mut result = vec![];
+        self.append(&mut result, timestamp, seq, payload);
+        // the segments which were waiting for this one are only usable now
+        result.extend(
+            self.drain_out_of_order()
+                .into_iter()
+                .map(|d| ReassembledData { timestamp, ..d }),
+        );
+        result
+    }
+
+    /// give up on the missing segments, for instance at the end of the capture
+    pub fn flush(&mut self) -> Vec<ReassembledData> {
+        let mut result = vec![];
+        while !self.out_of_order.is_empty() {
+            result.extend(self.skip_gap());
+        }
+        result
+    }
+
+    fn skip_gap(&mut self) -> Vec<ReassembledData> {
+        if let Some(next_seq) = self.next_seq {
+            if let Some(earliest) = self
+                .out_of_order
+                .iter()
+                .map(|s| s.seq)
+                .min_by_key(|s| seq_offset(*s, next_seq))
+            {
+                self.next_seq = Some(earliest);
+            }
+        }
+        self.drain_out_of_order()
+    }
+
+    fn drain_out_of_order(&mut self) -> Vec<ReassembledData> {
+        let mut result = vec![];
+        while let Some(next_seq) = self.next_seq {
+            match self
+                .out_of_order
+                .iter()
+                .position(|s| seq_offset(s.seq, next_seq) <= 0)
+            {
+                Some(idx) => {
+                    let segment = self.out_of_order.swap_remove(idx);
+                    self.append(
+                        &mut result,
+                        segment.timestamp,
+                        segment.seq,
+                        &segment.payload,
+                    );
+                }
+                None => break,
+            }
+        }
+        result
+    }
+
+    /// append the part of the segment that we didn't see yet (the rest
+    /// is a retransmission)
+    fn append(
+        &mut self,
+        result: &mut Vec<ReassembledData>,
+        timestamp: NaiveDateTime,
+        seq: u32,
+        payload: &[u8],
+    ) {
+        let next_seq = self.next_seq.unwrap_or(seq);
+        // how far before the data we expect the segment starts
+        let already_seen = if seq_offset(seq, next_seq) < 0 {
+            next_seq.wrapping_sub(seq) as usize
+        } else {
+            0
+        };
+        if already_seen >= payload.len() {
+            return;
+        }
+        let new_data = &payload[already_seen..];
+        self.next_seq = Some(next_seq.wrapping_add(new_data.len() as u32));
+        result.push(ReassembledData {
+            timestamp,
+            seq,
+            data: new_data.to_vec(),
+        });
+    }
+}
+
+#[test]
+fn should_reorder_and_drop_retransmissions() {
+    let ts = NaiveDateTime::from_timestamp(0, 0);
+    let mut dir = TcpDirection::default();
+    let mut data = vec![];
+    let mut add = |dir: &mut TcpDirection, seq, syn, payload: &[u8]| {
+        for r in dir.add_segment(ts, seq, syn, payload) {
+            data.extend(r.data);
+        }
+    };
+    add(&mut dir, u32::MAX - 1, true, b"");
+    add(&mut dir, 4, false, b"world");
+    add(&mut dir, u32::MAX, false, b"hello");
+    add(&mut dir, 1, false, b"lo wo");
+    add(&mut dir, 9, false, b"!");
+    assert_eq!(b"helloworld!".to_vec(), data);
+}
+
+#[test]
+fn should_drop_a_segment_half_the_sequence_space_behind() {
+    let ts = NaiveDateTime::from_timestamp(0, 0);
+    let mut dir = TcpDirection::default();
+    assert_eq!(1, dir.add_segment(ts, 0x8000_0000, false, b"hello").len());
+    // seq_offset is i32::MIN
+    assert!(dir.add_segment(ts, 5, false, b"world").is_empty());
+}
@@ -1,11 +1,15 @@
 use crate::config;
 use crate::config::Config;
+use crate::config::TSharkOutputFormat;
 use crate::tshark_communication;
 use crate::tshark_communication::TSharkPacket;
 use crate::tshark_communication::TcpStreamId;
+use crate::tshark_json;
 use nix::sys::signal::Signal;
 use nix::unistd::Pid;
 use quick_xml::events::Event;
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::Deserializer;
 use signal_hook::iterator::Signals;
 use std::borrow::Cow;
 use std::collections::HashSet;
@@ -18,6 +22,7 @@ use std::path::PathBuf;
 use std::process::Child;
 use std::process::Command;
 use std::process::Stdio;
+use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
 
@@ -36,18 +41,36 @@ pub enum InputStep {
 
 pub type ParseInputStep = Result<InputStep, String>;
 
-// it would be possible to ask tshark to "mix in" a keylog file
-// when opening the pcap file
-// (obtain the keylog file through `SSLKEYLOGFILE=browser_keylog.txt google-chrome` or firefox,
-// pass it to tshark through -o ssh.keylog_file:/path/to/keylog)
-// but we get in flatpak limitations (can only access the file that the user opened
-// due to the sandbox) => better to just mix in the secrets manually and open a single
-// file. this is done through => editcap --inject-secrets tls,/path/to/keylog.txt ~/testtls.pcap ~/outtls.pcapng
+/// The GUI receives the parsed packets through a relm channel,
+/// the headless mode through a plain mpsc channel.
+pub trait ParseInputStepSender {
+    fn send_step(&self, step: ParseInputStep);
+}
+
+impl ParseInputStepSender for relm::Sender<ParseInputStep> {
+    fn send_step(&self, step: ParseInputStep) {
+        self.send(step).unwrap()
+    }
+}
+
+impl ParseInputStepSender for mpsc::Sender<ParseInputStep> {
+    fn send_step(&self, step: ParseInputStep) {
+        self.send(step).unwrap()
+    }
+}
+
+// tshark can "mix in" a TLS keylog file when opening the pcap file
+// (obtain the keylog file through `SSLKEYLOGFILE=browser_keylog.txt google-chrome` or firefox),
+// we pass it through -o tls.keylog_file:/path/to/keylog.
+// In flatpak, we rather mix in the secrets in a copy of the capture file
+// through editcap, see inject_tls_secrets()
 pub fn invoke_tshark(
     input_type: TSharkInputType,
     fname: &Path,
     filters: &str,
-    sender: relm::Sender<ParseInputStep>,
+    output_format: TSharkOutputFormat,
+    tls_keylog: Option<&Path>,
+    sender: impl ParseInputStepSender,
 ) {
     // piping from tshark, not to load the entire JSON in ram...
     let mut tshark_params = vec![
@@ -57,11 +80,21 @@ pub fn invoke_tshark(
             "-i"
         },
         fname.to_str().expect("invalid filename"),
-        "-Tpdml",
-        // "-o",
-        // "ssl.keylog_file:/home/emmanuel/chrome_keylog.txt",
         // "tcp.stream eq 104",
     ];
+    let keylog_option = tls_keylog.map(|k| format!("tls.keylog_file:{}", k.display()));
+    if let Some(keylog_option) = keylog_option.as_deref() {
+        tshark_params.extend(&["-o", keylog_option]);
+    }
+    match output_format {
+        TSharkOutputFormat::Pdml => tshark_params.push("-Tpdml"),
+        TSharkOutputFormat::Json => tshark_params.extend(&[
+            "-Tjson",
+            "--no-duplicate-keys",
+            "-J",
+            tshark_json::JSON_PROTOCOLS,
+        ]),
+    }
     let pcap_output = config::get_tshark_pcap_output_path();
     if input_type == TSharkInputType::Fifo {
         // -l == flush after each packet
@@ -75,23 +108,23 @@ pub fn invoke_tshark(
         .stdout(Stdio::piped())
         .spawn();
     if tshark_child.is_err() {
-        sender
-            .send(Err(format!("Error launching tshark: {:?}", tshark_child)))
-            .unwrap();
+        sender.send_step(Err(format!("Error launching tshark: {:?}", tshark_child)));
         return;
     }
     let mut tshark_child = tshark_child.unwrap();
     let buf_reader = BufReader::new(tshark_child.stdout.take().unwrap());
-    sender
-        .send(Ok(InputStep::StartedTShark(tshark_child)))
-        .unwrap();
-    parse_pdml_stream(buf_reader, sender);
+    sender.send_step(Ok(InputStep::StartedTShark(tshark_child)));
+    match output_format {
+        TSharkOutputFormat::Pdml => parse_pdml_stream(buf_reader, sender),
+        TSharkOutputFormat::Json => parse_json_stream(buf_reader, sender),
+    }
 }
 
-pub fn parse_pdml_stream<B: BufRead>(buf_reader: B, sender: relm::Sender<ParseInputStep>) {
+pub fn parse_pdml_stream<B: BufRead>(buf_reader: B, sender: impl ParseInputStepSender) {
     let mut xml_reader = quick_xml::Reader::from_reader(buf_reader);
     let mut buf = vec![];
     let mut http1_streams: HashSet<TcpStreamId> = HashSet::new();
+    let mut redis_streams: HashSet<TcpStreamId> = HashSet::new();
     let mut temp_tcp_payload: Vec<u8> = vec![];
     loop {
         match xml_reader.read_event(&mut buf) {
@@ -100,41 +133,39 @@ pub fn parse_pdml_stream<B: BufRead>(buf_reader: B, sender: relm::Sender<ParseIn
                     match tshark_communication::parse_packet(
                         &mut xml_reader,
                         &http1_streams,
+                        &redis_streams,
                         &mut temp_tcp_payload,
                     ) {
                         Ok(packet) => {
                             if packet.http.is_some() {
                                 http1_streams.insert(packet.basic_info.tcp_stream_id);
                             }
-                            sender
-                                .send(Ok(InputStep::Packet(Box::new(packet))))
-                                .unwrap()
+                            if packet.redis.is_some() {
+                                redis_streams.insert(packet.basic_info.tcp_stream_id);
+                            }
+                            sender.send_step(Ok(InputStep::Packet(Box::new(packet))))
                         }
                         Err(e) => {
-                            sender
-                                .send(Err(format!(
-                                    "xml parsing error: {} at tshark output offset {}",
-                                    e,
-                                    xml_reader.buffer_position()
-                                )))
-                                .unwrap();
+                            sender.send_step(Err(format!(
+                                "xml parsing error: {} at tshark output offset {}",
+                                e,
+                                xml_reader.buffer_position()
+                            )));
                             break;
                         }
                     }
                 }
             }
             Ok(Event::Eof) => {
-                sender.send(Ok(InputStep::Eof)).unwrap();
+                sender.send_step(Ok(InputStep::Eof));
                 break;
             }
             Err(e) => {
-                sender
-                    .send(Err(format!(
-                        "xml parsing error: {} at tshark output offset {}",
-                        e,
-                        xml_reader.buffer_position()
-                    )))
-                    .unwrap();
+                sender.send_step(Err(format!(
+                    "xml parsing error: {} at tshark output offset {}",
+                    e,
+                    xml_reader.buffer_position()
+                )));
                 break;
             }
             _ => {}
@@ -143,6 +174,77 @@ pub fn parse_pdml_stream<B: BufRead>(buf_reader: B, sender: relm::Sender<ParseIn
     }
 }
 
+/// merge the TLS secrets in a copy of the capture file, which we can then open
+/// without passing the keylog file to tshark: within flatpak, the sandbox
+/// gets in the way of tshark reading the keylog file
+/// `editcap --inject-secrets tls,/path/to/keylog.txt ~/testtls.pcap ~/outtls.pcapng`
+pub fn inject_tls_secrets(fname: &Path, tls_keylog: &Path) -> Result<PathBuf, String> {
+    let output_path = config::get_tls_keylog_injected_pcap_path();
+    let output = Command::new("editcap")
+        .arg("--inject-secrets")
+        .arg(format!("tls,{}", tls_keylog.display()))
+        .arg(fname)
+        .arg(&output_path)
+        .output()
+        .map_err(|e| format!("Error launching editcap: {:?}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Error merging the TLS key log in the capture file: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(output_path)
+}
+
+/// tshark outputs one big array: we go through it packet by packet
+/// rather than loading it all in memory
+struct JsonPacketsVisitor<'a, S: ParseInputStepSender> {
+    sender: &'a S,
+}
+
+impl<'de, 'a, S: ParseInputStepSender> Visitor<'de> for JsonPacketsVisitor<'a, S> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of tshark packets")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut http1_streams: HashSet<TcpStreamId> = HashSet::new();
+        let mut redis_streams: HashSet<TcpStreamId> = HashSet::new();
+        while let Some(packet_json) = seq.next_element::<serde_json::Value>()? {
+            let packet = tshark_json::parse_packet(&packet_json, &http1_streams, &redis_streams)
+                .map_err(A::Error::custom)?;
+            if let Some(packet) = packet {
+                if packet.http.is_some() {
+                    http1_streams.insert(packet.basic_info.tcp_stream_id);
+                }
+                if packet.redis.is_some() {
+                    redis_streams.insert(packet.basic_info.tcp_stream_id);
+                }
+                self.sender
+                    .send_step(Ok(InputStep::Packet(Box::new(packet))));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_json_stream<B: BufRead>(buf_reader: B, sender: impl ParseInputStepSender) {
+    let mut deserializer = serde_json::Deserializer::from_reader(buf_reader);
+    match deserializer.deserialize_seq(JsonPacketsVisitor { sender: &sender }) {
+        // when we stop a capture, or if there are no packets at all,
+        // the output may stop before the end of the array
+        Ok(()) => sender.send_step(Ok(InputStep::Eof)),
+        Err(e) if e.is_eof() => sender.send_step(Ok(InputStep::Eof)),
+        Err(e) => sender.send_step(Err(format!(
+            "json parsing error: {} at tshark output line {}",
+            e,
+            e.line()
+        ))),
+    }
+}
+
 #[cfg(target_family = "windows")]
 pub fn cleanup_child_processes(
     tcpdump_child: Option<Child>,
@@ -0,0 +1,195 @@
+// the connection lifecycle: the startup, the authentication, the
+// settings the server reports, and the end of the connection
+// https://www.postgresql.org/docs/12/protocol-flow.html#id-1.10.5.7.3
+use super::tshark_pgsql::PostgresAuthRequest;
+
+/// the server parameters we show for the connection, in that order
+const SHOWN_PARAMETERS: &[&str] = &[
+    "server_version",
+    "client_encoding",
+    "TimeZone",
+    "application_name",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PostgresAuthMethod {
+    // the server accepted the connection without asking anything
+    Trust,
+    Password,
+    Md5,
+    // the mechanism name, "SCRAM-SHA-256"...
+    Sasl(String),
+    Gss,
+    Sspi,
+    KerberosV5,
+    Unknown(i32),
+}
+
+impl std::fmt::Display for PostgresAuthMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostgresAuthMethod::Trust => write!(f, "trust"),
+            PostgresAuthMethod::Password => write!(f, "password"),
+            PostgresAuthMethod::Md5 => write!(f, "md5"),
+            PostgresAuthMethod::Sasl(mechanism) => write!(f, "{}", mechanism),
+            PostgresAuthMethod::Gss => write!(f, "GSSAPI"),
+            PostgresAuthMethod::Sspi => write!(f, "SSPI"),
+            PostgresAuthMethod::KerberosV5 => write!(f, "Kerberos V5"),
+            PostgresAuthMethod::Unknown(auth_type) => write!(f, "unknown ({})", auth_type),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PostgresConnectionInfo {
+    pub username: Option<String>,
+    pub database: Option<String>,
+    // from the startup message
+    pub application: Option<String>,
+    pub ssl_requested: bool,
+    pub auth_method: Option<PostgresAuthMethod>,
+    pub authenticated: bool,
+    // in the order the server sent them, updated when they change
+    pub parameters: Vec<(String, String)>,
+    // the backend process id & secret key, to match cancel requests
+    pub backend_key: Option<(u32, u32)>,
+}
+
+impl PostgresConnectionInfo {
+    pub fn add_auth_request(&mut self, request: &PostgresAuthRequest) {
+        let method = match request {
+            PostgresAuthRequest::Ok => {
+                self.authenticated = true;
+                // no request before the Ok
+                if self.auth_method.is_none() {
+                    self.auth_method = Some(PostgresAuthMethod::Trust);
+                }
+                return;
+            }
+            PostgresAuthRequest::CleartextPassword => PostgresAuthMethod::Password,
+            PostgresAuthRequest::Md5Password => PostgresAuthMethod::Md5,
+            // the client picks one of the mechanisms in its response, which
+            // we don't parse. With TLS the server offers the channel binding
+            // variant first, the last one is the plain SCRAM-SHA-256
+            PostgresAuthRequest::Sasl(mechanisms) => PostgresAuthMethod::Sasl(
+                mechanisms
+                    .last()
+                    .cloned()
+                    .unwrap_or_else(|| "SASL".to_string()),
+            ),
+            PostgresAuthRequest::Gss => PostgresAuthMethod::Gss,
+            PostgresAuthRequest::Sspi => PostgresAuthMethod::Sspi,
+            PostgresAuthRequest::KerberosV5 => PostgresAuthMethod::KerberosV5,
+            PostgresAuthRequest::Unknown(auth_type) => PostgresAuthMethod::Unknown(*auth_type),
+            PostgresAuthRequest::GssContinue
+            | PostgresAuthRequest::SaslContinue
+            | PostgresAuthRequest::SaslFinal => return,
+        };
+        self.auth_method = Some(method);
+    }
+
+    pub fn set_parameter(&mut self, name: String, value: String) {
+        match self.parameters.iter_mut().find(|(n, _)| *n == name) {
+            Some(param) => param.1 = value,
+            None => self.parameters.push((name, value)),
+        }
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// for the message list: "LOGIN: user: bob, db: shop, app: psql, auth: md5"
+    pub fn login_summary(&self) -> String {
+        let mut summary = format!(
+            "LOGIN: user: {}, db: {}, app: {}",
+            self.username.as_deref().unwrap_or("-"),
+            self.database.as_deref().unwrap_or("-"),
+            self.application
+                .as_deref()
+                .or_else(|| self.parameter("application_name"))
+                .filter(|a| !a.is_empty())
+                .unwrap_or("-")
+        );
+        if let Some(method) = &self.auth_method {
+            summary.push_str(&format!(", auth: {}", method));
+        }
+        summary
+    }
+
+    /// for the details view, one setting per line
+    pub fn description(&self) -> String {
+        let mut lines = vec![format!(
+            "Authentication: {}{}",
+            self.auth_method
+                .as_ref()
+                .map(|m| m.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            if self.auth_method.is_some() && !self.authenticated {
+                " (not completed)"
+            } else {
+                ""
+            }
+        )];
+        if self.ssl_requested {
+            lines.push("SSL requested".to_string());
+        }
+        lines.extend(
+            SHOWN_PARAMETERS
+                .iter()
+                .filter_map(|name| self.parameter(name).map(|v| format!("{}: {}", name, v))),
+        );
+        if let Some((process_id, _)) = self.backend_key {
+            lines.push(format!("Backend process id: {}", process_id));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_describe_a_scram_login() {
+        let mut connection = PostgresConnectionInfo {
+            username: Some("bob".to_string()),
+            database: Some("shop".to_string()),
+            ..Default::default()
+        };
+        connection.add_auth_request(&PostgresAuthRequest::Sasl(vec![
+            "SCRAM-SHA-256-PLUS".to_string(),
+            "SCRAM-SHA-256".to_string(),
+        ]));
+        connection.add_auth_request(&PostgresAuthRequest::SaslContinue);
+        assert!(connection.description().contains("(not completed)"));
+        connection.add_auth_request(&PostgresAuthRequest::SaslFinal);
+        connection.add_auth_request(&PostgresAuthRequest::Ok);
+        connection.set_parameter("application_name".to_string(), "".to_string());
+        connection.set_parameter("server_version".to_string(), "13.4".to_string());
+        connection.set_parameter("application_name".to_string(), "psql".to_string());
+        connection.backend_key = Some((4242, 1));
+        assert_eq!(
+            "LOGIN: user: bob, db: shop, app: psql, auth: SCRAM-SHA-256",
+            connection.login_summary()
+        );
+        assert_eq!(
+            "Authentication: SCRAM-SHA-256\nserver_version: 13.4\napplication_name: psql\nBackend process id: 4242",
+            connection.description()
+        );
+    }
+
+    #[test]
+    fn should_detect_trust_authentication() {
+        let mut connection = PostgresConnectionInfo::default();
+        connection.add_auth_request(&PostgresAuthRequest::Ok);
+        assert_eq!(Some(PostgresAuthMethod::Trust), connection.auth_method);
+        assert_eq!(
+            "LOGIN: user: -, db: -, app: -, auth: trust",
+            connection.login_summary()
+        );
+    }
+}
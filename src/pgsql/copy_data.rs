@@ -0,0 +1,297 @@
+// the COPY sub-protocol, for COPY ... FROM STDIN and COPY ... TO STDOUT
+// https://www.postgresql.org/docs/12/protocol-flow.html#PROTOCOL-COPY
+// https://www.postgresql.org/docs/12/sql-copy.html
+// We keep the beginning of the data, and show it as a resultset.
+
+/// we keep that much of the data for the rows preview. Past that,
+/// we only count the bytes and the lines
+const COPY_PREVIEW_MAX_BYTES: usize = 4 * 1024 * 1024;
+
+lazy_static! {
+    static ref CSV_OPTION: regex::Regex = regex::Regex::new(r"(?i)\bcsv\b").unwrap();
+    static ref DELIMITER_OPTION: regex::Regex =
+        regex::Regex::new(r"(?i)\bdelimiter\s+(?:as\s+)?'(.)'").unwrap();
+    static ref HEADER_OPTION: regex::Regex =
+        regex::Regex::new(r"(?i)\bheader\b(\s+(?:false|off|0)\b)?").unwrap();
+    static ref COLUMN_LIST: regex::Regex =
+        regex::Regex::new(r#"(?is)^\s*copy\s+(?:"[^"]*"|[\w.])+\s*\(([^)]*)\)"#).unwrap();
+    static ref COMMAND_TAG_ROWS: regex::Regex = regex::Regex::new(r"^COPY (\d+)$").unwrap();
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PostgresCopyDirection {
+    // COPY ... FROM STDIN: the client sends the rows
+    FromClient,
+    // COPY ... TO STDOUT: the server sends the rows
+    ToClient,
+}
+
+/// what we keep about a COPY in the message. The rows
+/// preview is in the message resultset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostgresCopySummary {
+    pub direction: PostgresCopyDirection,
+    pub binary: bool,
+    // all the rows, the preview may have less
+    pub row_count: usize,
+    pub byte_count: usize,
+}
+
+/// a COPY for which we didn't get the end yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostgresCopyInProgress {
+    direction: PostgresCopyDirection,
+    binary: bool,
+    // None if the capture started after the CopyIn/CopyOut response
+    col_count: Option<usize>,
+    data: Vec<u8>,
+    byte_count: usize,
+    line_count: usize,
+}
+
+/// the rows preview is by column, like the resultset of the message
+pub struct PostgresCopyData {
+    pub summary: PostgresCopySummary,
+    pub col_names: Vec<String>,
+    pub string_cols: Vec<Vec<Option<String>>>,
+    pub preview_row_count: usize,
+}
+
+struct CopyOptions {
+    csv: bool,
+    delimiter: char,
+    header: bool,
+    col_names: Vec<String>,
+}
+
+fn copy_options(query: &str) -> CopyOptions {
+    let csv = CSV_OPTION.is_match(query);
+    CopyOptions {
+        csv,
+        delimiter: DELIMITER_OPTION
+            .captures(query)
+            .and_then(|c| c[1].chars().next())
+            .unwrap_or(if csv { ',' } else { '\t' }),
+        header: HEADER_OPTION
+            .captures(query)
+            .map_or(false, |c| c.get(1).is_none()),
+        col_names: COLUMN_LIST
+            .captures(query)
+            .map(|c| {
+                c[1].split(',')
+                    .map(|n| n.trim().trim_matches('"').to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+/// a row in the text format: \N is null, and special characters
+/// are escaped with a backslash
+fn parse_text_row(line: &str, delimiter: char) -> Vec<Option<String>> {
+    let mut fields = vec![];
+    let mut cur = String::new();
+    // \N is null only when it's the whole field
+    let mut is_null = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == delimiter {
+            fields.push(Some(std::mem::take(&mut cur)).filter(|_| !is_null));
+            is_null = false;
+        } else if c == '\\' {
+            match chars.next() {
+                Some('N') => is_null = cur.is_empty(),
+                Some('b') => cur.push('\u{8}'),
+                Some('f') => cur.push('\u{c}'),
+                Some('n') => cur.push('\n'),
+                Some('r') => cur.push('\r'),
+                Some('t') => cur.push('\t'),
+                Some('v') => cur.push('\u{b}'),
+                Some(other) => cur.push(other),
+                None => {}
+            }
+        } else {
+            is_null = false;
+            cur.push(c);
+        }
+    }
+    fields.push(Some(cur).filter(|_| !is_null));
+    fields
+}
+
+/// the rows in the CSV format: an unquoted empty value is null,
+/// quoted values may contain delimiters and newlines
+fn parse_csv_rows(data: &str, delimiter: char) -> Vec<Vec<Option<String>>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut cur = String::new();
+    let mut was_quoted = false;
+    let mut in_quotes = false;
+    let mut chars = data.chars().peekable();
+    let end_field = |cur: &mut String, was_quoted: &mut bool, row: &mut Vec<Option<String>>| {
+        row.push(Some(std::mem::take(cur)).filter(|v| *was_quoted || !v.is_empty()));
+        *was_quoted = false;
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                cur.push('"');
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                was_quoted = true;
+            }
+            c if in_quotes => cur.push(c),
+            c if c == delimiter => end_field(&mut cur, &mut was_quoted, &mut row),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                end_field(&mut cur, &mut was_quoted, &mut row);
+                rows.push(std::mem::take(&mut row));
+            }
+            c => cur.push(c),
+        }
+    }
+    if !cur.is_empty() || !row.is_empty() {
+        end_field(&mut cur, &mut was_quoted, &mut row);
+        rows.push(row);
+    }
+    rows
+}
+
+impl PostgresCopyInProgress {
+    pub fn new(
+        direction: PostgresCopyDirection,
+        binary: bool,
+        col_count: Option<usize>,
+    ) -> PostgresCopyInProgress {
+        PostgresCopyInProgress {
+            direction,
+            binary,
+            col_count,
+            data: vec![],
+            byte_count: 0,
+            line_count: 0,
+        }
+    }
+
+    pub fn add_data(&mut self, data: &[u8]) {
+        self.byte_count += data.len();
+        self.line_count += data.iter().filter(|b| **b == b'\n').count();
+        if !self.binary && self.data.len() < COPY_PREVIEW_MAX_BYTES {
+            let remaining = COPY_PREVIEW_MAX_BYTES - self.data.len();
+            self.data
+                .extend_from_slice(&data[..data.len().min(remaining)]);
+        }
+    }
+
+    /// `command_tag` is from the CommandComplete message, "COPY 42"
+    pub fn finish(self, query: Option<&str>, command_tag: Option<&str>) -> PostgresCopyData {
+        let options = copy_options(query.unwrap_or(""));
+        let truncated = self.byte_count > self.data.len();
+        let text = String::from_utf8_lossy(&self.data);
+        let mut rows = if self.binary {
+            vec![]
+        } else if options.csv {
+            parse_csv_rows(&text, options.delimiter)
+        } else {
+            text.lines()
+                // the end-of-data marker, from older clients
+                .filter(|l| *l != "\\.")
+                .map(|l| parse_text_row(l.strip_suffix('\r').unwrap_or(l), options.delimiter))
+                .collect()
+        };
+        if truncated {
+            // the last row is probably incomplete
+            rows.pop();
+        }
+        let header = if options.header && !rows.is_empty() {
+            Some(rows.remove(0))
+        } else {
+            None
+        };
+        let col_count = rows
+            .iter()
+            .map(|r| r.len())
+            .chain(self.col_count)
+            .max()
+            .unwrap_or(0);
+        let col_names = (0..col_count)
+            .map(|idx| {
+                header
+                    .as_ref()
+                    .and_then(|h| h.get(idx).cloned().flatten())
+                    .or_else(|| options.col_names.get(idx).cloned())
+                    .unwrap_or_else(|| format!("Column {}", idx + 1))
+            })
+            .collect();
+        let mut string_cols = vec![Vec::with_capacity(rows.len()); col_count];
+        for row in &rows {
+            for (idx, col) in string_cols.iter_mut().enumerate() {
+                col.push(row.get(idx).cloned().flatten());
+            }
+        }
+        let counted_rows = self
+            .line_count
+            .saturating_sub(if header.is_some() { 1 } else { 0 });
+        PostgresCopyData {
+            summary: PostgresCopySummary {
+                direction: self.direction,
+                binary: self.binary,
+                row_count: command_tag
+                    .and_then(|t| COMMAND_TAG_ROWS.captures(t))
+                    .and_then(|c| c[1].parse().ok())
+                    .unwrap_or(counted_rows),
+                byte_count: self.byte_count,
+            },
+            col_names,
+            string_cols,
+            preview_row_count: rows.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_text_format_rows() {
+        let mut copy = PostgresCopyInProgress::new(PostgresCopyDirection::ToClient, false, Some(3));
+        copy.add_data(b"1\tbob\t\\N\n2\tal\\tice\tx");
+        copy.add_data(b"\\\\y\n");
+        let data = copy.finish(Some("COPY users (id, \"name\", note) TO STDOUT"), None);
+        assert_eq!(vec!["id", "name", "note"], data.col_names);
+        assert_eq!(2, data.preview_row_count);
+        assert_eq!(2, data.summary.row_count);
+        assert_eq!(
+            vec![
+                vec![Some("1".to_string()), Some("2".to_string())],
+                vec![Some("bob".to_string()), Some("al\tice".to_string())],
+                vec![None, Some("x\\y".to_string())],
+            ],
+            data.string_cols
+        );
+    }
+
+    #[test]
+    fn should_parse_csv_rows_with_header() {
+        let mut copy =
+            PostgresCopyInProgress::new(PostgresCopyDirection::FromClient, false, Some(2));
+        copy.add_data(b"id;comment\r\n1;\"a;\"\"quoted\"\"\nvalue\"\r\n2;\n");
+        let data = copy.finish(
+            Some("copy items from stdin with (format csv, header true, delimiter ';')"),
+            Some("COPY 2"),
+        );
+        assert_eq!(vec!["id", "comment"], data.col_names);
+        assert_eq!(2, data.summary.row_count);
+        assert_eq!(39, data.summary.byte_count);
+        assert_eq!(
+            vec![
+                vec![Some("1".to_string()), Some("2".to_string())],
+                vec![Some("a;\"quoted\"\nvalue".to_string()), None],
+            ],
+            data.string_cols
+        );
+    }
+}
@@ -1,3 +1,8 @@
+pub mod connection;
+pub mod copy_data;
+pub mod native_pgsql;
 pub mod postgres_details_widget;
 pub mod postgres_streams_store;
+pub mod query_stats;
+pub mod transactions;
 pub mod tshark_pgsql;
@@ -0,0 +1,511 @@
+// PostgreSQL wire protocol parsing for the native capture reader.
+// We extract the same messages as we get from the tshark pgsql dissector.
+// https://www.postgresql.org/docs/12/protocol-message-formats.html
+use super::tshark_pgsql::{
+    PostgresAuthRequest, PostgresColType, PostgresDiagnostic, PostgresTransactionStatus,
+    PostgresWireMessage,
+};
+
+const PROTOCOL_VERSION_3: u32 = 196_608;
+const SSL_REQUEST_CODE: u32 = 80_877_103;
+const GSSENC_REQUEST_CODE: u32 = 80_877_104;
+const CANCEL_REQUEST_CODE: u32 = 80_877_102;
+
+// beyond that, we're probably not looking at postgres messages
+const MAX_MESSAGE_LENGTH: usize = 512 * 1024 * 1024;
+
+#[derive(Default)]
+pub struct PostgresStreamParser {
+    client_data: Vec<u8>,
+    server_data: Vec<u8>,
+    // the first message from the client has no message type byte
+    startup_done: bool,
+    awaiting_encryption_reply: bool,
+    // the client & server agreed on TLS or GSSAPI encryption
+    is_encrypted: bool,
+}
+
+struct MessageReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> MessageReader<'a> {
+    fn new(data: &'a [u8]) -> MessageReader<'a> {
+        MessageReader { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let r = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| "Truncated postgres message".to_string())?;
+        self.pos += len;
+        Ok(r)
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        let b = self.bytes(2)?;
+        Ok(i16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        let b = self.bytes(4)?;
+        Ok(i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn cstring(&mut self) -> Result<String, String> {
+        let rest = &self.data[self.pos.min(self.data.len())..];
+        let len = rest
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| "Unterminated string in postgres message".to_string())?;
+        self.pos += len + 1;
+        Ok(String::from_utf8_lossy(&rest[..len]).to_string())
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    /// a length, followed by that many bytes, or -1 for null.
+    /// we use the same representation as for tshark: hex-encoded values
+    fn length_and_value(&mut self) -> Result<(i64, String), String> {
+        Ok(match self.i32()? {
+            -1 => (-1, "6e756c6c".to_string()), // null in hex
+            len if len < 0 => return Err(format!("Invalid postgres value length: {}", len)),
+            len => (len.into(), hex::encode(self.bytes(len as usize)?)),
+        })
+    }
+}
+
+impl PostgresStreamParser {
+    /// after the client & server negotiated encryption, we can't decode anything anymore
+    pub fn is_encrypted(&self) -> bool {
+        self.is_encrypted
+    }
+
+    pub fn add_data(
+        &mut self,
+        from_client: bool,
+        data: &[u8],
+    ) -> Result<Vec<PostgresWireMessage>, String> {
+        if self.is_encrypted {
+            return Ok(vec![]);
+        }
+        let mut result = vec![];
+        if from_client {
+            self.client_data.extend_from_slice(data);
+            while let Some(msg) = self.next_client_message()? {
+                result.extend(msg);
+            }
+        } else {
+            self.server_data.extend_from_slice(data);
+            while let Some(msg) = self.next_server_message()? {
+                result.extend(msg);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Some(None) if we got a message that we don't care about,
+    /// None if we don't have a complete message
+    fn next_client_message(&mut self) -> Result<Option<Option<PostgresWireMessage>>, String> {
+        if !self.startup_done {
+            if self.client_data.len() < 8 {
+                return Ok(None);
+            }
+            let code = u32::from_be_bytes(self.client_data[4..8].try_into().unwrap());
+            match code {
+                PROTOCOL_VERSION_3 | SSL_REQUEST_CODE | GSSENC_REQUEST_CODE
+                | CANCEL_REQUEST_CODE => {
+                    let len =
+                        u32::from_be_bytes(self.client_data[0..4].try_into().unwrap()) as usize;
+                    if !(8..=MAX_MESSAGE_LENGTH).contains(&len) {
+                        return Err(format!("Invalid postgres startup message length: {}", len));
+                    }
+                    if self.client_data.len() < len {
+                        return Ok(None);
+                    }
+                    let msg: Vec<u8> = self.client_data.drain(..len).collect();
+                    return Ok(Some(match code {
+                        PROTOCOL_VERSION_3 => {
+                            self.startup_done = true;
+                            Some(parse_startup(&msg[8..])?)
+                        }
+                        SSL_REQUEST_CODE => {
+                            self.awaiting_encryption_reply = true;
+                            Some(PostgresWireMessage::SslRequest)
+                        }
+                        GSSENC_REQUEST_CODE => {
+                            self.awaiting_encryption_reply = true;
+                            None
+                        }
+                        _ => {
+                            let mut reader = MessageReader::new(&msg[8..]);
+                            Some(PostgresWireMessage::CancelRequest {
+                                process_id: reader.i32()? as u32,
+                                secret_key: reader.i32()? as u32,
+                            })
+                        }
+                    }));
+                }
+                // the capture started after the startup message
+                _ => self.startup_done = true,
+            }
+        }
+        let (msg_type, contents) = match take_typed_message(&mut self.client_data)? {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        Ok(Some(match msg_type {
+            b'Q' => Some(PostgresWireMessage::Query(Some(
+                MessageReader::new(&contents).cstring()?,
+            ))),
+            b'P' => Some(parse_parse(&contents)?),
+            b'B' => Some(parse_bind(&contents)?),
+            b'd' => Some(PostgresWireMessage::CopyData(contents)),
+            b'c' => Some(PostgresWireMessage::CopyDone),
+            b'X' => Some(PostgresWireMessage::Terminate),
+            _ => None,
+        }))
+    }
+
+    fn next_server_message(&mut self) -> Result<Option<Option<PostgresWireMessage>>, String> {
+        if self.awaiting_encryption_reply {
+            match self.server_data.first() {
+                None => return Ok(None),
+                Some(b'S') | Some(b'G') => {
+                    self.is_encrypted = true;
+                    self.server_data.clear();
+                    return Ok(None);
+                }
+                Some(b'N') => {
+                    self.awaiting_encryption_reply = false;
+                    self.server_data.remove(0);
+                    return Ok(Some(None));
+                }
+                // an error message, from an old server
+                Some(_) => self.awaiting_encryption_reply = false,
+            }
+        }
+        let (msg_type, contents) = match take_typed_message(&mut self.server_data)? {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        Ok(Some(match msg_type {
+            b'R' => Some(parse_authentication(&contents)?),
+            b'S' => {
+                let mut reader = MessageReader::new(&contents);
+                Some(PostgresWireMessage::ParameterStatus {
+                    name: reader.cstring()?,
+                    value: reader.cstring()?,
+                })
+            }
+            b'K' => {
+                let mut reader = MessageReader::new(&contents);
+                Some(PostgresWireMessage::BackendKeyData {
+                    process_id: reader.i32()? as u32,
+                    secret_key: reader.i32()? as u32,
+                })
+            }
+            b'T' => Some(parse_row_description(&contents)?),
+            b'D' => Some(parse_data_row(&contents)?),
+            b'Z' => Some(PostgresWireMessage::ReadyForQuery(
+                contents
+                    .first()
+                    .and_then(|s| PostgresTransactionStatus::from_status_byte(*s)),
+            )),
+            b'E' => Some(PostgresWireMessage::ErrorResponse(parse_diagnostic(
+                &contents,
+            )?)),
+            b'N' => Some(PostgresWireMessage::NoticeResponse(parse_diagnostic(
+                &contents,
+            )?)),
+            b'G' => {
+                let (binary, col_count) = parse_copy_response(&contents)?;
+                Some(PostgresWireMessage::CopyInResponse { binary, col_count })
+            }
+            b'H' => {
+                let (binary, col_count) = parse_copy_response(&contents)?;
+                Some(PostgresWireMessage::CopyOutResponse { binary, col_count })
+            }
+            b'd' => Some(PostgresWireMessage::CopyData(contents)),
+            b'c' => Some(PostgresWireMessage::CopyDone),
+            b'C' => Some(PostgresWireMessage::CommandComplete(Some(
+                MessageReader::new(&contents).cstring()?,
+            ))),
+            _ => None,
+        }))
+    }
+}
+
+/// the message type and its contents, or None if the message is incomplete
+fn take_typed_message(data: &mut Vec<u8>) -> Result<Option<(u8, Vec<u8>)>, String> {
+    if data.len() < 5 {
+        return Ok(None);
+    }
+    // the length includes itself, but not the message type
+    let len = u32::from_be_bytes(data[1..5].try_into().unwrap()) as usize;
+    if !(4..=MAX_MESSAGE_LENGTH).contains(&len) {
+        return Err(format!("Invalid postgres message length: {}", len));
+    }
+    if data.len() < len + 1 {
+        return Ok(None);
+    }
+    let msg_type = data[0];
+    let contents = data[5..len + 1].to_vec();
+    data.drain(..len + 1);
+    Ok(Some((msg_type, contents)))
+}
+
+fn parse_startup(contents: &[u8]) -> Result<PostgresWireMessage, String> {
+    let mut reader = MessageReader::new(contents);
+    let mut username = None;
+    let mut database = None;
+    let mut application = None;
+    while !reader.is_at_end() {
+        let name = reader.cstring()?;
+        if name.is_empty() {
+            break;
+        }
+        let value = reader.cstring()?;
+        match name.as_str() {
+            "user" => username = Some(value),
+            "database" => database = Some(value),
+            "application_name" => application = Some(value),
+            _ => {}
+        }
+    }
+    Ok(PostgresWireMessage::Startup {
+        username,
+        database,
+        application,
+    })
+}
+
+fn parse_parse(contents: &[u8]) -> Result<PostgresWireMessage, String> {
+    let mut reader = MessageReader::new(contents);
+    let statement = reader.cstring()?;
+    let query = reader.cstring()?;
+    let param_count = reader.i16()?;
+    let param_types = (0..param_count)
+        .map(|_| {
+            reader
+                .i32()
+                .map(|oid| PostgresColType::from_pg_oid(oid as u32))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(PostgresWireMessage::Parse {
+        query: Some(query),
+        statement: Some(statement),
+        param_types,
+    })
+}
+
+fn parse_bind(contents: &[u8]) -> Result<PostgresWireMessage, String> {
+    let mut reader = MessageReader::new(contents);
+    let _portal = reader.cstring()?;
+    let statement = reader.cstring()?;
+    let format_count = reader.i16()?.max(0) as usize;
+    reader.bytes(format_count * 2)?;
+    let param_count = reader.i16()?;
+    let parameter_lengths_and_vals = (0..param_count)
+        .map(|_| reader.length_and_value())
+        .collect::<Result<_, _>>()?;
+    Ok(PostgresWireMessage::Bind {
+        statement: Some(statement).filter(|s| !s.is_empty()),
+        parameter_lengths_and_vals,
+    })
+}
+
+fn parse_row_description(contents: &[u8]) -> Result<PostgresWireMessage, String> {
+    let mut reader = MessageReader::new(contents);
+    let col_count = reader.i16()?;
+    let mut col_names = vec![];
+    let mut col_types = vec![];
+    for _ in 0..col_count {
+        col_names.push(reader.cstring()?);
+        // table oid & column attribute number
+        reader.bytes(4 + 2)?;
+        col_types.push(PostgresColType::from_pg_oid(reader.i32()? as u32));
+        // type size, type modifier, format code
+        reader.bytes(2 + 4 + 2)?;
+    }
+    Ok(PostgresWireMessage::RowDescription {
+        col_names,
+        col_types,
+    })
+}
+
+/// the server asking for credentials (or saying it's happy with them),
+/// with the SASL mechanisms it offers
+fn parse_authentication(contents: &[u8]) -> Result<PostgresWireMessage, String> {
+    let mut reader = MessageReader::new(contents);
+    let auth_type = reader.i32()?;
+    let mut sasl_mechanisms = vec![];
+    if auth_type == 10 {
+        // the list ends with an empty string
+        loop {
+            let mechanism = reader.cstring()?;
+            if mechanism.is_empty() {
+                break;
+            }
+            sasl_mechanisms.push(mechanism);
+        }
+    }
+    Ok(PostgresWireMessage::AuthenticationRequest(
+        PostgresAuthRequest::from_auth_type(auth_type, sasl_mechanisms),
+    ))
+}
+
+/// whether the overall format is binary, and the number of columns
+fn parse_copy_response(contents: &[u8]) -> Result<(bool, usize), String> {
+    let mut reader = MessageReader::new(contents);
+    let binary = reader.bytes(1)?[0] == 1;
+    let col_count = reader.i16()?;
+    Ok((binary, col_count.max(0) as usize))
+}
+
+fn parse_data_row(contents: &[u8]) -> Result<PostgresWireMessage, String> {
+    let mut reader = MessageReader::new(contents);
+    let col_count = reader.i16()?;
+    let col_lengths_and_vals = (0..col_count)
+        .map(|_| reader.length_and_value())
+        .collect::<Result<_, _>>()?;
+    Ok(PostgresWireMessage::ResultSetRow {
+        col_lengths_and_vals,
+    })
+}
+
+fn parse_diagnostic(contents: &[u8]) -> Result<PostgresDiagnostic, String> {
+    let mut reader = MessageReader::new(contents);
+    let mut diagnostic = PostgresDiagnostic::default();
+    while !reader.is_at_end() {
+        let field_type = reader.bytes(1)?[0];
+        if field_type == 0 {
+            break;
+        }
+        let value = reader.cstring()?;
+        match field_type {
+            b'S' => diagnostic.severity = Some(value),
+            b'C' => diagnostic.code = Some(value),
+            b'M' => diagnostic.message = Some(value),
+            b'D' => diagnostic.detail = Some(value),
+            b'H' => diagnostic.hint = Some(value),
+            b'P' => diagnostic.position = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Ok(diagnostic)
+}
+
+#[cfg(test)]
+fn typed_message(msg_type: u8, contents: &[u8]) -> Vec<u8> {
+    let mut msg = vec![msg_type];
+    msg.extend_from_slice(&(contents.len() as u32 + 4).to_be_bytes());
+    msg.extend_from_slice(contents);
+    msg
+}
+
+#[test]
+fn should_parse_startup_bind_and_data_rows() {
+    let mut parser = PostgresStreamParser::default();
+    let mut startup = vec![0, 0, 0, 0, 0, 3, 0, 0];
+    startup.extend_from_slice(b"user\0bob\0database\0shop\0\0");
+    let len = startup.len() as u32;
+    startup[0..4].copy_from_slice(&len.to_be_bytes());
+    let mut client = startup;
+    client.extend(typed_message(
+        b'B',
+        b"\0S_1\0\0\0\0\x02\xff\xff\xff\xff\0\0\0\x0212\0\0",
+    ));
+    // the bind message arrives in two segments
+    let msgs = parser.add_data(true, &client[..client.len() - 3]).unwrap();
+    assert!(matches!(
+        &msgs[..],
+        [PostgresWireMessage::Startup { username: Some(u), database: Some(d), application: None }]
+            if u == "bob" && d == "shop"
+    ));
+    let msgs = parser.add_data(true, &client[client.len() - 3..]).unwrap();
+    assert!(matches!(
+        &msgs[..],
+        [PostgresWireMessage::Bind { statement: Some(s), parameter_lengths_and_vals }]
+            if s == "S_1" && parameter_lengths_and_vals == &vec![(-1, "6e756c6c".to_string()), (2, "3132".to_string())]
+    ));
+
+    let mut server = typed_message(b'D', b"\0\x01\0\0\0\x01a");
+    server.extend(typed_message(b'C', b"SELECT 1\0"));
+    server.extend(typed_message(b'Z', b"I"));
+    let msgs = parser.add_data(false, &server).unwrap();
+    assert!(matches!(
+        &msgs[..],
+        [PostgresWireMessage::ResultSetRow { col_lengths_and_vals }, PostgresWireMessage::CommandComplete(Some(tag)), PostgresWireMessage::ReadyForQuery(Some(PostgresTransactionStatus::Idle))]
+            if col_lengths_and_vals == &vec![(1, "61".to_string())] && tag == "SELECT 1"
+    ));
+
+    let msgs = parser
+        .add_data(true, &typed_message(b'Q', b"BEGIN\0"))
+        .unwrap();
+    assert!(matches!(&msgs[..], [PostgresWireMessage::Query(Some(q))] if q == "BEGIN"));
+    let msgs = parser.add_data(false, &typed_message(b'Z', b"T")).unwrap();
+    assert!(matches!(
+        &msgs[..],
+        [PostgresWireMessage::ReadyForQuery(Some(
+            PostgresTransactionStatus::InTransaction
+        ))]
+    ));
+}
+
+#[test]
+fn should_parse_copy_from_stdin() {
+    let mut parser = PostgresStreamParser::default();
+    parser.add_data(true, &[0, 0, 0, 8, 0, 3, 0, 0]).unwrap();
+    let msgs = parser
+        .add_data(false, &typed_message(b'G', b"\0\0\x02\0\0\0\0"))
+        .unwrap();
+    assert!(matches!(
+        &msgs[..],
+        [PostgresWireMessage::CopyInResponse {
+            binary: false,
+            col_count: 2
+        }]
+    ));
+    let mut client = typed_message(b'd', b"1\tbob\n");
+    client.extend(typed_message(b'c', b""));
+    let msgs = parser.add_data(true, &client).unwrap();
+    assert!(matches!(
+        &msgs[..],
+        [PostgresWireMessage::CopyData(d), PostgresWireMessage::CopyDone] if d == b"1\tbob\n"
+    ));
+}
+
+#[test]
+fn should_parse_authentication_and_cancel_requests() {
+    let mut parser = PostgresStreamParser::default();
+    parser.add_data(true, &[0, 0, 0, 8, 0, 3, 0, 0]).unwrap();
+    let mut server = typed_message(b'R', b"\0\0\0\x0aSCRAM-SHA-256\0\0");
+    server.extend(typed_message(b'S', b"TimeZone\0UTC\0"));
+    server.extend(typed_message(b'K', b"\0\0\x10\x92\0\0\x30\x39"));
+    let msgs = parser.add_data(false, &server).unwrap();
+    assert!(matches!(
+        &msgs[..],
+        [
+            PostgresWireMessage::AuthenticationRequest(PostgresAuthRequest::Sasl(mechanisms)),
+            PostgresWireMessage::ParameterStatus { name, value },
+            PostgresWireMessage::BackendKeyData { process_id: 4242, secret_key: 12345 },
+        ] if mechanisms == &vec!["SCRAM-SHA-256".to_string()] && name == "TimeZone" && value == "UTC"
+    ));
+
+    let mut cancel_parser = PostgresStreamParser::default();
+    let msgs = cancel_parser
+        .add_data(true, b"\0\0\0\x10\x04\xd2\x16\x2e\0\0\x10\x92\0\0\x30\x39")
+        .unwrap();
+    assert!(matches!(
+        &msgs[..],
+        [PostgresWireMessage::CancelRequest {
+            process_id: 4242,
+            secret_key: 12345
+        }]
+    ));
+}
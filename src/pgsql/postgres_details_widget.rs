@@ -1,5 +1,8 @@
+use super::copy_data::PostgresCopyDirection;
 use super::postgres_streams_store::PostgresMessageData;
-use crate::pgsql::tshark_pgsql::PostgresColType;
+use crate::colors;
+use crate::http::code_formatting;
+use crate::pgsql::tshark_pgsql::{PostgresColType, PostgresDiagnostic};
 use crate::tshark_communication::TcpStreamId;
 use crate::widgets::comm_info_header;
 use crate::widgets::comm_info_header::CommInfoHeader;
@@ -90,7 +93,7 @@ impl Widget for PostgresCommEntry {
         }
     }
 
-    fn prepare_syntax_highlight() -> Vec<(Regex, String)> {
+    pub fn prepare_syntax_highlight() -> Vec<(Regex, String)> {
         [
             "select",
             "SELECT",
@@ -266,14 +269,21 @@ impl Widget for PostgresCommEntry {
                 .build();
             let cell_r_txt = gtk::CellRendererText::new();
             col1.pack_start(&cell_r_txt, true);
-            col1.add_attribute(&cell_r_txt, "text", idx as i32);
+            col1.add_attribute(
+                &cell_r_txt,
+                if Self::is_json_col(&self.model.data, idx) {
+                    "markup"
+                } else {
+                    "text"
+                },
+                idx as i32,
+            );
             self.widgets.resultset.append_column(&col1);
         }
 
         for row_idx in 0..self.model.data.resultset_row_count {
             let iter = list_store.append();
-            Self::visit_resultset_row(
-                &self.model.data,
+            self.model.data.visit_resultset_row(
                 row_idx,
                 &mut (),
                 |col_idx, bool_val, _| {
@@ -297,11 +307,18 @@ impl Widget for PostgresCommEntry {
                     );
                 },
                 |col_idx, str_val, _| {
-                    list_store.set_value(
-                        &iter,
-                        col_idx as u32,
-                        &str_val.unwrap_or("null").to_value(),
-                    );
+                    let val = match str_val {
+                        Some(v) if Self::is_json_col(&self.model.data, col_idx) => {
+                            Cow::Owned(code_formatting::highlight_indent_truncate(
+                                true,
+                                v,
+                                Some("application/json"),
+                            ))
+                        }
+                        Some(v) => Cow::Borrowed(v),
+                        None => Cow::Borrowed("null"),
+                    };
+                    list_store.set_value(&iter, col_idx as u32, &val.to_value());
                 },
                 |col_idx, datetime_val, _| {
                     list_store.set_value(
@@ -320,64 +337,11 @@ impl Widget for PostgresCommEntry {
         self.model.list_store = Some(list_store);
     }
 
-    fn visit_resultset_row<D>(
-        pg_message: &PostgresMessageData,
-        row_idx: usize,
-        data: &mut D,
-        bool_value_cb: impl Fn(usize, Option<bool>, &mut D),
-        int_value_cb: impl Fn(usize, Option<i64>, &mut D),
-        string_value_cb: impl Fn(usize, Option<&str>, &mut D),
-        datetime_value_cb: impl Fn(usize, Option<NaiveDateTime>, &mut D),
-    ) {
-        let mut bool_idx = 0;
-        let mut int_idx = 0;
-        let mut datetime_idx = 0;
-        let mut bigint_idx = 0;
-        let mut str_idx = 0;
-        for (col_idx, col_type) in pg_message.resultset_col_types.iter().enumerate() {
-            match col_type {
-                PostgresColType::Bool => {
-                    bool_value_cb(
-                        col_idx,
-                        pg_message.resultset_bool_cols[bool_idx][row_idx],
-                        data,
-                    );
-                    bool_idx += 1;
-                }
-                PostgresColType::Int2 | PostgresColType::Int4 => {
-                    int_value_cb(
-                        col_idx,
-                        pg_message.resultset_int_cols[int_idx][row_idx].map(|i| i as i64),
-                        data,
-                    );
-                    int_idx += 1;
-                }
-                PostgresColType::Timestamp => {
-                    datetime_value_cb(
-                        col_idx,
-                        pg_message.resultset_datetime_cols[datetime_idx][row_idx],
-                        data,
-                    );
-                    datetime_idx += 1;
-                }
-                PostgresColType::Int8 => {
-                    int_value_cb(
-                        col_idx,
-                        pg_message.resultset_bigint_cols[bigint_idx][row_idx],
-                        data,
-                    );
-                    bigint_idx += 1;
-                }
-                _ => {
-                    string_value_cb(
-                        col_idx,
-                        pg_message.resultset_string_cols[str_idx][row_idx].as_deref(),
-                        data,
-                    );
-                    str_idx += 1;
-                }
-            }
-        }
+    fn is_json_col(pg_message: &PostgresMessageData, col_idx: usize) -> bool {
+        matches!(
+            pg_message.resultset_col_types.get(col_idx),
+            Some(PostgresColType::Json | PostgresColType::Jsonb)
+        )
     }
 
     fn save_resultset(
@@ -395,8 +359,7 @@ impl Widget for PostgresCommEntry {
         };
         let mut output = String::new();
         for row_idx in 0..pg_message.resultset_row_count {
-            Self::visit_resultset_row(
-                pg_message,
+            pg_message.visit_resultset_row(
                 row_idx,
                 &mut output,
                 |col_idx, bool_val, output| {
@@ -435,13 +398,62 @@ impl Widget for PostgresCommEntry {
         Ok(())
     }
 
-    fn highlight_sql(highlight: &[(Regex, String)], query: &str) -> String {
+    pub fn highlight_sql(highlight: &[(Regex, String)], query: &str) -> String {
         let result = glib::markup_escape_text(query).to_string();
         highlight.iter().fold(result, |sofar, (regex, repl)| {
             regex.replace_all(&sofar, repl).to_string()
         })
     }
 
+    fn diagnostic_markup(diagnostic: &PostgresDiagnostic, color: &str) -> String {
+        format!(
+            "<span foreground=\"{}\">{}</span>",
+            color,
+            glib::markup_escape_text(&diagnostic.to_string())
+        )
+    }
+
+    fn error_markup(data: &PostgresMessageData) -> String {
+        data.error
+            .as_ref()
+            .map(|e| Self::diagnostic_markup(e, colors::ERROR_COLOR))
+            .unwrap_or_default()
+    }
+
+    fn notices_markup(data: &PostgresMessageData) -> String {
+        data.notices
+            .iter()
+            .map(|n| Self::diagnostic_markup(n, colors::WARNING_COLOR))
+            .join("\n")
+    }
+
+    fn copy_description(data: &PostgresMessageData) -> String {
+        data.copy
+            .as_ref()
+            .map(|c| {
+                format!(
+                    "COPY {} ({}), {} rows, {} bytes",
+                    match c.direction {
+                        PostgresCopyDirection::FromClient => "FROM STDIN",
+                        PostgresCopyDirection::ToClient => "TO STDOUT",
+                    },
+                    if c.binary { "binary" } else { "text" },
+                    c.row_count,
+                    c.byte_count
+                )
+            })
+            .unwrap_or_default()
+    }
+
+    fn row_count_label(data: &PostgresMessageData) -> String {
+        // for a COPY, we may have kept only the first rows
+        if data.row_count() > data.resultset_row_count {
+            format!("{} of {}", data.resultset_row_count, data.row_count())
+        } else {
+            data.resultset_row_count.to_string()
+        }
+    }
+
     view! {
         gtk::Box {
             orientation: gtk::Orientation::Vertical,
@@ -476,6 +488,31 @@ impl Widget for PostgresCommEntry {
                             visible: !self.model.data.parameter_values.is_empty(),
                             xalign: 0.0,
                         },
+                        gtk::Label {
+                            markup: &Self::error_markup(&self.model.data),
+                            visible: self.model.data.error.is_some(),
+                            line_wrap: true,
+                            xalign: 0.0,
+                            selectable: true,
+                        },
+                        gtk::Label {
+                            markup: &Self::notices_markup(&self.model.data),
+                            visible: !self.model.data.notices.is_empty(),
+                            line_wrap: true,
+                            xalign: 0.0,
+                            selectable: true,
+                        },
+                        gtk::Label {
+                            label: &self.model.data.connection.as_ref().map(|c| c.description()).unwrap_or_default(),
+                            visible: self.model.data.connection.is_some(),
+                            xalign: 0.0,
+                            selectable: true,
+                        },
+                        gtk::Label {
+                            label: &Self::copy_description(&self.model.data),
+                            visible: self.model.data.copy.is_some(),
+                            xalign: 0.0,
+                        },
                     }
                 },
                 gtk::Box {
@@ -484,7 +521,7 @@ impl Widget for PostgresCommEntry {
                         orientation: gtk::Orientation::Horizontal,
                         visible: self.model.data.resultset_row_count > 0,
                         gtk::Label {
-                            label: &self.model.data.resultset_row_count.to_string(),
+                            label: &Self::row_count_label(&self.model.data),
                             xalign: 0.0,
                         },
                         gtk::Label {
@@ -1,20 +1,29 @@
+use super::connection::PostgresConnectionInfo;
+use super::copy_data::{PostgresCopyDirection, PostgresCopyInProgress, PostgresCopySummary};
 use super::postgres_details_widget;
 use super::postgres_details_widget::PostgresCommEntry;
+use super::query_stats;
+use super::transactions::{PostgresTransaction, PostgresTransactionOutcome, PostgresTransactions};
 use crate::colors;
+use crate::config::Config;
 use crate::custom_streams_store;
-use crate::custom_streams_store::{ClientServerInfo, CustomStreamsStore};
+use crate::custom_streams_store::{
+    ClientServerInfo, CustomStreamsParser, CustomStreamsStore, StatsTable,
+};
 use crate::icons::Icon;
-use crate::pgsql::tshark_pgsql::{PostgresColType, PostgresWireMessage};
+use crate::pgsql::tshark_pgsql::{
+    PostgresColType, PostgresDiagnostic, PostgresTransactionStatus, PostgresWireMessage,
+};
 use crate::search_expr;
-use crate::search_expr::SearchCriteria;
 use crate::tshark_communication::{TSharkPacket, TcpStreamId};
 use crate::widgets::win;
 use crate::BgFunc;
-use chrono::{NaiveDateTime, Utc};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use gtk::prelude::*;
 use relm::ContainerWidget;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::convert::TryInto;
 use std::iter;
 use std::str;
 use std::str::FromStr;
@@ -25,7 +34,7 @@ use strum_macros::{EnumString, EnumVariantNames};
 #[cfg(test)]
 use {
     crate::custom_streams_store::common_tests_parse_stream,
-    crate::tshark_communication::parse_test_xml, chrono::NaiveDate,
+    crate::tshark_communication::parse_test_xml,
 };
 
 #[derive(Default)]
@@ -33,13 +42,69 @@ pub struct PostgresStreamData {
     pub stream_globals: PostgresStreamGlobals,
     pub client_server: Option<ClientServerInfo>,
     pub messages: Vec<PostgresMessageData>,
+    pub transactions: PostgresTransactions,
     pub summary_details: Option<String>,
+    pub connection: PostgresConnectionInfo,
+    // the time of the startup message, until we know how the login went
+    login_timestamp: Option<NaiveDateTime>,
+}
+
+impl PostgresStreamData {
+    fn push_message(&mut self, message: PostgresMessageData) {
+        self.transactions.add_message(self.messages.len(), &message);
+        self.messages.push(message);
+    }
+
+    /// the login, cancel and logout messages: no resultset, but
+    /// the state of the connection at that time
+    fn push_connection_message(
+        &mut self,
+        query: String,
+        query_timestamp: NaiveDateTime,
+        result_timestamp: NaiveDateTime,
+        error: Option<PostgresDiagnostic>,
+    ) {
+        self.push_message(PostgresMessageData {
+            query: Some(Cow::Owned(query)),
+            query_timestamp,
+            result_timestamp,
+            parameter_values: vec![],
+            resultset_col_names: vec![],
+            resultset_row_count: 0,
+            resultset_int_cols: vec![],
+            resultset_bigint_cols: vec![],
+            resultset_bool_cols: vec![],
+            resultset_string_cols: vec![],
+            resultset_datetime_cols: vec![],
+            resultset_col_types: vec![],
+            error,
+            notices: vec![],
+            transaction_status: None,
+            copy: None,
+            connection: Some(self.connection.clone()),
+            resultset_float_cols: vec![],
+        });
+    }
+
+    /// the login is over when the server is ready for queries, or
+    /// when it refused the connection
+    fn finish_login(&mut self, timestamp: NaiveDateTime, error: Option<PostgresDiagnostic>) {
+        if let Some(login_timestamp) = self.login_timestamp.take() {
+            self.push_connection_message(
+                self.connection.login_summary(),
+                login_timestamp,
+                timestamp,
+                error,
+            );
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct PostgresStreamsStore {
     streams: HashMap<TcpStreamId, PostgresStreamData>,
     component: Option<relm::Component<PostgresCommEntry>>,
+    slow_threshold_ms: Option<u32>,
 }
 
 impl PostgresStreamsStore {
@@ -52,6 +117,105 @@ impl PostgresStreamsStore {
         let msg = stream.messages.get(msg_idx)?;
         Some((msg, stream.client_server?))
     }
+
+    /// the transaction rows show the first query timestamp, the total
+    /// duration and the outcome of the transaction
+    fn set_transaction_row_values(
+        &self,
+        ts: &gtk::TreeStore,
+        row: &gtk::TreeIter,
+        session_id: TcpStreamId,
+        messages: &[PostgresMessageData],
+        tx: &PostgresTransaction,
+    ) {
+        let first = &messages[tx.first_msg_idx];
+        let last = &messages[tx.last_msg_idx];
+        let duration_ms = (last.result_timestamp - first.query_timestamp).num_milliseconds();
+        let row_count: usize = messages[tx.first_msg_idx..=tx.last_msg_idx]
+            .iter()
+            .map(|m| m.row_count())
+            .sum();
+        ts.set(
+            row,
+            &[
+                (
+                    0,
+                    &format!("Transaction ({} queries)", tx.message_count()).to_value(),
+                ),
+                (
+                    1,
+                    &match tx.outcome {
+                        PostgresTransactionOutcome::InProgress => "In progress",
+                        PostgresTransactionOutcome::Committed => "COMMIT",
+                        PostgresTransactionOutcome::RolledBack if tx.failed => "ROLLBACK (failed)",
+                        PostgresTransactionOutcome::RolledBack => "ROLLBACK",
+                    }
+                    .to_value(),
+                ),
+                (
+                    custom_streams_store::TREE_STORE_STREAM_ID_COL_IDX,
+                    &session_id.as_u32().to_value(),
+                ),
+                (
+                    custom_streams_store::TREE_STORE_MESSAGE_INDEX_COL_IDX,
+                    &(tx.first_msg_idx as i32).to_value(),
+                ),
+                (4, &first.query_timestamp.to_string().to_value()),
+                (5, &first.query_timestamp.timestamp_nanos().to_value()),
+                (6, &duration_ms.to_value()),
+                (7, &format!("{} ms", duration_ms).to_value()),
+                (8, &(row_count as u32).to_value()),
+                (9, &"bookmark".to_value()),
+                (
+                    10,
+                    &colors::STREAM_COLORS
+                        [session_id.as_u32() as usize % colors::STREAM_COLORS.len()]
+                    .to_value(),
+                ),
+                (
+                    11,
+                    &if tx.failed {
+                        colors::ERROR_COLOR.to_value()
+                    } else {
+                        None::<&str>.to_value()
+                    },
+                ),
+                (
+                    12,
+                    &if self
+                        .slow_threshold_ms
+                        .map_or(false, |t| duration_ms > i64::from(t))
+                    {
+                        colors::WARNING_COLOR.to_value()
+                    } else {
+                        None::<&str>.to_value()
+                    },
+                ),
+            ],
+        );
+    }
+}
+
+/// the row of the transaction starting with that message. We search
+/// from the end, since it's usually one of the last rows
+fn find_transaction_row(
+    ts: &gtk::TreeStore,
+    session_id: TcpStreamId,
+    first_msg_idx: usize,
+) -> Option<gtk::TreeIter> {
+    let model = ts.upcast_ref::<gtk::TreeModel>();
+    let iter = model.iter_nth_child(None, model.iter_n_children(None) - 1)?;
+    loop {
+        if model.iter_has_child(&iter)
+            && custom_streams_store::get_message_helper(model, &iter)
+                == (session_id, first_msg_idx as u32)
+        {
+            return Some(iter);
+        }
+        if !model.iter_previous(&iter) {
+            return None;
+        }
+    }
 }
 
 #[derive(EnumString, EnumVariantNames)]
@@ -62,6 +226,17 @@ enum PostgresFilterKeys {
     ResultSet,
     #[strum(serialize = "pg.query_param")]
     QueryParamValue,
+    #[strum(serialize = "pg.error")]
+    Error,
+    // "true" or "false": whether the message is part of a transaction that failed
+    #[strum(serialize = "pg.in_failed_transaction")]
+    InFailedTransaction,
+}
+
+#[derive(EnumString, EnumVariantNames)]
+enum PostgresNumericFilterKeys {
+    #[strum(serialize = "pg.duration_ms")]
+    DurationMs,
 }
 
 fn get_pg_message<'a, 'b>(
@@ -75,7 +250,7 @@ fn get_pg_message<'a, 'b>(
         .and_then(|s| s.messages.get(idx as usize))
 }
 
-impl CustomStreamsStore for PostgresStreamsStore {
+impl CustomStreamsParser for PostgresStreamsStore {
     fn is_my_message(&self, msg: &TSharkPacket) -> bool {
         msg.pgsql.is_some()
     }
@@ -84,10 +259,6 @@ impl CustomStreamsStore for PostgresStreamsStore {
         "pgsql"
     }
 
-    fn protocol_icon(&self) -> Icon {
-        Icon::DATABASE
-    }
-
     fn protocol_name(&self) -> &'static str {
         "PGSQL"
     }
@@ -127,6 +298,18 @@ impl CustomStreamsStore for PostgresStreamsStore {
         stream_id: TcpStreamId,
         new_packet: TSharkPacket,
     ) -> Result<Option<ClientServerInfo>, String> {
+        // a cancel request comes on its own connection, find the one it cancels
+        let cancelled_stream_id = new_packet.pgsql.iter().flatten().find_map(|md| match md {
+            PostgresWireMessage::CancelRequest {
+                process_id,
+                secret_key,
+            } => self
+                .streams
+                .iter()
+                .find(|(_, s)| s.connection.backend_key == Some((*process_id, *secret_key)))
+                .map(|(id, _)| *id),
+            _ => None,
+        });
         let stream = self
             .streams
             .entry(stream_id)
@@ -136,19 +319,21 @@ impl CustomStreamsStore for PostgresStreamsStore {
             for md in mds {
                 match md {
                     PostgresWireMessage::Startup {
-                        username: Some(ref username),
-                        database: Some(ref database),
+                        username,
+                        database,
                         application,
                     } => {
-                        match stream.summary_details.as_ref() {
-                            None => {
-                                stream.summary_details = Some(database.to_string());
-                            }
-                            Some(other_db) if !other_db.contains(database) => {
-                                stream.summary_details =
-                                    Some(format!("{}, {}", other_db, database));
+                        if let Some(database) = database.as_deref() {
+                            match stream.summary_details.as_ref() {
+                                None => {
+                                    stream.summary_details = Some(database.to_string());
+                                }
+                                Some(other_db) if !other_db.contains(database) => {
+                                    stream.summary_details =
+                                        Some(format!("{}, {}", other_db, database));
+                                }
+                                _ => {}
                             }
-                            _ => {}
                         }
                         if stream.client_server.is_none() {
                             stream.client_server = Some(ClientServerInfo {
@@ -157,27 +342,69 @@ impl CustomStreamsStore for PostgresStreamsStore {
                                 server_port: new_packet.basic_info.port_dst,
                             });
                         }
-                        stream.messages.push(PostgresMessageData {
-                            query: Some(Cow::Owned(format!(
-                                "LOGIN: user: {}, db: {}, app: {}",
-                                username,
-                                database,
-                                application.as_deref().unwrap_or("-")
-                            ))),
-                            query_timestamp: timestamp,
-                            result_timestamp: timestamp,
-                            parameter_values: vec![],
-                            resultset_col_names: vec![],
-                            resultset_row_count: 0,
-                            resultset_int_cols: vec![],
-                            resultset_bigint_cols: vec![],
-                            resultset_bool_cols: vec![],
-                            resultset_string_cols: vec![],
-                            resultset_datetime_cols: vec![],
-                            resultset_col_types: vec![],
-                        });
+                        stream.connection.username = username;
+                        stream.connection.database = database;
+                        stream.connection.application = application;
+                        // the LOGIN message is added when we know how it went
+                        stream.login_timestamp = Some(timestamp);
+                    }
+                    PostgresWireMessage::SslRequest => {
+                        if stream.client_server.is_none() {
+                            stream.client_server = Some(ClientServerInfo {
+                                server_ip: new_packet.basic_info.ip_dst,
+                                client_ip: new_packet.basic_info.ip_src,
+                                server_port: new_packet.basic_info.port_dst,
+                            });
+                        }
+                        stream.connection.ssl_requested = true;
+                    }
+                    PostgresWireMessage::AuthenticationRequest(request) => {
+                        if stream.client_server.is_none() {
+                            stream.client_server = Some(ClientServerInfo {
+                                server_ip: new_packet.basic_info.ip_src,
+                                client_ip: new_packet.basic_info.ip_dst,
+                                server_port: new_packet.basic_info.port_src,
+                            });
+                        }
+                        stream.connection.add_auth_request(&request);
+                    }
+                    PostgresWireMessage::ParameterStatus { name, value } => {
+                        stream.connection.set_parameter(name, value);
+                    }
+                    PostgresWireMessage::BackendKeyData {
+                        process_id,
+                        secret_key,
+                    } => {
+                        stream.connection.backend_key = Some((process_id, secret_key));
+                    }
+                    PostgresWireMessage::CancelRequest { process_id, .. } => {
+                        if stream.client_server.is_none() {
+                            stream.client_server = Some(ClientServerInfo {
+                                server_ip: new_packet.basic_info.ip_dst,
+                                client_ip: new_packet.basic_info.ip_src,
+                                server_port: new_packet.basic_info.port_dst,
+                            });
+                        }
+                        let query = match cancelled_stream_id {
+                            Some(id) => format!(
+                                "CANCEL: backend pid {} (stream {})",
+                                process_id,
+                                id.as_u32()
+                            ),
+                            None => format!("CANCEL: backend pid {}", process_id),
+                        };
+                        stream.push_connection_message(query, timestamp, timestamp, None);
+                    }
+                    PostgresWireMessage::Terminate => {
+                        stream.finish_login(timestamp, None);
+                        stream.push_connection_message(
+                            "LOGOUT".to_string(),
+                            timestamp,
+                            timestamp,
+                            None,
+                        );
                     }
-                    PostgresWireMessage::Startup { .. } => {
+                    PostgresWireMessage::Query(query) => {
                         if stream.client_server.is_none() {
                             stream.client_server = Some(ClientServerInfo {
                                 server_ip: new_packet.basic_info.ip_dst,
@@ -185,6 +412,11 @@ impl CustomStreamsStore for PostgresStreamsStore {
                                 server_port: new_packet.basic_info.port_dst,
                             });
                         }
+                        // with the simple query protocol, the query is parsed,
+                        // bound and executed at once
+                        stream.stream_globals.cur_query = query;
+                        stream.stream_globals.was_bind = true;
+                        stream.stream_globals.query_timestamp = Some(timestamp);
                     }
                     PostgresWireMessage::Parse {
                         ref query,
@@ -206,6 +438,8 @@ impl CustomStreamsStore for PostgresStreamsStore {
                         }
                         stream.stream_globals.cur_query = query.clone();
                         stream.stream_globals.parse_param_types = param_types.clone();
+                        // will be overwritten by the bind, unless the parse fails
+                        stream.stream_globals.query_timestamp = Some(timestamp);
                     }
                     PostgresWireMessage::Bind {
                         statement,
@@ -272,6 +506,9 @@ impl CustomStreamsStore for PostgresStreamsStore {
                                 PostgresColType::Int8 => {
                                     stream.stream_globals.cur_rs_bigint_cols.push(vec![]);
                                 }
+                                PostgresColType::Float4 | PostgresColType::Float8 => {
+                                    stream.stream_globals.cur_rs_float_cols.push(vec![]);
+                                }
                                 _ => {
                                     stream.stream_globals.cur_rs_string_cols.push(vec![]);
                                 }
@@ -296,7 +533,7 @@ impl CustomStreamsStore for PostgresStreamsStore {
                                 .collect(),
                         )?;
                     }
-                    PostgresWireMessage::ReadyForQuery => {
+                    PostgresWireMessage::ReadyForQuery(transaction_status) => {
                         if stream.client_server.is_none() {
                             stream.client_server = Some(ClientServerInfo {
                                 server_ip: new_packet.basic_info.ip_src,
@@ -304,14 +541,22 @@ impl CustomStreamsStore for PostgresStreamsStore {
                                 server_port: new_packet.basic_info.port_src,
                             });
                         }
+                        stream.finish_login(timestamp, None);
 
                         // reset all the globals, but keep known_statements
                         let globals = std::mem::take(&mut stream.stream_globals);
                         stream.stream_globals.known_statements = globals.known_statements;
-                        if globals.was_bind {
-                            stream.messages.push(PostgresMessageData {
-                                query: globals.cur_query_with_fallback.map(Cow::Owned),
-                                query_timestamp: globals.query_timestamp.unwrap(), // know it was populated since was_bind is true
+                        // if the parse failed, we get an error but no bind
+                        if globals.was_bind
+                            || globals.cur_error.is_some()
+                            || globals.cur_copy.is_some()
+                        {
+                            let mut message = PostgresMessageData {
+                                query: globals
+                                    .cur_query_with_fallback
+                                    .or(globals.cur_query)
+                                    .map(Cow::Owned),
+                                query_timestamp: globals.query_timestamp.unwrap_or(timestamp),
                                 result_timestamp: timestamp,
                                 parameter_values: globals.cur_parameter_values,
                                 resultset_col_names: globals.cur_col_names,
@@ -320,12 +565,58 @@ impl CustomStreamsStore for PostgresStreamsStore {
                                 resultset_string_cols: globals.cur_rs_string_cols,
                                 resultset_int_cols: globals.cur_rs_int_cols,
                                 resultset_bigint_cols: globals.cur_rs_bigint_cols,
+                                resultset_float_cols: globals.cur_rs_float_cols,
                                 resultset_datetime_cols: globals.cur_rs_datetime_cols,
                                 resultset_col_types: globals.cur_col_types,
+                                error: globals.cur_error,
+                                notices: globals.cur_notices,
+                                transaction_status,
+                                copy: None,
+                                connection: None,
+                            };
+                            if let Some(copy) = globals.cur_copy {
+                                // the rows of a COPY go in the resultset
+                                let copy = copy.finish(
+                                    message.query.as_deref(),
+                                    globals.cur_command_tag.as_deref(),
+                                );
+                                message.resultset_col_types =
+                                    vec![PostgresColType::Text; copy.col_names.len()];
+                                message.resultset_col_names = copy.col_names;
+                                message.resultset_string_cols = copy.string_cols;
+                                message.resultset_row_count = copy.preview_row_count;
+                                message.copy = Some(copy.summary);
+                            }
+                            stream.push_message(message);
+                        }
+                    }
+                    PostgresWireMessage::ErrorResponse(diagnostic) => {
+                        if stream.client_server.is_none() {
+                            stream.client_server = Some(ClientServerInfo {
+                                server_ip: new_packet.basic_info.ip_src,
+                                client_ip: new_packet.basic_info.ip_dst,
+                                server_port: new_packet.basic_info.port_src,
+                            });
+                        }
+                        if stream.login_timestamp.is_some() {
+                            // the server refused the connection
+                            stream.finish_login(timestamp, Some(diagnostic));
+                        } else {
+                            stream.stream_globals.cur_error = Some(diagnostic);
+                        }
+                    }
+                    PostgresWireMessage::NoticeResponse(diagnostic) => {
+                        if stream.client_server.is_none() {
+                            stream.client_server = Some(ClientServerInfo {
+                                server_ip: new_packet.basic_info.ip_src,
+                                client_ip: new_packet.basic_info.ip_dst,
+                                server_port: new_packet.basic_info.port_src,
                             });
                         }
+                        stream.stream_globals.cur_notices.push(diagnostic);
                     }
-                    PostgresWireMessage::CopyData => {
+                    PostgresWireMessage::CopyInResponse { binary, col_count }
+                    | PostgresWireMessage::CopyOutResponse { binary, col_count } => {
                         if stream.client_server.is_none() {
                             stream.client_server = Some(ClientServerInfo {
                                 server_ip: new_packet.basic_info.ip_src,
@@ -333,20 +624,43 @@ impl CustomStreamsStore for PostgresStreamsStore {
                                 server_port: new_packet.basic_info.port_src,
                             });
                         }
-                        stream.messages.push(PostgresMessageData {
-                            query: Some(Cow::Borrowed("COPY DATA")),
-                            query_timestamp: timestamp,
-                            result_timestamp: timestamp,
-                            parameter_values: vec![],
-                            resultset_col_names: vec![],
-                            resultset_row_count: 0,
-                            resultset_int_cols: vec![],
-                            resultset_bigint_cols: vec![],
-                            resultset_bool_cols: vec![],
-                            resultset_string_cols: vec![],
-                            resultset_datetime_cols: vec![],
-                            resultset_col_types: vec![],
+                        let direction = if matches!(md, PostgresWireMessage::CopyInResponse { .. })
+                        {
+                            PostgresCopyDirection::FromClient
+                        } else {
+                            PostgresCopyDirection::ToClient
+                        };
+                        stream.stream_globals.cur_copy = Some(PostgresCopyInProgress::new(
+                            direction,
+                            binary,
+                            Some(col_count),
+                        ));
+                    }
+                    PostgresWireMessage::CopyData(data) => {
+                        let from_server = stream.client_server.map_or(true, |cs| {
+                            cs.server_ip == new_packet.basic_info.ip_src
+                                && cs.server_port == new_packet.basic_info.port_src
                         });
+                        stream
+                            .stream_globals
+                            .cur_copy
+                            // the capture started after the CopyIn/CopyOut response
+                            .get_or_insert_with(|| {
+                                PostgresCopyInProgress::new(
+                                    if from_server {
+                                        PostgresCopyDirection::ToClient
+                                    } else {
+                                        PostgresCopyDirection::FromClient
+                                    },
+                                    false,
+                                    None,
+                                )
+                            })
+                            .add_data(&data);
+                    }
+                    PostgresWireMessage::CopyDone => {}
+                    PostgresWireMessage::CommandComplete(tag) => {
+                        stream.stream_globals.cur_command_tag = tag;
                     }
                 }
             }
@@ -354,10 +668,53 @@ impl CustomStreamsStore for PostgresStreamsStore {
         Ok(stream.client_server)
     }
 
-    fn finish_stream(&mut self, _stream_id: TcpStreamId) -> Result<(), String> {
+    fn finish_stream(&mut self, stream_id: TcpStreamId) -> Result<(), String> {
+        if let Some(stream) = self.streams.get_mut(&stream_id) {
+            // the capture ended during the login
+            if let Some(login_timestamp) = stream.login_timestamp {
+                stream.finish_login(login_timestamp, None);
+            }
+        }
         Ok(())
     }
 
+    fn stream_messages_json(&self, stream_id: TcpStreamId) -> Vec<serde_json::Value> {
+        self.streams
+            .get(&stream_id)
+            .map(|s| s.messages.iter().map(|m| m.to_json()).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl CustomStreamsStore for PostgresStreamsStore {
+    fn protocol_icon(&self) -> Icon {
+        Icon::DATABASE
+    }
+
+    fn apply_config(&mut self, config: &Config) {
+        self.slow_threshold_ms = Some(config.pg_slow_threshold_ms);
+    }
+
+    fn stream_slow_message_count(&self, stream_id: TcpStreamId) -> usize {
+        match (self.streams.get(&stream_id), self.slow_threshold_ms) {
+            (Some(stream), Some(threshold)) => stream
+                .messages
+                .iter()
+                .filter(|m| m.duration_ms() > i64::from(threshold))
+                .count(),
+            _ => 0,
+        }
+    }
+
+    fn streams_stats(&self, stream_ids: &[TcpStreamId]) -> Option<StatsTable> {
+        Some(query_stats::query_stats(
+            stream_ids
+                .iter()
+                .filter_map(|id| self.streams.get(id))
+                .map(|s| &s.messages[..]),
+        ))
+    }
+
     fn prepare_treeview(&self, tv: &gtk::TreeView) {
         let streamcolor_col = gtk::builders::TreeViewColumnBuilder::new()
             .title("S")
@@ -401,6 +758,8 @@ impl CustomStreamsStore for PostgresStreamsStore {
         query_col.pack_start(&cell_q_txt, true);
         query_col.add_attribute(&cell_q_txt, "text", 0);
         tv.append_column(&query_col);
+        // the transactions are expanded from the query column
+        tv.set_expander_column(Some(&query_col));
 
         let result_col = gtk::builders::TreeViewColumnBuilder::new()
             .title("Result")
@@ -410,6 +769,7 @@ impl CustomStreamsStore for PostgresStreamsStore {
         let cell_r_txt = gtk::builders::CellRendererTextBuilder::new().build();
         result_col.pack_start(&cell_r_txt, true);
         result_col.add_attribute(&cell_r_txt, "text", 1);
+        result_col.add_attribute(&cell_r_txt, "foreground", 11);
         tv.append_column(&result_col);
 
         let duration_col = gtk::builders::TreeViewColumnBuilder::new()
@@ -420,11 +780,12 @@ impl CustomStreamsStore for PostgresStreamsStore {
         let cell_d_txt = gtk::builders::CellRendererTextBuilder::new().build();
         duration_col.pack_start(&cell_d_txt, true);
         duration_col.add_attribute(&cell_d_txt, "text", 7);
+        duration_col.add_attribute(&cell_d_txt, "foreground", 12);
         tv.append_column(&duration_col);
     }
 
-    fn get_empty_liststore(&self) -> gtk::ListStore {
-        gtk::ListStore::new(&[
+    fn get_empty_treestore(&self) -> gtk::TreeStore {
+        gtk::TreeStore::new(&[
             String::static_type(), // query first line
             String::static_type(), // response info (number of rows..)
             u32::static_type(),    // stream_id
@@ -436,20 +797,44 @@ impl CustomStreamsStore for PostgresStreamsStore {
             i64::static_type(),    // number of rows, for sorting
             String::static_type(), // query type: update, insert..
             String::static_type(), // stream color
+            String::static_type(), // result color
+            String::static_type(), // duration color (slow queries)
         ])
     }
 
     fn populate_treeview(
         &self,
-        ls: &gtk::ListStore,
+        ts: &gtk::TreeStore,
         session_id: TcpStreamId,
         start_idx: usize,
         item_count: usize,
     ) {
-        let messages = &self.streams.get(&session_id).unwrap().messages;
+        let stream = self.streams.get(&session_id).unwrap();
+        let messages = &stream.messages;
         // println!("adding {} rows", messages.len());
+        let mut cur_transaction_row: Option<(usize, gtk::TreeIter)> = None;
         for (idx, postgres) in messages.iter().skip(start_idx).take(item_count).enumerate() {
-            ls.insert_with_values(
+            let msg_idx = start_idx + idx;
+            // the messages of a transaction go under a row for the transaction
+            match stream.transactions.transaction_of(msg_idx) {
+                Some((tx_idx, tx))
+                    if cur_transaction_row.as_ref().map(|(i, _)| *i) != Some(tx_idx) =>
+                {
+                    // the transaction may have started in a previous batch
+                    let existing_row = if msg_idx == tx.first_msg_idx {
+                        None
+                    } else {
+                        find_transaction_row(ts, session_id, tx.first_msg_idx)
+                    };
+                    let row = existing_row.unwrap_or_else(|| ts.append(None));
+                    self.set_transaction_row_values(ts, &row, session_id, messages, tx);
+                    cur_transaction_row = Some((tx_idx, row));
+                }
+                Some(_) => {}
+                None => cur_transaction_row = None,
+            }
+            ts.insert_with_values(
+                cur_transaction_row.as_ref().map(|(_, row)| row),
                 None,
                 &[
                     (
@@ -464,7 +849,13 @@ impl CustomStreamsStore for PostgresStreamsStore {
                     ),
                     (
                         1,
-                        &format!("{} rows", postgres.resultset_row_count).to_value(),
+                        &match &postgres.error {
+                            Some(err) => {
+                                format!("ERROR {}", err.code.as_deref().unwrap_or(""))
+                            }
+                            None => format!("{} rows", postgres.row_count()),
+                        }
+                        .to_value(),
                     ),
                     (
                         custom_streams_store::TREE_STORE_STREAM_ID_COL_IDX,
@@ -476,22 +867,9 @@ impl CustomStreamsStore for PostgresStreamsStore {
                     ),
                     (4, &postgres.query_timestamp.to_string().to_value()),
                     (5, &postgres.query_timestamp.timestamp_nanos().to_value()),
-                    (
-                        6,
-                        &(postgres.result_timestamp - postgres.query_timestamp)
-                            .num_milliseconds()
-                            .to_value(),
-                    ),
-                    (
-                        7,
-                        &format!(
-                            "{} ms",
-                            (postgres.result_timestamp - postgres.query_timestamp)
-                                .num_milliseconds()
-                        )
-                        .to_value(),
-                    ),
-                    (8, &(postgres.resultset_row_count as u32).to_value()),
+                    (6, &postgres.duration_ms().to_value()),
+                    (7, &format!("{} ms", postgres.duration_ms()).to_value()),
+                    (8, &(postgres.row_count() as u32).to_value()),
                     (9, &get_query_type_desc(&postgres.query).to_value()),
                     (
                         10,
@@ -499,13 +877,32 @@ impl CustomStreamsStore for PostgresStreamsStore {
                             [session_id.as_u32() as usize % colors::STREAM_COLORS.len()]
                         .to_value(),
                     ),
+                    (
+                        11,
+                        &if postgres.error.is_some() {
+                            colors::ERROR_COLOR.to_value()
+                        } else {
+                            None::<&str>.to_value()
+                        },
+                    ),
+                    (
+                        12,
+                        &if self
+                            .slow_threshold_ms
+                            .map_or(false, |t| postgres.duration_ms() > i64::from(t))
+                        {
+                            colors::WARNING_COLOR.to_value()
+                        } else {
+                            None::<&str>.to_value()
+                        },
+                    ),
                 ],
             );
         }
     }
 
-    fn end_populate_treeview(&self, tv: &gtk::TreeView, ls: &gtk::ListStore) {
-        let model_sort = gtk::TreeModelSort::new(ls);
+    fn end_populate_treeview(&self, tv: &gtk::TreeView, ts: &gtk::TreeStore) {
+        let model_sort = gtk::TreeModelSort::new(ts);
         model_sort.set_sort_column_id(gtk::SortColumn::Index(5), gtk::SortType::Ascending);
         tv.set_model(Some(&model_sort));
     }
@@ -515,7 +912,7 @@ impl CustomStreamsStore for PostgresStreamsStore {
     }
 
     fn supported_numeric_filter_keys(&self) -> &'static [&'static str] {
-        &[]
+        PostgresNumericFilterKeys::VARIANTS
     }
 
     fn matches_filter(
@@ -525,34 +922,49 @@ impl CustomStreamsStore for PostgresStreamsStore {
         iter: &gtk::TreeIter,
     ) -> bool {
         let streams = &self.streams;
-        match (PostgresFilterKeys::from_str(filter.filter_key), &filter.op) {
-            (Ok(filter_key), SearchCriteria::Contains(filter_val)) => match filter_key {
-                PostgresFilterKeys::QueryString => model
-                    .value(iter, 0)
-                    .get::<&str>()
-                    .unwrap()
-                    .to_lowercase()
-                    .contains(&filter_val.to_lowercase()),
+        match (
+            PostgresFilterKeys::from_str(filter.filter_key),
+            PostgresNumericFilterKeys::from_str(filter.filter_key),
+            &filter.op,
+        ) {
+            (Ok(filter_key), _, op) => match filter_key {
+                // the full query: the list only shows the beginning
+                PostgresFilterKeys::QueryString => get_pg_message(streams, model, iter)
+                    .and_then(|pg_msg| pg_msg.query.as_deref())
+                    .map_or(false, |q| op.matches_str(q)),
                 PostgresFilterKeys::ResultSet => {
-                    let fv = filter_val.to_lowercase();
                     get_pg_message(streams, model, iter).map_or(false, |pg_msg| {
                         pg_msg.resultset_string_cols.iter().any(|v| {
-                            v.iter().any(|c| {
-                                c.as_ref().map_or(false, |v| v.to_lowercase().contains(&fv))
-                            })
+                            v.iter()
+                                .any(|c| c.as_ref().map_or(false, |v| op.matches_str(v)))
                         })
                     })
                 }
                 PostgresFilterKeys::QueryParamValue => {
-                    let fv = filter_val.to_lowercase();
                     get_pg_message(streams, model, iter).map_or(false, |pg_msg| {
                         pg_msg
                             .parameter_values
                             .iter()
-                            .any(|(_type, v)| v.to_lowercase().contains(&fv))
+                            .any(|(_type, v)| op.matches_str(v))
                     })
                 }
+                PostgresFilterKeys::Error => get_pg_message(streams, model, iter)
+                    .and_then(|pg_msg| pg_msg.error.as_ref())
+                    .map_or(false, |err| op.matches_str(&err.to_string())),
+                PostgresFilterKeys::InFailedTransaction => {
+                    let (stream_id, idx) = custom_streams_store::get_message_helper(model, iter);
+                    let failed = streams
+                        .get(&stream_id)
+                        .and_then(|s| s.transactions.transaction_of(idx as usize))
+                        .map_or(false, |(_, t)| t.failed);
+                    op.matches_str(if failed { "true" } else { "false" })
+                }
             },
+            (_, Ok(PostgresNumericFilterKeys::DurationMs), op) => {
+                get_pg_message(streams, model, iter).map_or(false, |pg_msg| {
+                    op.matches_number(pg_msg.duration_ms().max(0) as usize, 1)
+                })
+            }
             _ => true,
         }
     }
@@ -584,6 +996,12 @@ impl CustomStreamsStore for PostgresStreamsStore {
                 resultset_string_cols: vec![],
                 resultset_datetime_cols: vec![],
                 resultset_col_types: vec![],
+                error: None,
+                notices: vec![],
+                transaction_status: None,
+                copy: None,
+                connection: None,
+                resultset_float_cols: vec![],
             },
             win_msg_sender,
             bg_sender,
@@ -657,6 +1075,20 @@ fn decode_param(typ: PostgresColType, val: &str, length: i64) -> String {
         PostgresColType::Int4 | PostgresColType::Int2 => decode_integer_as_str::<i32>(typ, val),
         PostgresColType::Int8 => decode_integer_as_str::<i64>(typ, val),
         PostgresColType::Unknown => decode_unknown(val),
+        PostgresColType::TimestampTz
+        | PostgresColType::Date
+        | PostgresColType::Time
+        | PostgresColType::Interval
+        | PostgresColType::Numeric
+        | PostgresColType::Float4
+        | PostgresColType::Float8
+        | PostgresColType::Uuid
+        | PostgresColType::Json
+        | PostgresColType::Jsonb
+        | PostgresColType::Inet
+        | PostgresColType::Array(_) => hex_chars_to_bytes(val)
+            .and_then(|b| decode_text_or_binary(typ, &b))
+            .unwrap_or_else(|| format!("Error decoding: {}", val)),
         _ => hex_chars_to_string(val).unwrap_or_else(|| format!("Error decoding: {}", val)),
     }
 }
@@ -697,6 +1129,7 @@ fn handle_pgsql_resultset_row(
     let mut int_col_idx = 0;
     let mut datetime_col_idx = 0;
     let mut bigint_col_idx = 0;
+    let mut float_col_idx = 0;
     let mut bool_col_idx = 0;
     let mut string_col_idx = 0;
     if globals.cur_col_types.is_empty() {
@@ -729,9 +1162,13 @@ fn handle_pgsql_resultset_row(
                     if val_str.as_deref() == Some("null") {
                         None
                     } else {
-                        let parsed = val_str.and_then(|v| {
-                            NaiveDateTime::parse_from_str(&v, "%Y-%m-%d %H:%M:%S%.f").ok()
-                        });
+                        let parsed = val_str
+                            .and_then(|v| {
+                                NaiveDateTime::parse_from_str(&v, "%Y-%m-%d %H:%M:%S%.f").ok()
+                            })
+                            .or_else(|| {
+                                hex_chars_to_bytes(&val).and_then(|b| decode_binary_timestamp(&b))
+                            });
                         if parsed.is_some() {
                             parsed
                         } else {
@@ -757,6 +1194,47 @@ fn handle_pgsql_resultset_row(
                 );
                 bigint_col_idx += 1;
             }
+            PostgresColType::Float4 | PostgresColType::Float8 => {
+                let unhexed = hex_chars_to_string(&val);
+                globals.cur_rs_float_cols[float_col_idx].push(
+                    if unhexed.as_deref() == Some("null") {
+                        None
+                    } else {
+                        let parsed = hex_chars_to_bytes(&val)
+                            .and_then(|b| decode_text_or_binary(*col_type, &b))
+                            .and_then(|f| f.parse::<f64>().ok());
+                        if parsed.is_some() {
+                            parsed
+                        } else {
+                            return Err(format!("expected float value: {}", val));
+                        }
+                    },
+                );
+                float_col_idx += 1;
+            }
+            PostgresColType::TimestampTz
+            | PostgresColType::Date
+            | PostgresColType::Time
+            | PostgresColType::Interval
+            | PostgresColType::Numeric
+            | PostgresColType::Uuid
+            | PostgresColType::Json
+            | PostgresColType::Jsonb
+            | PostgresColType::Inet
+            | PostgresColType::Array(_) => {
+                globals.cur_rs_string_cols[string_col_idx].push(
+                    if hex_chars_to_string(&val).as_deref() == Some("null") {
+                        None
+                    } else {
+                        Some(
+                            hex_chars_to_bytes(&val)
+                                .and_then(|b| decode_text_or_binary(*col_type, &b))
+                                .unwrap_or_else(|| format!("Error decoding: {}", val)),
+                        )
+                    },
+                );
+                string_col_idx += 1;
+            }
             PostgresColType::Unknown => {
                 globals.cur_rs_string_cols[string_col_idx].push(
                     if hex_chars_to_string(&val).as_deref() == Some("null") {
@@ -777,7 +1255,7 @@ fn handle_pgsql_resultset_row(
     Ok(())
 }
 
-fn get_query_type_desc(query: &Option<Cow<'static, str>>) -> &'static str {
+pub fn get_query_type_desc(query: &Option<Cow<'static, str>>) -> &'static str {
     if query.as_ref().filter(|q| q.len() >= 5).is_none() {
         "-"
     } else {
@@ -804,8 +1282,10 @@ fn get_query_type_desc(query: &Option<Cow<'static, str>>) -> &'static str {
             "alter"
         } else if start_lower.starts_with("do ") {
             "plsql"
-        } else if start_lower.starts_with("login") {
+        } else if start_lower.starts_with("login") || start_lower.starts_with("logou") {
             "login"
+        } else if start_lower.starts_with("cance") {
+            "system"
         } else if start_lower.starts_with("copy ") {
             // copy data
             "copy"
@@ -829,7 +1309,258 @@ fn hex_chars_to_bytes(hex_chars: &str) -> Option<Vec<u8>> {
     hex::decode(&nocolons).ok().map(|c| c.into_iter().collect())
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+// the text format is always printable, the binary format practically never is,
+// and the RowDescription doesn't tell us reliably which format was picked,
+// because it may have been sent before the Bind.
+fn is_text_format(typ: PostgresColType, txt: &str) -> bool {
+    let starts_like_date =
+        |t: &str| t.len() >= 10 && t.as_bytes()[4] == b'-' || t == "infinity" || t == "-infinity";
+    if txt
+        .bytes()
+        .any(|b| b < 0x20 && b != b'\t' && b != b'\n' && b != b'\r')
+    {
+        return false;
+    }
+    match typ {
+        PostgresColType::Float4 | PostgresColType::Float8 | PostgresColType::Numeric => {
+            txt.parse::<f64>().is_ok()
+        }
+        PostgresColType::Int2 | PostgresColType::Int4 | PostgresColType::Int8 => {
+            txt.parse::<i64>().is_ok()
+        }
+        PostgresColType::Oid => txt.parse::<u32>().is_ok(),
+        PostgresColType::Bool => txt == "t" || txt == "f",
+        PostgresColType::Uuid => txt.len() == 36,
+        PostgresColType::Date | PostgresColType::Timestamp | PostgresColType::TimestampTz => {
+            starts_like_date(txt)
+        }
+        PostgresColType::Time => txt.len() >= 8 && txt.as_bytes()[2] == b':',
+        PostgresColType::Array(_) => txt.starts_with('{') || txt.starts_with('['),
+        _ => true,
+    }
+}
+
+/// decode a value, that may be in the text or the binary format, to its
+/// textual representation
+fn decode_text_or_binary(typ: PostgresColType, bytes: &[u8]) -> Option<String> {
+    match str::from_utf8(bytes) {
+        Ok(txt) if is_text_format(typ, txt) => Some(txt.to_string()),
+        _ => decode_binary(typ, bytes),
+    }
+}
+
+// https://github.com/postgres/postgres/blob/master/src/include/datatype/timestamp.h
+// binary dates & times are relative to 2000-01-01
+fn pg_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd(2000, 1, 1).and_hms(0, 0, 0)
+}
+
+fn decode_binary_timestamp(bytes: &[u8]) -> Option<NaiveDateTime> {
+    let micros = i64::from_be_bytes(bytes.try_into().ok()?);
+    pg_epoch().checked_add_signed(chrono::Duration::microseconds(micros))
+}
+
+fn decode_binary(typ: PostgresColType, bytes: &[u8]) -> Option<String> {
+    match typ {
+        PostgresColType::Bool => Some(if bytes.first()? != &0 { "t" } else { "f" }.to_string()),
+        PostgresColType::Int2 => Some(i16::from_be_bytes(bytes.try_into().ok()?).to_string()),
+        PostgresColType::Int4 => Some(i32::from_be_bytes(bytes.try_into().ok()?).to_string()),
+        PostgresColType::Int8 => Some(i64::from_be_bytes(bytes.try_into().ok()?).to_string()),
+        PostgresColType::Oid => Some(u32::from_be_bytes(bytes.try_into().ok()?).to_string()),
+        PostgresColType::Float4 => Some(f32::from_be_bytes(bytes.try_into().ok()?).to_string()),
+        PostgresColType::Float8 => Some(f64::from_be_bytes(bytes.try_into().ok()?).to_string()),
+        PostgresColType::Text
+        | PostgresColType::Varchar
+        | PostgresColType::Name
+        | PostgresColType::Char
+        | PostgresColType::Json => Some(String::from_utf8_lossy(bytes).to_string()),
+        // jsonb binary is a version byte, then the json text
+        PostgresColType::Jsonb => Some(String::from_utf8_lossy(bytes.get(1..)?).to_string()),
+        PostgresColType::ByteArray => Some(format!("\\x{}", hex::encode(bytes))),
+        PostgresColType::Uuid if bytes.len() == 16 => {
+            let h = hex::encode(bytes);
+            Some(format!(
+                "{}-{}-{}-{}-{}",
+                &h[0..8],
+                &h[8..12],
+                &h[12..16],
+                &h[16..20],
+                &h[20..32]
+            ))
+        }
+        PostgresColType::Date => {
+            let days = i32::from_be_bytes(bytes.try_into().ok()?);
+            match days {
+                i32::MAX => Some("infinity".to_string()),
+                i32::MIN => Some("-infinity".to_string()),
+                _ => pg_epoch()
+                    .date()
+                    .checked_add_signed(chrono::Duration::days(days.into()))
+                    .map(|d| d.to_string()),
+            }
+        }
+        PostgresColType::Time => {
+            let micros = i64::from_be_bytes(bytes.try_into().ok()?);
+            NaiveTime::from_hms(0, 0, 0)
+                .overflowing_add_signed(chrono::Duration::microseconds(micros))
+                .0
+                .to_string()
+                .into()
+        }
+        PostgresColType::Timestamp => decode_binary_timestamp(bytes).map(|d| d.to_string()),
+        PostgresColType::TimestampTz => decode_binary_timestamp(bytes).map(|d| format!("{}+00", d)),
+        PostgresColType::Interval if bytes.len() == 16 => {
+            let micros = i64::from_be_bytes(bytes[0..8].try_into().ok()?);
+            let days = i32::from_be_bytes(bytes[8..12].try_into().ok()?);
+            let months = i32::from_be_bytes(bytes[12..16].try_into().ok()?);
+            let mut parts = vec![];
+            if months / 12 != 0 {
+                parts.push(format!("{} years", months / 12));
+            }
+            if months % 12 != 0 {
+                parts.push(format!("{} mons", months % 12));
+            }
+            if days != 0 {
+                parts.push(format!("{} days", days));
+            }
+            if micros != 0 || parts.is_empty() {
+                let sign = if micros < 0 { "-" } else { "" };
+                let total_secs = micros.unsigned_abs() / 1_000_000;
+                let frac_micros = micros.unsigned_abs() % 1_000_000;
+                let mut time = format!(
+                    "{}{:02}:{:02}:{:02}",
+                    sign,
+                    total_secs / 3600,
+                    (total_secs / 60) % 60,
+                    total_secs % 60
+                );
+                if frac_micros != 0 {
+                    time.push_str(&format!(".{:06}", frac_micros));
+                }
+                parts.push(time);
+            }
+            Some(parts.join(" "))
+        }
+        PostgresColType::Inet => decode_binary_inet(bytes),
+        PostgresColType::Numeric => decode_binary_numeric(bytes),
+        PostgresColType::Array(elem_type) => decode_binary_array(*elem_type, bytes),
+        _ => None,
+    }
+}
+
+// https://github.com/postgres/postgres/blob/master/src/backend/utils/adt/network.c
+fn decode_binary_inet(bytes: &[u8]) -> Option<String> {
+    let (family, bits, addr) = (*bytes.first()?, *bytes.get(1)?, bytes.get(4..)?);
+    let (ip, max_bits): (std::net::IpAddr, u8) = match family {
+        2 => (<[u8; 4]>::try_from(addr).ok()?.into(), 32),
+        3 => (<[u8; 16]>::try_from(addr).ok()?.into(), 128),
+        _ => return None,
+    };
+    Some(if bits == max_bits {
+        ip.to_string()
+    } else {
+        format!("{}/{}", ip, bits)
+    })
+}
+
+// https://github.com/postgres/postgres/blob/master/src/backend/utils/adt/numeric.c
+// ndigits, weight, sign, dscale, then the digits in base 10000
+fn decode_binary_numeric(bytes: &[u8]) -> Option<String> {
+    let read_i16 = |idx: usize| -> Option<i16> {
+        Some(i16::from_be_bytes(
+            bytes.get(idx..idx + 2)?.try_into().ok()?,
+        ))
+    };
+    let ndigits = read_i16(0)? as usize;
+    let weight = read_i16(2)? as i32;
+    let sign = read_i16(4)? as u16;
+    let dscale = read_i16(6)? as usize;
+    match sign {
+        0xc000 => return Some("NaN".to_string()),
+        0xd000 => return Some("Infinity".to_string()),
+        0xf000 => return Some("-Infinity".to_string()),
+        _ => {}
+    }
+    let digits = (0..ndigits)
+        .map(|i| read_i16(8 + i * 2))
+        .collect::<Option<Vec<_>>>()?;
+    let mut int_part = String::new();
+    for w in 0..=weight.max(0) {
+        let digit = if w <= weight {
+            digits.get(w as usize).copied().unwrap_or(0)
+        } else {
+            0
+        };
+        if int_part.is_empty() {
+            int_part.push_str(&digit.to_string());
+        } else {
+            int_part.push_str(&format!("{:04}", digit));
+        }
+    }
+    if weight < 0 {
+        int_part = "0".to_string();
+    }
+    let mut frac_part = String::new();
+    let mut idx = weight + 1;
+    while frac_part.len() < dscale {
+        let digit = if idx >= 0 {
+            digits.get(idx as usize).copied().unwrap_or(0)
+        } else {
+            0
+        };
+        frac_part.push_str(&format!("{:04}", digit));
+        idx += 1;
+    }
+    frac_part.truncate(dscale);
+    let sign_str = if sign == 0x4000 { "-" } else { "" };
+    Some(if frac_part.is_empty() {
+        format!("{}{}", sign_str, int_part)
+    } else {
+        format!("{}{}.{}", sign_str, int_part, frac_part)
+    })
+}
+
+// https://github.com/postgres/postgres/blob/master/src/backend/utils/adt/arrayfuncs.c
+// ndim, has_nulls, element oid, (size, lower bound) for each dimension,
+// then for each element its length and contents
+fn decode_binary_array(elem_type: PostgresColType, bytes: &[u8]) -> Option<String> {
+    let read_i32 = |idx: usize| -> Option<i32> {
+        Some(i32::from_be_bytes(
+            bytes.get(idx..idx + 4)?.try_into().ok()?,
+        ))
+    };
+    let ndim = read_i32(0)? as usize;
+    if ndim == 0 {
+        return Some("{}".to_string());
+    }
+    let dims = (0..ndim)
+        .map(|d| read_i32(12 + d * 8).map(|s| s as usize))
+        .collect::<Option<Vec<_>>>()?;
+    let mut pos = 12 + ndim * 8;
+    let mut elements = vec![];
+    for _ in 0..dims.iter().product::<usize>() {
+        let len = read_i32(pos)?;
+        pos += 4;
+        if len < 0 {
+            elements.push("NULL".to_string());
+        } else {
+            let elem_bytes = bytes.get(pos..pos + len as usize)?;
+            elements.push(decode_binary(elem_type, elem_bytes)?);
+            pos += len as usize;
+        }
+    }
+    // nest the elements following the dimensions, innermost dimension last
+    let mut grouped = elements;
+    for dim_size in dims.iter().rev() {
+        grouped = grouped
+            .chunks((*dim_size).max(1))
+            .map(|c| format!("{{{}}}", c.join(",")))
+            .collect();
+    }
+    Some(grouped.join(","))
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct PostgresMessageData {
     // for prepared queries, it's possible the declaration
     // occured before we started recording the stream.
@@ -845,10 +1576,143 @@ pub struct PostgresMessageData {
     pub resultset_bool_cols: Vec<Vec<Option<bool>>>,
     pub resultset_int_cols: Vec<Vec<Option<i32>>>,
     pub resultset_bigint_cols: Vec<Vec<Option<i64>>>,
+    pub resultset_float_cols: Vec<Vec<Option<f64>>>,
     pub resultset_datetime_cols: Vec<Vec<Option<NaiveDateTime>>>,
+    pub error: Option<PostgresDiagnostic>,
+    pub notices: Vec<PostgresDiagnostic>,
+    // from the ReadyForQuery following the query
+    pub transaction_status: Option<PostgresTransactionStatus>,
+    // for a COPY, the rows preview is in the resultset
+    pub copy: Option<PostgresCopySummary>,
+    // for the login, cancel & logout messages
+    pub connection: Option<PostgresConnectionInfo>,
+}
+
+impl PostgresMessageData {
+    pub fn duration_ms(&self) -> i64 {
+        (self.result_timestamp - self.query_timestamp).num_milliseconds()
+    }
+
+    /// for a COPY, all the rows copied, not only the ones we kept
+    pub fn row_count(&self) -> usize {
+        self.copy
+            .as_ref()
+            .map_or(self.resultset_row_count, |c| c.row_count)
+    }
+
+    pub fn visit_resultset_row<D>(
+        &self,
+        row_idx: usize,
+        data: &mut D,
+        bool_value_cb: impl Fn(usize, Option<bool>, &mut D),
+        int_value_cb: impl Fn(usize, Option<i64>, &mut D),
+        string_value_cb: impl Fn(usize, Option<&str>, &mut D),
+        datetime_value_cb: impl Fn(usize, Option<NaiveDateTime>, &mut D),
+    ) {
+        let mut bool_idx = 0;
+        let mut int_idx = 0;
+        let mut datetime_idx = 0;
+        let mut bigint_idx = 0;
+        let mut float_idx = 0;
+        let mut str_idx = 0;
+        for (col_idx, col_type) in self.resultset_col_types.iter().enumerate() {
+            match col_type {
+                PostgresColType::Bool => {
+                    bool_value_cb(col_idx, self.resultset_bool_cols[bool_idx][row_idx], data);
+                    bool_idx += 1;
+                }
+                PostgresColType::Int2 | PostgresColType::Int4 => {
+                    int_value_cb(
+                        col_idx,
+                        self.resultset_int_cols[int_idx][row_idx].map(|i| i as i64),
+                        data,
+                    );
+                    int_idx += 1;
+                }
+                PostgresColType::Timestamp => {
+                    datetime_value_cb(
+                        col_idx,
+                        self.resultset_datetime_cols[datetime_idx][row_idx],
+                        data,
+                    );
+                    datetime_idx += 1;
+                }
+                PostgresColType::Int8 => {
+                    int_value_cb(
+                        col_idx,
+                        self.resultset_bigint_cols[bigint_idx][row_idx],
+                        data,
+                    );
+                    bigint_idx += 1;
+                }
+                PostgresColType::Float4 | PostgresColType::Float8 => {
+                    string_value_cb(
+                        col_idx,
+                        self.resultset_float_cols[float_idx][row_idx]
+                            .map(|f| f.to_string())
+                            .as_deref(),
+                        data,
+                    );
+                    float_idx += 1;
+                }
+                _ => {
+                    string_value_cb(
+                        col_idx,
+                        self.resultset_string_cols[str_idx][row_idx].as_deref(),
+                        data,
+                    );
+                    str_idx += 1;
+                }
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut rows = vec![];
+        for row_idx in 0..self.resultset_row_count {
+            let mut row = vec![serde_json::Value::Null; self.resultset_col_types.len()];
+            self.visit_resultset_row(
+                row_idx,
+                &mut row,
+                |col_idx, bool_val, r| r[col_idx] = serde_json::json!(bool_val),
+                |col_idx, int_val, r| r[col_idx] = serde_json::json!(int_val),
+                |col_idx, str_val, r| r[col_idx] = serde_json::json!(str_val),
+                |col_idx, datetime_val, r| {
+                    r[col_idx] = serde_json::json!(datetime_val.map(|d| d.to_string()))
+                },
+            );
+            rows.push(row);
+        }
+        serde_json::json!({
+            "query_timestamp": self.query_timestamp.to_string(),
+            "result_timestamp": self.result_timestamp.to_string(),
+            "query": self.query,
+            "parameter_values": self.parameter_values
+                .iter()
+                .map(|(_, v)| v)
+                .collect::<Vec<_>>(),
+            "resultset_col_names": self.resultset_col_names,
+            "resultset_rows": rows,
+            "error": self.error.as_ref().map(|e| e.to_string()),
+            "notices": self.notices.iter().map(|n| n.to_string()).collect::<Vec<_>>(),
+            "connection": self.connection.as_ref().map(|c| serde_json::json!({
+                "auth_method": c.auth_method.as_ref().map(|m| m.to_string()),
+                "authenticated": c.authenticated,
+                "ssl_requested": c.ssl_requested,
+                "parameters": c.parameters.iter().cloned().collect::<HashMap<_, _>>(),
+                "backend_pid": c.backend_key.map(|(pid, _)| pid),
+            })),
+            "copy": self.copy.as_ref().map(|c| serde_json::json!({
+                "from_client": c.direction == PostgresCopyDirection::FromClient,
+                "binary": c.binary,
+                "row_count": c.row_count,
+                "byte_count": c.byte_count,
+            })),
+        })
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[derive(Clone, Debug, PartialEq, Default)]
 pub struct PostgresStreamGlobals {
     known_statements: HashMap<String, String>,
     cur_query: Option<String>,
@@ -862,9 +1726,14 @@ pub struct PostgresStreamGlobals {
     cur_parameter_values: Vec<(PostgresColType, String)>,
     cur_rs_int_cols: Vec<Vec<Option<i32>>>,
     cur_rs_bigint_cols: Vec<Vec<Option<i64>>>,
+    cur_rs_float_cols: Vec<Vec<Option<f64>>>,
     cur_rs_bool_cols: Vec<Vec<Option<bool>>>,
     cur_rs_string_cols: Vec<Vec<Option<String>>>,
     cur_rs_datetime_cols: Vec<Vec<Option<NaiveDateTime>>>,
+    cur_error: Option<PostgresDiagnostic>,
+    cur_notices: Vec<PostgresDiagnostic>,
+    cur_copy: Option<PostgresCopyInProgress>,
+    cur_command_tag: Option<String>,
 }
 
 #[cfg(test)]
@@ -923,6 +1792,12 @@ fn should_parse_simple_query() {
             Some("PostgreSQL".to_string()),
             Some("9.6.12 on x8".to_string()),
         ]],
+        error: None,
+        notices: vec![],
+        transaction_status: None,
+        copy: None,
+        connection: None,
+        resultset_float_cols: vec![],
     }];
     assert_eq!(expected, parsed);
 }
@@ -974,6 +1849,12 @@ fn should_parse_prepared_statement() {
             resultset_datetime_cols: vec![],
             resultset_bool_cols: vec![],
             resultset_string_cols: vec![],
+            error: None,
+            notices: vec![],
+            transaction_status: None,
+            copy: None,
+            connection: None,
+            resultset_float_cols: vec![],
         },
         PostgresMessageData {
             query_timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms_nano(8, 49, 52, 736275000),
@@ -988,6 +1869,12 @@ fn should_parse_prepared_statement() {
             resultset_datetime_cols: vec![],
             resultset_bool_cols: vec![],
             resultset_string_cols: vec![vec![Some("PostgreSQL".to_string())]],
+            error: None,
+            notices: vec![],
+            transaction_status: None,
+            copy: None,
+            connection: None,
+            resultset_float_cols: vec![],
         },
     ];
     assert_eq!(expected, parsed);
@@ -1052,6 +1939,12 @@ fn should_parse_prepared_statement_with_parameters() {
             resultset_datetime_cols: vec![],
             resultset_bool_cols: vec![],
             resultset_string_cols: vec![],
+            error: None,
+            notices: vec![],
+            transaction_status: None,
+            copy: None,
+            connection: None,
+            resultset_float_cols: vec![],
         },
         PostgresMessageData {
             query_timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms_nano(8, 49, 52, 736275000),
@@ -1070,6 +1963,12 @@ fn should_parse_prepared_statement_with_parameters() {
             resultset_datetime_cols: vec![],
             resultset_bool_cols: vec![],
             resultset_string_cols: vec![vec![Some("PostgreSQL".to_string())]],
+            error: None,
+            notices: vec![],
+            transaction_status: None,
+            copy: None,
+            connection: None,
+            resultset_float_cols: vec![],
         },
     ];
     assert_eq!(expected, parsed);
@@ -1148,6 +2047,12 @@ fn should_parse_query_with_multiple_columns_and_nulls() {
         resultset_datetime_cols: vec![],
         resultset_bool_cols: vec![],
         resultset_int_cols: vec![vec![Some(26)]],
+        error: None,
+        notices: vec![],
+        transaction_status: None,
+        copy: None,
+        connection: None,
+        resultset_float_cols: vec![],
     }];
     assert_eq!(expected, parsed);
 }
@@ -1254,6 +2159,12 @@ fn should_parse_query_with_no_parse_and_unknown_bind() {
             vec![Some("GENERAL".to_string())],
             vec![Some("APPLICATION_TIMEZONE".to_string())],
         ],
+        error: None,
+        notices: vec![],
+        transaction_status: None,
+        copy: None,
+        connection: None,
+        resultset_float_cols: vec![],
     }];
     assert_eq!(expected, parsed);
 }
@@ -1266,3 +2177,274 @@ fn decode_23() {
         decode_integer_as_str::<i32>(PostgresColType::Int4, "3233")
     );
 }
+
+#[test]
+fn should_decode_extended_types_in_text_and_binary_format() {
+    let decode = |typ, hex| hex_chars_to_bytes(hex).and_then(|b| decode_text_or_binary(typ, &b));
+    assert_eq!(
+        Some("1.5".to_string()),
+        decode(PostgresColType::Float8, "3f:f8:00:00:00:00:00:00")
+    );
+    assert_eq!(
+        Some("1.5".to_string()),
+        decode(PostgresColType::Float8, "31:2e:35")
+    );
+    assert_eq!(
+        Some("123.45".to_string()),
+        decode(
+            PostgresColType::Numeric,
+            "00:02:00:00:00:00:00:02:00:7b:11:94"
+        )
+    );
+    assert_eq!(
+        Some("2024-01-02".to_string()),
+        decode(PostgresColType::Date, "32:30:32:34:2d:30:31:2d:30:32")
+    );
+    assert_eq!(
+        Some("2000-01-02".to_string()),
+        decode(PostgresColType::Date, "00:00:00:01")
+    );
+    assert_eq!(
+        Some("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11".to_string()),
+        decode(
+            PostgresColType::Uuid,
+            "a0:ee:bc:99:9c:0b:4e:f8:bb:6d:6b:b9:bd:38:0a:11"
+        )
+    );
+    assert_eq!(
+        Some("{\"a\":1}".to_string()),
+        decode(PostgresColType::Jsonb, "01:7b:22:61:22:3a:31:7d")
+    );
+    assert_eq!(
+        Some("-2562047788:00:54.775808".to_string()),
+        decode(
+            PostgresColType::Interval,
+            "80:00:00:00:00:00:00:00:00:00:00:00:00:00:00:00"
+        )
+    );
+    assert_eq!(
+        Some("{1,2}".to_string()),
+        decode(
+            PostgresColType::Array(&PostgresColType::Int4),
+            "00:00:00:01:00:00:00:00:00:00:00:17:00:00:00:02:00:00:00:01:\
+             00:00:00:04:00:00:00:01:00:00:00:04:00:00:00:02"
+        )
+    );
+}
+
+#[test]
+fn should_parse_error_response() {
+    let parsed = tests_parse_stream(parse_test_xml(
+        r#"
+  <proto name="pgsql" showname="PostgreSQL" size="25" pos="66">
+    <field name="pgsql.type" showname="Type: Parse" size="1" pos="66" show="Parse" value="50"/>
+    <field name="pgsql.query" show="select * from missing" />
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="101" pos="66">
+    <field name="pgsql.type" showname="Type: Error" size="1" pos="66" show="Error" value="45"/>
+    <field name="pgsql.length" showname="Length: 100" size="4" pos="67" show="100" value="00000064"/>
+    <field name="pgsql.severity" showname="Severity: ERROR" size="6" pos="72" show="ERROR" value="4552524f5200"/>
+    <field name="pgsql.code" showname="Code: 42P01" size="6" pos="85" show="42P01" value="343250303100"/>
+    <field name="pgsql.message" showname="Message: relation &quot;missing&quot; does not exist" size="35" pos="92" show="relation &quot;missing&quot; does not exist" value=""/>
+    <field name="pgsql.position" showname="Position: 15" size="3" pos="128" show="15" value="313500"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="239">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="239" show="Ready for query" value="5a"/>
+  </proto>
+        "#,
+    ))
+    .unwrap();
+    assert_eq!(1, parsed.len());
+    assert_eq!(
+        Some(Cow::Borrowed("select * from missing")),
+        parsed[0].query
+    );
+    assert_eq!(
+        Some(PostgresDiagnostic {
+            severity: Some("ERROR".to_string()),
+            code: Some("42P01".to_string()),
+            message: Some("relation \"missing\" does not exist".to_string()),
+            detail: None,
+            hint: None,
+            position: Some(15),
+        }),
+        parsed[0].error
+    );
+}
+
+#[test]
+fn should_parse_simple_queries_and_transaction_status() {
+    let mut parser = PostgresStreamsStore::default();
+    let sid = common_tests_parse_stream(
+        &mut parser,
+        parse_test_xml(
+            r#"
+  <proto name="pgsql" showname="PostgreSQL" size="11" pos="66">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="66" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: BEGIN" size="6" pos="71" show="BEGIN" value="424547494e00"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="239">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="239" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: In a transaction (84)" size="1" pos="244" show="84" value="54"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="12" pos="66">
+    <field name="pgsql.type" showname="Type: Simple query" size="1" pos="66" show="Simple query" value="51"/>
+    <field name="pgsql.query" showname="Query: COMMIT" size="7" pos="71" show="COMMIT" value="434f4d4d495400"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="239">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="239" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Idle (73)" size="1" pos="244" show="73" value="49"/>
+  </proto>
+        "#,
+        ),
+    )
+    .unwrap();
+    let stream = parser.streams.get(&sid).unwrap();
+    assert_eq!(
+        vec![
+            (
+                Some(Cow::Borrowed("BEGIN")),
+                Some(PostgresTransactionStatus::InTransaction)
+            ),
+            (
+                Some(Cow::Borrowed("COMMIT")),
+                Some(PostgresTransactionStatus::Idle)
+            ),
+        ],
+        stream
+            .messages
+            .iter()
+            .map(|m| (m.query.clone(), m.transaction_status))
+            .collect::<Vec<_>>()
+    );
+    let (_, tx) = stream.transactions.transaction_of(1).unwrap();
+    assert_eq!((0, 1), (tx.first_msg_idx, tx.last_msg_idx));
+    assert_eq!(PostgresTransactionOutcome::Committed, tx.outcome);
+}
+
+#[test]
+fn should_dump_resultset_to_json() {
+    let msg = PostgresMessageData {
+        query_timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms(8, 49, 52),
+        result_timestamp: NaiveDate::from_ymd(2021, 3, 5).and_hms(8, 49, 53),
+        query: Some(Cow::Borrowed("select id, name from users")),
+        parameter_values: vec![],
+        resultset_col_names: vec!["id".to_string(), "name".to_string()],
+        resultset_row_count: 2,
+        resultset_col_types: vec![PostgresColType::Int4, PostgresColType::Text],
+        resultset_string_cols: vec![vec![Some("joe".to_string()), None]],
+        resultset_bigint_cols: vec![],
+        resultset_float_cols: vec![],
+        resultset_datetime_cols: vec![],
+        resultset_bool_cols: vec![],
+        resultset_int_cols: vec![vec![Some(1), Some(2)]],
+        error: None,
+        notices: vec![],
+        transaction_status: None,
+        copy: None,
+        connection: None,
+    };
+    let json = msg.to_json();
+    assert_eq!(
+        serde_json::json!([[1, "joe"], [2, null]]),
+        json["resultset_rows"]
+    );
+    assert_eq!(
+        serde_json::json!("select id, name from users"),
+        json["query"]
+    );
+}
+
+#[test]
+fn should_parse_the_connection_lifecycle() {
+    let parsed = tests_parse_stream(parse_test_xml(
+        r#"
+  <proto name="pgsql" showname="PostgreSQL" size="8" pos="66">
+    <field name="pgsql.type" showname="Type: SSL request" size="0" pos="66" show="SSL request" value=""/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="41" pos="66">
+    <field name="pgsql.type" showname="Type: Startup message" size="0" pos="66" show="Startup message" value=""/>
+    <field name="pgsql.parameter_name" showname="Parameter name: user" size="5" pos="74" show="user" value="7573657200"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: bob" size="4" pos="79" show="bob" value="626f6200"/>
+    <field name="pgsql.parameter_name" showname="Parameter name: database" size="9" pos="83" show="database" value="646174616261736500"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: shop" size="5" pos="92" show="shop" value="73686f7000"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="13" pos="66">
+    <field name="pgsql.type" showname="Type: Authentication request" size="1" pos="66" show="Authentication request" value="52"/>
+    <field name="pgsql.authtype" showname="Authentication type: MD5 password (5)" size="4" pos="71" show="5" value="00000005"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="9" pos="66">
+    <field name="pgsql.type" showname="Type: Authentication request" size="1" pos="66" show="Authentication request" value="52"/>
+    <field name="pgsql.authtype" showname="Authentication type: Success (0)" size="4" pos="71" show="0" value="00000000"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="26" pos="75">
+    <field name="pgsql.type" showname="Type: Parameter status" size="1" pos="75" show="Parameter status" value="53"/>
+    <field name="pgsql.parameter_name" showname="Parameter name: server_version" size="15" pos="80" show="server_version" value=""/>
+    <field name="pgsql.parameter_value" showname="Parameter value: 13.4" size="5" pos="95" show="13.4" value=""/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="13" pos="101">
+    <field name="pgsql.type" showname="Type: Backend key data" size="1" pos="101" show="Backend key data" value="4b"/>
+    <field name="pgsql.pid" showname="PID: 4242" size="4" pos="106" show="4242" value="00001092"/>
+    <field name="pgsql.key" showname="Key: 12345" size="4" pos="110" show="12345" value="00003039"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="6" pos="114">
+    <field name="pgsql.type" showname="Type: Ready for query" size="1" pos="114" show="Ready for query" value="5a"/>
+    <field name="pgsql.status" showname="Status: Idle (73)" size="1" pos="119" show="73" value="49"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="5" pos="66">
+    <field name="pgsql.type" showname="Type: Termination" size="1" pos="66" show="Termination" value="58"/>
+  </proto>
+        "#,
+    ))
+    .unwrap();
+    assert_eq!(
+        vec![
+            Some(Cow::Borrowed(
+                "LOGIN: user: bob, db: shop, app: -, auth: md5"
+            )),
+            Some(Cow::Borrowed("LOGOUT"))
+        ],
+        parsed.iter().map(|m| m.query.clone()).collect::<Vec<_>>()
+    );
+    let connection = parsed[0].connection.as_ref().unwrap();
+    assert!(connection.ssl_requested && connection.authenticated);
+    assert_eq!(Some("13.4"), connection.parameter("server_version"));
+    assert_eq!(Some((4242, 12345)), connection.backend_key);
+}
+
+#[test]
+fn should_report_a_failed_login() {
+    let parsed = tests_parse_stream(parse_test_xml(
+        r#"
+  <proto name="pgsql" showname="PostgreSQL" size="41" pos="66">
+    <field name="pgsql.type" showname="Type: Startup message" size="0" pos="66" show="Startup message" value=""/>
+    <field name="pgsql.parameter_name" showname="Parameter name: user" size="5" pos="74" show="user" value="7573657200"/>
+    <field name="pgsql.parameter_value" showname="Parameter value: bob" size="4" pos="79" show="bob" value="626f6200"/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="32" pos="66">
+    <field name="pgsql.type" showname="Type: Authentication request" size="1" pos="66" show="Authentication request" value="52"/>
+    <field name="pgsql.authtype" showname="Authentication type: SASL (10)" size="4" pos="71" show="10" value="0000000a"/>
+    <field name="pgsql.auth.sasl.mech" showname="SASL authentication mechanism: SCRAM-SHA-256" size="14" pos="75" show="SCRAM-SHA-256" value=""/>
+  </proto>
+  <proto name="pgsql" showname="PostgreSQL" size="101" pos="66">
+    <field name="pgsql.type" showname="Type: Error" size="1" pos="66" show="Error" value="45"/>
+    <field name="pgsql.severity" showname="Severity: FATAL" size="6" pos="72" show="FATAL" value=""/>
+    <field name="pgsql.code" showname="Code: 28P01" size="6" pos="85" show="28P01" value=""/>
+    <field name="pgsql.message" showname="Message: password authentication failed for user &quot;bob&quot;" size="35" pos="92" show="password authentication failed for user &quot;bob&quot;" value=""/>
+  </proto>
+        "#,
+    ))
+    .unwrap();
+    assert_eq!(1, parsed.len());
+    assert_eq!(
+        Some(Cow::Borrowed(
+            "LOGIN: user: bob, db: -, app: -, auth: SCRAM-SHA-256"
+        )),
+        parsed[0].query
+    );
+    assert_eq!(
+        Some("28P01"),
+        parsed[0].error.as_ref().and_then(|e| e.code.as_deref())
+    );
+    assert!(!parsed[0].connection.as_ref().unwrap().authenticated);
+}
@@ -0,0 +1,403 @@
+// per-query statistics, for the statistics window, like pg_stat_statements
+// but for the captured traffic. The queries are grouped once normalized:
+// the literals and the $n parameters are replaced by ?.
+use super::postgres_streams_store::PostgresMessageData;
+use crate::custom_streams_store::{StatsCell, StatsRow, StatsTable};
+use std::collections::{HashMap, HashSet};
+
+/// the same query run that many times in a row, with different
+/// parameters, is probably an N+1 pattern: a query per item of a list
+const N_PLUS_ONE_MIN_RUN: usize = 5;
+
+// comments & whitespace, that the normalization drops
+const QUERY_GAP_PATTERN: &str = r"(?:\s|--[^\n]*|/\*.*?\*/)*";
+
+lazy_static! {
+    // "IN (?, ?, ?)" => "IN (?)", so that the list length doesn't matter
+    static ref IN_LIST: regex::Regex =
+        regex::Regex::new(r"(?i)\b(in)\s?\(\s?\?(?:\s?,\s?\?)*\s?\)").unwrap();
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// the index after the closing quote
+fn skip_quoted(chars: &[char], start: usize, quote: char, backslash_escapes: bool) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if backslash_escapes => i += 2,
+            // a doubled quote is an escaped quote
+            c if c == quote && chars.get(i + 1) == Some(&quote) => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// the index after the closing tag of a dollar-quoted string like
+/// $body$...$body$, None if that's not a dollar-quoted string
+fn skip_dollar_quoted(chars: &[char], start: usize) -> Option<usize> {
+    let tag_len = chars[start + 1..]
+        .iter()
+        .position(|c| *c == '$' || !is_identifier_char(*c))?;
+    if chars.get(start + 1 + tag_len) != Some(&'$') {
+        return None;
+    }
+    let tag = &chars[start..start + tag_len + 2];
+    let body_start = start + tag.len();
+    Some(
+        chars[body_start..]
+            .windows(tag.len())
+            .position(|w| w == tag)
+            .map_or(chars.len(), |p| body_start + p + tag.len()),
+    )
+}
+
+/// "select * from users where id=$1 and name = 'bob'"
+/// => "select * from users where id=? and name = ?"
+pub fn normalize_query(query: &str) -> String {
+    let chars: Vec<char> = query.chars().collect();
+    let mut result = String::with_capacity(query.len());
+    let mut pending_space = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let token_start = i;
+        let dollar_quoted_end = if c == '$' {
+            skip_dollar_quoted(&chars, i)
+        } else {
+            None
+        };
+        let is_literal = if c.is_whitespace() {
+            pending_space = true;
+            i += 1;
+            continue;
+        } else if c == '-' && next == Some('-') {
+            i = chars[i..]
+                .iter()
+                .position(|c| *c == '\n')
+                .map_or(chars.len(), |p| i + p);
+            pending_space = true;
+            continue;
+        } else if c == '/' && next == Some('*') {
+            i = chars[i + 2..]
+                .windows(2)
+                .position(|w| w == ['*', '/'])
+                .map_or(chars.len(), |p| i + 2 + p + 2);
+            pending_space = true;
+            continue;
+        } else if c == '\'' {
+            i = skip_quoted(&chars, i, '\'', false);
+            true
+        } else if matches!(c, 'e' | 'E' | 'b' | 'B' | 'x' | 'X') && next == Some('\'') {
+            // escape, bit & hex strings
+            i = skip_quoted(&chars, i + 1, '\'', c == 'e' || c == 'E');
+            true
+        } else if c == '"' {
+            // a quoted identifier
+            i = skip_quoted(&chars, i, '"', false);
+            false
+        } else if c == '$' && next.map_or(false, |n| n.is_ascii_digit()) {
+            i += 1;
+            while chars.get(i).map_or(false, |c| c.is_ascii_digit()) {
+                i += 1;
+            }
+            true
+        } else if let Some(end) = dollar_quoted_end {
+            i = end;
+            true
+        } else if c.is_ascii_digit() || (c == '.' && next.map_or(false, |n| n.is_ascii_digit())) {
+            while let Some(&n) = chars.get(i) {
+                let is_exponent_sign =
+                    (n == '+' || n == '-') && i > token_start && matches!(chars[i - 1], 'e' | 'E');
+                if n.is_ascii_digit() || n == '.' || n == 'e' || n == 'E' || is_exponent_sign {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            true
+        } else if is_identifier_char(c) {
+            while chars.get(i).map_or(false, |c| is_identifier_char(*c)) {
+                i += 1;
+            }
+            false
+        } else {
+            i += 1;
+            false
+        };
+        if pending_space && !result.is_empty() {
+            result.push(' ');
+        }
+        pending_space = false;
+        if is_literal {
+            result.push('?');
+        } else {
+            result.extend(&chars[token_start..i]);
+        }
+    }
+    IN_LIST.replace_all(&result, "$1 (?)").to_string()
+}
+
+/// a search expression matching the queries which normalize to that query
+pub fn query_search_filter(normalized_query: &str) -> String {
+    let mut regex = format!("(?s)^{}", QUERY_GAP_PATTERN);
+    for c in normalized_query.chars() {
+        match c {
+            ' ' => regex.push_str(r"\s*"),
+            '?' => regex.push_str(".+?"),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push_str(QUERY_GAP_PATTERN);
+    regex.push('$');
+    format!("pg.query matches /{}/", regex.replace('/', "\\/"))
+}
+
+#[derive(Default)]
+struct QueryStats {
+    count: u64,
+    total_duration_ms: u64,
+    max_duration_ms: u64,
+    total_rows: u64,
+    n_plus_one_runs: u64,
+    longest_n_plus_one_run: u64,
+}
+
+impl QueryStats {
+    fn cells(&self, normalized_query: &str) -> Vec<StatsCell> {
+        let duration_cell = |d: u64| StatsCell::Number(d, format!("{} ms", d));
+        let avg_duration_ms = self.total_duration_ms / self.count.max(1);
+        vec![
+            StatsCell::Text(normalized_query.to_string()),
+            StatsCell::Number(self.count, self.count.to_string()),
+            duration_cell(self.total_duration_ms),
+            duration_cell(avg_duration_ms),
+            duration_cell(self.max_duration_ms),
+            StatsCell::Number(self.total_rows, self.total_rows.to_string()),
+            StatsCell::Number(
+                self.longest_n_plus_one_run,
+                match self.n_plus_one_runs {
+                    0 => "".to_string(),
+                    1 => format!("{} in a row", self.longest_n_plus_one_run),
+                    n => format!("{} runs, up to {} in a row", n, self.longest_n_plus_one_run),
+                },
+            ),
+        ]
+    }
+}
+
+/// consecutive executions of the same normalized query on a stream
+#[derive(Default)]
+struct QueryRun<'a> {
+    normalized_query: String,
+    length: usize,
+    // the query & its parameter values: for simple queries,
+    // the parameters are literals in the query
+    distinct_executions: HashSet<(&'a str, Vec<&'a str>)>,
+}
+
+impl<'a> QueryRun<'a> {
+    fn end(&mut self, by_query: &mut HashMap<String, QueryStats>) {
+        if self.length >= N_PLUS_ONE_MIN_RUN && self.distinct_executions.len() > 1 {
+            if let Some(stats) = by_query.get_mut(&self.normalized_query) {
+                stats.n_plus_one_runs += 1;
+                stats.longest_n_plus_one_run = stats.longest_n_plus_one_run.max(self.length as u64);
+            }
+        }
+        *self = QueryRun::default();
+    }
+}
+
+/// the statistics of the messages of these streams (one slice per stream),
+/// grouped by normalized query, the longest total duration first
+pub fn query_stats<'a>(streams: impl Iterator<Item = &'a [PostgresMessageData]>) -> StatsTable {
+    let mut by_query: HashMap<String, QueryStats> = HashMap::new();
+    for messages in streams {
+        let mut run = QueryRun::default();
+        for message in messages {
+            // the login, cancel & logout messages are not queries, and
+            // shouldn't interrupt a run either
+            if message.connection.is_some() {
+                continue;
+            }
+            // without the query string (prepared before the capture
+            // started), we can't group the query
+            let query = match message.query.as_deref() {
+                Some(q) => q,
+                None => {
+                    run.end(&mut by_query);
+                    continue;
+                }
+            };
+            let normalized_query = normalize_query(query);
+            let duration_ms = message.duration_ms().max(0) as u64;
+            let stats = by_query.entry(normalized_query.clone()).or_default();
+            stats.count += 1;
+            stats.total_duration_ms += duration_ms;
+            stats.max_duration_ms = stats.max_duration_ms.max(duration_ms);
+            stats.total_rows += message.row_count() as u64;
+
+            if normalized_query != run.normalized_query {
+                run.end(&mut by_query);
+                run.normalized_query = normalized_query;
+            }
+            run.length += 1;
+            run.distinct_executions.insert((
+                query,
+                message
+                    .parameter_values
+                    .iter()
+                    .map(|(_type, v)| v.as_str())
+                    .collect(),
+            ));
+        }
+        run.end(&mut by_query);
+    }
+    let mut queries: Vec<_> = by_query.into_iter().collect();
+    queries.sort_by(|(q1, s1), (q2, s2)| {
+        s2.total_duration_ms
+            .cmp(&s1.total_duration_ms)
+            .then_with(|| q1.cmp(q2))
+    });
+    StatsTable {
+        column_titles: vec![
+            "Query",
+            "Count",
+            "Total duration",
+            "Avg duration",
+            "Max duration",
+            "Total rows",
+            "N+1",
+        ],
+        rows: queries
+            .into_iter()
+            .map(|(normalized_query, stats)| StatsRow {
+                search_filter: query_search_filter(&normalized_query),
+                cells: stats.cells(&normalized_query),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgsql::tshark_pgsql::PostgresColType;
+    use crate::search_expr::{self, SearchExpr, SearchOpExpr};
+    use chrono::NaiveDate;
+    use std::borrow::Cow;
+    use std::collections::BTreeSet;
+
+    fn message(query: &str, params: &[&str], duration_ms: i64) -> PostgresMessageData {
+        let query_timestamp = NaiveDate::from_ymd(2021, 3, 5).and_hms(8, 49, 52);
+        PostgresMessageData {
+            query_timestamp,
+            result_timestamp: query_timestamp + chrono::Duration::milliseconds(duration_ms),
+            query: Some(Cow::Owned(query.to_string())),
+            parameter_values: params
+                .iter()
+                .map(|p| (PostgresColType::Int4, p.to_string()))
+                .collect(),
+            resultset_col_names: vec![],
+            resultset_row_count: 1,
+            resultset_col_types: vec![],
+            resultset_string_cols: vec![],
+            resultset_bool_cols: vec![],
+            resultset_int_cols: vec![],
+            resultset_bigint_cols: vec![],
+            resultset_float_cols: vec![],
+            resultset_datetime_cols: vec![],
+            error: None,
+            notices: vec![],
+            transaction_status: None,
+            copy: None,
+            connection: None,
+        }
+    }
+
+    fn cell_text(cell: &StatsCell) -> &str {
+        match cell {
+            StatsCell::Text(t) => t,
+            StatsCell::Number(_, t) => t,
+        }
+    }
+
+    #[test]
+    fn should_normalize_queries() {
+        assert_eq!(
+            "select * from users2 where id=? and name = ? and tags in (?) and x > -?",
+            normalize_query(
+                "select * from users2\n  where id=$1 and name = 'o''brien' -- comment\n and tags in ($2, $3,$4) and x > -1.5e+3"
+            )
+        );
+        assert_eq!(
+            "insert into \"t 1\" values (?, ?, ?)",
+            normalize_query("insert into \"t 1\" /* c */ values (E'a\\'b', $$x'y$$, $tag$z$tag$)")
+        );
+    }
+
+    #[test]
+    fn should_match_the_queries_with_the_filter() {
+        let filter = query_search_filter(&normalize_query(
+            "select * from t where id = $1 and a in ($2, $3)",
+        ));
+        let string_keys: BTreeSet<&'static str> = ["pg.query"].into_iter().collect();
+        let numeric_keys = BTreeSet::new();
+        let (rest, expr) = search_expr::parse_search(&string_keys, &numeric_keys)(&filter).unwrap();
+        assert_eq!("", rest);
+        let op = match expr {
+            SearchExpr::SearchOpExpr(SearchOpExpr { op, .. }) => op,
+            _ => panic!("expected a single filter: {}", filter),
+        };
+        assert!(op.matches_str("select * from t where id = $1 and a in ($2, $3)"));
+        assert!(op.matches_str("/* app */ select *\nfrom t where id = 12 and a in (1,2,3)"));
+        assert!(!op.matches_str("select * from t where id = 1 and a in (1) and b = 2"));
+    }
+
+    #[test]
+    fn should_aggregate_queries_and_detect_n_plus_one() {
+        let mut messages = vec![message("select * from orders", &[], 10)];
+        for i in 0..6 {
+            messages.push(message(
+                "select * from items where order_id = $1",
+                &[&i.to_string()],
+                2,
+            ));
+        }
+        // a cancel request from another connection in the middle of the run
+        messages.insert(
+            3,
+            PostgresMessageData {
+                connection: Some(Default::default()),
+                ..message("CANCEL: backend pid 4242", &[], 0)
+            },
+        );
+        // the same parameters every time: not an N+1
+        for _ in 0..6 {
+            messages.push(message("select now()", &[], 1));
+        }
+        let table = query_stats(vec![&messages[..]].into_iter());
+        assert_eq!(3, table.rows.len());
+        let texts: Vec<_> = table.rows[0].cells.iter().map(cell_text).collect();
+        assert_eq!(
+            vec![
+                "select * from items where order_id = ?",
+                "6",
+                "12 ms",
+                "2 ms",
+                "2 ms",
+                "6",
+                "6 in a row"
+            ],
+            texts
+        );
+        assert_eq!("select * from orders", cell_text(&table.rows[1].cells[0]));
+        assert_eq!("select now()", cell_text(&table.rows[2].cells[0]));
+        assert_eq!("", cell_text(&table.rows[2].cells[6]));
+    }
+}
@@ -0,0 +1,223 @@
+// grouping of the messages of a stream in transactions. We rely on the
+// transaction status from the ReadyForQuery messages, and fall back on
+// the BEGIN/COMMIT/ROLLBACK queries when tshark doesn't give us the status.
+use super::postgres_streams_store::PostgresMessageData;
+use super::tshark_pgsql::PostgresTransactionStatus;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PostgresTransactionOutcome {
+    // still running, or the capture stopped before the end
+    InProgress,
+    Committed,
+    RolledBack,
+}
+
+/// the messages from the BEGIN until the transaction status is
+/// back to idle, both included
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostgresTransaction {
+    pub first_msg_idx: usize,
+    pub last_msg_idx: usize,
+    // a statement failed in the transaction, so it was rolled back
+    pub failed: bool,
+    pub outcome: PostgresTransactionOutcome,
+}
+
+impl PostgresTransaction {
+    pub fn message_count(&self) -> usize {
+        self.last_msg_idx - self.first_msg_idx + 1
+    }
+}
+
+#[derive(Default)]
+pub struct PostgresTransactions {
+    // the status after the last message
+    status: Option<PostgresTransactionStatus>,
+    transactions: Vec<PostgresTransaction>,
+}
+
+fn first_words_lower(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .take(2)
+        .map(|w| w.trim_end_matches(';').to_ascii_lowercase())
+        .collect()
+}
+
+fn is_rollback(query: Option<&str>) -> bool {
+    match first_words_lower(query.unwrap_or("")).as_slice() {
+        // "rollback to savepoint" doesn't end the transaction
+        [first, second] if first == "rollback" => second != "to",
+        [first, ..] => first == "rollback" || first == "abort",
+        _ => false,
+    }
+}
+
+/// guess the status after that message from its query
+fn status_from_query(
+    message: &PostgresMessageData,
+    before: PostgresTransactionStatus,
+) -> PostgresTransactionStatus {
+    let words = first_words_lower(message.query.as_deref().unwrap_or(""));
+    let first_word = words.first().map(|w| w.as_str());
+    if first_word == Some("begin") || words == ["start", "transaction"] {
+        PostgresTransactionStatus::InTransaction
+    } else if matches!(first_word, Some("commit") | Some("end"))
+        || is_rollback(message.query.as_deref())
+    {
+        PostgresTransactionStatus::Idle
+    } else if message.error.is_some() && before != PostgresTransactionStatus::Idle {
+        PostgresTransactionStatus::Failed
+    } else {
+        before
+    }
+}
+
+impl PostgresTransactions {
+    /// must be called for every message of the stream, in order
+    pub fn add_message(&mut self, msg_idx: usize, message: &PostgresMessageData) {
+        let before = self.status.unwrap_or(PostgresTransactionStatus::Idle);
+        let after = message
+            .transaction_status
+            .unwrap_or_else(|| status_from_query(message, before));
+        if before != PostgresTransactionStatus::Idle || after != PostgresTransactionStatus::Idle {
+            let in_progress = self
+                .transactions
+                .last()
+                .filter(|t| t.outcome == PostgresTransactionOutcome::InProgress)
+                .is_some();
+            if before == PostgresTransactionStatus::Idle || !in_progress {
+                self.transactions.push(PostgresTransaction {
+                    first_msg_idx: msg_idx,
+                    last_msg_idx: msg_idx,
+                    failed: false,
+                    outcome: PostgresTransactionOutcome::InProgress,
+                });
+            }
+            let transaction = self.transactions.last_mut().unwrap();
+            transaction.last_msg_idx = msg_idx;
+            transaction.failed |= after == PostgresTransactionStatus::Failed;
+            if after == PostgresTransactionStatus::Idle {
+                // COMMIT on a failed transaction also rolls it back
+                transaction.outcome = if transaction.failed || is_rollback(message.query.as_deref())
+                {
+                    PostgresTransactionOutcome::RolledBack
+                } else {
+                    PostgresTransactionOutcome::Committed
+                };
+            }
+        }
+        self.status = Some(after);
+    }
+
+    /// the index and the transaction containing that message, if any
+    pub fn transaction_of(&self, msg_idx: usize) -> Option<(usize, &PostgresTransaction)> {
+        let idx = self
+            .transactions
+            .partition_point(|t| t.first_msg_idx <= msg_idx)
+            .checked_sub(1)?;
+        Some((idx, &self.transactions[idx])).filter(|(_, t)| t.last_msg_idx >= msg_idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgsql::tshark_pgsql::PostgresDiagnostic;
+    use chrono::NaiveDate;
+    use std::borrow::Cow;
+
+    fn message(
+        query: &str,
+        status: Option<PostgresTransactionStatus>,
+        error: bool,
+    ) -> PostgresMessageData {
+        let timestamp = NaiveDate::from_ymd(2021, 3, 5).and_hms(8, 49, 52);
+        PostgresMessageData {
+            query_timestamp: timestamp,
+            result_timestamp: timestamp,
+            query: Some(Cow::Owned(query.to_string())),
+            parameter_values: vec![],
+            resultset_col_names: vec![],
+            resultset_row_count: 0,
+            resultset_col_types: vec![],
+            resultset_string_cols: vec![],
+            resultset_bool_cols: vec![],
+            resultset_int_cols: vec![],
+            resultset_bigint_cols: vec![],
+            resultset_float_cols: vec![],
+            resultset_datetime_cols: vec![],
+            error: Some(PostgresDiagnostic::default()).filter(|_| error),
+            notices: vec![],
+            transaction_status: status,
+            copy: None,
+            connection: None,
+        }
+    }
+
+    fn transactions(messages: &[PostgresMessageData]) -> PostgresTransactions {
+        let mut transactions = PostgresTransactions::default();
+        for (idx, message) in messages.iter().enumerate() {
+            transactions.add_message(idx, message);
+        }
+        transactions
+    }
+
+    #[test]
+    fn should_group_transactions_from_the_status() {
+        use PostgresTransactionStatus::*;
+        let txs = transactions(&[
+            message("select 1", Some(Idle), false),
+            message("BEGIN", Some(InTransaction), false),
+            message("update a set b=1", Some(InTransaction), false),
+            message("COMMIT", Some(Idle), false),
+            message("begin", Some(InTransaction), false),
+            message("insert into missing values (1)", Some(Failed), true),
+            message("select 2", Some(Failed), true),
+            message("commit", Some(Idle), false),
+            message("begin", Some(InTransaction), false),
+            message("select 3", Some(InTransaction), false),
+        ]);
+        assert_eq!(None, txs.transaction_of(0));
+        let (idx, tx) = txs.transaction_of(2).unwrap();
+        assert_eq!(0, idx);
+        assert_eq!(
+            (1, 3, false),
+            (tx.first_msg_idx, tx.last_msg_idx, tx.failed)
+        );
+        assert_eq!(PostgresTransactionOutcome::Committed, tx.outcome);
+        let (_, tx) = txs.transaction_of(7).unwrap();
+        assert_eq!(
+            (4, 4, true),
+            (tx.first_msg_idx, tx.message_count(), tx.failed)
+        );
+        assert_eq!(PostgresTransactionOutcome::RolledBack, tx.outcome);
+        let (idx, tx) = txs.transaction_of(9).unwrap();
+        assert_eq!(2, idx);
+        assert_eq!(PostgresTransactionOutcome::InProgress, tx.outcome);
+    }
+
+    #[test]
+    fn should_group_transactions_from_the_queries_without_status() {
+        let txs = transactions(&[
+            message("START TRANSACTION", None, false),
+            message("savepoint s1", None, false),
+            message("rollback to savepoint s1", None, false),
+            message("ROLLBACK;", None, false),
+            message("select 1", None, false),
+            message("begin", None, false),
+            message("select * from missing", None, true),
+            message("end", None, false),
+        ]);
+        let (_, tx) = txs.transaction_of(2).unwrap();
+        assert_eq!(
+            (0, 3, false),
+            (tx.first_msg_idx, tx.last_msg_idx, tx.failed)
+        );
+        assert_eq!(PostgresTransactionOutcome::RolledBack, tx.outcome);
+        assert_eq!(None, txs.transaction_of(4));
+        let (_, tx) = txs.transaction_of(5).unwrap();
+        assert_eq!((5, 7, true), (tx.first_msg_idx, tx.last_msg_idx, tx.failed));
+        assert_eq!(PostgresTransactionOutcome::RolledBack, tx.outcome);
+    }
+}
@@ -1,6 +1,8 @@
 // https://www.postgresql.org/docs/12/protocol.html
 use crate::tshark_communication;
+use crate::tshark_json;
 use quick_xml::events::Event;
+use serde_json::Value;
 use std::io::BufRead;
 use std::str;
 
@@ -17,6 +19,18 @@ pub enum PostgresColType {
     Int4,
     Int8,
     Timestamp,
+    TimestampTz,
+    Date,
+    Time,
+    Interval,
+    Numeric,
+    Float4,
+    Float8,
+    Uuid,
+    Json,
+    Jsonb,
+    Inet,
+    Array(&'static PostgresColType),
     Other,
 
     // for prepared queries, it's possible the declaration
@@ -28,25 +42,157 @@ pub enum PostgresColType {
 }
 
 impl PostgresColType {
-    /// select * from postgres.pg_catalog.pg_type
     fn from_pg_oid_type(typ: &str) -> PostgresColType {
-        match typ.parse() {
-            Ok(16) => PostgresColType::Bool,
-            Ok(17) => PostgresColType::ByteArray,
-            Ok(18) => PostgresColType::Char,
-            Ok(19) => PostgresColType::Name,
-            Ok(20) => PostgresColType::Int8,
-            Ok(21) => PostgresColType::Int2,
-            Ok(23) => PostgresColType::Int4,
-            Ok(25) => PostgresColType::Text,
-            Ok(26) => PostgresColType::Oid,
-            Ok(1043) => PostgresColType::Varchar,
-            Ok(1114) => PostgresColType::Timestamp,
+        typ.parse()
+            .map(Self::from_pg_oid)
+            .unwrap_or(PostgresColType::Other)
+    }
+
+    /// select * from postgres.pg_catalog.pg_type
+    pub fn from_pg_oid(oid: u32) -> PostgresColType {
+        match oid {
+            16 => PostgresColType::Bool,
+            17 => PostgresColType::ByteArray,
+            18 => PostgresColType::Char,
+            19 => PostgresColType::Name,
+            20 => PostgresColType::Int8,
+            21 => PostgresColType::Int2,
+            23 => PostgresColType::Int4,
+            25 => PostgresColType::Text,
+            26 => PostgresColType::Oid,
+            1043 => PostgresColType::Varchar,
+            1114 => PostgresColType::Timestamp,
+            1184 => PostgresColType::TimestampTz,
+            1082 => PostgresColType::Date,
+            1083 => PostgresColType::Time,
+            1186 => PostgresColType::Interval,
+            1700 => PostgresColType::Numeric,
+            700 => PostgresColType::Float4,
+            701 => PostgresColType::Float8,
+            2950 => PostgresColType::Uuid,
+            114 => PostgresColType::Json,
+            3802 => PostgresColType::Jsonb,
+            869 => PostgresColType::Inet,
+            1000 => PostgresColType::Array(&PostgresColType::Bool),
+            1001 => PostgresColType::Array(&PostgresColType::ByteArray),
+            1005 => PostgresColType::Array(&PostgresColType::Int2),
+            1007 => PostgresColType::Array(&PostgresColType::Int4),
+            1009 => PostgresColType::Array(&PostgresColType::Text),
+            1015 => PostgresColType::Array(&PostgresColType::Varchar),
+            1016 => PostgresColType::Array(&PostgresColType::Int8),
+            1021 => PostgresColType::Array(&PostgresColType::Float4),
+            1022 => PostgresColType::Array(&PostgresColType::Float8),
+            1028 => PostgresColType::Array(&PostgresColType::Oid),
+            1041 => PostgresColType::Array(&PostgresColType::Inet),
+            1115 => PostgresColType::Array(&PostgresColType::Timestamp),
+            1182 => PostgresColType::Array(&PostgresColType::Date),
+            1183 => PostgresColType::Array(&PostgresColType::Time),
+            1185 => PostgresColType::Array(&PostgresColType::TimestampTz),
+            1187 => PostgresColType::Array(&PostgresColType::Interval),
+            1231 => PostgresColType::Array(&PostgresColType::Numeric),
+            2951 => PostgresColType::Array(&PostgresColType::Uuid),
+            199 => PostgresColType::Array(&PostgresColType::Json),
+            3807 => PostgresColType::Array(&PostgresColType::Jsonb),
             _ => PostgresColType::Other,
         }
     }
 }
 
+/// The fields of the ErrorResponse and NoticeResponse messages
+/// https://www.postgresql.org/docs/12/protocol-error-fields.html
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PostgresDiagnostic {
+    pub severity: Option<String>,
+    pub code: Option<String>,
+    pub message: Option<String>,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    pub position: Option<u32>,
+}
+
+impl std::fmt::Display for PostgresDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}: {}",
+            self.severity.as_deref().unwrap_or("ERROR"),
+            self.code.as_deref().unwrap_or("-"),
+            self.message.as_deref().unwrap_or("")
+        )?;
+        if let Some(detail) = &self.detail {
+            write!(f, "\nDETAIL: {}", detail)?;
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, "\nHINT: {}", hint)?;
+        }
+        if let Some(position) = self.position {
+            write!(f, "\nPOSITION: {}", position)?;
+        }
+        Ok(())
+    }
+}
+
+/// The transaction status the server reports in ReadyForQuery messages
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PostgresTransactionStatus {
+    Idle,
+    InTransaction,
+    Failed,
+}
+
+impl PostgresTransactionStatus {
+    pub fn from_status_byte(status: u8) -> Option<PostgresTransactionStatus> {
+        match status {
+            b'I' => Some(PostgresTransactionStatus::Idle),
+            b'T' => Some(PostgresTransactionStatus::InTransaction),
+            b'E' => Some(PostgresTransactionStatus::Failed),
+            _ => None,
+        }
+    }
+
+    /// tshark gives us the status byte as a decimal number, "73" for 'I'
+    fn from_tshark_str(status: &str) -> Option<PostgresTransactionStatus> {
+        status.parse().ok().and_then(Self::from_status_byte)
+    }
+}
+
+/// What the server asks the client in the Authentication* messages
+/// https://www.postgresql.org/docs/12/protocol-message-formats.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PostgresAuthRequest {
+    Ok,
+    KerberosV5,
+    CleartextPassword,
+    Md5Password,
+    Gss,
+    // GSSAPI or SSPI data, after Gss or Sspi
+    GssContinue,
+    Sspi,
+    // the mechanisms the server offers, "SCRAM-SHA-256"...
+    Sasl(Vec<String>),
+    SaslContinue,
+    SaslFinal,
+    Unknown(i32),
+}
+
+impl PostgresAuthRequest {
+    pub fn from_auth_type(auth_type: i32, sasl_mechanisms: Vec<String>) -> PostgresAuthRequest {
+        match auth_type {
+            0 => PostgresAuthRequest::Ok,
+            2 => PostgresAuthRequest::KerberosV5,
+            3 => PostgresAuthRequest::CleartextPassword,
+            5 => PostgresAuthRequest::Md5Password,
+            7 => PostgresAuthRequest::Gss,
+            8 => PostgresAuthRequest::GssContinue,
+            9 => PostgresAuthRequest::Sspi,
+            10 => PostgresAuthRequest::Sasl(sasl_mechanisms),
+            11 => PostgresAuthRequest::SaslContinue,
+            12 => PostgresAuthRequest::SaslFinal,
+            other => PostgresAuthRequest::Unknown(other),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum PostgresWireMessage {
     Startup {
@@ -54,7 +200,41 @@ pub enum PostgresWireMessage {
         database: Option<String>,
         application: Option<String>,
     },
-    CopyData,
+    // the client asks to switch to TLS before the startup
+    SslRequest,
+    AuthenticationRequest(PostgresAuthRequest),
+    // the server tells the client about its settings: server_version, TimeZone...
+    ParameterStatus {
+        name: String,
+        value: String,
+    },
+    // what the client must give in a CancelRequest for that connection
+    BackendKeyData {
+        process_id: u32,
+        secret_key: u32,
+    },
+    // sent on a new connection, to cancel the query running on another one
+    CancelRequest {
+        process_id: u32,
+        secret_key: u32,
+    },
+    Terminate,
+    // COPY ... FROM STDIN: the server is ready to receive the rows
+    CopyInResponse {
+        binary: bool,
+        col_count: usize,
+    },
+    // COPY ... TO STDOUT: the server is about to send the rows
+    CopyOutResponse {
+        binary: bool,
+        col_count: usize,
+    },
+    CopyData(Vec<u8>),
+    CopyDone,
+    // "COPY 42", "INSERT 0 1"...
+    CommandComplete(Option<String>),
+    // the simple query protocol: no separate parse & bind
+    Query(Option<String>),
     Parse {
         query: Option<String>,
         statement: Option<String>,
@@ -74,7 +254,10 @@ pub enum PostgresWireMessage {
     ResultSetRow {
         col_lengths_and_vals: Vec<(i64, String)>,
     },
-    ReadyForQuery,
+    // None if we couldn't read the status
+    ReadyForQuery(Option<PostgresTransactionStatus>),
+    ErrorResponse(PostgresDiagnostic),
+    NoticeResponse(PostgresDiagnostic),
 }
 
 pub fn parse_pgsql_info<B: BufRead>(
@@ -91,18 +274,83 @@ pub fn parse_pgsql_info<B: BufRead>(
                         Some("Startup message") => {
                             return Ok(Some(parse_startup_message(xml_reader)?));
                         }
-                        Some("Copy data") => return Ok(Some(PostgresWireMessage::CopyData)),
+                        Some("SSL request") => return Ok(Some(PostgresWireMessage::SslRequest)),
+                        Some("Termination") => return Ok(Some(PostgresWireMessage::Terminate)),
+                        Some("Authentication request") => {
+                            let fields = parse_message_fields(xml_reader)?;
+                            return Ok(Some(PostgresWireMessage::AuthenticationRequest(
+                                auth_request_from_fields(&fields),
+                            )));
+                        }
+                        Some("Parameter status") => {
+                            let fields = parse_message_fields(xml_reader)?;
+                            return Ok(
+                                match (
+                                    first_field(&fields, "pgsql.parameter_name"),
+                                    first_field(&fields, "pgsql.parameter_value"),
+                                ) {
+                                    (Some(name), Some(value)) => {
+                                        Some(PostgresWireMessage::ParameterStatus {
+                                            name: name.to_string(),
+                                            value: value.to_string(),
+                                        })
+                                    }
+                                    _ => None,
+                                },
+                            );
+                        }
+                        Some(t @ "Backend key data") | Some(t @ "Cancel request") => {
+                            let fields = parse_message_fields(xml_reader)?;
+                            let number = |name| first_field(&fields, name).and_then(|v| v.parse().ok());
+                            return Ok(
+                                match (number("pgsql.pid"), number("pgsql.key")) {
+                                    (Some(process_id), Some(secret_key)) if t == "Backend key data" => {
+                                        Some(PostgresWireMessage::BackendKeyData { process_id, secret_key })
+                                    }
+                                    (Some(process_id), Some(secret_key)) => {
+                                        Some(PostgresWireMessage::CancelRequest { process_id, secret_key })
+                                    }
+                                    _ => None,
+                                },
+                            );
+                        }
+                        Some("Copy data") => return Ok(Some(parse_copy_data_message(xml_reader)?)),
+                        Some("Copy completion") => return Ok(Some(PostgresWireMessage::CopyDone)),
+                        Some("CopyIn response") => {
+                            let (binary, col_count) = parse_copy_response_message(xml_reader)?;
+                            return Ok(Some(PostgresWireMessage::CopyInResponse { binary, col_count }));
+                        }
+                        Some("CopyOut response") => {
+                            let (binary, col_count) = parse_copy_response_message(xml_reader)?;
+                            return Ok(Some(PostgresWireMessage::CopyOutResponse { binary, col_count }));
+                        }
+                        Some("Command completion") => {
+                            return Ok(Some(parse_command_completion_message(xml_reader)?));
+                        }
                         Some("Parse") => {
                             return Ok(Some(parse_parse_message(xml_reader)?));
                         },
                         Some("Bind") => return Ok(Some(parse_bind_message(xml_reader)?)),
+                        Some("Simple query") => {
+                            return Ok(Some(parse_simple_query_message(xml_reader)?));
+                        }
                         Some("Ready for query") => {
-                            return Ok(Some(PostgresWireMessage::ReadyForQuery));
+                            return Ok(Some(parse_ready_for_query_message(xml_reader)?));
                         }
                         Some("Row description") => {
                             return Ok(Some(parse_row_description_message(xml_reader)?));
                         }
                         Some("Data row") => return Ok(Some(parse_data_row_message(xml_reader)?)),
+                        Some("Error") => {
+                            return Ok(Some(PostgresWireMessage::ErrorResponse(
+                                parse_diagnostic_message(xml_reader)?,
+                            )));
+                        }
+                        Some("Notice") => {
+                            return Ok(Some(PostgresWireMessage::NoticeResponse(
+                                parse_diagnostic_message(xml_reader)?,
+                            )));
+                        }
                         _ => {}
                     }
                 }
@@ -162,6 +410,211 @@ fn parse_startup_message<B: BufRead>(
     )
 }
 
+/// the name and displayed value of all the fields of the message
+fn parse_message_fields<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<Vec<(String, String)>, String> {
+    let mut fields = vec![];
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if let (Some(name), Some(val)) = (
+                    name.map(|n| String::from_utf8_lossy(&n).to_string()),
+                    tshark_communication::element_attr_val_string(e, b"show")?,
+                ) {
+                    fields.push((name, val));
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(fields);
+            }
+        }
+    )
+}
+
+fn first_field<'a>(fields: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+fn auth_request_from_fields(fields: &[(String, String)]) -> PostgresAuthRequest {
+    PostgresAuthRequest::from_auth_type(
+        first_field(fields, "pgsql.authtype")
+            .and_then(|t| t.parse().ok())
+            .unwrap_or(-1),
+        fields
+            .iter()
+            .filter(|(n, _)| n == "pgsql.auth.sasl.mech")
+            .map(|(_, v)| v.clone())
+            .collect(),
+    )
+}
+
+fn parse_diagnostic_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresDiagnostic, String> {
+    let mut diagnostic = PostgresDiagnostic::default();
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                match name.as_deref() {
+                    Some(b"pgsql.severity") => {
+                        diagnostic.severity = tshark_communication::element_attr_val_string(e, b"show")?
+                    }
+                    Some(b"pgsql.code") => {
+                        diagnostic.code = tshark_communication::element_attr_val_string(e, b"show")?
+                    }
+                    Some(b"pgsql.message") => {
+                        diagnostic.message = tshark_communication::element_attr_val_string(e, b"show")?
+                    }
+                    Some(b"pgsql.detail") => {
+                        diagnostic.detail = tshark_communication::element_attr_val_string(e, b"show")?
+                    }
+                    Some(b"pgsql.hint") => {
+                        diagnostic.hint = tshark_communication::element_attr_val_string(e, b"show")?
+                    }
+                    Some(b"pgsql.position") => {
+                        diagnostic.position = tshark_communication::element_attr_val_string(e, b"show")?
+                            .and_then(|p| p.parse().ok())
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(diagnostic);
+            }
+        }
+    )
+}
+
+fn parse_copy_data_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut data = vec![];
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if name.as_deref() == Some(b"pgsql.copydata") {
+                    if let Some(hex) = tshark_communication::element_attr_val_string(e, b"value")? {
+                        data = hex::decode(&hex).map_err(|e| format!("Error decoding copy data: {:?}", e))?;
+                    }
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::CopyData(data));
+            }
+        }
+    )
+}
+
+/// whether the format is binary, and the number of columns. The first
+/// format field is the overall one, then we get one per column
+fn parse_copy_response_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<(bool, usize), String> {
+    let mut formats = vec![];
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if name.as_deref() == Some(b"pgsql.format") {
+                    formats.push(tshark_communication::element_attr_val_string(e, b"show")?);
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok((
+                    formats.first().map_or(false, |f| f.as_deref() == Some("1")),
+                    formats.len().saturating_sub(1),
+                ));
+            }
+        }
+    )
+}
+
+fn parse_command_completion_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut tag = None;
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if name.as_deref() == Some(b"pgsql.tag") {
+                    tag = tshark_communication::element_attr_val_string(e, b"show")?;
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::CommandComplete(tag));
+            }
+        }
+    )
+}
+
+fn parse_simple_query_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut query = None;
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if name.as_deref() == Some(b"pgsql.query") {
+                    query = tshark_communication::element_attr_val_string(e, b"show")?;
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::Query(query));
+            }
+        }
+    )
+}
+
+fn parse_ready_for_query_message<B: BufRead>(
+    xml_reader: &mut quick_xml::Reader<B>,
+) -> Result<PostgresWireMessage, String> {
+    let mut status = None;
+    let buf = &mut vec![];
+    xml_event_loop!(xml_reader, buf,
+        Ok(Event::Empty(ref e)) => {
+            if e.name() == b"field" {
+                let name = tshark_communication::attr_by_name(&mut e.attributes(), b"name")?;
+                if name.as_deref() == Some(b"pgsql.status") {
+                    status = tshark_communication::element_attr_val_string(e, b"show")?
+                        .and_then(|s| PostgresTransactionStatus::from_tshark_str(&s));
+                }
+            }
+        }
+        Ok(Event::End(ref e)) => {
+            if e.name() == b"proto" {
+                return Ok(PostgresWireMessage::ReadyForQuery(status));
+            }
+        }
+    )
+}
+
 fn parse_parse_message<B: BufRead>(
     xml_reader: &mut quick_xml::Reader<B>,
 ) -> Result<PostgresWireMessage, String> {
@@ -397,3 +850,141 @@ fn parse_data_row_message<B: BufRead>(
         }
     );
 }
+
+pub fn parse_pgsql_json(msg: &Value) -> Result<Option<PostgresWireMessage>, String> {
+    let field = |name: &str| tshark_json::first_str(msg, name);
+    let oid_types = || {
+        tshark_json::all_str(msg, "pgsql.oid.type")
+            .iter()
+            .map(|t| PostgresColType::from_pg_oid_type(t))
+            .collect()
+    };
+    Ok(match field("pgsql.type").as_deref() {
+        Some("Startup message") => {
+            let names = tshark_json::all_str(msg, "pgsql.parameter_name");
+            let values = tshark_json::all_str(msg, "pgsql.parameter_value");
+            let param = |param_name: &str| {
+                names
+                    .iter()
+                    .zip(values.iter())
+                    .find(|(n, _)| n.as_str() == param_name)
+                    .map(|(_, v)| v.clone())
+            };
+            Some(PostgresWireMessage::Startup {
+                username: param("user"),
+                database: param("database"),
+                application: param("application_name"),
+            })
+        }
+        Some("SSL request") => Some(PostgresWireMessage::SslRequest),
+        Some("Termination") => Some(PostgresWireMessage::Terminate),
+        Some("Authentication request") => Some(PostgresWireMessage::AuthenticationRequest(
+            PostgresAuthRequest::from_auth_type(
+                tshark_json::first_number(msg, "pgsql.authtype").unwrap_or(-1),
+                tshark_json::all_str(msg, "pgsql.auth.sasl.mech"),
+            ),
+        )),
+        Some("Parameter status") => {
+            match (
+                field("pgsql.parameter_name"),
+                field("pgsql.parameter_value"),
+            ) {
+                (Some(name), Some(value)) => {
+                    Some(PostgresWireMessage::ParameterStatus { name, value })
+                }
+                _ => None,
+            }
+        }
+        Some(t @ "Backend key data") | Some(t @ "Cancel request") => {
+            match (
+                tshark_json::first_number(msg, "pgsql.pid"),
+                tshark_json::first_number(msg, "pgsql.key"),
+            ) {
+                (Some(process_id), Some(secret_key)) if t == "Backend key data" => {
+                    Some(PostgresWireMessage::BackendKeyData {
+                        process_id,
+                        secret_key,
+                    })
+                }
+                (Some(process_id), Some(secret_key)) => Some(PostgresWireMessage::CancelRequest {
+                    process_id,
+                    secret_key,
+                }),
+                _ => None,
+            }
+        }
+        Some("Copy data") => Some(PostgresWireMessage::CopyData(
+            tshark_json::first_bytes(msg, "pgsql.copydata").unwrap_or_default(),
+        )),
+        Some("Copy completion") => Some(PostgresWireMessage::CopyDone),
+        Some(t @ "CopyIn response") | Some(t @ "CopyOut response") => {
+            let formats = tshark_json::all_str(msg, "pgsql.format");
+            let binary = formats.first().map_or(false, |f| f == "1");
+            let col_count = formats.len().saturating_sub(1);
+            Some(if t == "CopyIn response" {
+                PostgresWireMessage::CopyInResponse { binary, col_count }
+            } else {
+                PostgresWireMessage::CopyOutResponse { binary, col_count }
+            })
+        }
+        Some("Command completion") => {
+            Some(PostgresWireMessage::CommandComplete(field("pgsql.tag")))
+        }
+        Some("Parse") => Some(PostgresWireMessage::Parse {
+            statement: field("pgsql.statement"),
+            query: field("pgsql.query"),
+            param_types: oid_types(),
+        }),
+        Some("Bind") => Some(PostgresWireMessage::Bind {
+            statement: field("pgsql.statement").filter(|s| !s.is_empty()),
+            parameter_lengths_and_vals: json_lengths_and_vals(msg)?,
+        }),
+        Some("Simple query") => Some(PostgresWireMessage::Query(field("pgsql.query"))),
+        Some("Ready for query") => Some(PostgresWireMessage::ReadyForQuery(
+            field("pgsql.status").and_then(|s| PostgresTransactionStatus::from_tshark_str(&s)),
+        )),
+        Some("Row description") => Some(PostgresWireMessage::RowDescription {
+            col_names: tshark_json::all_str(msg, "pgsql.col.name"),
+            col_types: oid_types(),
+        }),
+        Some("Data row") => Some(PostgresWireMessage::ResultSetRow {
+            col_lengths_and_vals: json_lengths_and_vals(msg)?,
+        }),
+        Some("Error") => Some(PostgresWireMessage::ErrorResponse(json_diagnostic(msg))),
+        Some("Notice") => Some(PostgresWireMessage::NoticeResponse(json_diagnostic(msg))),
+        _ => None,
+    })
+}
+
+fn json_diagnostic(msg: &Value) -> PostgresDiagnostic {
+    let field = |name: &str| tshark_json::first_str(msg, name);
+    PostgresDiagnostic {
+        severity: field("pgsql.severity"),
+        code: field("pgsql.code"),
+        message: field("pgsql.message"),
+        detail: field("pgsql.detail"),
+        hint: field("pgsql.hint"),
+        position: field("pgsql.position").and_then(|p| p.parse().ok()),
+    }
+}
+
+/// the values in hex. There is no pgsql.val.data for null or empty values
+fn json_lengths_and_vals(msg: &Value) -> Result<Vec<(i64, String)>, String> {
+    let mut vals = tshark_json::all_hex(msg, "pgsql.val.data").into_iter();
+    let mut result = vec![];
+    for length in tshark_json::all_str(msg, "pgsql.val.length") {
+        match length
+            .parse::<i64>()
+            .map_err(|e| format!("Error parsing {}: {:?}", length, e))?
+        {
+            -1 => result.push((-1, "6e756c6c".to_string())), // null in hex
+            0 => result.push((0, "".to_string())),
+            l => {
+                if let Some(val) = vals.next() {
+                    result.push((l, val));
+                }
+            }
+        }
+    }
+    Ok(result)
+}
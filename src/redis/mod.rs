@@ -0,0 +1,3 @@
+pub mod redis_details_widget;
+pub mod redis_streams_store;
+pub mod tshark_redis;
@@ -0,0 +1,83 @@
+use super::redis_streams_store::RedisMessageData;
+use crate::tshark_communication::TcpStreamId;
+use crate::widgets::comm_info_header;
+use crate::widgets::comm_info_header::CommInfoHeader;
+use crate::BgFunc;
+use gtk::prelude::*;
+use relm::Widget;
+use relm_derive::{widget, Msg};
+use std::net::IpAddr;
+use std::sync::mpsc;
+
+pub struct Model {
+    stream_id: TcpStreamId,
+    client_ip: IpAddr,
+    data: RedisMessageData,
+}
+
+#[derive(Msg, Debug)]
+pub enum Msg {
+    DisplayDetails(mpsc::Sender<BgFunc>, IpAddr, TcpStreamId, RedisMessageData),
+}
+
+#[widget]
+impl Widget for RedisCommEntry {
+    fn model(
+        _relm: &relm::Relm<Self>,
+        params: (TcpStreamId, IpAddr, RedisMessageData, mpsc::Sender<BgFunc>),
+    ) -> Model {
+        let (stream_id, client_ip, data, _bg_sender) = params;
+        Model {
+            data,
+            stream_id,
+            client_ip,
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::DisplayDetails(_bg_sender, client_ip, stream_id, message_data) => {
+                self.model.data = message_data;
+                self.streams
+                    .comm_info_header
+                    .emit(comm_info_header::Msg::Update(client_ip, stream_id));
+                self.model.stream_id = stream_id;
+                self.model.client_ip = client_ip;
+            }
+        }
+    }
+
+    fn command_markup(data: &RedisMessageData) -> String {
+        format!("<b>{}</b>", glib::markup_escape_text(&data.command_line()))
+    }
+
+    view! {
+        gtk::Box {
+            orientation: gtk::Orientation::Vertical,
+            margin_top: 10,
+            margin_bottom: 10,
+            margin_start: 10,
+            margin_end: 10,
+            spacing: 10,
+            #[name="comm_info_header"]
+            CommInfoHeader(self.model.client_ip, self.model.stream_id) {
+            },
+            gtk::Label {
+                markup: &Self::command_markup(&self.model.data),
+                visible: !self.model.data.command.is_empty(),
+                line_wrap: true,
+                xalign: 0.0,
+                selectable: true,
+            },
+            gtk::Separator {},
+            gtk::Label {
+                label: &self.model.data.reply_display(),
+                line_wrap: true,
+                xalign: 0.0,
+                yalign: 0.0,
+                vexpand: true,
+                selectable: true,
+            },
+        }
+    }
+}
@@ -0,0 +1,712 @@
+use super::redis_details_widget;
+use super::redis_details_widget::RedisCommEntry;
+use super::tshark_redis;
+use super::tshark_redis::RespValue;
+use crate::colors;
+use crate::custom_streams_store;
+use crate::custom_streams_store::{ClientServerInfo, CustomStreamsParser, CustomStreamsStore};
+use crate::icons::Icon;
+use crate::search_expr;
+use crate::tshark_communication::{TSharkPacket, TcpStreamId};
+use crate::widgets::win;
+use crate::BgFunc;
+use chrono::{NaiveDateTime, Utc};
+use gtk::prelude::*;
+use itertools::Itertools;
+use relm::ContainerWidget;
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::mpsc;
+use strum::VariantNames;
+use strum_macros::{EnumString, EnumVariantNames};
+
+#[cfg(test)]
+use {
+    crate::custom_streams_store::common_tests_parse_stream,
+    crate::tshark_communication::parse_test_xml_no_wrapper,
+};
+
+#[derive(Default)]
+pub struct RedisStreamData {
+    pub stream_globals: RedisStreamGlobals,
+    pub client_server: Option<ClientServerInfo>,
+    pub messages: Vec<RedisMessageData>,
+    pub summary_details: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct RedisStreamGlobals {
+    // the client may pipeline commands: send several before
+    // getting the replies. The server replies in order.
+    cur_commands: VecDeque<(NaiveDateTime, Vec<String>)>,
+
+    // a command or a reply may span several TCP segments
+    client_leftover_payload: Vec<u8>,
+    server_leftover_payload: Vec<u8>,
+
+    // RESP can't resynchronize after invalid data, we stop
+    // parsing the stream after an error
+    parse_failed: bool,
+}
+
+#[derive(Default)]
+pub struct RedisStreamsStore {
+    streams: HashMap<TcpStreamId, RedisStreamData>,
+    component: Option<relm::Component<RedisCommEntry>>,
+}
+
+impl RedisStreamsStore {
+    fn get_msg_info(
+        &self,
+        stream_id: TcpStreamId,
+        msg_idx: usize,
+    ) -> Option<(&RedisMessageData, ClientServerInfo)> {
+        let stream = self.streams.get(&stream_id)?;
+        let msg = stream.messages.get(msg_idx)?;
+        Some((msg, stream.client_server?))
+    }
+}
+
+#[derive(EnumString, EnumVariantNames)]
+enum RedisFilterKeys {
+    #[strum(serialize = "redis.command")]
+    Command,
+    #[strum(serialize = "redis.key")]
+    Key,
+    #[strum(serialize = "redis.reply")]
+    Reply,
+}
+
+fn get_redis_message<'a, 'b>(
+    streams: &'a HashMap<TcpStreamId, RedisStreamData>,
+    model: &'b gtk::TreeModel,
+    iter: &'b gtk::TreeIter,
+) -> Option<&'a RedisMessageData> {
+    let (stream_id, idx) = custom_streams_store::get_message_helper(model, iter);
+    streams
+        .get(&stream_id)
+        .and_then(|s| s.messages.get(idx as usize))
+}
+
+/// parse all the complete values from the buffer, leaving
+/// in it the bytes of a value that is not yet complete.
+/// Also returns the parse error if we met invalid data after these values.
+fn parse_resp_values(leftover: &mut Vec<u8>, payload: &[u8]) -> (Vec<RespValue>, Option<String>) {
+    leftover.extend_from_slice(payload);
+    let mut result = vec![];
+    let mut consumed = 0;
+    loop {
+        match tshark_redis::parse_resp(&leftover[consumed..]) {
+            Ok(Some((val, len))) => {
+                result.push(val);
+                consumed += len;
+            }
+            Ok(None) => break,
+            Err(e) => {
+                leftover.clear();
+                return (result, Some(e));
+            }
+        }
+    }
+    leftover.drain(..consumed);
+    (result, None)
+}
+
+impl CustomStreamsParser for RedisStreamsStore {
+    fn is_my_message(&self, msg: &TSharkPacket) -> bool {
+        msg.redis.is_some()
+    }
+
+    fn tshark_filter_string(&self) -> &'static str {
+        // can't filter on the redis dissector, older tshark versions don't have it
+        // and then tshark would refuse the filter.
+        "tcp.port == 6379"
+    }
+
+    fn protocol_name(&self) -> &'static str {
+        "REDIS"
+    }
+
+    fn tcp_stream_ids(&self) -> Vec<TcpStreamId> {
+        self.streams.keys().copied().collect()
+    }
+
+    fn has_stream_id(&self, stream_id: TcpStreamId) -> bool {
+        self.streams.contains_key(&stream_id)
+    }
+
+    fn reset(&mut self) {
+        self.streams = HashMap::new();
+    }
+
+    fn stream_message_count(&self, stream_id: TcpStreamId) -> Option<usize> {
+        self.streams.get(&stream_id).map(|s| s.messages.len())
+    }
+
+    fn stream_summary_details(&self, stream_id: TcpStreamId) -> Option<&str> {
+        self.streams
+            .get(&stream_id)
+            .and_then(|s| s.summary_details.as_deref())
+    }
+
+    fn stream_client_server(&self, stream_id: TcpStreamId) -> Option<ClientServerInfo> {
+        self.streams.get(&stream_id).and_then(|s| s.client_server)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    fn add_to_stream(
+        &mut self,
+        stream_id: TcpStreamId,
+        new_packet: TSharkPacket,
+    ) -> Result<Option<ClientServerInfo>, String> {
+        let stream = self
+            .streams
+            .entry(stream_id)
+            .or_insert_with(RedisStreamData::default);
+        let basic_info = new_packet.basic_info;
+        if stream.client_server.is_none() {
+            stream.client_server = Some(if basic_info.port_src == tshark_redis::REDIS_PORT {
+                ClientServerInfo {
+                    server_ip: basic_info.ip_src,
+                    client_ip: basic_info.ip_dst,
+                    server_port: basic_info.port_src,
+                }
+            } else {
+                // not the standard port, assume the client talks first
+                ClientServerInfo {
+                    server_ip: basic_info.ip_dst,
+                    client_ip: basic_info.ip_src,
+                    server_port: basic_info.port_dst,
+                }
+            });
+        }
+        let is_from_server = stream
+            .client_server
+            .map_or(false, |cs| cs.server_port == basic_info.port_src);
+        if let Some(payload) = new_packet
+            .redis
+            .filter(|_| !stream.stream_globals.parse_failed)
+        {
+            let globals = &mut stream.stream_globals;
+            if is_from_server {
+                let (replies, parse_error) =
+                    parse_resp_values(&mut globals.server_leftover_payload, &payload);
+                for reply in replies {
+                    match (&reply, globals.cur_commands.pop_front()) {
+                        (RespValue::Push(_), cmd) => {
+                            // out-of-band data (for instance pub/sub), not the reply
+                            // to a command
+                            if let Some(c) = cmd {
+                                globals.cur_commands.push_front(c);
+                            }
+                            stream.messages.push(RedisMessageData {
+                                command_timestamp: basic_info.frame_time,
+                                reply_timestamp: Some(basic_info.frame_time),
+                                command: vec![],
+                                reply: Some(reply),
+                                parse_error: None,
+                            });
+                        }
+                        (_, cmd) => {
+                            let (command_timestamp, command) =
+                                cmd.unwrap_or((basic_info.frame_time, vec![]));
+                            if command
+                                .first()
+                                .filter(|c| c.eq_ignore_ascii_case("select"))
+                                .is_some()
+                                && !reply.is_error()
+                            {
+                                stream.summary_details =
+                                    command.get(1).map(|db| format!("db {}", db));
+                            }
+                            stream.messages.push(RedisMessageData {
+                                command_timestamp,
+                                reply_timestamp: Some(basic_info.frame_time),
+                                command,
+                                reply: Some(reply),
+                                parse_error: None,
+                            });
+                        }
+                    }
+                }
+                if let Some(e) = parse_error {
+                    // that was presumably the reply to the next command
+                    let (command_timestamp, command) = globals
+                        .cur_commands
+                        .pop_front()
+                        .unwrap_or((basic_info.frame_time, vec![]));
+                    globals.parse_failed = true;
+                    stream.messages.push(RedisMessageData {
+                        command_timestamp,
+                        reply_timestamp: Some(basic_info.frame_time),
+                        command,
+                        reply: None,
+                        parse_error: Some(e),
+                    });
+                }
+            } else {
+                let (commands, parse_error) =
+                    parse_resp_values(&mut globals.client_leftover_payload, &payload);
+                for cmd in commands {
+                    globals.cur_commands.push_back((
+                        basic_info.frame_time,
+                        cmd.as_command_args().unwrap_or_default(),
+                    ));
+                }
+                if let Some(e) = parse_error {
+                    globals.parse_failed = true;
+                    stream.messages.push(RedisMessageData {
+                        command_timestamp: basic_info.frame_time,
+                        reply_timestamp: None,
+                        command: vec![],
+                        reply: None,
+                        parse_error: Some(e),
+                    });
+                }
+            }
+        }
+        Ok(stream.client_server)
+    }
+
+    fn finish_stream(&mut self, stream_id: TcpStreamId) -> Result<(), String> {
+        if let Some(stream) = self.streams.get_mut(&stream_id) {
+            // commands for which we didn't get a reply
+            let globals = std::mem::take(&mut stream.stream_globals);
+            for (command_timestamp, command) in globals.cur_commands {
+                stream.messages.push(RedisMessageData {
+                    command_timestamp,
+                    reply_timestamp: None,
+                    command,
+                    reply: None,
+                    parse_error: None,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn stream_messages_json(&self, stream_id: TcpStreamId) -> Vec<serde_json::Value> {
+        self.streams
+            .get(&stream_id)
+            .map(|s| s.messages.iter().map(|m| m.to_json()).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl CustomStreamsStore for RedisStreamsStore {
+    fn protocol_icon(&self) -> Icon {
+        Icon::DATABASE
+    }
+
+    fn prepare_treeview(&self, tv: &gtk::TreeView) {
+        let streamcolor_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("S")
+            .fixed_width(10)
+            .sort_column_id(2)
+            .build();
+        let cell_s_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        streamcolor_col.pack_start(&cell_s_txt, true);
+        streamcolor_col.add_attribute(&cell_s_txt, "background", 10);
+        tv.append_column(&streamcolor_col);
+
+        let timestamp_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Timestamp")
+            .resizable(true)
+            .sort_column_id(5)
+            .build();
+        let cell_t_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        timestamp_col.pack_start(&cell_t_txt, true);
+        timestamp_col.add_attribute(&cell_t_txt, "text", 4);
+        tv.append_column(&timestamp_col);
+
+        let command_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Command")
+            .resizable(true)
+            .sort_column_id(0)
+            .build();
+        let cell_c_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        command_col.pack_start(&cell_c_txt, true);
+        command_col.add_attribute(&cell_c_txt, "text", 0);
+        tv.append_column(&command_col);
+
+        let key_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Key")
+            .expand(true)
+            .resizable(true)
+            .sort_column_id(1)
+            .build();
+        let cell_k_txt = gtk::builders::CellRendererTextBuilder::new()
+            .ellipsize(pango::EllipsizeMode::End)
+            .build();
+        key_col.pack_start(&cell_k_txt, true);
+        key_col.add_attribute(&cell_k_txt, "text", 1);
+        tv.append_column(&key_col);
+
+        let reply_type_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Reply")
+            .resizable(true)
+            .sort_column_id(8)
+            .build();
+        let cell_rt_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        reply_type_col.pack_start(&cell_rt_txt, true);
+        reply_type_col.add_attribute(&cell_rt_txt, "text", 8);
+        reply_type_col.add_attribute(&cell_rt_txt, "foreground", 11);
+        tv.append_column(&reply_type_col);
+
+        let reply_size_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Size")
+            .resizable(true)
+            .sort_column_id(9)
+            .build();
+        let cell_rs_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        reply_size_col.pack_start(&cell_rs_txt, true);
+        reply_size_col.add_attribute(&cell_rs_txt, "text", 12);
+        tv.append_column(&reply_size_col);
+
+        let duration_col = gtk::builders::TreeViewColumnBuilder::new()
+            .title("Duration")
+            .resizable(true)
+            .sort_column_id(6)
+            .build();
+        let cell_d_txt = gtk::builders::CellRendererTextBuilder::new().build();
+        duration_col.pack_start(&cell_d_txt, true);
+        duration_col.add_attribute(&cell_d_txt, "text", 7);
+        tv.append_column(&duration_col);
+    }
+
+    fn get_empty_treestore(&self) -> gtk::TreeStore {
+        gtk::TreeStore::new(&[
+            String::static_type(), // command name
+            String::static_type(), // key
+            u32::static_type(),    // stream_id
+            u32::static_type(),    // index of the comm in the model vector
+            String::static_type(), // command timestamp (string)
+            i64::static_type(),    // command timestamp (integer, for sorting)
+            i32::static_type(),    // duration (millis, for sorting)
+            String::static_type(), // duration display
+            String::static_type(), // reply type
+            i64::static_type(),    // reply size, for sorting
+            String::static_type(), // stream color
+            String::static_type(), // reply color
+            String::static_type(), // reply size display
+        ])
+    }
+
+    fn populate_treeview(
+        &self,
+        ts: &gtk::TreeStore,
+        session_id: TcpStreamId,
+        start_idx: usize,
+        item_count: usize,
+    ) {
+        let messages = &self.streams.get(&session_id).unwrap().messages;
+        for (idx, redis) in messages.iter().skip(start_idx).take(item_count).enumerate() {
+            let duration = redis
+                .reply_timestamp
+                .map(|t| (t - redis.command_timestamp).num_milliseconds());
+            ts.insert_with_values(
+                None,
+                None,
+                &[
+                    (0, &redis.command_name().to_value()),
+                    (1, &redis.key().unwrap_or("").to_value()),
+                    (
+                        custom_streams_store::TREE_STORE_STREAM_ID_COL_IDX,
+                        &session_id.as_u32().to_value(),
+                    ),
+                    (
+                        custom_streams_store::TREE_STORE_MESSAGE_INDEX_COL_IDX,
+                        &((start_idx + idx) as i32).to_value(),
+                    ),
+                    (4, &redis.command_timestamp.to_string().to_value()),
+                    (5, &redis.command_timestamp.timestamp_nanos().to_value()),
+                    (6, &(duration.unwrap_or(0) as i32).to_value()),
+                    (
+                        7,
+                        &duration
+                            .map(|d| format!("{} ms", d))
+                            .unwrap_or_else(|| "-".to_string())
+                            .to_value(),
+                    ),
+                    (
+                        8,
+                        &redis
+                            .reply
+                            .as_ref()
+                            .map(|r| r.type_desc())
+                            .unwrap_or(if redis.parse_error.is_some() {
+                                "parse error"
+                            } else {
+                                "missing reply"
+                            })
+                            .to_value(),
+                    ),
+                    (
+                        9,
+                        &(redis.reply.as_ref().map(|r| r.size()).unwrap_or(0) as i64).to_value(),
+                    ),
+                    (
+                        10,
+                        &colors::STREAM_COLORS
+                            [session_id.as_u32() as usize % colors::STREAM_COLORS.len()]
+                        .to_value(),
+                    ),
+                    (
+                        11,
+                        &match &redis.reply {
+                            Some(r) if r.is_error() => colors::ERROR_COLOR.to_value(),
+                            None if redis.parse_error.is_some() => colors::ERROR_COLOR.to_value(),
+                            None => colors::WARNING_COLOR.to_value(),
+                            _ => None::<&str>.to_value(),
+                        },
+                    ),
+                    (
+                        12,
+                        &redis
+                            .reply
+                            .as_ref()
+                            .map(|r| r.size().to_string())
+                            .unwrap_or_default()
+                            .to_value(),
+                    ),
+                ],
+            );
+        }
+    }
+
+    fn end_populate_treeview(&self, tv: &gtk::TreeView, ts: &gtk::TreeStore) {
+        let model_sort = gtk::TreeModelSort::new(ts);
+        model_sort.set_sort_column_id(gtk::SortColumn::Index(5), gtk::SortType::Ascending);
+        tv.set_model(Some(&model_sort));
+    }
+
+    fn supported_string_filter_keys(&self) -> &'static [&'static str] {
+        RedisFilterKeys::VARIANTS
+    }
+
+    fn supported_numeric_filter_keys(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn matches_filter(
+        &self,
+        filter: &search_expr::SearchOpExpr,
+        model: &gtk::TreeModel,
+        iter: &gtk::TreeIter,
+    ) -> bool {
+        let streams = &self.streams;
+        match (RedisFilterKeys::from_str(filter.filter_key), &filter.op) {
+            (Ok(filter_key), op) => match filter_key {
+                RedisFilterKeys::Command => {
+                    op.matches_str(model.value(iter, 0).get::<&str>().unwrap())
+                }
+                RedisFilterKeys::Key => op.matches_str(model.value(iter, 1).get::<&str>().unwrap()),
+                RedisFilterKeys::Reply => get_redis_message(streams, model, iter)
+                    .and_then(|redis_msg| redis_msg.reply.as_ref())
+                    .map_or(false, |r| op.matches_str(&r.display())),
+            },
+            _ => true,
+        }
+    }
+
+    fn requests_details_overlay(&self) -> bool {
+        false
+    }
+
+    fn add_details_to_scroll(
+        &mut self,
+        parent: &gtk::ScrolledWindow,
+        _overlay: Option<&gtk::Overlay>,
+        bg_sender: mpsc::Sender<BgFunc>,
+        _win_msg_sender: relm::StreamHandle<win::Msg>,
+    ) {
+        let component = parent.add_widget::<RedisCommEntry>((
+            TcpStreamId(0),
+            "0.0.0.0".parse().unwrap(),
+            RedisMessageData {
+                command_timestamp: Utc::now().naive_local(),
+                reply_timestamp: None,
+                command: vec![],
+                reply: None,
+                parse_error: None,
+            },
+            bg_sender,
+        ));
+        self.component = Some(component);
+    }
+
+    fn display_in_details_widget(
+        &self,
+        bg_sender: mpsc::Sender<BgFunc>,
+        stream_id: TcpStreamId,
+        msg_idx: usize,
+    ) {
+        if let Some((redis_msg, client_server)) = self.get_msg_info(stream_id, msg_idx) {
+            self.component.as_ref().unwrap().stream().emit(
+                redis_details_widget::Msg::DisplayDetails(
+                    bg_sender,
+                    client_server.client_ip,
+                    stream_id,
+                    redis_msg.clone(),
+                ),
+            )
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RedisMessageData {
+    pub command_timestamp: NaiveDateTime,
+    // None if we didn't get the reply
+    pub reply_timestamp: Option<NaiveDateTime>,
+    // the command name followed by the arguments.
+    // empty for server pushes
+    pub command: Vec<String>,
+    pub reply: Option<RespValue>,
+    // the data was not valid RESP, we stopped parsing the stream there
+    pub parse_error: Option<String>,
+}
+
+impl RedisMessageData {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "command_timestamp": self.command_timestamp.to_string(),
+            "reply_timestamp": self.reply_timestamp.map(|t| t.to_string()),
+            "command": self.command,
+            "reply": self.reply.as_ref().map(|r| r.to_json()),
+            "parse_error": self.parse_error,
+        })
+    }
+
+    pub fn reply_display(&self) -> String {
+        match (&self.reply, &self.parse_error) {
+            (Some(r), _) => r.display(),
+            (None, Some(e)) => format!("Error parsing the data: {}", e),
+            (None, None) => "Missing reply".to_string(),
+        }
+    }
+
+    pub fn command_name(&self) -> String {
+        self.command
+            .first()
+            .map(|c| c.to_uppercase())
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    /// most redis commands take the key as first parameter
+    pub fn key(&self) -> Option<&str> {
+        self.command.get(1).map(|k| k.as_str())
+    }
+
+    pub fn command_line(&self) -> String {
+        self.command
+            .iter()
+            .map(|a| {
+                if a.contains(char::is_whitespace) || a.is_empty() {
+                    format!("{:?}", a)
+                } else {
+                    a.clone()
+                }
+            })
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+fn tests_parse_stream(
+    packets: Result<Vec<TSharkPacket>, String>,
+) -> Result<Vec<RedisMessageData>, String> {
+    let mut parser = RedisStreamsStore::default();
+    let sid = common_tests_parse_stream(&mut parser, packets)?;
+    Ok(parser.streams.get(&sid).unwrap().messages.clone())
+}
+
+#[cfg(test)]
+fn redis_test_packet(from_server: bool, hex_payload: &str) -> String {
+    let (srcport, dstport) = if from_server {
+        (6379, 52796)
+    } else {
+        (52796, 6379)
+    };
+    format!(
+        r#"
+<packet>
+  <proto name="frame">
+    <field name="frame.time" show="Mar  5, 2021 08:49:52.736275000 CET"/>
+  </proto>
+  <proto name="ip">
+    <field name="ip.src" show="10.215.215.9" />
+    <field name="ip.dst" show="10.215.215.10" />
+  </proto>
+  <proto name="tcp">
+    <field name="tcp.srcport" show="{}" />
+    <field name="tcp.dstport" show="{}" />
+    <field name="tcp.seq_raw" show="1963007432" />
+    <field name="tcp.stream" show="4"/>
+    <field name="tcp.payload" value="{}"/>
+  </proto>
+</packet>
+"#,
+        srcport, dstport, hex_payload
+    )
+}
+
+#[test]
+fn should_pair_pipelined_commands_with_replies() {
+    let xml = format!(
+        "<pdml>{}{}{}{}</pdml>",
+        // SET k1 v1 and GET k1 in the same segment
+        redis_test_packet(
+            false,
+            &hex::encode(
+                "*3\r\n$3\r\nSET\r\n$2\r\nk1\r\n$2\r\nv1\r\n*2\r\n$3\r\nGET\r\n$2\r\nk1\r\n"
+            )
+        ),
+        redis_test_packet(true, &hex::encode("+OK\r\n$2\r\nv")),
+        // the bulk string reply is split over two segments
+        redis_test_packet(true, &hex::encode("1\r\n")),
+        redis_test_packet(false, &hex::encode("*2\r\n$3\r\nGET\r\n$7\r\nmissing\r\n")),
+    );
+    let parsed = tests_parse_stream(parse_test_xml_no_wrapper(&xml)).unwrap();
+    let summary: Vec<_> = parsed
+        .iter()
+        .map(|m| (m.command_line(), m.reply.clone()))
+        .collect();
+    assert_eq!(
+        vec![
+            (
+                "SET k1 v1".to_string(),
+                Some(RespValue::SimpleString("OK".to_string()))
+            ),
+            (
+                "GET k1".to_string(),
+                Some(RespValue::BulkString(Some(b"v1".to_vec())))
+            ),
+            ("GET missing".to_string(), None),
+        ],
+        summary
+    );
+    assert_eq!(Some("k1"), parsed[1].key());
+}
+
+#[test]
+fn should_record_invalid_resp_on_the_message() {
+    let xml = format!(
+        "<pdml>{}{}{}</pdml>",
+        redis_test_packet(false, &hex::encode("*2\r\n$3\r\nGET\r\n$2\r\nk1\r\n")),
+        redis_test_packet(true, &hex::encode("$100000000000\r\nabc")),
+        // we don't try to make sense of the rest of the stream
+        redis_test_packet(true, &hex::encode("+OK\r\n")),
+    );
+    let parsed = tests_parse_stream(parse_test_xml_no_wrapper(&xml)).unwrap();
+    assert_eq!(1, parsed.len());
+    assert_eq!("GET k1", parsed[0].command_line());
+    assert_eq!(None, parsed[0].reply);
+    assert_eq!(
+        "Error parsing the data: RESP bulk string too long: 100000000000 bytes",
+        parsed[0].reply_display()
+    );
+}
@@ -0,0 +1,358 @@
+// https://redis.io/docs/reference/protocol-spec/
+use crate::tshark_communication::NetworkPort;
+use std::str;
+
+pub const REDIS_PORT: NetworkPort = NetworkPort(6379);
+
+// the redis server default for proto-max-bulk-len. Past that, we're
+// not looking at valid RESP and we'd buffer the stream forever
+const MAX_BULK_LENGTH: usize = 512 * 1024 * 1024;
+
+// arrays and maps can be nested, but not that deep in practice
+const MAX_NESTING_DEPTH: usize = 64;
+
+pub fn is_redis_port(port_src: NetworkPort, port_dst: NetworkPort) -> bool {
+    port_src == REDIS_PORT || port_dst == REDIS_PORT
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RespValue {
+    SimpleString(String),
+    Error(String),
+    Integer(i64),
+    BulkString(Option<Vec<u8>>),
+    Array(Option<Vec<RespValue>>),
+    // RESP3 only
+    Null,
+    Boolean(bool),
+    Double(String),
+    BigNumber(String),
+    BulkError(String),
+    VerbatimString(String),
+    Map(Vec<(RespValue, RespValue)>),
+    Set(Vec<RespValue>),
+    Push(Vec<RespValue>),
+}
+
+impl RespValue {
+    pub fn type_desc(&self) -> &'static str {
+        match self {
+            RespValue::SimpleString(_) => "string",
+            RespValue::Error(_) | RespValue::BulkError(_) => "error",
+            RespValue::Integer(_) => "integer",
+            RespValue::BulkString(Some(_)) => "bulk string",
+            RespValue::Array(Some(_)) => "array",
+            RespValue::BulkString(None) | RespValue::Array(None) | RespValue::Null => "nil",
+            RespValue::Boolean(_) => "boolean",
+            RespValue::Double(_) => "double",
+            RespValue::BigNumber(_) => "big number",
+            RespValue::VerbatimString(_) => "verbatim string",
+            RespValue::Map(_) => "map",
+            RespValue::Set(_) => "set",
+            RespValue::Push(_) => "push",
+        }
+    }
+
+    /// byte count for strings, element count for aggregates
+    pub fn size(&self) -> usize {
+        match self {
+            RespValue::SimpleString(s)
+            | RespValue::Error(s)
+            | RespValue::Double(s)
+            | RespValue::BigNumber(s)
+            | RespValue::BulkError(s)
+            | RespValue::VerbatimString(s) => s.len(),
+            RespValue::BulkString(Some(b)) => b.len(),
+            RespValue::Array(Some(items)) | RespValue::Set(items) | RespValue::Push(items) => {
+                items.len()
+            }
+            RespValue::Map(items) => items.len(),
+            RespValue::Integer(_) | RespValue::Boolean(_) => 1,
+            RespValue::BulkString(None) | RespValue::Array(None) | RespValue::Null => 0,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, RespValue::Error(_) | RespValue::BulkError(_))
+    }
+
+    /// for commands: the command name followed by its arguments
+    pub fn as_command_args(&self) -> Option<Vec<String>> {
+        match self {
+            RespValue::Array(Some(items)) => items
+                .iter()
+                .map(|i| match i {
+                    RespValue::BulkString(Some(b)) => Some(String::from_utf8_lossy(b).to_string()),
+                    RespValue::SimpleString(s) => Some(s.clone()),
+                    RespValue::Integer(i) => Some(i.to_string()),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            RespValue::SimpleString(s)
+            | RespValue::VerbatimString(s)
+            | RespValue::Double(s)
+            | RespValue::BigNumber(s) => serde_json::json!(s),
+            RespValue::Error(s) | RespValue::BulkError(s) => serde_json::json!({ "error": s }),
+            RespValue::Integer(i) => serde_json::json!(i),
+            RespValue::Boolean(b) => serde_json::json!(b),
+            RespValue::BulkString(Some(b)) => serde_json::json!(String::from_utf8_lossy(b)),
+            RespValue::BulkString(None) | RespValue::Array(None) | RespValue::Null => {
+                serde_json::Value::Null
+            }
+            RespValue::Array(Some(items)) | RespValue::Set(items) | RespValue::Push(items) => {
+                serde_json::Value::Array(items.iter().map(|i| i.to_json()).collect())
+            }
+            RespValue::Map(items) => serde_json::Value::Array(
+                items
+                    .iter()
+                    .map(|(k, v)| serde_json::json!([k.to_json(), v.to_json()]))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// display the value the way redis-cli does
+    pub fn display(&self) -> String {
+        let mut result = String::new();
+        self.display_indent(&mut result, 0);
+        result
+    }
+
+    fn display_indent(&self, output: &mut String, indent: usize) {
+        match self {
+            RespValue::SimpleString(s) => output.push_str(s),
+            RespValue::Error(s) | RespValue::BulkError(s) => {
+                output.push_str("(error) ");
+                output.push_str(s);
+            }
+            RespValue::Integer(i) => output.push_str(&format!("(integer) {}", i)),
+            RespValue::BulkString(Some(b)) => {
+                output.push_str(&format!("{:?}", String::from_utf8_lossy(b)))
+            }
+            RespValue::BulkString(None) | RespValue::Array(None) | RespValue::Null => {
+                output.push_str("(nil)")
+            }
+            RespValue::Boolean(b) => output.push_str(&format!("({})", b)),
+            RespValue::Double(d) => output.push_str(&format!("(double) {}", d)),
+            RespValue::BigNumber(n) => output.push_str(&format!("(big number) {}", n)),
+            RespValue::VerbatimString(s) => output.push_str(s),
+            RespValue::Array(Some(items)) | RespValue::Set(items) | RespValue::Push(items) => {
+                if items.is_empty() {
+                    output.push_str("(empty array)");
+                }
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        output.push('\n');
+                        output.push_str(&" ".repeat(indent));
+                    }
+                    let prefix = format!("{}) ", idx + 1);
+                    output.push_str(&prefix);
+                    item.display_indent(output, indent + prefix.len());
+                }
+            }
+            RespValue::Map(items) => {
+                if items.is_empty() {
+                    output.push_str("(empty hash)");
+                }
+                for (idx, (k, v)) in items.iter().enumerate() {
+                    if idx > 0 {
+                        output.push('\n');
+                        output.push_str(&" ".repeat(indent));
+                    }
+                    let prefix = format!("{}# ", idx + 1);
+                    output.push_str(&prefix);
+                    k.display_indent(output, indent + prefix.len());
+                    output.push_str(" => ");
+                    v.display_indent(output, indent + prefix.len());
+                }
+            }
+        }
+    }
+}
+
+fn read_line(data: &[u8]) -> Option<(&str, usize)> {
+    let pos = data.windows(2).position(|w| w == b"\r\n")?;
+    Some((str::from_utf8(&data[..pos]).ok()?, pos + 2))
+}
+
+fn parse_number<T: str::FromStr>(line: &str) -> Result<T, String> {
+    line.parse()
+        .map_err(|_| format!("Invalid RESP number: {}", line))
+}
+
+/// Returns Ok(None) if the data is incomplete: we must wait for more TCP segments.
+/// Otherwise returns the value and the number of bytes it consumed.
+pub fn parse_resp(data: &[u8]) -> Result<Option<(RespValue, usize)>, String> {
+    parse_resp_nested(data, 0)
+}
+
+fn parse_resp_nested(data: &[u8], depth: usize) -> Result<Option<(RespValue, usize)>, String> {
+    if depth > MAX_NESTING_DEPTH {
+        return Err("RESP values nested too deep".to_string());
+    }
+    let (type_byte, rest) = match data.split_first() {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+    let (line, line_len) = match read_line(rest) {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+    let header_len = 1 + line_len;
+    let value_and_len = match type_byte {
+        b'+' => (RespValue::SimpleString(line.to_string()), header_len),
+        b'-' => (RespValue::Error(line.to_string()), header_len),
+        b':' => (RespValue::Integer(parse_number(line)?), header_len),
+        b',' => (RespValue::Double(line.to_string()), header_len),
+        b'(' => (RespValue::BigNumber(line.to_string()), header_len),
+        b'_' => (RespValue::Null, header_len),
+        b'#' => (RespValue::Boolean(line == "t"), header_len),
+        b'$' | b'!' | b'=' => {
+            let len: i64 = parse_number(line)?;
+            if len < 0 {
+                (RespValue::BulkString(None), header_len)
+            } else {
+                let len = len as usize;
+                if len > MAX_BULK_LENGTH {
+                    return Err(format!("RESP bulk string too long: {} bytes", len));
+                }
+                if rest.len() < line_len + len + 2 {
+                    return Ok(None);
+                }
+                let contents = &rest[line_len..line_len + len];
+                (
+                    match type_byte {
+                        b'$' => RespValue::BulkString(Some(contents.to_vec())),
+                        b'!' => RespValue::BulkError(String::from_utf8_lossy(contents).to_string()),
+                        // verbatim strings start with the format, for instance "txt:"
+                        _ => RespValue::VerbatimString(
+                            String::from_utf8_lossy(contents.get(4..).unwrap_or(contents))
+                                .to_string(),
+                        ),
+                    },
+                    header_len + len + 2,
+                )
+            }
+        }
+        b'*' | b'~' | b'>' | b'%' | b'|' => {
+            let count: i64 = parse_number(line)?;
+            if count < 0 {
+                (RespValue::Array(None), header_len)
+            } else {
+                let item_count = if matches!(type_byte, b'%' | b'|') {
+                    (count as usize).saturating_mul(2)
+                } else {
+                    count as usize
+                };
+                // don't trust the count for the allocation: we stop
+                // at the end of the data anyway
+                let mut items = vec![];
+                let mut consumed = header_len;
+                for _ in 0..item_count {
+                    match parse_resp_nested(&data[consumed..], depth + 1)? {
+                        Some((item, len)) => {
+                            items.push(item);
+                            consumed += len;
+                        }
+                        None => return Ok(None),
+                    }
+                }
+                match type_byte {
+                    b'*' => (RespValue::Array(Some(items)), consumed),
+                    b'~' => (RespValue::Set(items), consumed),
+                    b'>' => (RespValue::Push(items), consumed),
+                    b'%' => (RespValue::Map(pairs(items)), consumed),
+                    _ => {
+                        // attributes are out-of-band metadata about the following value,
+                        // skip them
+                        return Ok(parse_resp_nested(&data[consumed..], depth + 1)?
+                            .map(|(v, len)| (v, consumed + len)));
+                    }
+                }
+            }
+        }
+        _ => {
+            // inline command, for instance typed in telnet
+            (
+                RespValue::Array(Some(
+                    (str::from_utf8(&data[..line_len - 1]).map_err(|e| e.to_string())?)
+                        .split_ascii_whitespace()
+                        .map(|s| RespValue::BulkString(Some(s.as_bytes().to_vec())))
+                        .collect(),
+                )),
+                line_len + 1,
+            )
+        }
+    };
+    Ok(Some(value_and_len))
+}
+
+fn pairs(items: Vec<RespValue>) -> Vec<(RespValue, RespValue)> {
+    let mut result = Vec::with_capacity(items.len() / 2);
+    let mut it = items.into_iter();
+    while let (Some(k), Some(v)) = (it.next(), it.next()) {
+        result.push((k, v));
+    }
+    result
+}
+
+#[test]
+fn should_parse_resp_command() {
+    assert_eq!(
+        Ok(Some((
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"GET".to_vec())),
+                RespValue::BulkString(Some(b"mykey".to_vec())),
+            ])),
+            24
+        ))),
+        parse_resp(b"*2\r\n$3\r\nGET\r\n$5\r\nmykey\r\n")
+    );
+}
+
+#[test]
+fn should_wait_for_incomplete_resp() {
+    assert_eq!(Ok(None), parse_resp(b"*2\r\n$3\r\nGET\r\n$5\r\nmyk"));
+    assert_eq!(Ok(None), parse_resp(b"$10\r\nabc"));
+}
+
+#[test]
+fn should_reject_bogus_resp_lengths() {
+    // no allocation from the count, we wait for the items
+    assert_eq!(Ok(None), parse_resp(b"*100000000000\r\n:1\r\n"));
+    assert_eq!(Ok(None), parse_resp(b"%9223372036854775807\r\n"));
+    assert!(parse_resp(b"$100000000000\r\nabc").is_err());
+    assert!(parse_resp("*1\r\n".repeat(100_000).as_bytes()).is_err());
+    assert!(parse_resp("|0\r\n".repeat(100_000).as_bytes()).is_err());
+}
+
+#[test]
+fn should_parse_resp3_map() {
+    assert_eq!(
+        Ok(Some((
+            RespValue::Map(vec![(
+                RespValue::SimpleString("a".to_string()),
+                RespValue::Integer(1)
+            )]),
+            12
+        ))),
+        parse_resp(b"%1\r\n+a\r\n:1\r\n")
+    );
+}
+
+#[test]
+fn should_parse_inline_command() {
+    assert_eq!(
+        Ok(Some((
+            RespValue::Array(Some(vec![RespValue::BulkString(Some(b"PING".to_vec()))])),
+            6
+        ))),
+        parse_resp(b"PING\r\n")
+    );
+}
@@ -0,0 +1,167 @@
+// named searches that the user saved, stored next to config.toml.
+// the file can be exported & imported, so that a team can share its searches.
+use crate::config;
+use serde_derive::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SavedSearch {
+    pub name: String,
+    pub search: String,
+}
+
+#[derive(Deserialize, Serialize, Default)]
+struct SavedSearchesFile {
+    #[serde(default)]
+    searches: Vec<SavedSearch>,
+}
+
+/// offered in addition to the user's searches. We only display the searches
+/// which are valid for the protocol of the selected server, so these are
+/// effectively per-protocol presets.
+const BUILTIN_SEARCHES: &[(&str, &str)] = &[
+    // the response column holds the whole status line: "HTTP/1.1 500 Internal
+    // Server Error" for http1, "HTTP/2 status 500" for http2
+    (
+        "HTTP 5xx",
+        r"http.resp_status matches /^HTTP\/\S+ (status )?5\d\d\b/",
+    ),
+    (
+        "HTTP 4xx",
+        r"http.resp_status matches /^HTTP\/\S+ (status )?4\d\d\b/",
+    ),
+    (
+        "HTTP bodies > 1MB",
+        "http.req_body_megabytes > 1 or http.resp_body_megabytes > 1",
+    ),
+    (
+        "HTTP JSON responses",
+        "http.resp_content_type contains json",
+    ),
+    ("pg errors", "pg.error matches /./"),
+    ("pg failed transactions", "pg.in_failed_transaction is true"),
+    (
+        "pg failed logins",
+        "pg.query startsWith LOGIN and pg.error matches /./",
+    ),
+    ("MySQL errors", "mysql.error matches /./"),
+    ("Redis errors", "redis.reply startsWith \"(error)\""),
+];
+
+pub fn builtin_saved_searches() -> Vec<SavedSearch> {
+    BUILTIN_SEARCHES
+        .iter()
+        .map(|(name, search)| SavedSearch {
+            name: name.to_string(),
+            search: search.to_string(),
+        })
+        .collect()
+}
+
+pub fn saved_searches_path() -> config::Result<PathBuf> {
+    Ok(config::config_folder()?.join("saved_searches.toml"))
+}
+
+/// the searches the user saved (not including the built-in ones)
+pub fn read_saved_searches() -> config::Result<Vec<SavedSearch>> {
+    let path = saved_searches_path()?;
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+    read_saved_searches_file(&path)
+}
+
+pub fn save_saved_searches(searches: &[SavedSearch]) -> config::Result<()> {
+    write_saved_searches_file(&saved_searches_path()?, searches)
+}
+
+pub fn read_saved_searches_file(path: &Path) -> config::Result<Vec<SavedSearch>> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    parse_saved_searches(&contents)
+}
+
+pub fn write_saved_searches_file(path: &Path, searches: &[SavedSearch]) -> config::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(serialize_saved_searches(searches)?.as_bytes())?;
+    Ok(())
+}
+
+fn parse_saved_searches(contents: &str) -> config::Result<Vec<SavedSearch>> {
+    let file: SavedSearchesFile = toml::from_str(contents)?;
+    Ok(file.searches)
+}
+
+fn serialize_saved_searches(searches: &[SavedSearch]) -> config::Result<String> {
+    Ok(toml::to_string_pretty(&SavedSearchesFile {
+        searches: searches.to_vec(),
+    })?)
+}
+
+/// add or replace (by name) a search in the list
+pub fn upsert_saved_search(searches: &mut Vec<SavedSearch>, search: SavedSearch) {
+    match searches.iter_mut().find(|s| s.name == search.name) {
+        Some(existing) => *existing = search,
+        None => searches.push(search),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search_expr;
+    use crate::streams::Streams;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn should_roundtrip_saved_searches_file() {
+        let mut searches = vec![SavedSearch {
+            name: "slow".to_string(),
+            search: "http.duration_ms > 1000".to_string(),
+        }];
+        upsert_saved_search(
+            &mut searches,
+            SavedSearch {
+                name: "with \"quotes\"".to_string(),
+                search: "pg.query contains \"select *\"".to_string(),
+            },
+        );
+        upsert_saved_search(
+            &mut searches,
+            SavedSearch {
+                name: "slow".to_string(),
+                search: "http.duration_ms > 2000".to_string(),
+            },
+        );
+        assert_eq!(2, searches.len());
+        assert_eq!("http.duration_ms > 2000", searches[0].search);
+        assert_eq!(
+            searches,
+            parse_saved_searches(&serialize_saved_searches(&searches).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn builtin_searches_should_be_valid() {
+        let streams = Streams::default();
+        let store_count = streams.get_streams_stores().len();
+        let string_keys: BTreeSet<_> = (0..store_count)
+            .flat_map(|idx| streams.supported_string_filter_keys(idx))
+            .collect();
+        let numeric_keys: BTreeSet<_> = (0..store_count)
+            .flat_map(|idx| streams.supported_numeric_filter_keys(idx))
+            .collect();
+        for search in builtin_saved_searches() {
+            assert!(
+                matches!(
+                    search_expr::parse_search(&string_keys, &numeric_keys)(&search.search),
+                    Ok(("", _))
+                ),
+                "{}",
+                search.name
+            );
+        }
+    }
+}
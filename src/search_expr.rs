@@ -1,6 +1,7 @@
+use chrono::{NaiveDate, NaiveDateTime};
 use nom;
 use nom::branch::*;
-use nom::bytes::complete::tag;
+use nom::bytes::complete::{tag, take_while1};
 use nom::character::complete::*;
 use nom::combinator::*;
 use nom::error::*;
@@ -25,30 +26,181 @@ pub enum OperatorNegation {
     NotNegated,
 }
 
+/// the operators offered by the search options popover and the search completion
+pub const STRING_OPERATORS: &[&str] = &["contains", "doesntContain", "is", "startsWith", "matches"];
+pub const NUMERIC_OPERATORS: &[&str] = &[">", "<", ">=", "<=", "between"];
+
+/// regex::Regex doesn't implement Eq, so we compare the patterns
+#[derive(Debug, Clone)]
+pub struct SearchRegex(pub regex::Regex);
+
+impl PartialEq for SearchRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for SearchRegex {}
+
+/// number + number of decimal places
+pub type NumberWithDecimals = (usize, u8);
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum SearchCriteria {
     Contains(String),
+    Is(String),
+    StartsWith(String),
+    Matches(SearchRegex),
     // number + number of decimal places
     GreaterThan(usize, u8),
+    GreaterOrEqual(usize, u8),
+    LessOrEqual(usize, u8),
+    Between(NumberWithDecimals, NumberWithDecimals),
+}
+
+fn display_number(val: usize, dec: u8) -> String {
+    if dec != 0 {
+        // 0.05 is stored as (5, 2): pad to "005" so there's a digit before the dot
+        let dec = dec as usize;
+        let val_str = format!("{:0width$}", val, width = dec + 1);
+        format!(
+            "{}.{}",
+            &val_str[0..(val_str.len() - dec)],
+            &val_str[(val_str.len() - dec)..]
+        )
+    } else {
+        val.to_string()
+    }
+}
+
+fn number_with_multiplier(val: usize, dec: u8, multiplier: usize) -> usize {
+    val * multiplier / 10_usize.pow(dec.into())
 }
 
 impl SearchCriteria {
     pub fn display_val(&self) -> Cow<str> {
         match &self {
-            SearchCriteria::Contains(s) => Cow::Borrowed(s),
-            SearchCriteria::GreaterThan(val, dec) => {
-                let val_str = val.to_string();
-                if *dec != 0 {
-                    Cow::Owned(format!(
-                        "{}.{}",
-                        &val_str[0..(val_str.len() - *dec as usize)],
-                        &val_str[(val_str.len() - *dec as usize)..]
-                    ))
-                } else {
-                    Cow::Owned(val_str)
-                }
+            SearchCriteria::Contains(s) | SearchCriteria::Is(s) | SearchCriteria::StartsWith(s) => {
+                Cow::Borrowed(s)
             }
+            SearchCriteria::Matches(r) => Cow::Borrowed(r.0.as_str()),
+            SearchCriteria::GreaterThan(val, dec)
+            | SearchCriteria::GreaterOrEqual(val, dec)
+            | SearchCriteria::LessOrEqual(val, dec) => Cow::Owned(display_number(*val, *dec)),
+            SearchCriteria::Between((low, low_dec), (high, high_dec)) => Cow::Owned(format!(
+                "{} and {}",
+                display_number(*low, *low_dec),
+                display_number(*high, *high_dec)
+            )),
+        }
+    }
+
+    /// the operator, as typed in a search expression
+    pub fn operator(&self, op_negation: OperatorNegation) -> &'static str {
+        match (self, op_negation) {
+            (SearchCriteria::Contains(_), OperatorNegation::NotNegated) => "contains",
+            (SearchCriteria::Contains(_), OperatorNegation::Negated) => "doesntContain",
+            (SearchCriteria::Is(_), _) => "is",
+            (SearchCriteria::StartsWith(_), _) => "startsWith",
+            (SearchCriteria::Matches(_), _) => "matches",
+            (SearchCriteria::GreaterThan(_, _), OperatorNegation::NotNegated) => ">",
+            (SearchCriteria::GreaterThan(_, _), OperatorNegation::Negated) => "<",
+            (SearchCriteria::GreaterOrEqual(_, _), _) => ">=",
+            (SearchCriteria::LessOrEqual(_, _), _) => "<=",
+            (SearchCriteria::Between(_, _), _) => "between",
+        }
+    }
+
+    /// string criteria ignore the case, except for regular expressions
+    /// (which can use (?i) for that)
+    pub fn matches_str(&self, val: &str) -> bool {
+        match self {
+            SearchCriteria::Contains(s) => val.to_lowercase().contains(&s.to_lowercase()),
+            SearchCriteria::Is(s) => val.to_lowercase() == s.to_lowercase(),
+            SearchCriteria::StartsWith(s) => val.to_lowercase().starts_with(&s.to_lowercase()),
+            SearchCriteria::Matches(r) => r.0.is_match(val),
+            _ => false,
+        }
+    }
+
+    /// the multiplier allows to express the filter value in a different
+    /// unit than the value, for instance kilobytes for a byte count
+    pub fn matches_number(&self, val: usize, multiplier: usize) -> bool {
+        let filter_val = |v, dec| number_with_multiplier(v, dec, multiplier);
+        match self {
+            SearchCriteria::GreaterThan(v, dec) => val > filter_val(*v, *dec),
+            SearchCriteria::GreaterOrEqual(v, dec) => val >= filter_val(*v, *dec),
+            SearchCriteria::LessOrEqual(v, dec) => val <= filter_val(*v, *dec),
+            SearchCriteria::Between((low, low_dec), (high, high_dec)) => {
+                val >= filter_val(*low, *low_dec) && val <= filter_val(*high, *high_dec)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// build the criteria from an operator and a value as entered separately
+/// in the GUI (so the value isn't quoted, and regexes aren't between slashes)
+pub fn criteria_from_operator(
+    operator: &str,
+    val: &str,
+) -> Option<(SearchCriteria, OperatorNegation)> {
+    let number = || match parse_filter_val_number(val) {
+        Ok(("", n)) => Some(n),
+        _ => None,
+    };
+    match operator {
+        "contains" => Some((
+            SearchCriteria::Contains(val.to_string()),
+            OperatorNegation::NotNegated,
+        )),
+        "doesntContain" => Some((
+            SearchCriteria::Contains(val.to_string()),
+            OperatorNegation::Negated,
+        )),
+        "is" => Some((
+            SearchCriteria::Is(val.to_string()),
+            OperatorNegation::NotNegated,
+        )),
+        "startsWith" => Some((
+            SearchCriteria::StartsWith(val.to_string()),
+            OperatorNegation::NotNegated,
+        )),
+        "matches" => regex::Regex::new(val).ok().map(|r| {
+            (
+                SearchCriteria::Matches(SearchRegex(r)),
+                OperatorNegation::NotNegated,
+            )
+        }),
+        ">" => number().map(|(v, d)| {
+            (
+                SearchCriteria::GreaterThan(v, d),
+                OperatorNegation::NotNegated,
+            )
+        }),
+        "<" => {
+            number().map(|(v, d)| (SearchCriteria::GreaterThan(v, d), OperatorNegation::Negated))
         }
+        ">=" => number().map(|(v, d)| {
+            (
+                SearchCriteria::GreaterOrEqual(v, d),
+                OperatorNegation::NotNegated,
+            )
+        }),
+        "<=" => number().map(|(v, d)| {
+            (
+                SearchCriteria::LessOrEqual(v, d),
+                OperatorNegation::NotNegated,
+            )
+        }),
+        "between" => match parse_filter_val_number_range(val) {
+            Ok(("", (low, high))) => Some((
+                SearchCriteria::Between(low, high),
+                OperatorNegation::NotNegated,
+            )),
+            _ => None,
+        },
+        _ => None,
     }
 }
 
@@ -236,7 +388,49 @@ fn parse_number_decimals(input: &str) -> nom::IResult<&str, &str> {
     digit1(input)
 }
 
+/// timestamps are compared as milliseconds since the epoch, so that
+/// all the numeric operators work on them
 pub fn parse_filter_val_number(input: &str) -> nom::IResult<&str, (usize, u8)> {
+    alt((
+        map(parse_filter_val_datetime, |dt| {
+            (dt.timestamp_millis().max(0) as usize, 0)
+        }),
+        parse_filter_val_plain_number,
+    ))(input)
+}
+
+/// a timestamp as displayed in the messages list, either quoted:
+/// `"2021-03-04 12:30:05.123"`, or with a T: `2021-03-04T12:30:05`,
+/// or just a date: `2021-03-04`
+fn parse_filter_val_datetime(input: &str) -> nom::IResult<&str, NaiveDateTime> {
+    map_res(
+        alt((
+            parse_quoted_string,
+            map(
+                recognize(tuple((
+                    digit1,
+                    char('-'),
+                    digit1,
+                    char('-'),
+                    digit1,
+                    opt(pair(
+                        char('T'),
+                        take_while1(|c: char| c.is_ascii_digit() || c == ':' || c == '.'),
+                    )),
+                ))),
+                |s: &str| s.to_string(),
+            ),
+        )),
+        |s: String| {
+            let s = s.replace('T', " ");
+            NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S%.f")
+                .or_else(|_| NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M"))
+                .or_else(|_| NaiveDate::parse_from_str(&s, "%Y-%m-%d").map(|d| d.and_hms(0, 0, 0)))
+        },
+    )(input)
+}
+
+fn parse_filter_val_plain_number(input: &str) -> nom::IResult<&str, (usize, u8)> {
     let (input, digits) = digit1(input)?;
     let (input, decimal_number) = opt(parse_number_decimals)(input)?;
     match decimal_number.map(|s| s.len()).unwrap_or(0).try_into() {
@@ -269,10 +463,32 @@ pub fn parse_filter_val_number(input: &str) -> nom::IResult<&str, (usize, u8)> {
     }
 }
 
+/// `low and high`
+pub fn parse_filter_val_number_range(
+    input: &str,
+) -> nom::IResult<&str, (NumberWithDecimals, NumberWithDecimals)> {
+    let (input, low) = parse_filter_val_number(input)?;
+    let (input, _) = space1(input)?;
+    let (input, _) = tag("and")(input)?;
+    let (input, _) = space1(input)?;
+    let (input, high) = parse_filter_val_number(input)?;
+    Ok((input, (low, high)))
+}
+
 fn parse_filter_op_number(input: &str) -> nom::IResult<&str, (SearchCriteria, OperatorNegation)> {
-    // TODO duplicated with headerbar_search::update_search_completion
-    let (input, t) = alt((tag(">"), tag("<")))(input)?;
+    // TODO duplicated with NUMERIC_OPERATORS
+    let (input, t) = alt((tag(">="), tag("<="), tag(">"), tag("<"), tag("between")))(input)?;
     let (input, _) = space1(input)?;
+    if t == "between" {
+        let (input, (low, high)) = parse_filter_val_number_range(input)?;
+        return Ok((
+            input,
+            (
+                SearchCriteria::Between(low, high),
+                OperatorNegation::NotNegated,
+            ),
+        ));
+    }
     let (input, (val, dec)) = parse_filter_val_number(input)?;
     match t {
         ">" => Ok((
@@ -289,6 +505,20 @@ fn parse_filter_op_number(input: &str) -> nom::IResult<&str, (SearchCriteria, Op
                 OperatorNegation::Negated,
             ),
         )),
+        ">=" => Ok((
+            input,
+            (
+                SearchCriteria::GreaterOrEqual(val, dec),
+                OperatorNegation::NotNegated,
+            ),
+        )),
+        "<=" => Ok((
+            input,
+            (
+                SearchCriteria::LessOrEqual(val, dec),
+                OperatorNegation::NotNegated,
+            ),
+        )),
         _ => panic!("unhandled: {}", t),
     }
 }
@@ -298,11 +528,35 @@ fn parse_filter_val_string(input: &str) -> nom::IResult<&str, String> {
 }
 
 fn parse_filter_op_string(input: &str) -> nom::IResult<&str, (SearchCriteria, OperatorNegation)> {
-    // TODO duplicated with headerbar_search::update_search_completion
-    let (input, t) = alt((tag("doesntContain"), tag("contains")))(input)?;
+    // TODO duplicated with STRING_OPERATORS
+    let (input, t) = alt((
+        tag("doesntContain"),
+        tag("contains"),
+        tag("is"),
+        tag("startsWith"),
+        tag("matches"),
+    ))(input)?;
     let (input, _) = space1(input)?;
+    if t == "matches" {
+        let (input, regex) = parse_regex(input)?;
+        return Ok((
+            input,
+            (SearchCriteria::Matches(regex), OperatorNegation::NotNegated),
+        ));
+    }
     let (input, filter_val) = parse_filter_val_string(input)?;
     match t {
+        "is" => Ok((
+            input,
+            (SearchCriteria::Is(filter_val), OperatorNegation::NotNegated),
+        )),
+        "startsWith" => Ok((
+            input,
+            (
+                SearchCriteria::StartsWith(filter_val),
+                OperatorNegation::NotNegated,
+            ),
+        )),
         "contains" => Ok((
             input,
             (
@@ -337,8 +591,11 @@ fn parse_filter_key(
 
 fn parse_filter_key_basic(input: &str) -> nom::IResult<&str, ()> {
     let (input, _) = alpha1(input)?;
-    let (input, _) = char('.')(input)?;
-    let (input, _) = many1(satisfy(|c| c.is_alpha() || c == '_'))(input)?;
+    // most keys are prefixed by the protocol, but not all ("time")
+    let (input, _) = opt(pair(
+        char('.'),
+        many1(satisfy(|c| c.is_alpha() || c == '_')),
+    ))(input)?;
     Ok((input, ()))
 }
 
@@ -352,6 +609,32 @@ fn parse_quoted_string(input: &str) -> nom::IResult<&str, String> {
     Ok((input, st))
 }
 
+/// `/regex/`, with `\/` for a slash within the regex
+fn parse_regex(input: &str) -> nom::IResult<&str, SearchRegex> {
+    let (input, _) = char('/')(input)?;
+    let (input, pattern) = fold_many0(
+        alt((
+            map(tag("\\/"), |_| "/"),
+            recognize(pair(char('\\'), anychar)),
+            recognize(none_of("\\/")),
+        )),
+        String::new,
+        |mut sofar, cur| {
+            sofar.push_str(cur);
+            sofar
+        },
+    )(input)?;
+    let (rest, _) = char('/')(input)?;
+    let regex = regex::Regex::new(&pattern).map_err(|_| {
+        Err::Error(Error::from_external_error(
+            input,
+            ErrorKind::Verify,
+            "Invalid regular expression",
+        ))
+    })?;
+    Ok((rest, SearchRegex(regex)))
+}
+
 fn quoted_string_char(input: &str) -> nom::IResult<&str, char> {
     alt((none_of("\\\""), escaped_char))(input)
 }
@@ -574,4 +857,134 @@ mod tests {
             .unwrap()
         );
     }
+
+    #[test]
+    fn parse_between_search_expression() {
+        assert_eq!(
+            (
+                "",
+                SearchExpr::SearchOpExpr(SearchOpExpr {
+                    filter_key: "http.req_size_bytes",
+                    op: SearchCriteria::Between((10, 0), (125, 1)),
+                    op_negation: OperatorNegation::NotNegated,
+                })
+            ),
+            parse_search(
+                &BTreeSet::new(),
+                &["http.req_size_bytes"].iter().cloned().collect()
+            )("http.req_size_bytes between 10 and 12.5")
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_greater_or_equal_search_expression() {
+        assert_eq!(
+            (
+                "",
+                SearchExpr::SearchOpExpr(SearchOpExpr {
+                    filter_key: "http.req_size_bytes",
+                    op: SearchCriteria::GreaterOrEqual(10, 0),
+                    op_negation: OperatorNegation::NotNegated,
+                })
+            ),
+            parse_search(
+                &BTreeSet::new(),
+                &["http.req_size_bytes"].iter().cloned().collect()
+            )("http.req_size_bytes >= 10")
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_regex_search_expression() {
+        let string_keys = ["http.req_line"].iter().cloned().collect();
+        let numeric_keys = BTreeSet::new();
+        let (rest, expr) = parse_search(&string_keys, &numeric_keys)(
+            "http.req_line matches /^GET \\/api\\/v\\d+/ and http.req_line is \"GET /\"",
+        )
+        .unwrap();
+        assert_eq!("", rest);
+        match expr {
+            SearchExpr::And(left, right) => {
+                match *left {
+                    SearchExpr::SearchOpExpr(SearchOpExpr {
+                        op: SearchCriteria::Matches(r),
+                        ..
+                    }) => {
+                        assert_eq!("^GET /api/v\\d+", r.0.as_str());
+                        assert!(SearchCriteria::Matches(r).matches_str("GET /api/v2/users"));
+                    }
+                    _ => panic!(),
+                }
+                assert_eq!(
+                    SearchExpr::SearchOpExpr(SearchOpExpr {
+                        filter_key: "http.req_line",
+                        op: SearchCriteria::Is("GET /".to_string()),
+                        op_negation: OperatorNegation::NotNegated,
+                    }),
+                    *right
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn should_reject_invalid_regex() {
+        assert!(parse_search(
+            &["http.req_line"].iter().cloned().collect(),
+            &BTreeSet::new(),
+        )("http.req_line matches /GET (/")
+        .is_err());
+    }
+
+    #[test]
+    fn should_display_values_with_leading_decimal_zeros() {
+        let string_keys = BTreeSet::new();
+        let numeric_keys = ["http.duration_ms"].iter().cloned().collect();
+        let display = |expr: &str| {
+            let parsed = parse_search(&string_keys, &numeric_keys)(expr);
+            match parsed.unwrap().1 {
+                SearchExpr::SearchOpExpr(SearchOpExpr { op, .. }) => op.display_val().to_string(),
+                _ => panic!(),
+            }
+        };
+        assert_eq!("0.05", display("http.duration_ms >= 0.05"));
+        assert_eq!("0.05 and 1", display("http.duration_ms between 0.05 and 1"));
+        assert_eq!("12.5", display("http.duration_ms > 12.5"));
+    }
+
+    #[test]
+    fn parse_time_search_expression() {
+        let expected_millis = |dt: &str| {
+            NaiveDateTime::parse_from_str(dt, "%Y-%m-%d %H:%M:%S%.f")
+                .unwrap()
+                .timestamp_millis() as usize
+        };
+        assert_eq!(
+            (
+                "",
+                SearchExpr::And(
+                    Box::new(SearchExpr::SearchOpExpr(SearchOpExpr {
+                        filter_key: "time",
+                        op: SearchCriteria::GreaterThan(
+                            expected_millis("2021-03-04 12:30:05.5"),
+                            0
+                        ),
+                        op_negation: OperatorNegation::NotNegated,
+                    })),
+                    Box::new(SearchExpr::SearchOpExpr(SearchOpExpr {
+                        filter_key: "time",
+                        op: SearchCriteria::GreaterThan(expected_millis("2021-03-05 00:00:00"), 0),
+                        op_negation: OperatorNegation::Negated,
+                    }))
+                )
+            ),
+            parse_search(&BTreeSet::new(), &["time"].iter().cloned().collect())(
+                "time > \"2021-03-04 12:30:05.5\" and time < 2021-03-05"
+            )
+            .unwrap()
+        );
+    }
 }
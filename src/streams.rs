@@ -1,11 +1,17 @@
 use crate::{
-    custom_streams_store::{ClientServerInfo, CustomStreamsStore},
+    config::Config,
+    custom_streams_store::{
+        ClientServerInfo, CommonNumericFilterKeys, CommonStringFilterKeys, CustomStreamsStore,
+    },
     http::http_streams_store::HttpStreamsStore,
     http2::http2_streams_store::Http2StreamsStore,
+    mysql::mysql_streams_store::MysqlStreamsStore,
     pgsql::postgres_streams_store::PostgresStreamsStore,
+    redis::redis_streams_store::RedisStreamsStore,
     tshark_communication::{TSharkPacket, TcpStreamId},
 };
 use itertools::Itertools;
+use strum::VariantNames;
 
 pub struct Streams {
     // this field name is 200% wrong
@@ -25,6 +31,8 @@ impl Default for Streams {
                 Box::new(HttpStreamsStore::default()),
                 Box::new(PostgresStreamsStore::default()),
                 Box::new(Http2StreamsStore::default()),
+                Box::new(MysqlStreamsStore::default()),
+                Box::new(RedisStreamsStore::default()),
             ],
         }
     }
@@ -79,18 +87,27 @@ impl Streams {
             .find_map(|s| s.stream_client_server(stream_id))
     }
 
-    pub fn supported_string_filter_keys(&self, store_index: usize) -> &'static [&'static str] {
-        self.streams
-            .get(store_index)
-            .unwrap()
-            .supported_string_filter_keys()
+    /// the filter keys of that store, and those that all the stores support
+    pub fn supported_string_filter_keys(&self, store_index: usize) -> Vec<&'static str> {
+        let mut keys = CommonStringFilterKeys::VARIANTS.to_vec();
+        keys.extend_from_slice(
+            self.streams
+                .get(store_index)
+                .unwrap()
+                .supported_string_filter_keys(),
+        );
+        keys
     }
 
-    pub fn supported_numeric_filter_keys(&self, store_index: usize) -> &'static [&'static str] {
-        self.streams
-            .get(store_index)
-            .unwrap()
-            .supported_numeric_filter_keys()
+    pub fn supported_numeric_filter_keys(&self, store_index: usize) -> Vec<&'static str> {
+        let mut keys = CommonNumericFilterKeys::VARIANTS.to_vec();
+        keys.extend_from_slice(
+            self.streams
+                .get(store_index)
+                .unwrap()
+                .supported_numeric_filter_keys(),
+        );
+        keys
     }
 
     pub fn finish_stream(&mut self, stream_id: TcpStreamId) -> Result<(), String> {
@@ -107,6 +124,19 @@ impl Streams {
         }
     }
 
+    pub fn apply_config(&mut self, config: &Config) {
+        for mp in &mut self.streams {
+            mp.apply_config(config);
+        }
+    }
+
+    pub fn stream_slow_message_count(&self, stream_id: TcpStreamId) -> usize {
+        self.streams
+            .iter()
+            .find(|s| s.has_stream_id(stream_id))
+            .map_or(0, |s| s.stream_slow_message_count(stream_id))
+    }
+
     pub fn is_empty(&self) -> bool {
         self.streams.iter().all(|mp| mp.is_empty())
     }
@@ -1,6 +1,8 @@
 use crate::http::tshark_http;
 use crate::http2::tshark_http2;
+use crate::mysql::tshark_mysql;
 use crate::pgsql::tshark_pgsql;
+use crate::redis::tshark_redis;
 use chrono::NaiveDateTime;
 use quick_xml::events::attributes::Attributes;
 use quick_xml::events::Event;
@@ -94,6 +96,10 @@ pub struct TSharkPacket {
     pub http: Option<Vec<tshark_http::TSharkHttp>>,
     pub http2: Option<Vec<tshark_http2::TSharkHttp2Message>>,
     pub pgsql: Option<Vec<tshark_pgsql::PostgresWireMessage>>,
+    pub mysql: Option<Vec<tshark_mysql::MysqlWireMessage>>,
+    // raw RESP bytes: they're parsed by the redis streams store,
+    // because a command or reply may span multiple TCP segments
+    pub redis: Option<Vec<u8>>,
     pub tcp_payload: Option<Vec<u8>>,
     pub is_malformed: bool,
 }
@@ -101,6 +107,7 @@ pub struct TSharkPacket {
 pub fn parse_packet<B: BufRead>(
     xml_reader: &mut quick_xml::Reader<B>,
     http1_streams: &HashSet<TcpStreamId>,
+    redis_streams: &HashSet<TcpStreamId>,
     temp_tcp_payload: &mut Vec<u8>,
 ) -> Result<TSharkPacket, String> {
     let mut frame_time = NaiveDateTime::from_timestamp(0, 0);
@@ -113,6 +120,7 @@ pub fn parse_packet<B: BufRead>(
     let mut http = None::<Vec<tshark_http::TSharkHttp>>;
     let mut http2 = None::<Vec<tshark_http2::TSharkHttp2Message>>;
     let mut pgsql = None::<Vec<tshark_pgsql::PostgresWireMessage>>;
+    let mut mysql = None::<Vec<tshark_mysql::MysqlWireMessage>>;
     let mut is_malformed = false;
     temp_tcp_payload.clear();
     let buf = &mut vec![];
@@ -134,7 +142,7 @@ pub fn parse_packet<B: BufRead>(
                     }
                     Some(b"tcp") => {
                         // waiting for https://github.com/rust-lang/rust/issues/71126
-                        let tcp_info = parse_tcp_info(xml_reader, http1_streams, temp_tcp_payload)?;
+                        let tcp_info = parse_tcp_info(xml_reader, http1_streams, redis_streams, temp_tcp_payload)?;
                         tcp_seq_number = tcp_info.0;
                         tcp_stream_id = tcp_info.1;
                         port_src = tcp_info.2;
@@ -168,6 +176,16 @@ pub fn parse_packet<B: BufRead>(
                             }
                         }
                     }
+                    Some(b"mysql") => {
+                        if let Some(mysql_packet) = tshark_mysql::parse_mysql_info(xml_reader)? {
+                            if let Some(mut sofar) = mysql {
+                                sofar.push(mysql_packet);
+                                mysql = Some(sofar);
+                            } else {
+                                mysql = Some(vec![mysql_packet]);
+                            }
+                        }
+                    }
                     Some(b"icmp") => {
                         // need to skip ICMP info, because it also contains IP info that can confuse us
                         skip_until_proto_end(xml_reader)?;
@@ -180,12 +198,16 @@ pub fn parse_packet<B: BufRead>(
             }
         }
         Ok(Event::End(ref e)) => {
-            let tcp_payload =
-                if !temp_tcp_payload.is_empty() && http1_streams.contains(&tcp_stream_id) && http.is_none() {
-                    hex::decode(&temp_tcp_payload).ok()
-                } else {
-                    None
-                };
+            // we parse the RESP contents from the TCP payload ourselves, so that
+            // we don't depend on the dissector being present. We recognize redis
+            // by its port only: the payload is gone by the time we see the dissector
+            let is_redis = redis_streams.contains(&tcp_stream_id)
+                || tshark_redis::is_redis_port(port_src, port_dst);
+            let (redis, tcp_payload) = keep_tcp_payload(
+                temp_tcp_payload,
+                is_redis,
+                http1_streams.contains(&tcp_stream_id) && http.is_none(),
+            );
             if let (b"packet", Some(src), Some(dst)) = (e.name(), ip_src, ip_dst) {
                 return Ok(TSharkPacket {
                     basic_info: TSharkPacketBasicInfo {
@@ -200,6 +222,8 @@ pub fn parse_packet<B: BufRead>(
                     http,
                     http2,
                     pgsql,
+                    mysql,
+                    redis,
                     tcp_payload,
                     is_malformed
                 });
@@ -208,6 +232,30 @@ pub fn parse_packet<B: BufRead>(
     )
 }
 
+/// we keep the raw TCP payload for redis, which we parse ourselves, and for
+/// HTTP/1 streams when tshark didn't recognize the packet as HTTP.
+/// Returns the redis payload & the other TCP payload.
+pub fn keep_tcp_payload(
+    hex_payload: &[u8],
+    is_redis: bool,
+    is_unparsed_http1: bool,
+) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+    if hex_payload.is_empty() {
+        return (None, None);
+    }
+    let redis = if is_redis {
+        hex::decode(hex_payload).ok()
+    } else {
+        None
+    };
+    let tcp_payload = if is_unparsed_http1 {
+        hex::decode(hex_payload).ok()
+    } else {
+        None
+    };
+    (redis, tcp_payload)
+}
+
 fn skip_until_proto_end<B: BufRead>(xml_reader: &mut quick_xml::Reader<B>) -> Result<(), String> {
     let buf = &mut vec![];
     let mut proto_count = 1;
@@ -240,19 +288,23 @@ fn parse_frame_info<B: BufRead>(
                         .filter(|a| a.key == b"show")
                         .and_then(|a| String::from_utf8(a.value.to_vec()).ok())
                 }) {
-                    // must use NaiveDateTime because chrono can't read string timezone names.
-                    // https://docs.rs/chrono/0.4.19/chrono/format/strftime/index.html#specifiers
-                    // > %Z: Offset will not be populated from the parsed data, nor will it be validated.
-                    // > Timezone is completely ignored. Similar to the glibc strptime treatment of this format code.
-                    // > It is not possible to reliably convert from an abbreviation to an offset, for example CDT
-                    // > can mean either Central Daylight Time (North America) or China Daylight Time.
-                    return NaiveDateTime::parse_from_str(&time_str, "%b %e, %Y %T.%f %Z").map_err(|e| e.to_string());
+                    return parse_frame_time(&time_str);
                 }
             }
         }
     )
 }
 
+pub fn parse_frame_time(time_str: &str) -> Result<NaiveDateTime, String> {
+    // must use NaiveDateTime because chrono can't read string timezone names.
+    // https://docs.rs/chrono/0.4.19/chrono/format/strftime/index.html#specifiers
+    // > %Z: Offset will not be populated from the parsed data, nor will it be validated.
+    // > Timezone is completely ignored. Similar to the glibc strptime treatment of this format code.
+    // > It is not possible to reliably convert from an abbreviation to an offset, for example CDT
+    // > can mean either Central Daylight Time (North America) or China Daylight Time.
+    NaiveDateTime::parse_from_str(time_str, "%b %e, %Y %T.%f %Z").map_err(|e| e.to_string())
+}
+
 fn parse_ip_info<B: BufRead>(
     xml_reader: &mut quick_xml::Reader<B>,
 ) -> Result<(Option<IpAddr>, Option<IpAddr>), String> {
@@ -285,6 +337,7 @@ fn parse_ip_info<B: BufRead>(
 fn parse_tcp_info<B: BufRead>(
     xml_reader: &mut quick_xml::Reader<B>,
     http1_streams: &HashSet<TcpStreamId>,
+    redis_streams: &HashSet<TcpStreamId>,
     temp_tcp_payload: &mut Vec<u8>,
 ) -> Result<(TcpSeqNumber, TcpStreamId, NetworkPort, NetworkPort), String> {
     let mut tcp_seq_number = TcpSeqNumber(0);
@@ -318,7 +371,10 @@ fn parse_tcp_info<B: BufRead>(
                         }
                     }
                     Some(b"tcp.payload") => {
-                        if http1_streams.contains(&tcp_stream_id) {
+                        if http1_streams.contains(&tcp_stream_id)
+                            || redis_streams.contains(&tcp_stream_id)
+                            || tshark_redis::is_redis_port(port_src, port_dst)
+                        {
                             let payload = element_attr_val_bytes_lazy(e, b"value")?;
                             // https://stackoverflow.com/a/50707947/516188
                             temp_tcp_payload.extend_from_slice(&payload);
@@ -436,16 +492,25 @@ pub fn parse_test_xml_no_wrapper(xml: &str) -> Result<Vec<TSharkPacket>, String>
     let mut res = vec![];
     let mut buf = vec![];
     let mut http1_streams: HashSet<TcpStreamId> = HashSet::new();
+    let mut redis_streams: HashSet<TcpStreamId> = HashSet::new();
     let mut temp_tcp_payload: Vec<u8> = vec![];
     loop {
         match xml_reader.read_event(&mut buf) {
             Ok(Event::Start(ref e)) => {
                 if e.name() == b"packet" {
-                    match parse_packet(&mut xml_reader, &http1_streams, &mut temp_tcp_payload) {
+                    match parse_packet(
+                        &mut xml_reader,
+                        &http1_streams,
+                        &redis_streams,
+                        &mut temp_tcp_payload,
+                    ) {
                         Ok(packet) => {
                             if packet.http.is_some() {
                                 http1_streams.insert(packet.basic_info.tcp_stream_id);
                             }
+                            if packet.redis.is_some() {
+                                redis_streams.insert(packet.basic_info.tcp_stream_id);
+                            }
                             res.push(packet);
                         }
                         Err(e) => {
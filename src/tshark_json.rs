@@ -0,0 +1,338 @@
+// parsing of `tshark -T json --no-duplicate-keys`. Compared to PDML, we can
+// ask tshark for only the protocols we care about, which makes the output
+// (and our parsing) much smaller on large captures.
+//
+// A field is output as "name": "displayed value", and if it has children,
+// they're in "name_tree": {...} (or directly in "name": {...} if the
+// field itself has no value). Text items without a field name are keyed
+// by their label. --no-duplicate-keys merges repeated keys in arrays.
+// We don't ask for the raw bytes of the fields (-x): that doubles the output.
+// Byte fields are displayed as colon-separated hex instead.
+use crate::http::tshark_http;
+use crate::http2::tshark_http2;
+use crate::mysql::tshark_mysql;
+use crate::pgsql::tshark_pgsql;
+use crate::redis::tshark_redis;
+use crate::tshark_communication;
+use crate::tshark_communication::{
+    NetworkPort, TSharkPacket, TSharkPacketBasicInfo, TcpSeqNumber, TcpStreamId,
+};
+use chrono::NaiveDateTime;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// the protocols the stores consume, for tshark's -J
+pub const JSON_PROTOCOLS: &str = "frame ip ipv6 tcp http http2 pgsql mysql _ws.malformed";
+
+fn collect_values<'a>(
+    node: &'a Value,
+    matches: &dyn Fn(&str) -> bool,
+    flatten: bool,
+    result: &mut Vec<&'a Value>,
+) {
+    match node {
+        Value::Object(map) => {
+            for (key, val) in map {
+                if !matches(key) {
+                    collect_values(val, matches, flatten, result);
+                } else if let (true, Value::Array(items)) = (flatten, val) {
+                    result.extend(items);
+                } else {
+                    result.push(val);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_values(item, matches, flatten, result);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// the values of all the occurences of a field, anywhere under that node
+pub fn find_all<'a>(node: &'a Value, name: &str) -> Vec<&'a Value> {
+    let mut result = vec![];
+    collect_values(node, &|k| k == name, true, &mut result);
+    result
+}
+
+/// the children of all the occurences of a field, anywhere under that node
+pub fn find_subtrees<'a>(node: &'a Value, name: &str) -> Vec<&'a Value> {
+    let tree_name = format!("{}_tree", name);
+    let mut result = vec![];
+    collect_values(node, &|k| k == name || k == tree_name, true, &mut result);
+    result.retain(|v| v.is_object());
+    result
+}
+
+/// a protocol may be present several times in a packet
+pub fn layer_values<'a>(layers: &'a Value, name: &str) -> Vec<&'a Value> {
+    match layers.get(name) {
+        Some(Value::Array(items)) => items.iter().collect(),
+        Some(v) => vec![v],
+        None => vec![],
+    }
+}
+
+fn value_str(val: &Value) -> Option<String> {
+    match val {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(if *b { "1" } else { "0" }.to_string()),
+        _ => None,
+    }
+}
+
+pub fn first_str(node: &Value, name: &str) -> Option<String> {
+    find_all(node, name).into_iter().find_map(value_str)
+}
+
+pub fn all_str(node: &Value, name: &str) -> Vec<String> {
+    find_all(node, name)
+        .into_iter()
+        .filter_map(value_str)
+        .collect()
+}
+
+pub fn first_number<F: FromStr>(node: &Value, name: &str) -> Option<F> {
+    first_str(node, name).and_then(|s| s.parse().ok())
+}
+
+pub fn is_flag_set(node: &Value, name: &str) -> bool {
+    matches!(
+        first_str(node, name).as_deref(),
+        Some("1") | Some("true") | Some("True")
+    )
+}
+
+/// the bytes of all the occurences of a byte field, in hex
+pub fn all_hex(node: &Value, name: &str) -> Vec<String> {
+    all_str(node, name)
+        .into_iter()
+        .map(|s| s.replace(':', ""))
+        .collect()
+}
+
+/// "68:69" => "hi"
+fn decode_colon_hex(displayed: &str) -> Option<Vec<u8>> {
+    let is_colon_hex = displayed.len() % 3 == 2
+        && displayed.bytes().enumerate().all(|(i, b)| {
+            if i % 3 == 2 {
+                b == b':'
+            } else {
+                b.is_ascii_hexdigit()
+            }
+        });
+    if is_colon_hex {
+        hex::decode(displayed.replace(':', "")).ok()
+    } else {
+        None
+    }
+}
+
+/// the bytes of a field. Some byte fields (the HTTP body...) are displayed
+/// as text when they're printable, and as colon-separated hex otherwise
+pub fn first_bytes(node: &Value, name: &str) -> Option<Vec<u8>> {
+    first_str(node, name).map(|s| decode_colon_hex(&s).unwrap_or_else(|| s.into_bytes()))
+}
+
+/// `packet` is an element of the array that tshark outputs
+pub fn parse_packet(
+    packet: &Value,
+    http1_streams: &HashSet<TcpStreamId>,
+    redis_streams: &HashSet<TcpStreamId>,
+) -> Result<Option<TSharkPacket>, String> {
+    let layers = &packet["_source"]["layers"];
+    let frame_time = match layers.get("frame").and_then(|f| first_str(f, "frame.time")) {
+        Some(t) => tshark_communication::parse_frame_time(&t)?,
+        None => NaiveDateTime::from_timestamp(0, 0),
+    };
+    let ip_addr =
+        |layer: &Value, name: &str| first_str(layer, name).and_then(|s| s.parse::<IpAddr>().ok());
+    let ip_addrs = if let Some(ip) = layer_values(layers, "ip").first() {
+        ip_addr(ip, "ip.src").zip(ip_addr(ip, "ip.dst"))
+    } else if let Some(ipv6) = layer_values(layers, "ipv6").first() {
+        ip_addr(ipv6, "ipv6.src").zip(ip_addr(ipv6, "ipv6.dst"))
+    } else {
+        None
+    };
+    let (ip_src, ip_dst) = match ip_addrs {
+        Some(addrs) => addrs,
+        None => return Ok(None),
+    };
+    let tcp = layer_values(layers, "tcp").first().copied();
+    let tcp_number = |name: &str| tcp.and_then(|t| first_number::<u32>(t, name));
+    let tcp_port = |name: &str| tcp.and_then(|t| first_number::<u16>(t, name));
+    let tcp_seq_number = TcpSeqNumber(tcp_number("tcp.seq_raw").unwrap_or(0));
+    let tcp_stream_id = TcpStreamId(tcp_number("tcp.stream").unwrap_or(0));
+    let port_src = NetworkPort(tcp_port("tcp.srcport").unwrap_or(0));
+    let port_dst = NetworkPort(tcp_port("tcp.dstport").unwrap_or(0));
+
+    let http: Vec<_> = layer_values(layers, "http")
+        .into_iter()
+        .map(tshark_http::parse_http_json)
+        .collect();
+    let http2_layers = layer_values(layers, "http2");
+    let http2 = if http2_layers.is_empty() {
+        None
+    } else {
+        Some(
+            http2_layers
+                .into_iter()
+                .flat_map(tshark_http2::parse_http2_json)
+                .collect(),
+        )
+    };
+    let pgsql = layer_values(layers, "pgsql")
+        .into_iter()
+        .filter_map(|l| tshark_pgsql::parse_pgsql_json(l).transpose())
+        .collect::<Result<Vec<_>, _>>()?;
+    let mysql: Vec<_> = layer_values(layers, "mysql")
+        .into_iter()
+        .filter_map(tshark_mysql::parse_mysql_json)
+        .collect();
+
+    let is_redis_port = tshark_redis::is_redis_port(port_src, port_dst);
+    let hex_payload = if http1_streams.contains(&tcp_stream_id)
+        || redis_streams.contains(&tcp_stream_id)
+        || is_redis_port
+    {
+        tcp.and_then(|t| all_hex(t, "tcp.payload").into_iter().next())
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+    // like for PDML, we recognize redis by its port, not the dissector
+    let is_redis = redis_streams.contains(&tcp_stream_id) || is_redis_port;
+    let (redis, tcp_payload) = tshark_communication::keep_tcp_payload(
+        hex_payload.as_bytes(),
+        is_redis,
+        http1_streams.contains(&tcp_stream_id) && http.is_empty(),
+    );
+    Ok(Some(TSharkPacket {
+        basic_info: TSharkPacketBasicInfo {
+            frame_time,
+            ip_src,
+            ip_dst,
+            tcp_seq_number,
+            tcp_stream_id,
+            port_src,
+            port_dst,
+        },
+        http: Some(http).filter(|h| !h.is_empty()),
+        http2,
+        pgsql: Some(pgsql).filter(|p| !p.is_empty()),
+        mysql: Some(mysql).filter(|m| !m.is_empty()),
+        redis,
+        tcp_payload,
+        is_malformed: layers.get("_ws.malformed").is_some(),
+    }))
+}
+
+#[cfg(test)]
+pub fn parse_test_json(json: &str) -> Vec<TSharkPacket> {
+    let packets: Vec<Value> = serde_json::from_str(json).unwrap();
+    let mut http1_streams = HashSet::new();
+    let redis_streams = HashSet::new();
+    packets
+        .iter()
+        .filter_map(|p| {
+            let packet = parse_packet(p, &http1_streams, &redis_streams).unwrap()?;
+            if packet.http.is_some() {
+                http1_streams.insert(packet.basic_info.tcp_stream_id);
+            }
+            Some(packet)
+        })
+        .collect()
+}
+
+#[test]
+fn should_parse_json_http_packet() {
+    let packets = parse_test_json(
+        r#"[{"_source": {"layers": {
+            "frame": {"frame.time": "Mar  5, 2021 08:49:52.736275000 CET"},
+            "ip": {"ip.src": "10.215.215.9", "ip.dst": "10.215.215.10"},
+            "tcp": {"tcp.srcport": "52796", "tcp.dstport": "80", "tcp.seq_raw": "1963007432", "tcp.stream": "4"},
+            "http": {
+                "GET /test?x=1 HTTP/1.1\\r\\n": {
+                    "http.request.method": "GET",
+                    "http.request.uri": "/test?x=1",
+                    "http.request.version": "HTTP/1.1"
+                },
+                "http.host": "example.com",
+                "http.request.line": ["Host: example.com\r\n", "Accept: */*\r\n"],
+                "http.file_data": "hello"
+            }
+        }}}]"#,
+    );
+    assert_eq!(1, packets.len());
+    let p = &packets[0];
+    assert_eq!(4, p.basic_info.tcp_stream_id.as_u32());
+    assert_eq!(80, p.basic_info.port_dst.as_u16());
+    assert_eq!(1963007432, p.basic_info.tcp_seq_number.as_u32());
+    assert_eq!("10.215.215.10", p.basic_info.ip_dst.to_string());
+    let http = &p.http.as_ref().unwrap()[0];
+    assert_eq!("GET /test?x=1 HTTP/1.1", http.first_line);
+    assert_eq!("Host: example.com\r\nAccept: */*\r\n", http.other_lines);
+    assert_eq!(Some("example.com".to_string()), http.http_host);
+    assert_eq!(Some(b"hello".to_vec()), http.body);
+}
+
+#[test]
+fn should_parse_json_pgsql_bind_and_data_row() {
+    let packets = parse_test_json(
+        r#"[{"_source": {"layers": {
+            "ip": {"ip.src": "10.215.215.9", "ip.dst": "10.215.215.10"},
+            "tcp": {"tcp.srcport": "52796", "tcp.dstport": "5432", "tcp.stream": "1"},
+            "pgsql": [
+                {
+                    "pgsql.type": "Bind",
+                    "pgsql.statement": "S_1",
+                    "Parameter values: 3": {
+                        "pgsql.val.length": ["-1", "2", "0"],
+                        "pgsql.val.data": "34:35"
+                    }
+                },
+                {
+                    "pgsql.type": "Data row",
+                    "pgsql.field.count": "2",
+                    "pgsql.field.count_tree": {
+                        "pgsql.val.length": ["1", "1"],
+                        "pgsql.val.data": ["61", "62"]
+                    }
+                }
+            ]
+        }}}]"#,
+    );
+    let pgsql = packets[0].pgsql.as_ref().unwrap();
+    assert!(matches!(
+        &pgsql[0],
+        tshark_pgsql::PostgresWireMessage::Bind { statement: Some(s), parameter_lengths_and_vals: vals }
+            if s == "S_1" && vals == &[(-1, "6e756c6c".to_string()), (2, "3435".to_string()), (0, "".to_string())]
+    ));
+    assert!(matches!(
+        &pgsql[1],
+        tshark_pgsql::PostgresWireMessage::ResultSetRow { col_lengths_and_vals: vals }
+            if vals == &[(1, "61".to_string()), (1, "62".to_string())]
+    ));
+}
+
+#[test]
+fn should_decode_displayed_bytes() {
+    let node = serde_json::json!({
+        "http.file_data": "00:ff:7b",
+        "data.data": "cafe",
+        "pgsql.val.data": ["7b:7d", "61"],
+    });
+    assert_eq!(
+        Some(vec![0, 0xff, b'{']),
+        first_bytes(&node, "http.file_data")
+    );
+    // printable bodies are displayed as text
+    assert_eq!(Some(b"cafe".to_vec()), first_bytes(&node, "data.data"));
+    assert_eq!(vec!["7b7d", "61"], all_hex(&node, "pgsql.val.data"));
+}
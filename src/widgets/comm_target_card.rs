@@ -1,10 +1,10 @@
 use crate::custom_streams_store::ClientServerInfo;
 use crate::icons::Icon;
-use crate::tshark_communication::NetworkPort;
+use crate::tshark_communication::{NetworkPort, TcpStreamId};
 use gtk::prelude::*;
 use relm::Widget;
 use relm_derive::{widget, Msg};
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::net::IpAddr;
 
 #[derive(Msg)]
@@ -54,6 +54,8 @@ pub struct CommTargetCardData {
     pub protocol_icon: Icon,
     pub summary_details: Option<SummaryDetails>,
     incoming_session_count: usize,
+    // per stream, slower than the threshold from the preferences
+    slow_message_counts: HashMap<TcpStreamId, usize>,
 }
 
 impl CommTargetCardData {
@@ -74,6 +76,7 @@ impl CommTargetCardData {
             protocol_icon,
             summary_details,
             incoming_session_count,
+            slow_message_counts: HashMap::new(),
         }
     }
 
@@ -81,6 +84,12 @@ impl CommTargetCardData {
         self.incoming_session_count += 1;
     }
 
+    pub fn set_stream_slow_message_count(&mut self, stream_id: TcpStreamId, count: usize) {
+        if count > 0 {
+            self.slow_message_counts.insert(stream_id, count);
+        }
+    }
+
     pub fn to_key(&self) -> CommTargetCardKey {
         CommTargetCardKey {
             ip: self.ip,
@@ -102,6 +111,7 @@ impl Widget for CommTargetCard {
                 self.model.remote_hosts = d.remote_hosts;
                 self.model.summary_details = d.summary_details;
                 self.model.incoming_session_count = d.incoming_session_count;
+                self.model.slow_message_counts = d.slow_message_counts;
             }
         }
     }
@@ -110,6 +120,10 @@ impl Widget for CommTargetCard {
         Self::server_ip_port_display_format(data.ip, data.port)
     }
 
+    fn slow_message_count_display(slow_message_counts: &HashMap<TcpStreamId, usize>) -> String {
+        format!("{} slow", slow_message_counts.values().sum::<usize>())
+    }
+
     fn server_ip_port_display_format(ip: IpAddr, port: NetworkPort) -> String {
         format!("{}:{}", ip, port)
     }
@@ -163,6 +177,13 @@ impl Widget for CommTargetCard {
                     gtk::Label {
                         label: &self.model.incoming_session_count.to_string(),
                     },
+                    #[style_class="card_slow_messages"]
+                    gtk::Label {
+                        margin_start: 3,
+                        label: &CommTargetCard::slow_message_count_display(&self.model.slow_message_counts),
+                        tooltip_text: Some("Messages slower than the threshold set in the preferences"),
+                        visible: !self.model.slow_message_counts.is_empty(),
+                    },
                     gtk::Label {
                         margin_start: 2,
                         label: self.model.summary_details.as_ref().map(|d| d.details.as_str()).unwrap_or(""),
@@ -0,0 +1,165 @@
+use super::messages_treeview::GlobalSearchHit;
+use crate::search_expr;
+use gtk::prelude::*;
+use relm::Widget;
+use relm_derive::{widget, Msg};
+use std::collections::BTreeSet;
+
+#[derive(Msg)]
+pub enum Msg {
+    SearchActivated,
+    SearchResults(Vec<GlobalSearchHit>),
+    HitActivated(gtk::TreePath),
+
+    // meant for my parent
+    Search(search_expr::SearchExpr),
+    JumpToHit(GlobalSearchHit),
+}
+
+pub struct Model {
+    relm: relm::Relm<GlobalSearch>,
+    string_filter_keys: BTreeSet<&'static str>,
+    numeric_filter_keys: BTreeSet<&'static str>,
+    hits: Vec<GlobalSearchHit>,
+    status: String,
+}
+
+#[widget]
+impl Widget for GlobalSearch {
+    fn init_view(&mut self) {
+        for (idx, title) in ["Server", "Protocol", "Stream", "Message"]
+            .iter()
+            .enumerate()
+        {
+            let col = gtk::builders::TreeViewColumnBuilder::new()
+                .title(title)
+                .resizable(true)
+                .expand(idx == 3)
+                .sort_column_id(idx as i32)
+                .build();
+            let cell = gtk::builders::CellRendererTextBuilder::new()
+                .ellipsize(pango::EllipsizeMode::End)
+                .build();
+            col.pack_start(&cell, true);
+            col.add_attribute(&cell, "text", idx as i32);
+            self.widgets.hits_treeview.append_column(&col);
+        }
+    }
+
+    fn model(
+        relm: &relm::Relm<Self>,
+        filter_keys: (BTreeSet<&'static str>, BTreeSet<&'static str>),
+    ) -> Model {
+        let (string_filter_keys, numeric_filter_keys) = filter_keys;
+        Model {
+            relm: relm.clone(),
+            string_filter_keys,
+            numeric_filter_keys,
+            hits: vec![],
+            status: "Search the messages of all the servers".to_string(),
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::SearchActivated => {
+                let text = self.widgets.search_entry.text().to_string();
+                let parsed = search_expr::parse_search(
+                    &self.model.string_filter_keys,
+                    &self.model.numeric_filter_keys,
+                )(&text)
+                .map(|(rest, expr)| (rest.to_string(), expr));
+                match parsed {
+                    Ok((rest, expr)) if rest.is_empty() => {
+                        self.model.status = "Searching...".to_string();
+                        self.model.relm.stream().emit(Msg::Search(expr));
+                    }
+                    _ => {
+                        self.model.status = "Invalid search expression".to_string();
+                    }
+                }
+            }
+            Msg::SearchResults(hits) => {
+                let store = gtk::ListStore::new(&[
+                    String::static_type(), // server
+                    String::static_type(), // protocol
+                    u32::static_type(),    // stream id
+                    String::static_type(), // message description
+                    u32::static_type(),    // index in the hits vector
+                ]);
+                for (idx, hit) in hits.iter().enumerate() {
+                    store.insert_with_values(
+                        None,
+                        &[
+                            (0, &hit.server.to_value()),
+                            (1, &hit.protocol_name.to_value()),
+                            (2, &hit.stream_id.as_u32().to_value()),
+                            (3, &hit.description.to_value()),
+                            (4, &(idx as u32).to_value()),
+                        ],
+                    );
+                }
+                self.widgets.hits_treeview.set_model(Some(&store));
+                self.model.status = match hits.len() {
+                    0 => "No matching message".to_string(),
+                    1 => "1 matching message".to_string(),
+                    n => format!("{} matching messages", n),
+                };
+                self.model.hits = hits;
+            }
+            Msg::HitActivated(path) => {
+                if let Some(hit) = self
+                    .widgets
+                    .hits_treeview
+                    .model()
+                    .and_then(|m| m.iter(&path).map(|iter| m.value(&iter, 4)))
+                    .and_then(|v| v.get::<u32>().ok())
+                    .and_then(|idx| self.model.hits.get(idx as usize))
+                {
+                    self.model.relm.stream().emit(Msg::JumpToHit(hit.clone()));
+                }
+            }
+            // meant for my parent
+            Msg::Search(_) => {}
+            Msg::JumpToHit(_) => {}
+        }
+    }
+
+    view! {
+        gtk::Window {
+            titlebar: view! {
+                gtk::HeaderBar {
+                    title: Some("Search all servers"),
+                    show_close_button: true,
+                }
+            },
+            default_width: 800,
+            default_height: 500,
+            gtk::Box {
+                orientation: gtk::Orientation::Vertical,
+                margin_top: 10,
+                margin_start: 10,
+                margin_end: 10,
+                margin_bottom: 10,
+                spacing: 6,
+                #[name="search_entry"]
+                gtk::SearchEntry {
+                    placeholder_text: Some("For instance: http.req_header contains X-Correlation-Id"),
+                    activate => Msg::SearchActivated,
+                },
+                gtk::Label {
+                    label: &self.model.status,
+                    xalign: 0.0,
+                },
+                gtk::ScrolledWindow {
+                    vexpand: true,
+                    #[name="hits_treeview"]
+                    gtk::TreeView {
+                        activate_on_single_click: true,
+                        row_activated(_, path, _) => Msg::HitActivated(path.clone()),
+                    },
+                },
+            }
+        }
+    }
+}
@@ -2,6 +2,8 @@ use super::search_options;
 use super::search_options::Msg as SearchOptionsMsg;
 use super::search_options::SearchOptions;
 use crate::config;
+use crate::saved_searches;
+use crate::saved_searches::SavedSearch;
 use crate::search_expr;
 use crate::search_expr::SearchExpr;
 use crate::BgFunc;
@@ -33,6 +35,7 @@ pub enum Msg {
         numeric_keys: BTreeSet<&'static str>,
     },
     ClearSearchTextClick,
+    SetSearchText(String),
     DisplayNoSearchError,
     DisplayWithSearchErrors,
     OpenSearchAddPopover,
@@ -48,6 +51,11 @@ pub enum Msg {
     SearchCompletionAction(String),
     ActivateRecentSearch(i32),
     MaybeSaveRecentSearch(String),
+    ActivateSavedSearch(i32),
+    SaveCurrentSearch,
+    RemoveSavedSearch(String),
+    ImportSavedSearches,
+    ExportSavedSearches,
 }
 
 pub struct Model {
@@ -64,6 +72,12 @@ pub struct Model {
     search_completion: gtk::EntryCompletion,
     recent_searches: Vec<String>,
     recent_searches_box: gtk::ListBox,
+    // the searches that the user saved, the built-in ones are not in there
+    saved_searches: Vec<SavedSearch>,
+    saved_searches_box: gtk::ListBox,
+    // the search strings of the rows of saved_searches_box: we only display
+    // the searches that are valid for the protocol of the current card
+    displayed_saved_searches: Vec<String>,
 }
 
 #[widget]
@@ -101,6 +115,7 @@ impl Widget for HeaderbarSearch {
         self.update_search_status(None);
 
         self.init_recent_searches();
+        self.init_saved_searches();
     }
 
     fn init_recent_searches(&mut self) {
@@ -150,6 +165,129 @@ impl Widget for HeaderbarSearch {
         self.model.recent_searches_box.show_all();
     }
 
+    fn init_saved_searches(&mut self) {
+        relm::connect!(
+            self.model.relm,
+            self.model.saved_searches_box,
+            connect_row_activated(_, row),
+            Msg::ActivateSavedSearch(row.index())
+        );
+        let popover_box = gtk::builders::BoxBuilder::new()
+            .orientation(gtk::Orientation::Vertical)
+            .child(
+                &gtk::builders::LabelBuilder::new()
+                    .label("Saved searches")
+                    .name("popover_title")
+                    .build(),
+            )
+            .build();
+        popover_box.add(&self.model.saved_searches_box);
+        let buttons_box = gtk::builders::BoxBuilder::new()
+            .margin(7)
+            .spacing(6)
+            .homogeneous(true)
+            .build();
+        let save_btn = gtk::Button::with_label("Save current...");
+        relm::connect!(
+            self.model.relm,
+            save_btn,
+            connect_clicked(_),
+            Msg::SaveCurrentSearch
+        );
+        buttons_box.add(&save_btn);
+        let import_btn = gtk::Button::with_label("Import...");
+        relm::connect!(
+            self.model.relm,
+            import_btn,
+            connect_clicked(_),
+            Msg::ImportSavedSearches
+        );
+        buttons_box.add(&import_btn);
+        let export_btn = gtk::Button::with_label("Export...");
+        relm::connect!(
+            self.model.relm,
+            export_btn,
+            connect_clicked(_),
+            Msg::ExportSavedSearches
+        );
+        buttons_box.add(&export_btn);
+        popover_box.add(&buttons_box);
+        popover_box.show_all();
+        let saved_searches_popover = gtk::builders::PopoverBuilder::new()
+            .child(&popover_box)
+            .build();
+        self.widgets
+            .saved_searches_btn
+            .set_popover(Some(&saved_searches_popover));
+
+        self.refresh_saved_searches();
+    }
+
+    fn refresh_saved_searches(&mut self) {
+        for child in self.model.saved_searches_box.children() {
+            self.model.saved_searches_box.remove(&child);
+        }
+        self.model.displayed_saved_searches.clear();
+        let is_valid_search = |search: &str| {
+            matches!(
+                search_expr::parse_search(
+                    &self.model.known_string_filter_keys,
+                    &self.model.known_numeric_filter_keys,
+                )(search),
+                Ok(("", _))
+            )
+        };
+        let user_searches = self.model.saved_searches.iter().map(|s| (s, true));
+        let builtin_searches = saved_searches::builtin_saved_searches();
+        for (saved, is_user_search) in
+            user_searches.chain(builtin_searches.iter().map(|s| (s, false)))
+        {
+            if !is_valid_search(&saved.search) {
+                continue;
+            }
+            let row_box = gtk::builders::BoxBuilder::new()
+                .margin(7)
+                .spacing(6)
+                .build();
+            row_box.pack_start(
+                &gtk::builders::LabelBuilder::new()
+                    .label(&saved.name)
+                    .tooltip_text(&saved.search)
+                    .width_request(200)
+                    .ellipsize(pango::EllipsizeMode::End)
+                    .halign(gtk::Align::Start)
+                    .xalign(0.0)
+                    .build(),
+                true,
+                true,
+                0,
+            );
+            if is_user_search {
+                let remove_btn = gtk::builders::ButtonBuilder::new()
+                    .image(&gtk::Image::from_icon_name(
+                        Some("user-trash-symbolic"),
+                        gtk::IconSize::Menu,
+                    ))
+                    .relief(gtk::ReliefStyle::None)
+                    .tooltip_text("Remove this saved search")
+                    .build();
+                let name = saved.name.clone();
+                relm::connect!(
+                    self.model.relm,
+                    remove_btn,
+                    connect_clicked(_),
+                    Msg::RemoveSavedSearch(name.clone())
+                );
+                row_box.pack_end(&remove_btn, false, false, 0);
+            }
+            self.model.saved_searches_box.add(&row_box);
+            self.model
+                .displayed_saved_searches
+                .push(saved.search.clone());
+        }
+        self.model.saved_searches_box.show_all();
+    }
+
     fn model(relm: &relm::Relm<Self>, params: (mpsc::Sender<BgFunc>, Vec<String>)) -> Model {
         let (bg_sender, recent_searches) = params;
         let cell_area = gtk::builders::CellAreaBoxBuilder::new().build();
@@ -171,6 +309,13 @@ impl Widget for HeaderbarSearch {
         let recent_searches_box = gtk::builders::ListBoxBuilder::new()
             .activate_on_single_click(true)
             .build();
+        let saved_searches_box = gtk::builders::ListBoxBuilder::new()
+            .activate_on_single_click(true)
+            .build();
+        let saved_searches = saved_searches::read_saved_searches().unwrap_or_else(|e| {
+            eprintln!("Error loading the saved searches: {}", e);
+            vec![]
+        });
         Model {
             relm: relm.clone(),
             bg_sender,
@@ -182,6 +327,9 @@ impl Widget for HeaderbarSearch {
             search_completion,
             recent_searches,
             recent_searches_box,
+            saved_searches,
+            saved_searches_box,
+            displayed_saved_searches: vec![],
         }
     }
 
@@ -201,6 +349,7 @@ impl Widget for HeaderbarSearch {
                 self.update_search_completion(&all_filter_keys);
                 self.model.known_string_filter_keys = string_keys;
                 self.model.known_numeric_filter_keys = numeric_keys;
+                self.refresh_saved_searches();
             }
             Msg::SearchActiveChanged(is_active) => {
                 if is_active {
@@ -290,32 +439,22 @@ impl Widget for HeaderbarSearch {
                     None => {}
                 }
                 t.push_str(filter_key);
-                match (&search_op, op_negation) {
-                    (
-                        search_expr::SearchCriteria::Contains(val),
-                        search_expr::OperatorNegation::NotNegated,
-                    ) => {
-                        t.push_str(" contains ");
+                t.push(' ');
+                t.push_str(search_op.operator(op_negation));
+                t.push(' ');
+                match &search_op {
+                    search_expr::SearchCriteria::Contains(val)
+                    | search_expr::SearchCriteria::Is(val)
+                    | search_expr::SearchCriteria::StartsWith(val) => {
                         Self::format_search_val_str(&mut t, val);
                     }
-                    (
-                        search_expr::SearchCriteria::Contains(val),
-                        search_expr::OperatorNegation::Negated,
-                    ) => {
-                        t.push_str(" doesntContain ");
-                        Self::format_search_val_str(&mut t, val);
-                    }
-                    (
-                        search_expr::SearchCriteria::GreaterThan(_val, _dec),
-                        search_expr::OperatorNegation::NotNegated,
-                    ) => {
-                        t.push_str(&format!(" > {}", search_op.display_val()));
+                    search_expr::SearchCriteria::Matches(regex) => {
+                        t.push('/');
+                        t.push_str(&regex.0.as_str().replace('/', "\\/"));
+                        t.push('/');
                     }
-                    (
-                        search_expr::SearchCriteria::GreaterThan(_val, _dec),
-                        search_expr::OperatorNegation::Negated,
-                    ) => {
-                        t.push_str(&format!(" < {}", search_op.display_val()));
+                    _ => {
+                        t.push_str(&search_op.display_val());
                     }
                 }
                 self.widgets.search_entry.set_text(&t);
@@ -323,6 +462,9 @@ impl Widget for HeaderbarSearch {
             Msg::ClearSearchTextClick => {
                 self.widgets.search_entry.set_text("");
             }
+            Msg::SetSearchText(text) => {
+                self.widgets.search_entry.set_text(&text);
+            }
             Msg::RequestOptionsClose => {
                 if let Some(popover) = self.widgets.search_options_btn.popover() {
                     popover.popdown();
@@ -361,9 +503,132 @@ impl Widget for HeaderbarSearch {
                     }
                 }
             }
+            Msg::ActivateSavedSearch(idx) => {
+                if let Some(search) = self.model.displayed_saved_searches.get(idx as usize) {
+                    self.widgets.search_entry.set_text(search);
+                }
+                if let Some(popover) = self.widgets.saved_searches_btn.popover() {
+                    popover.popdown();
+                }
+            }
+            Msg::SaveCurrentSearch => {
+                let search = self.widgets.search_entry.text().to_string();
+                if search.is_empty() {
+                    return;
+                }
+                if let Some(name) = self.ask_saved_search_name() {
+                    saved_searches::upsert_saved_search(
+                        &mut self.model.saved_searches,
+                        SavedSearch { name, search },
+                    );
+                    self.saved_searches_changed();
+                }
+            }
+            Msg::RemoveSavedSearch(name) => {
+                self.model.saved_searches.retain(|s| s.name != name);
+                self.saved_searches_changed();
+            }
+            Msg::ImportSavedSearches => {
+                let dialog = gtk::builders::FileChooserNativeBuilder::new()
+                    .action(gtk::FileChooserAction::Open)
+                    .title("Import saved searches")
+                    .modal(true)
+                    .build();
+                if dialog.run() == gtk::ResponseType::Accept {
+                    if let Some(fname) = dialog.filename() {
+                        match saved_searches::read_saved_searches_file(&fname) {
+                            Ok(imported) => {
+                                for search in imported {
+                                    saved_searches::upsert_saved_search(
+                                        &mut self.model.saved_searches,
+                                        search,
+                                    );
+                                }
+                                self.saved_searches_changed();
+                            }
+                            Err(e) => self.display_error("Error importing saved searches", e),
+                        }
+                    }
+                }
+            }
+            Msg::ExportSavedSearches => {
+                let dialog = gtk::builders::FileChooserNativeBuilder::new()
+                    .action(gtk::FileChooserAction::Save)
+                    .title("Export saved searches")
+                    .do_overwrite_confirmation(true)
+                    .modal(true)
+                    .build();
+                dialog.set_current_name("saved_searches.toml");
+                if dialog.run() == gtk::ResponseType::Accept {
+                    if let Some(fname) = dialog.filename() {
+                        if let Err(e) = saved_searches::write_saved_searches_file(
+                            &fname,
+                            &self.model.saved_searches,
+                        ) {
+                            self.display_error("Error exporting saved searches", e);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn saved_searches_changed(&mut self) {
+        self.refresh_saved_searches();
+        let to_save = self.model.saved_searches.clone();
+        if let Err(e) = self.model.bg_sender.send(BgFunc::new(move || {
+            if let Err(e) = saved_searches::save_saved_searches(&to_save) {
+                eprintln!("Error saving the saved searches to file: {}", e);
+            }
+        })) {
+            eprintln!("Error saving the saved searches: {}", e);
         }
     }
 
+    fn parent_window(&self) -> Option<gtk::Window> {
+        self.widgets
+            .search_entry
+            .toplevel()
+            .and_then(|w| w.dynamic_cast::<gtk::Window>().ok())
+    }
+
+    fn ask_saved_search_name(&self) -> Option<String> {
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Save the search"),
+            self.parent_window().as_ref(),
+            gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+            &[
+                ("Cancel", gtk::ResponseType::Cancel),
+                ("Save", gtk::ResponseType::Accept),
+            ],
+        );
+        dialog.set_default_response(gtk::ResponseType::Accept);
+        let entry = gtk::builders::EntryBuilder::new()
+            .placeholder_text("Name of the search")
+            .activates_default(true)
+            .margin(10)
+            .build();
+        dialog.content_area().add(&entry);
+        entry.show();
+        let response = dialog.run();
+        let name = entry.text().trim().to_string();
+        dialog.close();
+        Some(name).filter(|n| response == gtk::ResponseType::Accept && !n.is_empty())
+    }
+
+    fn display_error(&self, title: &str, e: Box<dyn std::error::Error + Sync + Send>) {
+        let dialog = gtk::MessageDialog::new(
+            self.parent_window().as_ref(),
+            gtk::DialogFlags::all(),
+            gtk::MessageType::Error,
+            gtk::ButtonsType::Close,
+            title,
+        );
+        dialog.set_secondary_text(Some(&e.to_string()));
+        let _r = dialog.run();
+        dialog.close();
+    }
+
     fn format_search_val_str(t: &mut String, val: &str) {
         if val.contains(' ') || val.contains('"') {
             t.push('"');
@@ -464,23 +729,21 @@ impl Widget for HeaderbarSearch {
                 ],
             );
         }
-        // TODO duplicated with search_expr::parse_filter_op
-        store.insert_with_values(
-            None,
-            &[
-                (0, &"contains".to_value()),
-                (1, &ITEM_TYPE_FILTER_COMBINATOR.to_value()),
-                (2, &"Filter combinator".to_value()),
-            ],
-        );
-        store.insert_with_values(
-            None,
-            &[
-                (0, &"doesntContain".to_value()),
-                (1, &ITEM_TYPE_FILTER_COMBINATOR.to_value()),
-                (2, &"Filter combinator".to_value()),
-            ],
-        );
+        // no point in completing the symbolic operators such as >=
+        for op in search_expr::STRING_OPERATORS
+            .iter()
+            .chain(search_expr::NUMERIC_OPERATORS)
+            .filter(|op| op.starts_with(char::is_alphabetic))
+        {
+            store.insert_with_values(
+                None,
+                &[
+                    (0, &op.to_value()),
+                    (1, &ITEM_TYPE_FILTER_COMBINATOR.to_value()),
+                    (2, &"Filter combinator".to_value()),
+                ],
+            );
+        }
         self.model.search_completion.set_model(Some(&store));
         self.model
             .search_completion
@@ -532,6 +795,12 @@ impl Widget for HeaderbarSearch {
                     image: Some(&gtk::Image::from_icon_name(Some("document-open-recent-symbolic"), gtk::IconSize::Menu)),
                     always_show_image: true,
                 },
+                #[name="saved_searches_btn"]
+                gtk::MenuButton {
+                    image: Some(&gtk::Image::from_icon_name(Some("starred-symbolic"), gtk::IconSize::Menu)),
+                    always_show_image: true,
+                    tooltip_text: Some("Saved searches"),
+                },
                 #[name="search_entry"]
                 gtk::SearchEntry {
                     hexpand: true,
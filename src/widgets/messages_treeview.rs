@@ -34,12 +34,12 @@ pub struct MessagesTreeviewState {
     comm_remote_servers_stack: gtk::Stack,
     message_treeviews: Vec<(gtk::TreeView, TreeViewSignals)>,
     details_adjustments: Vec<gtk::Adjustment>,
-    cur_liststore: Option<(CommTargetCardKey, gtk::ListStore)>,
+    cur_treestore: Option<(CommTargetCardKey, gtk::TreeStore)>,
 }
 
 impl MessagesTreeviewState {
     pub fn file_closed(&mut self) {
-        self.cur_liststore = None;
+        self.cur_treestore = None;
     }
 }
 
@@ -67,7 +67,7 @@ pub fn init_grids_and_panes(
         comm_remote_servers_stack,
         message_treeviews,
         details_adjustments,
-        cur_liststore: None,
+        cur_treestore: None,
     }
 }
 
@@ -93,7 +93,7 @@ fn add_streams_store_grid_and_pane<'a, 'b>(
                 match smodel.clone().dynamic_cast::<gtk::TreeModelFilter>() {
                     Ok(modelfilter) => {
                         let model = modelfilter.model().unwrap();
-                        let store = model.dynamic_cast::<gtk::ListStore>().unwrap();
+                        let store = model.dynamic_cast::<gtk::TreeStore>().unwrap();
                         let path = stree
                             .path(&iter)
                             .and_then(|p| stree.convert_path_to_child_path(&p));
@@ -105,7 +105,7 @@ fn add_streams_store_grid_and_pane<'a, 'b>(
                     }
                     _ => {
                         let path = stree.path(&iter);
-                        let store = smodel.dynamic_cast::<gtk::ListStore>().unwrap();
+                        let store = smodel.dynamic_cast::<gtk::TreeStore>().unwrap();
                         if let Some(childpath) =
                             path.and_then(|p| stree.convert_path_to_child_path(&p))
                         {
@@ -174,7 +174,7 @@ fn add_streams_store_grid_and_pane<'a, 'b>(
 }
 
 fn row_selected(
-    store: &gtk::ListStore,
+    store: &gtk::TreeStore,
     path: &gtk::TreePath,
     rstream: &relm::StreamHandle<win::Msg>,
 ) {
@@ -242,14 +242,14 @@ pub fn refresh_remote_servers(
             .comm_remote_servers_stack
             .set_visible_child_name(&card.store_index.to_string());
         let (ref tv, ref _signals) = &tv_state.message_treeviews.get(card.store_index).unwrap();
-        let ls = mp.get_empty_liststore();
+        let ts = mp.get_empty_treestore();
         let populate_by = 100;
         for tcp_sessions in by_remote_ip.values() {
             for session_id in tcp_sessions {
                 let mut idx = 0;
                 let session_message_count = mp.stream_message_count(*session_id).unwrap_or(0);
                 while idx < session_message_count {
-                    mp.populate_treeview(&ls, *session_id, idx, populate_by);
+                    mp.populate_treeview(&ts, *session_id, idx, populate_by);
                     idx += populate_by;
                     // https://developer.gnome.org/gtk3/stable/gtk3-General.html#gtk-events-pending
                     // I've had this loop last almost 3 seconds!!
@@ -263,10 +263,10 @@ pub fn refresh_remote_servers(
                 }
             }
         }
-        mp.end_populate_treeview(tv, &ls);
+        mp.end_populate_treeview(tv, &ts);
         let ip_hash = by_remote_ip.keys().copied().collect::<HashSet<_>>();
 
-        tv_state.cur_liststore = Some((card.to_key(), ls));
+        tv_state.cur_treestore = Some((card.to_key(), ts));
         return RefreshRemoteIpsAndStreams::Yes(card, ip_hash);
     }
     RefreshRemoteIpsAndStreams::No
@@ -327,8 +327,8 @@ fn setup_selection_signals(
 
 /// the model may in the end by held by a TreeModelSort or a
 /// TreeModelFilter. The hierarchy can be either:
-/// 1. TreeModelSort / TreeModelFilter / ListStore
-/// 2. TreeModelSort / ListStore
+/// 1. TreeModelSort / TreeModelFilter / TreeStore
+/// 2. TreeModelSort / TreeStore
 /// If the TreeModelSort is not at the toplevel, the user can't
 /// sort by clicking on column headers in the GUI.
 fn get_store_holding_model(
@@ -342,12 +342,12 @@ fn get_store_holding_model(
         .dynamic_cast::<gtk::TreeModelSort>()
         .unwrap();
 
-    // does the ModelSort contain directly the ListStore?
-    let store_holding_model = if model_sort.model().dynamic_cast::<gtk::ListStore>().is_ok() {
+    // does the ModelSort contain directly the TreeStore?
+    let store_holding_model = if model_sort.model().dynamic_cast::<gtk::TreeStore>().is_ok() {
         // YES => we want to return the ModelSort
         model_sort.model()
     } else {
-        // NO => it must be a ModelFilter, and the ListStore's in there, return that
+        // NO => it must be a ModelFilter, and the TreeStore's in there, return that
         model_sort
             .model()
             .dynamic_cast::<gtk::TreeModelFilter>()
@@ -374,12 +374,120 @@ fn matches_filter(
             matches_filter(mp, a, streams, model, iter)
                 || matches_filter(mp, b, streams, model, iter)
         }
-        search_expr::SearchExpr::SearchOpExpr(expr)
-            if expr.op_negation == OperatorNegation::Negated =>
-        {
-            !mp.matches_filter(expr, model, iter)
+        search_expr::SearchExpr::SearchOpExpr(expr) => {
+            let matches = match custom_streams_store::common_matches_filter(mp, expr, model, iter) {
+                Some(m) => m,
+                // can happen with the global search, that spans several protocols:
+                // a message never matches the filter keys of another protocol
+                None if !store_supports_filter_key(mp, expr.filter_key) => return false,
+                None => mp.matches_filter(expr, model, iter),
+            };
+            if expr.op_negation == OperatorNegation::Negated {
+                !matches
+            } else {
+                matches
+            }
+        }
+    }
+}
+
+fn store_supports_filter_key(mp: &dyn CustomStreamsStore, filter_key: &str) -> bool {
+    mp.supported_string_filter_keys().contains(&filter_key)
+        || mp.supported_numeric_filter_keys().contains(&filter_key)
+}
+
+/// a message matching the global search
+#[derive(Debug, Clone)]
+pub struct GlobalSearchHit {
+    pub store_index: usize,
+    pub stream_id: TcpStreamId,
+    pub msg_idx: u32,
+    pub server: String,
+    pub protocol_name: &'static str,
+    pub description: String,
+}
+
+/// search all the messages of all the streams stores, regardless of the
+/// selected card. We populate a throwaway treestore for each store, since
+/// the stores know how to match filters only against their treeview model.
+pub fn global_search(streams: &Streams, filter: &search_expr::SearchExpr) -> Vec<GlobalSearchHit> {
+    let mut hits = vec![];
+    for (store_index, mp) in streams.get_streams_stores().iter().enumerate() {
+        let ts = mp.get_empty_treestore();
+        for stream_id in mp.tcp_stream_ids() {
+            let message_count = mp.stream_message_count(stream_id).unwrap_or(0);
+            mp.populate_treeview(&ts, stream_id, 0, message_count);
+        }
+        let model = ts.upcast_ref::<gtk::TreeModel>();
+        for_each_message_row(model, None, &mut |iter| {
+            if matches_filter(mp.as_ref(), filter, streams, model, iter) {
+                let (stream_id, msg_idx) = custom_streams_store::get_message_helper(model, iter);
+                hits.push(GlobalSearchHit {
+                    store_index,
+                    stream_id,
+                    msg_idx,
+                    server: mp
+                        .stream_client_server(stream_id)
+                        .map(|cs| format!("{}:{}", cs.server_ip, cs.server_port))
+                        .unwrap_or_default(),
+                    protocol_name: mp.protocol_name(),
+                    description: model.value(iter, 0).get::<String>().unwrap_or_default(),
+                });
+            }
+            true
+        });
+    }
+    hits
+}
+
+/// call `f` on the rows holding messages, in order: the rows without children.
+/// Some stores group their messages under parent rows, like the postgres
+/// transactions. Stops as soon as `f` returns false, and then returns false.
+fn for_each_message_row(
+    model: &gtk::TreeModel,
+    parent: Option<&gtk::TreeIter>,
+    f: &mut dyn FnMut(&gtk::TreeIter) -> bool,
+) -> bool {
+    if let Some(iter) = model.iter_children(parent) {
+        loop {
+            let keep_going = if model.iter_has_child(&iter) {
+                for_each_message_row(model, Some(&iter), f)
+            } else {
+                f(&iter)
+            };
+            if !keep_going {
+                return false;
+            }
+            if !model.iter_next(&iter) {
+                break;
+            }
         }
-        search_expr::SearchExpr::SearchOpExpr(expr) => mp.matches_filter(expr, model, iter),
+    }
+    true
+}
+
+/// select the message in the treeview of that store, if it's displayed
+/// (it may be hidden by a search for instance)
+pub fn select_message(
+    tv_state: &MessagesTreeviewState,
+    store_index: usize,
+    stream_id: TcpStreamId,
+    msg_idx: u32,
+) {
+    let (ref tv, ref _signals) = tv_state.message_treeviews.get(store_index).unwrap();
+    if let Some(model) = tv.model() {
+        for_each_message_row(&model, None, &mut |iter| {
+            if custom_streams_store::get_message_helper(&model, iter) != (stream_id, msg_idx) {
+                return true;
+            }
+            if let Some(path) = model.path(iter) {
+                // the message may be in a collapsed group
+                tv.expand_to_path(&path);
+                tv.selection().select_path(&path);
+                tv.scroll_to_cell(Some(&path), None::<&gtk::TreeViewColumn>, false, 0.0, 0.0);
+            }
+            false
+        });
     }
 }
 
@@ -395,62 +503,37 @@ pub fn search_text_changed(
     let mut shown = HashSet::new();
     let store = m
         .clone()
-        .dynamic_cast::<gtk::ListStore>()
+        .dynamic_cast::<gtk::TreeStore>()
         .unwrap_or_else(|_| {
             m.clone()
                 .dynamic_cast::<gtk::TreeModelFilter>()
                 .unwrap()
                 .model()
                 .unwrap()
-                .dynamic_cast::<gtk::ListStore>()
+                .dynamic_cast::<gtk::TreeStore>()
                 .unwrap()
         });
     let mp = streams.get_streams_store(store_index);
-    let cur_iter_o = m.iter_first();
-    if let Some(cur_iter) = cur_iter_o {
-        if let Some(f) = filter {
-            loop {
-                if matches_filter(mp.as_ref(), f, streams, &m, &cur_iter) {
-                    let stream_id = store
-                        .value(
-                            &cur_iter,
-                            custom_streams_store::TREE_STORE_STREAM_ID_COL_IDX as i32,
-                        )
-                        .get::<u32>()
-                        .unwrap();
-                    let idx = store
-                        .value(
-                            &cur_iter,
-                            custom_streams_store::TREE_STORE_MESSAGE_INDEX_COL_IDX as i32,
-                        )
-                        .get::<u32>()
-                        .unwrap();
-                    shown.insert((stream_id, idx));
-                }
-                if !m.iter_next(&cur_iter) {
-                    break;
-                }
+    if let Some(f) = filter {
+        for_each_message_row(&m, None, &mut |iter| {
+            if matches_filter(mp.as_ref(), f, streams, &m, iter) {
+                shown.insert(custom_streams_store::get_message_helper(&m, iter));
             }
-        }
+            true
+        });
     }
     let new_model_filter = gtk::TreeModelFilter::new(&store, None);
     if filter.is_some() {
         new_model_filter.set_visible_func(move |model, iter| {
-            let stream_id = model
-                .value(
-                    iter,
-                    custom_streams_store::TREE_STORE_STREAM_ID_COL_IDX as i32,
-                )
-                .get::<u32>()
-                .unwrap();
-            let idx = model
-                .value(
-                    iter,
-                    custom_streams_store::TREE_STORE_MESSAGE_INDEX_COL_IDX as i32,
-                )
-                .get::<u32>()
-                .unwrap();
-            shown.contains(&(stream_id, idx))
+            let is_shown = |iter: &gtk::TreeIter| {
+                shown.contains(&custom_streams_store::get_message_helper(model, iter))
+            };
+            if model.iter_has_child(iter) {
+                // a group is visible if any of its messages is
+                !for_each_message_row(model, Some(iter), &mut |child| !is_shown(child))
+            } else {
+                is_shown(iter)
+            }
         });
     }
     let previous_sort = tv
@@ -493,8 +576,8 @@ pub fn refresh_grids_new_messages(
             && store_index == card.store_index
         {
             let store = streams.get_streams_store(store_index);
-            let ls = tv_state
-                .cur_liststore
+            let ts = tv_state
+                .cur_treestore
                 .as_ref()
                 .filter(|(c, _s)| {
                     c.ip == card.ip && c.port == card.port && c.store_index == card.store_index
@@ -502,16 +585,16 @@ pub fn refresh_grids_new_messages(
                 .map(|(_c, s)| s.clone())
                 .unwrap_or_else(|| {
                     let key = card.to_key();
-                    let ls = store.get_empty_liststore();
-                    tv_state.cur_liststore = Some((key, ls.clone()));
+                    let ts = store.get_empty_treestore();
+                    tv_state.cur_treestore = Some((key, ts.clone()));
                     let (ref tv, ref _signals) =
                         &tv_state.message_treeviews.get(card.store_index).unwrap();
-                    store.end_populate_treeview(tv, &ls);
-                    ls
+                    store.end_populate_treeview(tv, &ts);
+                    ts
                 });
             // refresh_remote_ips_streams_tree() // <------
             store.populate_treeview(
-                &ls,
+                &ts,
                 stream_id,
                 streams.stream_message_count(stream_id).unwrap() - added_messages,
                 added_messages,
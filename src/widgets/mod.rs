@@ -1,9 +1,11 @@
 pub mod comm_info_header;
 mod comm_target_card;
+mod global_search;
 pub mod headerbar_search;
 mod ips_and_streams_treeview;
 mod messages_treeview;
 mod preferences;
 mod recent_file_item;
 mod search_options;
+mod server_stats;
 pub mod win;
@@ -1,5 +1,5 @@
 use super::win;
-use crate::config::Config;
+use crate::config::{Config, InputBackend, TSharkOutputFormat};
 use gtk::prelude::*;
 use gtk::traits::SettingsExt;
 use relm::Widget;
@@ -11,12 +11,22 @@ pub enum Msg {
     CustomTcpDumpBufferSizeToggled(bool),
     CustomTcpDumpBufferSizeValueChanged,
     TcpdumpUsePkexecChanged(bool),
+    NativeInputBackendToggled(bool),
+    TSharkJsonOutputToggled(bool),
+    SlowThresholdsChanged,
+    GrpcProtoPathChanged,
+    PickGrpcProtoPath(gtk::FileChooserAction),
 }
 
 pub struct Model {
     prefer_dark_theme: bool,
     custom_tcpdump_buffer_size_kib: Option<usize>,
     tcpdump_use_pkexec_if_possible: bool,
+    input_backend: InputBackend,
+    tshark_output_format: TSharkOutputFormat,
+    http_slow_threshold_ms: u32,
+    pg_slow_threshold_ms: u32,
+    grpc_proto_path: Option<String>,
 }
 
 #[widget]
@@ -33,6 +43,23 @@ impl Widget for Preferences {
         self.widgets
             .tcpdump_buf_size_spinner
             .set_increments(128.0, 128.0);
+        for (spinner, val) in &[
+            (
+                &self.widgets.http_slow_threshold_spinner,
+                config.http_slow_threshold_ms,
+            ),
+            (
+                &self.widgets.pg_slow_threshold_spinner,
+                config.pg_slow_threshold_ms,
+            ),
+        ] {
+            spinner.set_range(1.0, 600_000.0);
+            spinner.set_increments(50.0, 500.0);
+            spinner.set_value(*val as f64);
+        }
+        self.widgets
+            .grpc_proto_entry
+            .set_text(config.grpc_proto_path.as_deref().unwrap_or(""));
     }
 
     fn model() -> Model {
@@ -41,6 +68,11 @@ impl Widget for Preferences {
             prefer_dark_theme: config.prefer_dark_theme,
             custom_tcpdump_buffer_size_kib: config.custom_tcpdump_buffer_size_kib,
             tcpdump_use_pkexec_if_possible: config.tcpdump_use_pkexec_if_possible,
+            input_backend: config.input_backend,
+            tshark_output_format: config.tshark_output_format,
+            http_slow_threshold_ms: config.http_slow_threshold_ms,
+            pg_slow_threshold_ms: config.pg_slow_threshold_ms,
+            grpc_proto_path: config.grpc_proto_path,
         }
     }
 
@@ -49,6 +81,11 @@ impl Widget for Preferences {
             prefer_dark_theme: self.model.prefer_dark_theme,
             custom_tcpdump_buffer_size_kib: self.model.custom_tcpdump_buffer_size_kib,
             tcpdump_use_pkexec_if_possible: self.model.tcpdump_use_pkexec_if_possible,
+            input_backend: self.model.input_backend,
+            tshark_output_format: self.model.tshark_output_format,
+            http_slow_threshold_ms: self.model.http_slow_threshold_ms,
+            pg_slow_threshold_ms: self.model.pg_slow_threshold_ms,
+            grpc_proto_path: self.model.grpc_proto_path.clone(),
         }
     }
 
@@ -74,6 +111,54 @@ impl Widget for Preferences {
                 self.model.tcpdump_use_pkexec_if_possible = t;
                 self.get_config().save_config(&self.widgets.prefs_window);
             }
+            Msg::NativeInputBackendToggled(t) => {
+                self.model.input_backend = if t {
+                    InputBackend::Native
+                } else {
+                    InputBackend::TShark
+                };
+                self.get_config().save_config(&self.widgets.prefs_window);
+            }
+            Msg::TSharkJsonOutputToggled(t) => {
+                self.model.tshark_output_format = if t {
+                    TSharkOutputFormat::Json
+                } else {
+                    TSharkOutputFormat::Pdml
+                };
+                self.get_config().save_config(&self.widgets.prefs_window);
+            }
+            Msg::SlowThresholdsChanged => {
+                self.model.http_slow_threshold_ms =
+                    self.widgets.http_slow_threshold_spinner.value() as u32;
+                self.model.pg_slow_threshold_ms =
+                    self.widgets.pg_slow_threshold_spinner.value() as u32;
+                self.get_config().save_config(&self.widgets.prefs_window);
+            }
+            Msg::GrpcProtoPathChanged => {
+                let path = self.widgets.grpc_proto_entry.text().to_string();
+                let path = Some(path).filter(|p| !p.is_empty());
+                // we get there also when the entry merely loses the focus
+                if path != self.model.grpc_proto_path {
+                    self.model.grpc_proto_path = path;
+                    self.get_config().save_config(&self.widgets.prefs_window);
+                }
+            }
+            Msg::PickGrpcProtoPath(action) => {
+                let dialog = gtk::builders::FileChooserNativeBuilder::new()
+                    .action(action)
+                    .title("Select the .proto files or descriptor set")
+                    .transient_for(&self.widgets.prefs_window)
+                    .modal(true)
+                    .build();
+                if dialog.run() == gtk::ResponseType::Accept {
+                    if let Some(path) = dialog.filename() {
+                        self.widgets
+                            .grpc_proto_entry
+                            .set_text(&path.to_string_lossy());
+                        self.update(Msg::GrpcProtoPathChanged);
+                    }
+                }
+            }
         }
     }
 
@@ -123,6 +208,63 @@ impl Widget for Preferences {
                     toggled(t) => Msg::TcpdumpUsePkexecChanged(t.is_active()),
                     visible: cfg!(target_os = "linux") && !win::is_flatpak()
                 },
+                gtk::CheckButton {
+                    label: "Decode HTTP/1 and PostgreSQL without tshark when opening files (faster)",
+                    active: self.model.input_backend == InputBackend::Native,
+                    toggled(t) => Msg::NativeInputBackendToggled(t.is_active()),
+                },
+                gtk::CheckButton {
+                    label: "Ask tshark for JSON output of only the protocols we decode (faster)",
+                    active: self.model.tshark_output_format == TSharkOutputFormat::Json,
+                    toggled(t) => Msg::TSharkJsonOutputToggled(t.is_active()),
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 5,
+                    gtk::Label {
+                        label: "Highlight HTTP requests slower than (ms)",
+                    },
+                    #[name="http_slow_threshold_spinner"]
+                    gtk::SpinButton {
+                        numeric: true,
+                        value_changed => Msg::SlowThresholdsChanged,
+                    },
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 5,
+                    gtk::Label {
+                        label: "Highlight PostgreSQL queries slower than (ms)",
+                    },
+                    #[name="pg_slow_threshold_spinner"]
+                    gtk::SpinButton {
+                        numeric: true,
+                        value_changed => Msg::SlowThresholdsChanged,
+                    },
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 5,
+                    gtk::Label {
+                        label: "gRPC schema",
+                    },
+                    #[name="grpc_proto_entry"]
+                    gtk::Entry {
+                        hexpand: true,
+                        placeholder_text: Some("A folder of .proto files, or a descriptor set"),
+                        // not on every keystroke, we'd save a config for each
+                        activate => Msg::GrpcProtoPathChanged,
+                        focus_out_event(_, _) => (Msg::GrpcProtoPathChanged, Inhibit(false)),
+                    },
+                    gtk::Button {
+                        label: "Folder...",
+                        clicked => Msg::PickGrpcProtoPath(gtk::FileChooserAction::SelectFolder),
+                    },
+                    gtk::Button {
+                        label: "File...",
+                        clicked => Msg::PickGrpcProtoPath(gtk::FileChooserAction::Open),
+                    },
+                },
             }
         }
     }
@@ -9,12 +9,22 @@ pub enum Msg {}
 
 pub struct Model {
     recent_file: PathBuf,
+    tls_keylog_label: Option<String>,
 }
 
 #[widget]
 impl Widget for RecentFileItem {
-    fn model(_relm: &relm::Relm<Self>, recent_file: PathBuf) -> Model {
-        Model { recent_file }
+    fn model(_relm: &relm::Relm<Self>, params: (PathBuf, Option<PathBuf>)) -> Model {
+        let (recent_file, tls_keylog) = params;
+        Model {
+            recent_file,
+            tls_keylog_label: tls_keylog.map(|k| {
+                format!(
+                    "TLS key log: {}",
+                    k.file_name().and_then(|f| f.to_str()).unwrap_or("")
+                )
+            }),
+        }
     }
 
     fn update(&mut self, _event: Msg) {}
@@ -43,6 +53,13 @@ impl Widget for RecentFileItem {
                 // https://github.com/flatpak/xdg-desktop-portal/issues/475
                 visible: !win::is_flatpak(),
             },
+            #[style_class="recent_file_folder"]
+            gtk::Label {
+                halign: gtk::Align::Start,
+                label: self.model.tls_keylog_label.as_deref().unwrap_or(""),
+                ellipsize: pango::EllipsizeMode::End,
+                visible: self.model.tls_keylog_label.is_some(),
+            },
         }
     }
 }
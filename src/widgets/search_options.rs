@@ -37,13 +37,13 @@ pub enum Msg {
     EnableOptionsWithAndOr,
     EnableOptionsWithoutAndOr,
     FilterKeyChanged,
+    SearchOpChanged,
 }
 
 pub struct Model {
     relm: relm::Relm<SearchOptions>,
     filter_keys: BTreeSet<&'static str>,
     string_filter_keys: BTreeSet<&'static str>,
-    numeric_filter_keys: BTreeSet<&'static str>,
 }
 
 #[widget]
@@ -56,8 +56,9 @@ impl Widget for SearchOptions {
             self.widgets.filter_key_combo.append_text(k);
         }
         self.widgets.filter_key_combo.set_active(Some(0));
-        self.widgets.search_op_combo.append_text("contains");
-        self.widgets.search_op_combo.append_text("doesntContain");
+        for op in search_expr::STRING_OPERATORS {
+            self.widgets.search_op_combo.append_text(op);
+        }
         self.widgets.search_op_combo.set_active(Some(0));
     }
 
@@ -66,7 +67,6 @@ impl Widget for SearchOptions {
             relm: relm.clone(),
             filter_keys: BTreeSet::new(),
             string_filter_keys: BTreeSet::new(),
-            numeric_filter_keys: BTreeSet::new(),
         }
     }
 
@@ -82,7 +82,6 @@ impl Widget for SearchOptions {
             } => {
                 self.model.filter_keys = string_keys.union(&numeric_keys).cloned().collect();
                 self.model.string_filter_keys = string_keys;
-                self.model.numeric_filter_keys = numeric_keys;
                 self.widgets.filter_key_combo.remove_all();
                 for k in self.model.filter_keys.iter() {
                     self.widgets.filter_key_combo.append_text(k);
@@ -98,21 +97,16 @@ impl Widget for SearchOptions {
                 }
             }
             Msg::SearchTextChanged => {
-                // for string searches, we don't care about the format
+                // for most string searches, we don't care about the format
                 // of the string, the value is always valid. for numeric searches
-                // however, validate the format of the search value
-                let numeric_filter_key = self
+                // and regular expressions however, validate the search value
+                let search_txt = self.widgets.search_entry.text().to_string();
+                let allow_add = self
                     .widgets
-                    .filter_key_combo
+                    .search_op_combo
                     .active_text()
-                    .as_ref()
-                    .and_then(|fk| self.model.numeric_filter_keys.get(fk.as_str()));
-                let allow_add = if numeric_filter_key.is_some() {
-                    let search_txt = self.widgets.search_entry.text().to_string();
-                    Self::try_parse_filter_val_number(&search_txt).is_some()
-                } else {
-                    true
-                };
+                    .and_then(|op| search_expr::criteria_from_operator(op.as_str(), &search_txt))
+                    .is_some();
                 self.widgets.add_btn.set_sensitive(allow_add);
                 self.widgets.add_and_close_btn.set_sensitive(allow_add);
             }
@@ -148,12 +142,13 @@ impl Widget for SearchOptions {
                     .map(|s| s.to_string());
                 self.widgets.search_op_combo.remove_all();
                 if let Some(key_str) = filter_key.as_deref() {
-                    if self.model.string_filter_keys.contains(key_str) {
-                        self.widgets.search_op_combo.append_text("contains");
-                        self.widgets.search_op_combo.append_text("doesntContain");
+                    let ops = if self.model.string_filter_keys.contains(key_str) {
+                        search_expr::STRING_OPERATORS
                     } else {
-                        self.widgets.search_op_combo.append_text(">");
-                        self.widgets.search_op_combo.append_text("<");
+                        search_expr::NUMERIC_OPERATORS
+                    };
+                    for op in ops {
+                        self.widgets.search_op_combo.append_text(op);
                     }
                 }
                 self.widgets.search_op_combo.set_active(Some(0));
@@ -165,6 +160,17 @@ impl Widget for SearchOptions {
                 // for a string.
                 self.model.relm.stream().emit(Msg::SearchTextChanged);
             }
+            Msg::SearchOpChanged => {
+                let placeholder = match self.widgets.search_op_combo.active_text().as_deref() {
+                    Some("matches") => "Regular expression",
+                    Some("between") => "10 and 20",
+                    _ => "",
+                };
+                self.widgets
+                    .search_entry
+                    .set_placeholder_text(Some(placeholder));
+                self.model.relm.stream().emit(Msg::SearchTextChanged);
+            }
             // meant for my parent
             Msg::Add(_) => {}
             Msg::ClearSearchTextClick => {}
@@ -191,27 +197,12 @@ impl Widget for SearchOptions {
             .and_then(|fk| self.model.filter_keys.get(fk.as_str()))
             .unwrap();
         let search_txt = self.widgets.search_entry.text().to_string();
-        if let Some((search_op, op_negation)) = match self
+        if let Some((search_op, op_negation)) = self
             .widgets
             .search_op_combo
             .active_text()
-            .as_ref()
-            .map(|k| k.as_str())
+            .and_then(|op| search_expr::criteria_from_operator(op.as_str(), &search_txt))
         {
-            Some("contains") => Some((
-                SearchCriteria::Contains(search_txt),
-                OperatorNegation::NotNegated,
-            )),
-            Some("doesntContain") => Some((
-                SearchCriteria::Contains(search_txt),
-                OperatorNegation::Negated,
-            )),
-            Some(">") => Self::try_parse_filter_val_number(&search_txt)
-                .map(|sc| (sc, OperatorNegation::NotNegated)),
-            Some("<") => Self::try_parse_filter_val_number(&search_txt)
-                .map(|sc| (sc, OperatorNegation::Negated)),
-            x => panic!("unhandled search_op: {:?}", x),
-        } {
             self.model.relm.stream().emit(Msg::Add((
                 combine_operator,
                 filter_key,
@@ -221,13 +212,6 @@ impl Widget for SearchOptions {
         }
     }
 
-    fn try_parse_filter_val_number(val: &str) -> Option<SearchCriteria> {
-        match search_expr::parse_filter_val_number(val) {
-            Ok(("", (n, d))) => Some(SearchCriteria::GreaterThan(n, d)),
-            _ => None,
-        }
-    }
-
     view! {
         #[name="root_stack"]
         gtk::Stack {
@@ -263,6 +247,7 @@ impl Widget for SearchOptions {
                         left_attach: 0,
                         top_attach: 1,
                     },
+                    changed => Msg::SearchOpChanged,
                 },
                 #[name="search_entry"]
                 gtk::SearchEntry {
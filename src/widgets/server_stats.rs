@@ -0,0 +1,126 @@
+use crate::custom_streams_store::{StatsCell, StatsTable};
+use gtk::prelude::*;
+use relm::Widget;
+use relm_derive::{widget, Msg};
+
+#[derive(Msg)]
+pub enum Msg {
+    RowActivated(gtk::TreePath),
+
+    // meant for my parent
+    ApplySearchFilter(String),
+}
+
+pub struct Model {
+    relm: relm::Relm<ServerStats>,
+    title: String,
+    table: StatsTable,
+}
+
+#[widget]
+impl Widget for ServerStats {
+    fn init_view(&mut self) {
+        self.widgets.headerbar.set_title(Some(&self.model.title));
+        // for every column, the displayed text then a number to sort by.
+        // The last column is the search filter for the row.
+        let column_count = self.model.table.column_titles.len();
+        let mut types = vec![];
+        for _ in 0..column_count {
+            types.push(String::static_type());
+            types.push(u64::static_type());
+        }
+        types.push(String::static_type());
+        let store = gtk::ListStore::new(&types);
+        for row in &self.model.table.rows {
+            let iter = store.append();
+            for (idx, cell) in row.cells.iter().enumerate() {
+                let (text, sort_key) = match cell {
+                    StatsCell::Text(t) => (t, 0),
+                    StatsCell::Number(n, t) => (t, *n),
+                };
+                store.set_value(&iter, 2 * idx as u32, &text.to_value());
+                store.set_value(&iter, 2 * idx as u32 + 1, &sort_key.to_value());
+            }
+            store.set_value(
+                &iter,
+                2 * column_count as u32,
+                &row.search_filter.to_value(),
+            );
+        }
+        for (idx, title) in self.model.table.column_titles.iter().enumerate() {
+            let is_number = matches!(
+                self.model.table.rows.first().and_then(|r| r.cells.get(idx)),
+                Some(StatsCell::Number(_, _))
+            );
+            let col = gtk::builders::TreeViewColumnBuilder::new()
+                .title(title)
+                .resizable(true)
+                .expand(idx == 0)
+                .sort_column_id(2 * idx as i32 + if is_number { 1 } else { 0 })
+                .build();
+            let cell = gtk::builders::CellRendererTextBuilder::new()
+                .ellipsize(pango::EllipsizeMode::End)
+                .xalign(if is_number { 1.0 } else { 0.0 })
+                .build();
+            col.pack_start(&cell, true);
+            col.add_attribute(&cell, "text", 2 * idx as i32);
+            self.widgets.stats_treeview.append_column(&col);
+        }
+        self.widgets.stats_treeview.set_model(Some(&store));
+    }
+
+    fn model(relm: &relm::Relm<Self>, params: (String, StatsTable)) -> Model {
+        let (title, table) = params;
+        Model {
+            relm: relm.clone(),
+            title,
+            table,
+        }
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::RowActivated(path) => {
+                let filter_col = 2 * self.model.table.column_titles.len() as i32;
+                if let Some(filter) = self
+                    .widgets
+                    .stats_treeview
+                    .model()
+                    .and_then(|m| m.iter(&path).map(|iter| m.value(&iter, filter_col)))
+                    .and_then(|v| v.get::<String>().ok())
+                {
+                    self.model
+                        .relm
+                        .stream()
+                        .emit(Msg::ApplySearchFilter(filter));
+                }
+            }
+            // meant for my parent
+            Msg::ApplySearchFilter(_) => {}
+        }
+    }
+
+    view! {
+        gtk::Window {
+            titlebar: view! {
+                #[name="headerbar"]
+                gtk::HeaderBar {
+                    subtitle: Some("Double-click a row to search for its messages"),
+                    show_close_button: true,
+                }
+            },
+            default_width: 1000,
+            default_height: 500,
+            gtk::ScrolledWindow {
+                margin_top: 10,
+                margin_start: 10,
+                margin_end: 10,
+                margin_bottom: 10,
+                #[name="stats_treeview"]
+                gtk::TreeView {
+                    row_activated(_, path, _) => Msg::RowActivated(path.clone()),
+                },
+            },
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use super::comm_target_card;
 use super::comm_target_card::{CommTargetCard, CommTargetCardData};
+use super::global_search::GlobalSearch;
+use super::global_search::Msg as GlobalSearchMsg;
 use super::headerbar_search::HeaderbarSearch;
 use super::headerbar_search::Msg as HeaderbarSearchMsg;
 use super::headerbar_search::Msg::SearchActiveChanged as HbsMsgSearchActiveChanged;
@@ -8,10 +10,14 @@ use super::ips_and_streams_treeview;
 use super::messages_treeview;
 use super::preferences::Preferences;
 use super::recent_file_item::RecentFileItem;
+use super::server_stats::Msg as ServerStatsMsg;
+use super::server_stats::ServerStats;
 use crate::config;
-use crate::config::Config;
+use crate::config::{Config, InputBackend, TSharkOutputFormat};
 use crate::custom_streams_store::ClientServerInfo;
+use crate::http::har;
 use crate::icons::Icon;
+use crate::native_capture;
 use crate::packets_read;
 use crate::packets_read::{InputStep, ParseInputStep, TSharkInputType};
 use crate::search_expr;
@@ -28,6 +34,7 @@ use relm::{Component, ContainerWidget, Widget};
 use relm_derive::{widget, Msg};
 use std::cmp::Reverse;
 use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::Write;
 use std::net::IpAddr;
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::FileTypeExt;
@@ -59,16 +66,26 @@ pub enum InfobarOptions {
     TimeLimitedWithCloseButton,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportScope {
+    SelectedCard,
+    WholeCapture,
+}
+
 #[derive(Msg, Debug)]
 pub enum Msg {
     SearchClicked,
     OpenFile,
+    OpenFileWithTlsKeylog,
     OpenRecentFile(usize),
     DisplayPreferences,
+    DisplayGlobalSearch,
+    DisplayServerStats,
     DisplayAbout,
     DisplayShortcuts,
     CaptureToggled,
     SaveCapture,
+    ExportHar(ExportScope),
     ChildProcessDied,
 
     DragDataReceived(gdk::DragContext, gtk::SelectionData),
@@ -76,6 +93,9 @@ pub enum Msg {
     KeyPress(gdk::EventKey),
     SearchActiveChanged(bool),
     SearchExprChanged(Option<Result<(String, search_expr::SearchExpr), String>>),
+    GlobalSearch(search_expr::SearchExpr),
+    GlobalSearchJumpToHit(messages_treeview::GlobalSearchHit),
+    ServerStatsApplyFilter(String),
 
     LoadedData(ParseInputStep),
     OpenFileFirstPacketDisplayed,
@@ -89,6 +109,7 @@ pub enum Msg {
     SelectCardFromRemoteIpsAndStreams(CommTargetCardData, Vec<IpAddr>, Vec<TcpStreamId>),
 
     DisplayDetails(TcpStreamId, u32),
+    SelectMessage(usize, TcpStreamId, u32),
 
     Quit,
 }
@@ -102,7 +123,8 @@ pub struct Model {
     search_toggle_signal: Option<glib::SignalHandlerId>,
     window_subtitle: Option<String>,
     current_file: Option<(PathBuf, TSharkInputType)>,
-    recent_files: Vec<PathBuf>,
+    // the capture files, and the TLS key logs they were opened with
+    recent_files: Vec<(PathBuf, Option<PathBuf>)>,
 
     set_sidebar_height: bool,
 
@@ -129,6 +151,10 @@ pub struct Model {
     _recent_file_item_components: Vec<Component<RecentFileItem>>,
 
     prefs_win: Option<Component<Preferences>>,
+    global_search_win: Option<Component<GlobalSearch>>,
+    server_stats_win: Option<Component<ServerStats>>,
+    // the server that the statistics window is about
+    server_stats_card: Option<CommTargetCardKey>,
 
     capture_malformed_packets: usize,
     tcpdump_child: Option<Child>,
@@ -235,7 +261,8 @@ impl Widget for Win {
         // self.refresh_remote_servers(RefreshRemoteIpsAndStreams::Yes, &[], &[]);
         let path = self.model.current_file.as_ref().map(|(p, _t)| p).cloned();
         if let Some(p) = path {
-            self.gui_load_file(p);
+            let tls_keylog = Self::saved_tls_keylog(&p);
+            self.gui_load_file(p, tls_keylog);
         }
     }
 
@@ -277,6 +304,10 @@ impl Widget for Win {
         items.sort_by_key(|i| normalize_uri(i.uri()));
         items.dedup_by_key(|i| normalize_uri(i.uri()));
         items.sort_by_key(|i| Reverse(i.modified()));
+        let mut tls_keylogs = config::read_recent_tls_keylogs().unwrap_or_else(|e| {
+            eprintln!("Error reading the recent TLS key logs: {}", e);
+            HashMap::new()
+        });
         items
             .into_iter()
             .filter(|i| {
@@ -289,11 +320,14 @@ impl Widget for Win {
             .flat_map(|fi| fi.uri())
             .map(|gs| tshark_communication::string_to_path(gs.as_str()))
             .for_each(|pb| {
-                self.model.recent_files.push(pb.clone());
+                let tls_keylog = tls_keylogs.remove(&pb);
+                self.model
+                    .recent_files
+                    .push((pb.clone(), tls_keylog.clone()));
                 self.model._recent_file_item_components.push(
                     self.widgets
                         .recent_files_list
-                        .add_widget::<RecentFileItem>(pb),
+                        .add_widget::<RecentFileItem>((pb, tls_keylog)),
                 );
             });
     }
@@ -360,6 +394,9 @@ impl Widget for Win {
                 .height_request(24)
                 .build(),
             prefs_win: None,
+            global_search_win: None,
+            server_stats_win: None,
+            server_stats_card: None,
             search_toggle_signal: None,
             infobar_label: gtk::builders::LabelBuilder::new().build(),
             comm_targets_components: HashMap::new(),
@@ -392,6 +429,12 @@ impl Widget for Win {
         //     }
         // }
         match event {
+            Msg::DisplayGlobalSearch => {
+                self.display_global_search();
+            }
+            Msg::DisplayServerStats => {
+                self.display_server_stats();
+            }
             Msg::DisplayPreferences => {
                 self.display_preferences();
             }
@@ -406,12 +449,17 @@ impl Widget for Win {
                     .map(|u| u.as_str())
                     .and_then(|u| u.strip_prefix("file://"))
                 {
-                    self.gui_load_file(uri.into());
+                    let path = PathBuf::from(uri);
+                    let tls_keylog = Self::saved_tls_keylog(&path);
+                    self.gui_load_file(path, tls_keylog);
                 }
             }
             Msg::OpenFile => {
                 self.open_file();
             }
+            Msg::OpenFileWithTlsKeylog => {
+                self.open_file_with_tls_keylog();
+            }
             Msg::SearchClicked => {
                 let is_active = self.widgets.search_toggle.is_active();
                 self.widgets
@@ -433,6 +481,9 @@ impl Widget for Win {
             Msg::SaveCapture => {
                 self.handle_save_capture();
             }
+            Msg::ExportHar(scope) => {
+                self.handle_export_har(scope);
+            }
             Msg::ChildProcessDied => {
                 // the problem i'm trying to fix is the user triggering
                 // a capture... so we call pkexec to launch tcpdump.. but the user closes pkexec and
@@ -486,9 +537,21 @@ impl Widget for Win {
                     );
                 }
             }
+            Msg::GlobalSearch(expr) => {
+                let hits = messages_treeview::global_search(&self.model.streams, &expr);
+                if let Some(global_search_win) = self.model.global_search_win.as_ref() {
+                    global_search_win.emit(GlobalSearchMsg::SearchResults(hits));
+                }
+            }
+            Msg::GlobalSearchJumpToHit(hit) => {
+                self.global_search_jump_to_hit(hit);
+            }
+            Msg::ServerStatsApplyFilter(filter) => {
+                self.server_stats_apply_filter(filter);
+            }
             Msg::OpenRecentFile(idx) => {
-                let path = self.model.recent_files[idx].clone();
-                self.gui_load_file(path);
+                let (path, tls_keylog) = self.model.recent_files[idx].clone();
+                self.gui_load_file(path, tls_keylog);
             }
             Msg::OpenFileFirstPacketDisplayed => {
                 if self.model.current_file.is_none()
@@ -571,6 +634,14 @@ impl Widget for Win {
                     idx as usize,
                 );
             }
+            Msg::SelectMessage(store_index, stream_id, idx) => {
+                messages_treeview::select_message(
+                    self.model.messages_treeview_state.as_ref().unwrap(),
+                    store_index,
+                    stream_id,
+                    idx,
+                );
+            }
             Msg::Quit => {
                 // needed for the pcap save temp files at least
                 if let Err(e) =
@@ -627,15 +698,13 @@ impl Widget for Win {
                         .model
                         .streams
                         .supported_string_filter_keys(card.store_index)
-                        .iter()
-                        .cloned()
+                        .into_iter()
                         .collect(),
                     numeric_keys: self
                         .model
                         .streams
                         .supported_numeric_filter_keys(card.store_index)
-                        .iter()
-                        .cloned()
+                        .into_iter()
                         .collect(),
                 });
         }
@@ -944,6 +1013,7 @@ impl Widget for Win {
         session_change_type: SessionChangeType,
     ) {
         let summary_details = self.model.streams.stream_summary_details(stream_id);
+        let slow_message_count = self.model.streams.stream_slow_message_count(stream_id);
         let card_key = CommTargetCardKey {
             ip: client_server_info.server_ip,
             port: client_server_info.server_port,
@@ -960,6 +1030,7 @@ impl Widget for Win {
                 card.increase_incoming_session_count();
             }
             card.remote_hosts.insert(client_server_info.client_ip);
+            card.set_stream_slow_message_count(stream_id, slow_message_count);
             if card.summary_details.is_none() {
                 if let Some(details) = summary_details {
                     card.summary_details = SummaryDetails::new(details.to_string(), card_key);
@@ -972,7 +1043,7 @@ impl Widget for Win {
                 .emit(comm_target_card::Msg::Update(card.clone()));
         } else {
             // add new card
-            let card = CommTargetCardData::new(
+            let mut card = CommTargetCardData::new(
                 client_server_info.server_ip,
                 client_server_info.server_port,
                 store_index,
@@ -985,6 +1056,7 @@ impl Widget for Win {
                 summary_details.and_then(|d| SummaryDetails::new(d.to_string(), card_key)),
                 1,
             );
+            card.set_stream_slow_message_count(stream_id, slow_message_count);
             self.model.comm_target_cards.push(card.clone());
             self.model.comm_targets_components.insert(
                 card_key,
@@ -1039,6 +1111,142 @@ impl Widget for Win {
         prefs_win.widget().show();
     }
 
+    fn display_global_search(&mut self) {
+        let stores = self.model.streams.get_streams_stores();
+        let string_keys = (0..stores.len())
+            .flat_map(|idx| self.model.streams.supported_string_filter_keys(idx))
+            .collect();
+        let numeric_keys = (0..stores.len())
+            .flat_map(|idx| self.model.streams.supported_numeric_filter_keys(idx))
+            .collect();
+        let global_search_win = relm::init::<GlobalSearch>((string_keys, numeric_keys))
+            .expect("Error initializing the global search window");
+        relm::connect!(global_search_win@GlobalSearchMsg::Search(ref expr), self.model.relm, Msg::GlobalSearch(expr.clone()));
+        relm::connect!(global_search_win@GlobalSearchMsg::JumpToHit(ref hit), self.model.relm, Msg::GlobalSearchJumpToHit(hit.clone()));
+        global_search_win
+            .widget()
+            .set_transient_for(Some(&self.widgets.window));
+        global_search_win
+            .widget()
+            .set_position(gtk::WindowPosition::CenterOnParent);
+        global_search_win.widget().show();
+        self.model.global_search_win = Some(global_search_win);
+    }
+
+    fn global_search_jump_to_hit(&mut self, hit: messages_treeview::GlobalSearchHit) {
+        let card_idx =
+            self.model
+                .streams
+                .get_client_server(hit.stream_id)
+                .and_then(|cs| {
+                    self.model.comm_target_cards.iter().position(|c| {
+                        c.store_index == hit.store_index && c.to_key().matches_server(cs)
+                    })
+                });
+        if let Some(idx) = card_idx {
+            let is_selected = self.model.selected_card.as_ref().map_or(false, |c| {
+                c.to_key() == self.model.comm_target_cards[idx].to_key()
+            });
+            if !is_selected {
+                // emits SelectCard, which will be handled before SelectMessage
+                self.widgets.comm_target_list.select_row(
+                    self.widgets
+                        .comm_target_list
+                        .row_at_index(idx as i32)
+                        .as_ref(),
+                );
+            }
+            self.model.relm.stream().emit(Msg::SelectMessage(
+                hit.store_index,
+                hit.stream_id,
+                hit.msg_idx,
+            ));
+            self.widgets.window.present();
+        }
+    }
+
+    fn display_server_stats(&mut self) {
+        let card = match self.model.selected_card.as_ref() {
+            Some(card) => card,
+            None => {
+                self.handle_infobar_show(
+                    "Please select a server first",
+                    InfobarOptions::TimeLimitedWithCloseButton,
+                );
+                return;
+            }
+        };
+        let card_key = card.to_key();
+        let streams = &self.model.streams;
+        let stream_ids: Vec<_> = streams
+            .tcp_stream_ids()
+            .into_iter()
+            .filter(|stream_id| {
+                streams.get_store_index(*stream_id) == Some(card.store_index)
+                    && streams
+                        .get_client_server(*stream_id)
+                        .map_or(false, |cs| card_key.matches_server(cs))
+            })
+            .collect();
+        let table = match streams
+            .get_streams_store(card.store_index)
+            .streams_stats(&stream_ids)
+        {
+            Some(table) => table,
+            None => {
+                self.handle_infobar_show(
+                    "Statistics are not available for this protocol",
+                    InfobarOptions::TimeLimitedWithCloseButton,
+                );
+                return;
+            }
+        };
+        let title = format!("Statistics for {}:{}", card.ip, card.port);
+        let server_stats_win = relm::init::<ServerStats>((title, table))
+            .expect("Error initializing the statistics window");
+        relm::connect!(server_stats_win@ServerStatsMsg::ApplySearchFilter(ref filter), self.model.relm, Msg::ServerStatsApplyFilter(filter.clone()));
+        server_stats_win
+            .widget()
+            .set_transient_for(Some(&self.widgets.window));
+        server_stats_win
+            .widget()
+            .set_position(gtk::WindowPosition::CenterOnParent);
+        server_stats_win.widget().show();
+        self.model.server_stats_win = Some(server_stats_win);
+        self.model.server_stats_card = Some(card_key);
+    }
+
+    fn server_stats_apply_filter(&mut self, filter: String) {
+        let card_idx = self.model.server_stats_card.as_ref().and_then(|key| {
+            self.model
+                .comm_target_cards
+                .iter()
+                .position(|c| &c.to_key() == key)
+        });
+        if let Some(idx) = card_idx {
+            let is_selected = self.model.selected_card.as_ref().map_or(false, |c| {
+                c.to_key() == self.model.comm_target_cards[idx].to_key()
+            });
+            if !is_selected {
+                // emits SelectCard, which will be handled before the search text change
+                self.widgets.comm_target_list.select_row(
+                    self.widgets
+                        .comm_target_list
+                        .row_at_index(idx as i32)
+                        .as_ref(),
+                );
+            }
+            self.model
+                .relm
+                .stream()
+                .emit(Msg::SearchActiveChanged(true));
+            self.components
+                .headerbar_search
+                .emit(HeaderbarSearchMsg::SetSearchText(filter));
+            self.widgets.window.present();
+        }
+    }
+
     fn display_about(&mut self) {
         let tshark_version = Command::new("tshark")
             .args(&["--version"])
@@ -1106,7 +1314,15 @@ impl Widget for Win {
             self.model
                 .bg_sender
                 .send(BgFunc::new(move || {
-                    Self::load_file(TSharkInputType::Fifo, &filter, fifo_path.clone(), s.clone());
+                    Self::load_file(
+                        TSharkInputType::Fifo,
+                        InputBackend::TShark,
+                        config.tshark_output_format,
+                        &filter,
+                        fifo_path.clone(),
+                        None,
+                        s.clone(),
+                    );
                 }))
                 .unwrap();
         } else {
@@ -1199,7 +1415,73 @@ impl Widget for Win {
         }
     }
 
-    fn open_file(&mut self) {
+    fn handle_export_har(&mut self, scope: ExportScope) {
+        let card = self.model.selected_card.as_ref();
+        if scope == ExportScope::SelectedCard && card.is_none() {
+            self.handle_infobar_show(
+                "Please select a server first",
+                InfobarOptions::TimeLimitedWithCloseButton,
+            );
+            return;
+        }
+        let streams = &self.model.streams;
+        let mut entries: Vec<_> = streams
+            .tcp_stream_ids()
+            .into_iter()
+            .filter(|stream_id| match (scope, card) {
+                (ExportScope::SelectedCard, Some(card)) => {
+                    streams.get_store_index(*stream_id) == Some(card.store_index)
+                        && matches!(streams.get_client_server(*stream_id),
+                                    Some(cs) if cs.server_ip == card.ip && cs.server_port == card.port)
+                }
+                _ => true,
+            })
+            .flat_map(|stream_id| {
+                streams
+                    .get_store_index(stream_id)
+                    .map(|idx| streams.get_streams_store(idx).stream_har_entries(stream_id))
+                    .unwrap_or_default()
+            })
+            .collect();
+        if entries.is_empty() {
+            self.handle_infobar_show(
+                "No HTTP messages to export",
+                InfobarOptions::TimeLimitedWithCloseButton,
+            );
+            return;
+        }
+        entries.sort_by(|a, b| {
+            a["startedDateTime"]
+                .as_str()
+                .cmp(&b["startedDateTime"].as_str())
+        });
+        let dialog = gtk::builders::FileChooserNativeBuilder::new()
+            .action(gtk::FileChooserAction::Save)
+            .title("Export to...")
+            .do_overwrite_confirmation(true)
+            .modal(true)
+            .build();
+        dialog.set_current_name("capture.har");
+        if dialog.run() == gtk::ResponseType::Accept {
+            if let Some(fname) = dialog.filename() {
+                let har = har::har_document(entries);
+                if let Err(e) = std::fs::File::create(&fname).and_then(|f| {
+                    let mut writer = std::io::BufWriter::new(f);
+                    serde_json::to_writer_pretty(&mut writer, &har)?;
+                    writer.flush()
+                }) {
+                    Self::display_error_block("Error exporting to HAR", Some(&e.to_string()));
+                } else {
+                    self.handle_infobar_show(
+                        "The export has completed successfully",
+                        InfobarOptions::TimeLimitedWithCloseButton,
+                    );
+                }
+            }
+        }
+    }
+
+    fn select_capture_file() -> Option<PathBuf> {
         let dialog = gtk::builders::FileChooserNativeBuilder::new()
             .action(gtk::FileChooserAction::Open)
             .title("Select file")
@@ -1210,8 +1492,39 @@ impl Widget for Win {
         filter.add_pattern("*.pcapng");
         dialog.set_filter(&filter);
         if dialog.run() == gtk::ResponseType::Accept {
-            if let Some(fname) = dialog.filename() {
-                self.gui_load_file(fname);
+            dialog.filename()
+        } else {
+            None
+        }
+    }
+
+    /// the TLS key log we used the last time we opened that capture
+    fn saved_tls_keylog(fname: &Path) -> Option<PathBuf> {
+        config::read_recent_tls_keylogs()
+            .unwrap_or_else(|e| {
+                eprintln!("Error reading the recent TLS key logs: {}", e);
+                HashMap::new()
+            })
+            .remove(fname)
+    }
+
+    fn open_file(&mut self) {
+        if let Some(fname) = Self::select_capture_file() {
+            self.gui_load_file(fname, None);
+        }
+    }
+
+    fn open_file_with_tls_keylog(&mut self) {
+        if let Some(fname) = Self::select_capture_file() {
+            let dialog = gtk::builders::FileChooserNativeBuilder::new()
+                .action(gtk::FileChooserAction::Open)
+                .title("Select the TLS key log file")
+                .modal(true)
+                .build();
+            if dialog.run() == gtk::ResponseType::Accept {
+                if let Some(tls_keylog) = dialog.filename() {
+                    self.gui_load_file(fname, Some(tls_keylog));
+                }
             }
         }
     }
@@ -1274,6 +1587,7 @@ impl Widget for Win {
             .root_stack
             .set_visible_child_name(LOADING_STACK_NAME);
         self.model.streams.clear();
+        self.model.streams.apply_config(&Config::read_config());
         if let Some(ref mut tv_state) = self.model.messages_treeview_state {
             tv_state.file_closed();
         }
@@ -1305,7 +1619,7 @@ impl Widget for Win {
         }
     }
 
-    fn gui_load_file(&mut self, fname: PathBuf) {
+    fn gui_load_file(&mut self, fname: PathBuf, tls_keylog: Option<PathBuf>) {
         // clear potentially present errors from previous files
         self.model
             .relm
@@ -1313,6 +1627,12 @@ impl Widget for Win {
             .emit(Msg::InfoBarShow(None, InfobarOptions::Default));
         self.widgets.open_btn.set_active(false);
         Self::add_to_recent_files(&fname);
+        // opening a capture without a key log must not forget the one we saved for it
+        if let Some(keylog) = tls_keylog.as_deref() {
+            if let Err(e) = config::save_recent_tls_keylog(&fname, keylog) {
+                eprintln!("Error saving the recent TLS key logs: {}", e);
+            }
+        }
         let is_fifo = if cfg!(unix) {
             std::fs::metadata(&fname)
                 .ok()
@@ -1349,6 +1669,10 @@ impl Widget for Win {
 
         let s = self.model.loaded_data_sender.clone();
         let filter = self.model.streams.tshark_filter_string();
+        // read the config in the GUI thread, because it may display an error dialog
+        let config = Config::read_config();
+        let input_backend = config.input_backend;
+        let output_format = config.tshark_output_format;
         // self.init_remote_ips_streams_tree();
         self.model
             .bg_sender
@@ -1359,8 +1683,11 @@ impl Widget for Win {
                     } else {
                         TSharkInputType::File
                     },
+                    input_backend,
+                    output_format,
                     &filter,
                     fname.clone(),
+                    tls_keylog.clone(),
                     s.clone(),
                 );
             }))
@@ -1370,11 +1697,42 @@ impl Widget for Win {
 
     fn load_file(
         file_type: TSharkInputType,
+        input_backend: InputBackend,
+        output_format: TSharkOutputFormat,
         filter_string: &str,
         fname: PathBuf,
+        tls_keylog: Option<PathBuf>,
         sender: relm::Sender<ParseInputStep>,
     ) {
-        packets_read::invoke_tshark(file_type, &fname, filter_string, sender);
+        // in flatpak, tshark can't read the keylog file: merge it in a copy of the capture
+        let (fname, tls_keylog) = match tls_keylog {
+            Some(keylog) if is_flatpak() => match packets_read::inject_tls_secrets(&fname, &keylog)
+            {
+                Ok(merged_fname) => (merged_fname, None),
+                Err(e) => {
+                    sender.send(Err(e)).unwrap();
+                    return;
+                }
+            },
+            _ => (fname, tls_keylog),
+        };
+        match (file_type, input_backend) {
+            (TSharkInputType::File, InputBackend::Native) => native_capture::read_capture_file(
+                &fname,
+                filter_string,
+                output_format,
+                tls_keylog.as_deref(),
+                sender,
+            ),
+            _ => packets_read::invoke_tshark(
+                file_type,
+                &fname,
+                filter_string,
+                output_format,
+                tls_keylog.as_deref(),
+                sender,
+            ),
+        }
     }
 
     view! {
@@ -1420,6 +1778,11 @@ impl Widget for Win {
                                         label: "Other Documents...",
                                         hexpand: true,
                                         clicked => Msg::OpenFile,
+                                    },
+                                    gtk::Button {
+                                        label: "Open with TLS Key Log...",
+                                        hexpand: true,
+                                        clicked => Msg::OpenFileWithTlsKeylog,
                                     }
                                 }
                             }
@@ -1460,6 +1823,27 @@ impl Widget for Win {
                                     margin_start: 10,
                                     margin_end: 10,
                                     margin_bottom: 10,
+                                    gtk::ModelButton {
+                                        label: "Export server as HAR...",
+                                        hexpand: true,
+                                        clicked => Msg::ExportHar(ExportScope::SelectedCard),
+                                    },
+                                    gtk::ModelButton {
+                                        label: "Export capture as HAR...",
+                                        hexpand: true,
+                                        clicked => Msg::ExportHar(ExportScope::WholeCapture),
+                                    },
+                                    gtk::ModelButton {
+                                        label: "Search all servers...",
+                                        hexpand: true,
+                                        clicked => Msg::DisplayGlobalSearch,
+                                    },
+                                    gtk::ModelButton {
+                                        label: "Server statistics...",
+                                        hexpand: true,
+                                        clicked => Msg::DisplayServerStats,
+                                    },
+                                    gtk::Separator {},
                                     gtk::ModelButton {
                                         label: "Preferences",
                                         hexpand: true,